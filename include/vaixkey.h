@@ -0,0 +1,76 @@
+#ifndef VAIXKEY_H
+#define VAIXKEY_H
+
+/* Generated with cbindgen from src/ffi/mod.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Telex input method (aa → â, s → sắc)
+#define VAIXKEY_METHOD_TELEX 0
+
+// VNI input method (a6 → â, 1 → sắc)
+#define VAIXKEY_METHOD_VNI 1
+
+// VIQR input method (a^ → â, ' → sắc)
+#define VAIXKEY_METHOD_VIQR 2
+
+// A null engine or output pointer was passed
+#define VAIXKEY_ERR_NULL -1
+
+// The codepoint or method is not valid
+#define VAIXKEY_ERR_INVALID -2
+
+// The output buffer cannot hold the result and its NUL terminator
+#define VAIXKEY_ERR_BUFFER_TOO_SMALL -3
+
+// Output buffer size that always fits a single result
+#define VAIXKEY_OUTPUT_CAPACITY 256
+
+// Opaque engine handle owned by the C caller
+typedef struct VaixkeyEngine VaixkeyEngine;
+
+// Create a new engine in Vietnamese mode using Telex.
+// The handle must be released with `vaixkey_free`.
+struct VaixkeyEngine *vaixkey_new(void);
+
+// Release an engine created by `vaixkey_new`. Passing null is a no-op.
+//
+// # Safety
+// `engine` must be null or a handle returned by `vaixkey_new` that has not
+// been freed yet.
+void vaixkey_free(struct VaixkeyEngine *engine);
+
+// Clear the composing buffer, e.g. when the caret moves or focus changes.
+//
+// # Safety
+// `engine` must be null or a live handle returned by `vaixkey_new`.
+void vaixkey_reset(struct VaixkeyEngine *engine);
+
+// Select the input method (`VAIXKEY_METHOD_*`). Returns 0 on success.
+// A word being composed is committed as it stands on screen; the next key
+// starts a new one.
+//
+// # Safety
+// `engine` must be null or a live handle returned by `vaixkey_new`.
+int32_t vaixkey_set_method(struct VaixkeyEngine *engine, uint32_t method);
+
+// Feed one keystroke to the engine.
+//
+// On success returns the number of characters the host must delete before
+// the caret, and writes the UTF-8 text to insert (NUL-terminated) to `out`.
+// A return of 0 with `out` holding the key itself means the key passes
+// through unchanged. Negative values are `VAIXKEY_ERR_*` codes.
+//
+// # Safety
+// `engine` must be null or a live handle returned by `vaixkey_new`, and
+// `out` must be null or point to at least `out_len` writable bytes.
+int32_t vaixkey_process(struct VaixkeyEngine *engine,
+                        uint32_t codepoint,
+                        char *out,
+                        size_t out_len);
+
+#endif  /* VAIXKEY_H */
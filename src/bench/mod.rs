@@ -0,0 +1,114 @@
+// Engine throughput benchmark (`vaixkey --benchmark [keystrokes]`)
+// Replays a Telex corpus through UnikeyEngine::process one key at a time and
+// reports throughput, per-keystroke latency and heap allocations, then
+// replays it again with the experimental syllable cache on to compare.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use vaixkey::input_method::UnikeyEngine;
+
+/// Telex keystrokes for a few paragraphs of everyday Vietnamese
+const CORPUS: &str = include_str!("../../tests/fixtures/telex_corpus.txt");
+
+/// Keystrokes replayed when no size is given
+pub const DEFAULT_KEYSTROKES: usize = 1_000_000;
+
+/// `engine.syllable_cache` for the comparison run: enough for every word
+/// of the corpus
+const CACHE_CAPACITY: usize = 512;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// System allocator that counts allocations for the benchmark report
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Feed `keystrokes` keys from the corpus (repeated as needed) through a
+/// fresh engine and print the results
+pub fn run(keystrokes: usize) {
+    let keys: Vec<char> = CORPUS.chars().cycle().take(keystrokes).collect();
+    if keys.is_empty() {
+        println!("❌ Nothing to benchmark");
+        return;
+    }
+
+    println!("📊 VaixKey Engine Benchmark");
+    println!("===========================");
+    println!("   Corpus: {} keystrokes ({} key fixture, repeated)", keys.len(), CORPUS.chars().count());
+
+    let mut engine = UnikeyEngine::new();
+    let mut min = Duration::MAX;
+    let mut max = Duration::ZERO;
+
+    let allocations_before = ALLOCATIONS.load(Ordering::Relaxed);
+    let bytes_before = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let start = Instant::now();
+
+    for &key in &keys {
+        let key_start = Instant::now();
+        let result = engine.process(key);
+        let elapsed = key_start.elapsed();
+        std::hint::black_box(result);
+
+        min = min.min(elapsed);
+        max = max.max(elapsed);
+    }
+
+    let total = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations_before;
+    let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes_before;
+    let mean = total / keys.len() as u32;
+
+    println!("\n⏱️  Timing:");
+    println!("   Total: {:.2?}", total);
+    println!("   Throughput: {:.0} keystrokes/s", keys.len() as f64 / total.as_secs_f64());
+    println!("   Per keystroke: min {:.2?}, mean {:.2?}, max {:.2?}", min, mean, max);
+    println!("   (per-keystroke figures include timer overhead)");
+
+    println!("\n🧮 Allocations:");
+    println!("   Count: {} ({:.2} per keystroke)", allocations, allocations as f64 / keys.len() as f64);
+    println!("   Bytes: {} ({:.1} per keystroke)", bytes, bytes as f64 / keys.len() as f64);
+
+    // Timed per run, not per key, so timer overhead doesn't hide the
+    // difference
+    let uncached_total = replay(&mut UnikeyEngine::new(), &keys);
+    let mut cached = UnikeyEngine::new();
+    cached.set_syllable_cache(CACHE_CAPACITY);
+    let cached_total = replay(&mut cached, &keys);
+    let (hits, misses) = cached.syllable_cache_stats().unwrap_or_default();
+
+    println!("\n🗃️  Syllable Cache ({} entries):", CACHE_CAPACITY);
+    println!("   Throughput: {:.0} keystrokes/s ({:+.1}% against no cache)",
+        keys.len() as f64 / cached_total.as_secs_f64(),
+        (uncached_total.as_secs_f64() / cached_total.as_secs_f64() - 1.0) * 100.0);
+    println!("   Hits: {} of {} lookups", hits, hits + misses);
+}
+
+/// Time `engine` over all of `keys`
+fn replay(engine: &mut UnikeyEngine, keys: &[char]) -> Duration {
+    let start = Instant::now();
+    for &key in keys {
+        std::hint::black_box(engine.process(key));
+    }
+    start.elapsed()
+}
@@ -0,0 +1,91 @@
+// Binary cache of the resolved config (`~/.config/vaixkey/cache.bin`)
+// Startup reads the bincode cache instead of parsing and migrating the TOML
+// file while the file is unchanged: same path, modification time and length,
+// written by the same build. Anything else (a missing or corrupt cache, an
+// edited file, another profile or `--config`, an upgrade) falls back to
+// `Config::load` and rewrites the cache. `--no-cache` skips it altogether.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use vaixkey::{Config, VaixError};
+
+/// File name of the cache inside the config directory
+pub const CACHE_FILE: &str = "cache.bin";
+
+/// Bump when `Entry` changes shape
+const CACHE_FORMAT: u32 = 1;
+
+/// What the cache holds: the config and the file it was parsed from
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    format: u32,
+    /// Crate version of the build that wrote it, since `Config` may differ
+    build: String,
+    config_path: PathBuf,
+    modified: SystemTime,
+    len: u64,
+    config: Config,
+}
+
+/// `~/.config/vaixkey/cache.bin`, shared by every profile and `--config`
+pub fn cache_path() -> Result<PathBuf, VaixError> {
+    Ok(Config::config_dir()?.join(CACHE_FILE))
+}
+
+/// `Config::load`, served from the cache when it matches the config file
+pub fn load() -> Result<Config, VaixError> {
+    let config_path = Config::config_path()?;
+    let cache = cache_path()?;
+    if let Some(config) = read(&cache, &config_path) {
+        log::debug!("Config loaded from cache {}", cache.display());
+        return Ok(config);
+    }
+
+    // Stat before loading: if the file changes (or `load` migrates or
+    // creates it) in between, the next start only misses the cache
+    let stamp = stamp(&config_path);
+    let config = Config::load()?;
+    if let Some((modified, len)) = stamp {
+        if let Err(e) = write(&cache, &config_path, modified, len, &config) {
+            log::debug!("Couldn't write config cache {}: {}", cache.display(), e);
+        }
+    }
+    Ok(config)
+}
+
+/// The cached config, if `cache` was written for `config_path` as it is now
+pub fn read(cache: &Path, config_path: &Path) -> Option<Config> {
+    let bytes = fs::read(cache).ok()?;
+    let entry: Entry = bincode::deserialize(&bytes).ok()?;
+    if entry.format != CACHE_FORMAT || entry.build != env!("CARGO_PKG_VERSION") || entry.config_path != config_path {
+        return None;
+    }
+    (stamp(config_path) == Some((entry.modified, entry.len))).then_some(entry.config)
+}
+
+/// Cache `config` as parsed from `config_path` with the given stamp. The file
+/// is replaced in one rename, so a concurrent start never reads half of it.
+pub fn write(cache: &Path, config_path: &Path, modified: SystemTime, len: u64, config: &Config) -> Result<(), Box<dyn Error>> {
+    let entry = Entry {
+        format: CACHE_FORMAT,
+        build: env!("CARGO_PKG_VERSION").to_string(),
+        config_path: config_path.to_path_buf(),
+        modified,
+        len,
+        config: config.clone(),
+    };
+    let temp = cache.with_extension("bin.tmp");
+    fs::write(&temp, bincode::serialize(&entry)?)?;
+    fs::rename(&temp, cache)?;
+    Ok(())
+}
+
+/// Modification time and length of a file, what the cache is keyed on
+pub fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
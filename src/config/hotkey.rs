@@ -0,0 +1,144 @@
+// Hotkey combos as written in config.toml ("Ctrl+Shift", "Ctrl+Alt+V")
+// Parsing normalises modifier order and aliases so two spellings of the same
+// combo compare equal.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A parsed modifier+key combination
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hotkey {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    /// Command on macOS, Super/Windows elsewhere
+    pub meta: bool,
+    /// Non-modifier key, if any. Modifier-only combos like "Ctrl+Shift"
+    /// trigger when the modifiers are released together.
+    pub key: Option<String>,
+}
+
+/// Named keys accepted in a combo besides letters and digits
+const NAMED_KEYS: &[&str] = &[
+    "Space", "Tab", "Enter", "Escape", "Backspace", "F1", "F2", "F3", "F4", "F5", "F6", "F7",
+    "F8", "F9", "F10", "F11", "F12",
+];
+
+/// Combos the OS or most apps already own, with what they do
+const OS_SHORTCUTS: &[(&str, &str)] = &[
+    ("Cmd+Space", "Spotlight on macOS"),
+    ("Ctrl+Space", "macOS input source switching"),
+    ("Ctrl+Alt+Space", "macOS input source menu"),
+    ("Cmd+Tab", "the macOS app switcher"),
+    ("Alt+Tab", "the window switcher on Windows and Linux"),
+    ("Cmd+Q", "Quit on macOS"),
+    ("Cmd+W", "Close Window on macOS"),
+    ("Cmd+C", "Copy on macOS"),
+    ("Cmd+V", "Paste on macOS"),
+    ("Cmd+X", "Cut on macOS"),
+    ("Cmd+Z", "Undo on macOS"),
+    ("Cmd+A", "Select All on macOS"),
+    ("Ctrl+C", "Copy (and interrupt in terminals)"),
+    ("Ctrl+V", "Paste on Windows and Linux"),
+    ("Ctrl+X", "Cut on Windows and Linux"),
+    ("Ctrl+Z", "Undo on Windows and Linux"),
+];
+
+impl Hotkey {
+    /// Describe the OS shortcut this combo shadows, if any
+    pub fn os_conflict(&self) -> Option<&'static str> {
+        OS_SHORTCUTS.iter().find_map(|(combo, owner)| {
+            let shortcut: Hotkey = combo.parse().ok()?;
+            (shortcut == *self).then_some(*owner)
+        })
+    }
+}
+
+impl FromStr for Hotkey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut hotkey = Hotkey::default();
+        let mut modifiers = 0;
+
+        for part in s.split('+').map(str::trim) {
+            if part.is_empty() {
+                return Err(format!("'{}' has an empty key", s));
+            }
+
+            let flag = match part.to_lowercase().as_str() {
+                "ctrl" | "control" => Some(&mut hotkey.ctrl),
+                "alt" | "option" | "opt" => Some(&mut hotkey.alt),
+                "shift" => Some(&mut hotkey.shift),
+                "cmd" | "command" | "meta" | "super" | "win" => Some(&mut hotkey.meta),
+                _ => None,
+            };
+
+            if let Some(flag) = flag {
+                if *flag {
+                    return Err(format!("'{}' repeats the modifier '{}'", s, part));
+                }
+                *flag = true;
+                modifiers += 1;
+                continue;
+            }
+
+            if let Some(key) = &hotkey.key {
+                return Err(format!("'{}' has more than one key ('{}' and '{}')", s, key, part));
+            }
+            hotkey.key = Some(parse_key(part).ok_or_else(|| format!("'{}' has an unknown key '{}'", s, part))?);
+        }
+
+        if modifiers == 0 {
+            return Err(format!("'{}' needs at least one of Ctrl, Alt, Shift or Cmd", s));
+        }
+        if hotkey.key.is_none() && modifiers < 2 {
+            // A lone modifier would fire on every Shift-ed letter
+            return Err(format!("'{}' needs a key or a second modifier", s));
+        }
+
+        Ok(hotkey)
+    }
+}
+
+/// Normalise a key name: letters are upper-cased, named keys take their
+/// canonical spelling
+fn parse_key(part: &str) -> Option<String> {
+    let mut chars = part.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return c.is_ascii_alphanumeric().then(|| c.to_ascii_uppercase().to_string());
+    }
+
+    let lower = part.to_lowercase();
+    let lower = match lower.as_str() {
+        "return" => "enter",
+        "esc" => "escape",
+        other => other,
+    };
+    NAMED_KEYS
+        .iter()
+        .find(|name| name.to_lowercase() == lower)
+        .map(|name| name.to_string())
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if self.ctrl {
+            parts.push("Ctrl");
+        }
+        if self.alt {
+            parts.push("Alt");
+        }
+        if self.shift {
+            parts.push("Shift");
+        }
+        if self.meta {
+            parts.push("Cmd");
+        }
+        if let Some(key) = &self.key {
+            parts.push(key);
+        }
+        write!(f, "{}", parts.join("+"))
+    }
+}
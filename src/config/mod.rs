@@ -1,49 +1,404 @@
+mod hotkey;
+
+pub use hotkey::Hotkey;
+
+use crate::VaixError;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Schema version written by this build. Bump it and add a step to
+/// `Config::migrate` whenever the file layout changes.
+pub const CONFIG_VERSION: u32 = 1;
 
+/// Environment variable that points VaixKey at a different config file
+pub const CONFIG_ENV_VAR: &str = "VAIXKEY_CONFIG";
+
+/// Path given with `--config`, which takes precedence over the env var
+static PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+// No `skip_serializing_if` in these structs: TOML leaves `None` out anyway,
+// and the app's binary config cache needs every field written
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    #[serde(default = "default_version")]
+    pub version: u32,
     pub input_method: InputMethodType,
+    /// Layout the keyboard is set to, for keys the OS reports without the
+    /// character they typed
+    #[serde(default)]
+    pub keyboard_layout: KeyboardLayout,
+    /// How replacements reach the focused app
+    #[serde(default)]
+    pub injection_mode: InjectionMode,
+    /// When the app rejects a typed Unicode string, type the rest of the
+    /// session with key presses only: base letters, plus dead keys for the
+    /// marks the layout composes. No effect on macOS yet, where a dropped
+    /// event can't be detected
+    #[serde(default)]
+    pub injection_fallback: bool,
+    /// Feedback when Vietnamese mode is toggled
+    #[serde(default)]
+    pub toggle_feedback: FeedbackKind,
+    /// Language of `--status`, the permission guides and notifications
+    #[serde(default)]
+    pub language: Language,
     pub hotkeys: HotkeyConfig,
     pub auto_start: bool,
     pub show_status_bar: bool,
+    /// Notify when the input mode changes
+    #[serde(default = "default_show_notifications")]
+    pub show_notifications: bool,
+    #[serde(default)]
+    pub engine: EngineConfig,
+    #[serde(default)]
+    pub ui: UiConfig,
+    #[serde(default)]
+    pub separators: SeparatorConfig,
+    /// Words that are typed as a symbol: when a separator ends a word whose
+    /// keys are exactly a trigger, the word is replaced (`vnd` → ₫)
+    #[serde(default = "default_symbols")]
+    pub symbols: BTreeMap<String, String>,
+    /// Words that are always written as a proper noun: when a separator
+    /// ends a word that composes to an entry, ignoring case, it is replaced
+    /// (`hanoi` → Hà Nội)
+    #[serde(default)]
+    pub proper_nouns: BTreeMap<String, String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum InputMethodType {
     Telex,
     Vni,
+    /// Telex for beginners: a `w` with no vowel to horn stays a `w` instead
+    /// of typing ư, `[` and `]` are always literal, and tone keys are applied
+    /// only when the word is committed (as with `tone_at_end`)
     SimpleTelex,
 }
 
+impl InputMethodType {
+    /// Every method, in the order `--list-methods` shows them
+    pub const ALL: [InputMethodType; 3] = [InputMethodType::Telex, InputMethodType::Vni, InputMethodType::SimpleTelex];
+
+    /// One line for `--list-methods`
+    pub fn description(self) -> &'static str {
+        match self {
+            InputMethodType::Telex => "letters mark the word: aa → â, ow → ơ, dd → đ, s f r x j place tones",
+            InputMethodType::Vni => "digits after a letter place tones: a1 → á, a2 → à ... a5 → ạ, 0 removes",
+            InputMethodType::SimpleTelex => "Telex without shortcuts: w and brackets stay literal, tones apply when the word ends",
+        }
+    }
+}
+
+/// Where a tone goes on an open two-vowel syllable
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum TonePlacement {
+    /// On the second vowel of oa, oe, uy: hoà, khoẻ, thuý
+    #[default]
+    Modern,
+    /// On the first: hòa, khỏe, thúy
+    Classic,
+}
+
+/// What `z` does in Telex
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum ZKey {
+    /// A plain letter
+    #[default]
+    Literal,
+    /// Removes the tone: `asz` → `a`
+    RemoveTone,
+    /// Removes the tone and the marks of the whole word: `aaz` → `a`,
+    /// `ddoongz` → `dong`
+    RemoveMarks,
+}
+
+/// Keyboard layout, used to tell which letter a physical key typed when the
+/// OS doesn't say, and which key types a letter when injecting
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum KeyboardLayout {
+    #[default]
+    Qwerty,
+    Dvorak,
+    Colemak,
+}
+
+/// How the keyboard layer types a replacement: key by key, or by putting it
+/// on the clipboard and pressing Cmd+V, which is faster for long
+/// replacements and survives apps that drop synthetic keys. Paste restores
+/// the clipboard afterwards and needs it to hold text, typing key by key
+/// otherwise; it is only implemented on macOS.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum InjectionMode {
+    #[default]
+    Simulate,
+    Paste,
+}
+
+/// What tells the user that Vietnamese mode was switched on or off, for
+/// anyone who can't watch the menu bar
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum FeedbackKind {
+    #[default]
+    None,
+    /// A short system sound, a different one for each mode
+    Sound,
+    /// The mode notification, unless `show_notifications` is off
+    Notification,
+}
+
+/// Language of the app's messages
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    En,
+    Vi,
+}
+
+/// `en` or `vi`, case-insensitive, as given to `--lang`
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "en" => Ok(Language::En),
+            "vi" => Ok(Language::Vi),
+            _ => Err(format!("unknown language '{}'; valid languages: en, vi", s)),
+        }
+    }
+}
+
+/// Case-insensitive, as in the config file (`SimpleTelex`) or with a
+/// separator (`simple-telex`)
+impl FromStr for InputMethodType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name: String = s.chars().filter(|c| !matches!(c, '-' | '_' | ' ')).collect();
+        Self::ALL
+            .into_iter()
+            .find(|method| format!("{:?}", method).eq_ignore_ascii_case(&name))
+            .ok_or_else(|| {
+                let valid: Vec<String> = Self::ALL.iter().map(|m| format!("{:?}", m)).collect();
+                format!("unknown input method '{}'; valid methods: {}", s, valid.join(", "))
+            })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HotkeyConfig {
     pub toggle_vietnamese: String,
     pub switch_input_method: String,
+    /// Copy the word being composed and the keys behind it to the clipboard,
+    /// for bug reports. Off unless set.
+    #[serde(default)]
+    pub dump_buffer: Option<String>,
+    /// Convert the text on the clipboard as if it had been typed, and put
+    /// the result back. Off unless set.
+    #[serde(default)]
+    pub convert_clipboard: Option<String>,
+}
+
+/// Options controlling how the Unikey engine composes syllables
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineConfig {
+    /// Apply tone keys only when the syllable is committed by a separator
+    pub tone_at_end: bool,
+    /// Telex: type ơ with `[` and ư with `]` instead of literal brackets
+    pub bracket_shortcuts: bool,
+    /// On commit, retype the raw keys when the transformed word isn't in the
+    /// bundled Vietnamese word list ("ưindơ" → "window"). Needs the
+    /// `dictionary` build feature.
+    pub dictionary_revert: bool,
+    /// Drop the word being composed when a key is pressed with Ctrl, Alt or
+    /// Cmd held (Cmd+A, Cmd+Left...), since the caret may have moved
+    pub reset_on_modifier: bool,
+    /// Offer other tones of the word being composed as suggestions
+    /// (`chao` → chào, chảo, cháo). Advisory only, nothing is applied. Needs
+    /// the `dictionary` build feature.
+    pub suggestions: bool,
+    /// Commit the word being composed after this many milliseconds without
+    /// a key, so a key typed much later can't change an old syllable. Off
+    /// unless set.
+    pub commit_timeout_ms: Option<u64>,
+    /// Telex: treat the VNI keys 6-9 as marks in a syllable no Telex key
+    /// has marked yet (`a6` → â, `tam8` → tăm), for people who mix methods
+    pub allow_vni_in_telex: bool,
+    /// Leave keys untransformed while typing and convert the whole word when
+    /// a separator commits it (`vieetj ` → `việt `)
+    pub compose_on_commit: bool,
+    /// Let the numpad digits type VNI tones and marks like the top-row
+    /// digits. Off: they reach the app untouched and end the word.
+    pub numpad_as_vni_tones: bool,
+    /// Tab and Enter finish the word like Space, so deferred tones, symbols
+    /// and `compose_on_commit` apply before them. Off, they drop the word as
+    /// it is on screen, for editors where they accept a completion that a
+    /// rewrite would break
+    pub tab_enter_commit: bool,
+    /// Backspace over the separator that ended a word makes the word
+    /// editable again, to add a forgotten tone (`vieet ` ⌫ `j` → `việt`)
+    pub restore_previous_syllable: bool,
+    /// Telex: a tone key typed twice in a row types both letters instead of
+    /// undoing the tone to one, so English words survive inline (`class`,
+    /// `pass`, `staff`). Off: `ass` → `as`.
+    pub english_double_letters: bool,
+    /// Type the rest of a word literally once it has this many letters, so
+    /// URLs and base64 aren't transformed halfway. 0 never stops.
+    pub max_syllable_len: usize,
+    /// Letters a tone key may reach back over after the vowels: 2 covers
+    /// every coda (`manhj` → `mạnh`); more tones across longer runs
+    pub max_after_vowel: usize,
+    /// Refuse huyền, hỏi and ngã on syllables ending in c, ch, p or t,
+    /// typing the tone key literally (`hocf`); sắc and nặng still apply
+    pub strict_tones: bool,
+    /// Tone placement on oa, oe and uy only, whatever the style used for
+    /// other vowels (`Classic`: hòa, khỏe, thúy)
+    pub oa_oe_uy_style: TonePlacement,
+    /// Telex: `dd` types đ only at the start of a syllable, since đ is
+    /// never anywhere else (`add` stays `add`). Off by default: any `dd`
+    /// doubles, `add` → `ađ`
+    pub dd_initial_only: bool,
+    /// Telex tone keys to type as plain letters, for tones never used or
+    /// keys that clash with English words (`["x"]`: `max` stays `max`)
+    pub disabled_tone_keys: Vec<char>,
+    /// Telex: whether `z` removes the tone, the tone and the marks, or
+    /// nothing. A `z` with nothing to remove is typed as is
+    pub z_key: ZKey,
+    /// Longest a key may take to process, in microseconds, before it is
+    /// passed through untouched and the word dropped. 0 never gives up.
+    pub max_process_us: u64,
+    /// Experimental: remember how this many recent key sequences composed
+    /// and replay them instead of recomputing. 0 (the default) is off;
+    /// `vaixkey --benchmark` shows whether it pays off
+    pub syllable_cache: usize,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            tone_at_end: false,
+            bracket_shortcuts: false,
+            dictionary_revert: false,
+            reset_on_modifier: true,
+            suggestions: false,
+            commit_timeout_ms: None,
+            allow_vni_in_telex: false,
+            compose_on_commit: false,
+            numpad_as_vni_tones: false,
+            tab_enter_commit: true,
+            restore_previous_syllable: false,
+            english_double_letters: false,
+            max_syllable_len: 12,
+            max_after_vowel: 2,
+            strict_tones: false,
+            oa_oe_uy_style: TonePlacement::Modern,
+            dd_initial_only: false,
+            disabled_tone_keys: Vec::new(),
+            z_key: ZKey::Literal,
+            max_process_us: 8000,
+            syllable_cache: 0,
+        }
+    }
+}
+
+fn default_version() -> u32 {
+    CONFIG_VERSION
+}
+
+fn default_show_notifications() -> bool {
+    true
+}
+
+fn default_symbols() -> BTreeMap<String, String> {
+    BTreeMap::from([("vnd".to_string(), "₫".to_string())])
+}
+
+/// On-screen feedback while typing
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiConfig {
+    /// Show the word being composed in a small window under the caret
+    /// (macOS only)
+    pub show_preedit: bool,
+    /// Offer "Convert Selection to Vietnamese" in the Services menu (macOS
+    /// only, when run from a `.app` bundle declaring the service)
+    pub services: bool,
+}
+
+/// Changes to the characters that end a word (space, punctuation, digits)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SeparatorConfig {
+    /// More characters that end a word (`"…"`, `"–"`). Letters are ignored.
+    pub extra: Vec<char>,
+    /// Default separators a word may contain instead, e.g. `-` for
+    /// hyphenated words or `_` in identifiers
+    pub exclude: Vec<char>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
+            version: CONFIG_VERSION,
             input_method: InputMethodType::Telex,
+            keyboard_layout: KeyboardLayout::Qwerty,
+            injection_mode: InjectionMode::Simulate,
+            injection_fallback: false,
+            toggle_feedback: FeedbackKind::None,
+            language: Language::En,
             hotkeys: HotkeyConfig {
                 toggle_vietnamese: "Ctrl+Shift".to_string(),
                 switch_input_method: "Ctrl+Alt+V".to_string(),
+                dump_buffer: None,
+                convert_clipboard: None,
             },
             auto_start: false,
             show_status_bar: true,
+            show_notifications: true,
+            engine: EngineConfig::default(),
+            ui: UiConfig::default(),
+            separators: SeparatorConfig::default(),
+            symbols: default_symbols(),
+            proper_nouns: BTreeMap::new(),
         }
     }
 }
 
 impl Config {
-    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn load() -> Result<Self, VaixError> {
         let config_path = Self::config_path()?;
 
         if config_path.exists() {
-            let content = fs::read_to_string(&config_path)?;
-            let config: Config = toml::from_str(&content)?;
+            let content = fs::read_to_string(&config_path)
+                .map_err(|e| VaixError::io("read config file", &config_path, e))?;
+            let (config, migrated) = Self::from_toml(&content)?;
+            for warning in config.validate_hotkeys().map_err(|e| {
+                VaixError::InvalidConfig(format!("invalid hotkeys in {}: {}", config_path.display(), e))
+            })?
+            {
+                log::warn!("{}", warning);
+            }
+            if config.engine.dictionary_revert && !cfg!(feature = "dictionary") {
+                log::warn!("engine.dictionary_revert is ignored: this build has no `dictionary` feature");
+            }
+            if config.engine.suggestions && !cfg!(feature = "dictionary") {
+                log::warn!("engine.suggestions is ignored: this build has no `dictionary` feature");
+            }
+            for letter in config.separators.extra.iter().filter(|c| c.is_alphabetic()) {
+                log::warn!("separators.extra: ignoring '{}', letters can't end a word", letter);
+            }
+            for key in config.engine.disabled_tone_keys.iter().filter(|c| !"sfrxj".contains(c.to_ascii_lowercase())) {
+                log::warn!("engine.disabled_tone_keys: ignoring '{}', not a Telex tone key", key);
+            }
+            if migrated {
+                // Rewrite the file so it records the current layout
+                log::info!("Migrated config at {} to version {}", config_path.display(), CONFIG_VERSION);
+                config.save()?;
+            }
             Ok(config)
         } else {
             // Create default config if none exists
@@ -53,24 +408,205 @@ impl Config {
         }
     }
 
-    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+    /// Check that the hotkeys parse and do not collide. Returns warnings for
+    /// combos that shadow common OS shortcuts.
+    pub fn validate_hotkeys(&self) -> Result<Vec<String>, String> {
+        let toggle: Hotkey = self
+            .hotkeys
+            .toggle_vietnamese
+            .parse()
+            .map_err(|e| format!("toggle_vietnamese: {}", e))?;
+        let switch: Hotkey = self
+            .hotkeys
+            .switch_input_method
+            .parse()
+            .map_err(|e| format!("switch_input_method: {}", e))?;
+
+        let mut hotkeys = vec![("toggle_vietnamese", toggle), ("switch_input_method", switch)];
+        if let Some(dump) = &self.hotkeys.dump_buffer {
+            let dump: Hotkey = dump.parse().map_err(|e| format!("dump_buffer: {}", e))?;
+            hotkeys.push(("dump_buffer", dump));
+        }
+        if let Some(convert) = &self.hotkeys.convert_clipboard {
+            let convert: Hotkey = convert.parse().map_err(|e| format!("convert_clipboard: {}", e))?;
+            hotkeys.push(("convert_clipboard", convert));
+        }
+
+        for (i, (name, hotkey)) in hotkeys.iter().enumerate() {
+            if let Some((other, _)) = hotkeys[i + 1..].iter().find(|(_, other)| other == hotkey) {
+                return Err(format!("{} and {} are both {}", name, other, hotkey));
+            }
+        }
+
+        let warnings = hotkeys
+            .iter()
+            .filter_map(|(name, hotkey)| {
+                hotkey
+                    .os_conflict()
+                    .map(|owner| format!("{} = {} is also used by {}", name, hotkey, owner))
+            })
+            .collect();
+        Ok(warnings)
+    }
+
+    /// Parse a config file, upgrading older layouts. Returns the config and
+    /// whether a migration was applied. Files without a `version` key predate
+    /// versioning and are treated as version 0.
+    pub fn from_toml(content: &str) -> Result<(Self, bool), VaixError> {
+        let mut table: toml::Table = toml::from_str(content)?;
+        let version = match table.get("version") {
+            Some(value) => value
+                .as_integer()
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or_else(|| {
+                    VaixError::InvalidConfig("config `version` must be a non-negative integer".to_string())
+                })?,
+            None => 0,
+        };
+
+        if version > CONFIG_VERSION {
+            return Err(VaixError::UnsupportedVersion { found: version, supported: CONFIG_VERSION });
+        }
+
+        let migrated = version < CONFIG_VERSION;
+        if migrated {
+            Self::migrate(&mut table, version);
+        }
+
+        let config: Config = toml::Value::Table(table).try_into()?;
+        Ok((config, migrated))
+    }
+
+    /// Upgrade a raw config table from `version` to `CONFIG_VERSION`, one
+    /// step at a time. v0 -> v1 only adds the stamp: every v1 field has a
+    /// serde default, so a v0 file already parses.
+    fn migrate(table: &mut toml::Table, _version: u32) {
+        table.insert("version".to_string(), toml::Value::Integer(CONFIG_VERSION as i64));
+    }
+
+    /// `Config::default()` as the TOML `save` writes, for
+    /// `--print-default-config`
+    pub fn default_toml() -> Result<String, VaixError> {
+        Ok(toml::to_string_pretty(&Config::default())?)
+    }
+
+    /// Overwrite the config file with the defaults, whatever it holds now.
+    /// Returns the path written.
+    pub fn reset() -> Result<PathBuf, VaixError> {
+        Config::default().save()?;
+        Self::config_path()
+    }
+
+    pub fn save(&self) -> Result<(), VaixError> {
         let config_path = Self::config_path()?;
 
         // Create config directory if it doesn't exist
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent)?;
+        if let Some(parent) = config_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(|e| VaixError::io("create config directory", parent, e))?;
         }
 
         let content = toml::to_string_pretty(self)?;
-        fs::write(&config_path, content)?;
+        fs::write(&config_path, content)
+            .map_err(|e| VaixError::io("write config file", &config_path, e))?;
+        Ok(())
+    }
+
+    /// Use `path` instead of the default location for the rest of the
+    /// process. Only the first call has an effect.
+    pub fn set_path_override(path: PathBuf) {
+        let _ = PATH_OVERRIDE.set(path);
+    }
+
+    /// Resolve the config file: `--config`, then `VAIXKEY_CONFIG`, then the
+    /// active profile, then `~/.config/vaixkey/config.toml`
+    pub fn config_path() -> Result<PathBuf, VaixError> {
+        if let Some(path) = PATH_OVERRIDE.get() {
+            return Ok(path.clone());
+        }
+
+        if let Some(path) = std::env::var_os(CONFIG_ENV_VAR).filter(|p| !p.is_empty()) {
+            return Ok(PathBuf::from(path));
+        }
+
+        match Self::current_profile()? {
+            Some(name) => Ok(Self::profile_path(&name)?),
+            None => Ok(Self::config_dir()?.join("config.toml")),
+        }
+    }
+
+    /// `~/.config/vaixkey`, home of the default config and profiles
+    pub fn config_dir() -> Result<PathBuf, VaixError> {
+        let home = std::env::var("HOME").map_err(|_| VaixError::HomeNotSet { env_var: CONFIG_ENV_VAR })?;
+        Ok(PathBuf::from(home).join(".config").join("vaixkey"))
+    }
+
+    fn profile_path(name: &str) -> Result<PathBuf, VaixError> {
+        Ok(Self::config_dir()?.join("profiles").join(format!("{}.toml", name)))
+    }
+
+    /// Profile selected by the last `--profile`, if any
+    pub fn current_profile() -> Result<Option<String>, VaixError> {
+        let pointer = Self::config_dir()?.join("current_profile");
+        match fs::read_to_string(&pointer) {
+            Ok(name) => {
+                let name = name.trim();
+                Ok((!name.is_empty()).then(|| name.to_string()))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(VaixError::io("read", pointer, e)),
+        }
+    }
+
+    /// Make `name` the active profile, remembered across restarts. `None` or
+    /// `"default"` goes back to `config.toml`.
+    pub fn set_current_profile(name: Option<&str>) -> Result<(), VaixError> {
+        let dir = Self::config_dir()?;
+        let pointer = dir.join("current_profile");
+
+        match name.filter(|n| *n != "default") {
+            Some(name) => {
+                let valid = !name.is_empty()
+                    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+                if !valid {
+                    return Err(VaixError::InvalidConfig(format!(
+                        "invalid profile name '{}': use letters, digits, '-' and '_'",
+                        name
+                    )));
+                }
+                fs::create_dir_all(&dir).map_err(|e| VaixError::io("create config directory", &dir, e))?;
+                fs::write(&pointer, name).map_err(|e| VaixError::io("write", &pointer, e))?;
+            }
+            None => {
+                if pointer.exists() {
+                    fs::remove_file(&pointer).map_err(|e| VaixError::io("remove", &pointer, e))?;
+                }
+            }
+        }
         Ok(())
     }
 
-    fn config_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
-        let home = std::env::var("HOME")?;
-        Ok(PathBuf::from(home)
-            .join(".config")
-            .join("vaixkey")
-            .join("config.toml"))
+    /// Switch to another profile and load it, creating it with defaults if
+    /// it does not exist yet
+    pub fn switch_profile(name: &str) -> Result<Self, VaixError> {
+        Self::set_current_profile(Some(name))?;
+        Self::load()
+    }
+
+    /// Names of the profiles in `~/.config/vaixkey/profiles`, sorted
+    pub fn list_profiles() -> Result<Vec<String>, VaixError> {
+        let dir = Self::config_dir()?.join("profiles");
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .map_err(|e| VaixError::io("read profile directory", &dir, e))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .filter_map(|path| path.file_stem().map(|s| s.to_string_lossy().into_owned()))
+            .collect();
+        names.sort();
+        Ok(names)
     }
 }
\ No newline at end of file
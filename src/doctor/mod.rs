@@ -0,0 +1,175 @@
+// Setup diagnostics (`vaixkey --doctor`)
+// Runs every check a bug report usually needs in one go: permissions, the
+// config file, the config directory, the helper tools and the OS version,
+// then a short keyboard grab. Prints a table plus a hint for each failure
+// and exits 1 if a critical check failed.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use vaixkey::config::Config;
+
+/// How long the grab must stay up to count as working
+const GRAB_PROBE: Duration = Duration::from_millis(500);
+
+/// One row of the report
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub passed: bool,
+    /// A failure here stops VaixKey from working
+    pub critical: bool,
+    pub detail: String,
+    /// What to do about a failure
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, passed: true, critical: false, detail: detail.into(), hint: None }
+    }
+
+    fn fail(name: &'static str, critical: bool, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { name, passed: false, critical, detail: detail.into(), hint: Some(hint.into()) }
+    }
+}
+
+/// Whether the config at `path` parses. A missing file is fine: the
+/// defaults are used and written on first start.
+pub fn check_config(path: &Path) -> Check {
+    const NAME: &str = "Config file";
+    match std::fs::read_to_string(path) {
+        Ok(content) => match Config::from_toml(&content) {
+            Ok(_) => Check::pass(NAME, path.display().to_string()),
+            Err(e) => Check::fail(NAME, true, e.to_string(),
+                format!("fix or delete {} (a fresh default is written on start)", path.display())),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Check::pass(NAME, format!("{} not created yet, defaults apply", path.display()))
+        }
+        Err(e) => Check::fail(NAME, true, format!("cannot read {}: {}", path.display(), e),
+            format!("check the permissions of {}", path.display())),
+    }
+}
+
+/// Whether settings can be saved in `dir`, by writing and removing a probe
+/// file. The directory is created if it does not exist yet.
+pub fn check_writable(dir: &Path) -> Check {
+    const NAME: &str = "Config directory";
+    let probe = dir.join(format!(".doctor-{}", std::process::id()));
+    let result = std::fs::create_dir_all(dir)
+        .and_then(|()| std::fs::write(&probe, b""))
+        .and_then(|()| std::fs::remove_file(&probe));
+    match result {
+        Ok(()) => Check::pass(NAME, format!("{} is writable", dir.display())),
+        Err(e) => Check::fail(NAME, true, format!("cannot write to {}: {}", dir.display(), e),
+            format!("make {} writable, or pass --config <path>", dir.display())),
+    }
+}
+
+/// Find `program` on PATH
+pub fn find_in_path(program: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+/// `open` shows settings, `osascript` posts notifications; neither is needed
+/// to type
+fn check_tool(name: &'static str, program: &str) -> Check {
+    match find_in_path(program) {
+        Some(path) => Check::pass(name, path.display().to_string()),
+        None => Check::fail(name, false, format!("{} not found on PATH", program),
+            "add /usr/bin to PATH; settings and notifications need it"),
+    }
+}
+
+fn check_macos_version() -> Check {
+    const NAME: &str = "macOS version";
+    match Command::new("sw_vers").arg("-productVersion").output() {
+        Ok(output) if output.status.success() => {
+            Check::pass(NAME, String::from_utf8_lossy(&output.stdout).trim().to_string())
+        }
+        Ok(output) => Check::fail(NAME, false, format!("sw_vers exited with {}", output.status),
+            "VaixKey is only tested on macOS"),
+        Err(e) => Check::fail(NAME, false, format!("cannot run sw_vers: {}", e),
+            "VaixKey is only tested on macOS"),
+    }
+}
+
+fn check_accessibility() -> Check {
+    const NAME: &str = "Accessibility";
+    if crate::check_accessibility_trusted(false) {
+        Check::pass(NAME, "granted")
+    } else {
+        Check::fail(NAME, true, "not granted",
+            "run `vaixkey --request-permission`, then restart the terminal")
+    }
+}
+
+fn check_grab() -> Check {
+    const NAME: &str = "Keyboard grab";
+    match crate::keyboard::probe_grab(GRAB_PROBE) {
+        Ok(()) => Check::pass(NAME, "listener started"),
+        Err(e) => Check::fail(NAME, true, e.to_string(),
+            "grant Accessibility and Input Monitoring, then restart the terminal"),
+    }
+}
+
+/// Run every check, cheapest first
+pub fn checks() -> Vec<Check> {
+    let mut checks = vec![check_accessibility()];
+    match (Config::config_path(), Config::config_dir()) {
+        (Ok(path), Ok(dir)) => {
+            checks.push(check_config(&path));
+            checks.push(check_writable(&dir));
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            checks.push(Check::fail("Config file", true, e.to_string(), "set HOME or pass --config <path>"));
+        }
+    }
+    checks.push(check_tool("open", "open"));
+    checks.push(check_tool("osascript", "osascript"));
+    checks.push(check_macos_version());
+    checks.push(check_grab());
+    checks
+}
+
+/// 1 if any critical check failed, else 0
+pub fn exit_code(checks: &[Check]) -> i32 {
+    if checks.iter().any(|c| c.critical && !c.passed) { 1 } else { 0 }
+}
+
+/// `vaixkey --doctor`: print the report and return the exit code
+pub fn run() -> i32 {
+    println!("🩺 VaixKey Doctor");
+    println!("==========================================");
+
+    let checks = checks();
+    for check in &checks {
+        let status = match (check.passed, check.critical) {
+            (true, _) => "✅",
+            (false, true) => "❌",
+            (false, false) => "⚠️ ",
+        };
+        println!("   {} {:<18} {}", status, check.name, check.detail);
+    }
+
+    let hints: Vec<_> = checks.iter().filter_map(|c| c.hint.as_ref().map(|h| (c.name, h))).collect();
+    if !hints.is_empty() {
+        println!("\n💡 Hints:");
+        for (name, hint) in hints {
+            println!("   • {}: {}", name, hint);
+        }
+    }
+
+    let code = exit_code(&checks);
+    if code == 0 {
+        println!("\n✅ Everything VaixKey needs is in place");
+    } else {
+        println!("\n❌ VaixKey cannot run until the critical checks pass");
+    }
+    code
+}
@@ -0,0 +1,53 @@
+// Errors shared by the library and the app, so callers can tell a broken
+// config file from a missing permission instead of matching on messages
+
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, thiserror::Error)]
+pub enum VaixError {
+    /// Reading or writing a file failed
+    #[error("cannot {action} {}: {source}", path.display())]
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// The config file is not valid TOML or does not match the schema
+    #[error("invalid config: {0}")]
+    ConfigParse(#[from] toml::de::Error),
+
+    /// The config could not be written out as TOML
+    #[error("cannot serialize config: {0}")]
+    ConfigSerialize(#[from] toml::ser::Error),
+
+    /// The config file was written by a newer build
+    #[error("config version {found} is newer than this build of VaixKey supports (version {supported})")]
+    UnsupportedVersion { found: u32, supported: u32 },
+
+    /// A setting parsed but its value is rejected: hotkeys, profile names,
+    /// the `version` key
+    #[error("{0}")]
+    InvalidConfig(String),
+
+    /// No HOME to find `~/.config/vaixkey` in
+    #[error("HOME is not set; pass --config <path> or set {env_var} to choose a config file")]
+    HomeNotSet { env_var: &'static str },
+
+    /// The OS refused access to the keyboard (Accessibility on macOS, the
+    /// input devices on Linux)
+    #[error("permission denied: {0}")]
+    PermissionDenied(String),
+
+    /// The input backend could not capture or inject keys
+    #[error("{backend} backend: {message}")]
+    Backend { backend: &'static str, message: String },
+}
+
+impl VaixError {
+    pub(crate) fn io(action: &'static str, path: impl Into<PathBuf>, source: io::Error) -> Self {
+        VaixError::Io { action, path: path.into(), source }
+    }
+}
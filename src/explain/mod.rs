@@ -0,0 +1,60 @@
+// Keystroke-by-keystroke walkthrough of a word (`vaixkey --explain <word>`).
+// Each key is typed through the engine and the text is printed with the
+// characters that key rewrote in brackets, so `nguyeenj` shows the tone
+// landing on `ê`. Letters that were only appended are not marked. Uses the
+// loaded config, so `--config` picks Telex or VNI.
+
+use vaixkey::input_method::{harness::ScreenModel, InputMethodEngine};
+
+/// The text after one key and which of its characters that key changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub key: char,
+    pub text: String,
+    /// Character indices rewritten in place (tone, mark or undo)
+    pub changed: Vec<usize>,
+}
+
+impl Step {
+    /// The text with every changed character in brackets: `nguy[ệ]n`
+    pub fn highlighted(&self) -> String {
+        self.text
+            .chars()
+            .enumerate()
+            .map(|(i, c)| if self.changed.contains(&i) { format!("[{}]", c) } else { c.to_string() })
+            .collect()
+    }
+}
+
+/// Type `word` from an empty buffer and record every step
+pub fn explain(engine: &mut InputMethodEngine, word: &str) -> Vec<Step> {
+    engine.reset_buffer();
+    let mut screen = ScreenModel::new();
+    let mut steps = Vec::with_capacity(word.len());
+
+    for key in word.chars() {
+        let before: Vec<char> = screen.text().chars().collect();
+        let _ = screen.apply(&engine.process_keypress(key));
+        let text = screen.text();
+        let changed = text
+            .chars()
+            .zip(before.iter())
+            .enumerate()
+            .filter(|(_, (after, before))| after != *before)
+            .map(|(i, _)| i)
+            .collect();
+        steps.push(Step { key, text, changed });
+    }
+    steps
+}
+
+/// `vaixkey --explain <word>`: print one line per keystroke
+pub fn run(engine: &mut InputMethodEngine, word: &str) {
+    println!("🔎 Explaining '{}'", word);
+    println!("==========================================");
+
+    for step in explain(engine, word) {
+        println!("   {:<3} → {}", step.key, step.highlighted());
+    }
+    engine.reset_buffer();
+}
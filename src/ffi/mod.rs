@@ -0,0 +1,124 @@
+// C ABI for embedding the VaixKey engine in non-Rust hosts
+// The header in include/vaixkey.h is generated from this module with cbindgen:
+//   cbindgen --config cbindgen.toml --output include/vaixkey.h
+
+use crate::input_method::{InputMethod, ProcessResult, UnikeyEngine};
+use std::os::raw::c_char;
+
+/// Telex input method (aa → â, s → sắc)
+pub const VAIXKEY_METHOD_TELEX: u32 = 0;
+/// VNI input method (a6 → â, 1 → sắc)
+pub const VAIXKEY_METHOD_VNI: u32 = 1;
+/// VIQR input method (a^ → â, ' → sắc)
+pub const VAIXKEY_METHOD_VIQR: u32 = 2;
+
+/// A null engine or output pointer was passed
+pub const VAIXKEY_ERR_NULL: i32 = -1;
+/// The codepoint or method is not valid
+pub const VAIXKEY_ERR_INVALID: i32 = -2;
+/// The output buffer cannot hold the result and its NUL terminator
+pub const VAIXKEY_ERR_BUFFER_TOO_SMALL: i32 = -3;
+
+/// Output buffer size that always fits a single result
+pub const VAIXKEY_OUTPUT_CAPACITY: usize = 256;
+
+/// Opaque engine handle owned by the C caller
+pub struct VaixkeyEngine {
+    engine: UnikeyEngine,
+}
+
+/// Create a new engine in Vietnamese mode using Telex.
+/// The handle must be released with `vaixkey_free`.
+#[no_mangle]
+pub extern "C" fn vaixkey_new() -> *mut VaixkeyEngine {
+    Box::into_raw(Box::new(VaixkeyEngine {
+        engine: UnikeyEngine::new(),
+    }))
+}
+
+/// Release an engine created by `vaixkey_new`. Passing null is a no-op.
+///
+/// # Safety
+/// `engine` must be null or a handle returned by `vaixkey_new` that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn vaixkey_free(engine: *mut VaixkeyEngine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+/// Clear the composing buffer, e.g. when the caret moves or focus changes.
+///
+/// # Safety
+/// `engine` must be null or a live handle returned by `vaixkey_new`.
+#[no_mangle]
+pub unsafe extern "C" fn vaixkey_reset(engine: *mut VaixkeyEngine) {
+    if let Some(handle) = engine.as_mut() {
+        handle.engine.clear_buf();
+    }
+}
+
+/// Select the input method (`VAIXKEY_METHOD_*`). Returns 0 on success.
+/// A word being composed is committed as it stands on screen; the next key
+/// starts a new one.
+///
+/// # Safety
+/// `engine` must be null or a live handle returned by `vaixkey_new`.
+#[no_mangle]
+pub unsafe extern "C" fn vaixkey_set_method(engine: *mut VaixkeyEngine, method: u32) -> i32 {
+    let Some(handle) = engine.as_mut() else {
+        return VAIXKEY_ERR_NULL;
+    };
+    let method = match method {
+        VAIXKEY_METHOD_TELEX => InputMethod::Telex,
+        VAIXKEY_METHOD_VNI => InputMethod::Vni,
+        VAIXKEY_METHOD_VIQR => InputMethod::Viqr,
+        _ => return VAIXKEY_ERR_INVALID,
+    };
+    handle.engine.set_input_method(method);
+    0
+}
+
+/// Feed one keystroke to the engine.
+///
+/// On success returns the number of characters the host must delete before
+/// the caret, and writes the UTF-8 text to insert (NUL-terminated) to `out`.
+/// A return of 0 with `out` holding the key itself means the key passes
+/// through unchanged. Negative values are `VAIXKEY_ERR_*` codes.
+///
+/// # Safety
+/// `engine` must be null or a live handle returned by `vaixkey_new`, and
+/// `out` must be null or point to at least `out_len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn vaixkey_process(
+    engine: *mut VaixkeyEngine,
+    codepoint: u32,
+    out: *mut c_char,
+    out_len: usize,
+) -> i32 {
+    let Some(handle) = engine.as_mut() else {
+        return VAIXKEY_ERR_NULL;
+    };
+    if out.is_null() {
+        return VAIXKEY_ERR_NULL;
+    }
+    let Some(key) = char::from_u32(codepoint) else {
+        return VAIXKEY_ERR_INVALID;
+    };
+
+    let (backspaces, text) = match handle.engine.process(key) {
+        ProcessResult::PassThrough(c) => (0, c.to_string()),
+        ProcessResult::Output(text) => (0, text),
+        ProcessResult::Replace { backspaces, text } => (backspaces, text),
+    };
+
+    let bytes = text.as_bytes();
+    if bytes.len() >= out_len {
+        return VAIXKEY_ERR_BUFFER_TOO_SMALL;
+    }
+    std::ptr::copy_nonoverlapping(bytes.as_ptr(), out as *mut u8, bytes.len());
+    *out.add(bytes.len()) = 0;
+
+    backspaces as i32
+}
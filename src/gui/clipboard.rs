@@ -0,0 +1,57 @@
+// General pasteboard access, used to hand engine state to the user for bug
+// reports without typing it anywhere, to convert copied text and to inject
+// replacements by pasting (`injection_mode = "Paste"`)
+
+use cocoa::appkit::{NSPasteboard, NSPasteboardTypeString};
+use cocoa::base::{id, nil, NO};
+use cocoa::foundation::NSString;
+use objc::{msg_send, sel, sel_impl};
+use std::ffi::CStr;
+
+/// Replace the clipboard contents with `text`
+pub fn copy(text: &str) -> Result<(), String> {
+    write_string(general_pasteboard()?, text)
+}
+
+/// The clipboard's text, or None if it holds something else (an image,
+/// files) or nothing
+pub fn paste() -> Result<Option<String>, String> {
+    Ok(read_string(general_pasteboard()?))
+}
+
+fn general_pasteboard() -> Result<id, String> {
+    let pasteboard = unsafe { NSPasteboard::generalPasteboard(nil) };
+    if pasteboard == nil {
+        return Err("no general pasteboard".to_string());
+    }
+    Ok(pasteboard)
+}
+
+/// Replace `pasteboard`'s contents with `text`
+pub fn write_string(pasteboard: id, text: &str) -> Result<(), String> {
+    unsafe {
+        pasteboard.clearContents();
+        let string = NSString::alloc(nil).init_str(text);
+        let ok = pasteboard.setString_forType(string, NSPasteboardTypeString);
+        let _: () = msg_send![string, release];
+        if ok == NO {
+            return Err("the pasteboard refused the text".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// `pasteboard`'s text, or None if it holds no text
+pub fn read_string(pasteboard: id) -> Option<String> {
+    unsafe {
+        let string = pasteboard.stringForType(NSPasteboardTypeString);
+        if string == nil {
+            return None;
+        }
+        let bytes = string.UTF8String();
+        if bytes.is_null() {
+            return None;
+        }
+        Some(CStr::from_ptr(bytes).to_string_lossy().into_owned())
+    }
+}
@@ -1,28 +1,110 @@
 // GUI module for VaixKey settings and status display
 // Native macOS GUI implementation
 
-use crate::config::Config;
-use log::info;
+#[cfg(target_os = "macos")]
+pub(crate) mod clipboard;
+mod notification;
+pub mod sound;
+#[cfg(target_os = "macos")]
+mod preedit;
+#[cfg(target_os = "macos")]
+mod service;
+
+use crate::config::{Config, FeedbackKind, InputMethodType};
+use crate::i18n::t;
+use log::{error, info};
 use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use vaixkey::input_method::InputMethodEngine;
+use vaixkey::lock;
+
+pub use notification::{NotificationThrottle, COALESCE_WINDOW};
+
+#[cfg(target_os = "macos")]
+pub use preedit::{caret_position, run_event_loop};
+#[cfg(target_os = "macos")]
+use core_graphics::geometry::CGPoint;
+
+/// Settings page written by `show_settings`, removed on shutdown
+pub const SETTINGS_HTML_PATH: &str = "/tmp/vaixkey_settings.html";
+
+/// How often `watch_mode` looks at the engine's mode
+pub const MODE_POLL_INTERVAL: Duration = Duration::from_millis(200);
 
 pub struct GuiManager {
     // GUI state management
+    show_notifications: bool,
+    toggle_feedback: FeedbackKind,
+    throttle: Arc<Mutex<NotificationThrottle>>,
 }
 
 impl GuiManager {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            show_notifications: true,
+            toggle_feedback: FeedbackKind::None,
+            throttle: Arc::new(Mutex::new(NotificationThrottle::new())),
+        }
+    }
+
+    /// Follow the `show_notifications` config option
+    pub fn set_show_notifications(&mut self, enabled: bool) {
+        self.show_notifications = enabled;
+    }
+
+    /// Follow the `toggle_feedback` config option
+    pub fn set_toggle_feedback(&mut self, kind: FeedbackKind) {
+        self.toggle_feedback = kind;
+    }
+
+    /// What `mode_toggled` gives: the configured kind, except that a
+    /// notification is nothing while `show_notifications` is off
+    pub fn feedback_for_toggle(&self) -> FeedbackKind {
+        match self.toggle_feedback {
+            FeedbackKind::Notification if !self.show_notifications => FeedbackKind::None,
+            kind => kind,
+        }
+    }
+
+    /// Tell the user Vietnamese mode was just switched on or off
+    pub async fn mode_toggled(&self, is_vietnamese: bool) -> Result<(), Box<dyn std::error::Error>> {
+        match self.feedback_for_toggle() {
+            FeedbackKind::None => {}
+            FeedbackKind::Sound => sound::play(sound::sound_for(is_vietnamese))?,
+            FeedbackKind::Notification => self.show_status_indicator(is_vietnamese).await?,
+        }
+        Ok(())
+    }
+
+    /// Give `mode_toggled` feedback whenever the engine's mode changes,
+    /// whichever path changed it (such as the JSON-RPC `toggle`), checking
+    /// every `MODE_POLL_INTERVAL` until the process exits
+    pub fn watch_mode(self, engine: Arc<Mutex<InputMethodEngine>>) {
+        tokio::spawn(async move {
+            let mut was_vietnamese = lock(&engine).is_vietnamese_mode();
+            loop {
+                tokio::time::sleep(MODE_POLL_INTERVAL).await;
+                let is_vietnamese = lock(&engine).is_vietnamese_mode();
+                if is_vietnamese != was_vietnamese {
+                    was_vietnamese = is_vietnamese;
+                    if let Err(e) = self.mode_toggled(is_vietnamese).await {
+                        error!("Failed to give toggle feedback: {}", e);
+                    }
+                }
+            }
+        });
     }
 
-    pub async fn show_settings(&self) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn show_settings(&self, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
         info!("Opening settings interface");
 
         // Create a simple HTML settings page and open it in the default browser
-        self.create_settings_html().await?;
+        self.create_settings_html(config).await?;
 
         // Open the settings page
         Command::new("open")
-            .arg("file:///tmp/vaixkey_settings.html")
+            .arg(format!("file://{}", SETTINGS_HTML_PATH))
             .spawn()?;
 
         Ok(())
@@ -32,21 +114,148 @@ impl GuiManager {
         let mode_text = if is_vietnamese { "Vietnamese" } else { "English" };
         info!("Status: {} mode active", mode_text);
 
-        // For now, just show a notification
-        self.show_notification(&format!("VaixKey: {} Mode", mode_text)).await?;
+        if self.show_notifications {
+            let message = if is_vietnamese { t!("notify.vietnamese_mode") } else { t!("notify.english_mode") };
+            self.show_notification(message).await?;
+        }
 
         Ok(())
     }
 
+    /// Show `message` at once, outside the status coalescing, for problems
+    /// the user has to act on. Still silenced by `show_notifications = false`.
+    pub fn show_alert(&self, message: &str) {
+        if self.show_notifications {
+            if let Err(e) = send_notification(message) {
+                error!("Failed to show notification: {}", e);
+            }
+        }
+    }
+
+    /// Put `text` on the clipboard, replacing what was there
+    pub fn copy_to_clipboard(&self, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+        #[cfg(target_os = "macos")]
+        {
+            clipboard::copy(text)?;
+            Ok(())
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = text;
+            Err("copying to the clipboard is only supported on macOS".into())
+        }
+    }
+
+    /// The clipboard's text, or None if it doesn't hold text
+    pub fn read_clipboard(&self) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        #[cfg(target_os = "macos")]
+        {
+            Ok(clipboard::paste()?)
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            Err("reading the clipboard is only supported on macOS".into())
+        }
+    }
+
+    /// Replace the clipboard's text with `convert` of it. Returns the new
+    /// text, or None if the clipboard held no text and was left alone. If
+    /// writing fails the original text is put back.
+    pub fn convert_clipboard(
+        &self,
+        convert: impl Fn(&str) -> String,
+    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let Some(original) = self.read_clipboard()? else {
+            return Ok(None);
+        };
+        let converted = convert(&original);
+        if converted != original {
+            if let Err(e) = self.copy_to_clipboard(&converted) {
+                let _ = self.copy_to_clipboard(&original);
+                return Err(e);
+            }
+        }
+        Ok(Some(converted))
+    }
+
+    /// Show the word being composed under the caret
+    #[cfg(target_os = "macos")]
+    pub fn show_preedit(&self, text: &str, caret: CGPoint) {
+        preedit::show(text, caret);
+    }
+
+    /// Hide the composing overlay once the word is committed
+    #[cfg(target_os = "macos")]
+    pub fn hide_preedit(&self) {
+        preedit::hide();
+    }
+
+    /// Offer "Convert Selection to Vietnamese" in the Services menu, passing
+    /// the selection through `convert`. Call on the main thread before
+    /// `run_event_loop`; needs the bundle's NSServices entry.
+    #[cfg(target_os = "macos")]
+    pub fn register_services(&self, convert: impl Fn(&str) -> String + Send + Sync + 'static) {
+        service::register(Box::new(convert));
+    }
+
     pub async fn update_config(&self, config: Config) -> Result<(), Box<dyn std::error::Error>> {
         info!("Updating configuration through GUI");
         config.save()?;
         Ok(())
     }
 
-    async fn create_settings_html(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let html_content = r#"
-<!DOCTYPE html>
+    /// Write the settings page for `config` to `SETTINGS_HTML_PATH`
+    async fn create_settings_html(&self, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(SETTINGS_HTML_PATH, settings_html(config))?;
+        Ok(())
+    }
+
+    /// Queue a notification; one fired within `COALESCE_WINDOW` of the next
+    /// is replaced by it
+    async fn show_notification(&self, message: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let due = self
+            .throttle
+            .lock()
+            .map_err(|_| "notification throttle poisoned")?
+            .submit(message.to_string(), Instant::now());
+
+        let throttle = self.throttle.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep_until(due.into()).await;
+            let message = throttle.lock().ok().and_then(|mut throttle| throttle.take_due(Instant::now()));
+            if let Some(message) = message {
+                if let Err(e) = send_notification(&message) {
+                    error!("Failed to show notification: {}", e);
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+/// The settings page showing `config`: its input method selected, its
+/// hotkeys and its checkboxes
+pub fn settings_html(config: &Config) -> String {
+    let selected = |method| if config.input_method == method { "selected" } else { "" };
+    let checked = |on| if on { "checked" } else { "" };
+    SETTINGS_TEMPLATE
+        .replace("{{TELEX_SELECTED}}", selected(InputMethodType::Telex))
+        .replace("{{VNI_SELECTED}}", selected(InputMethodType::Vni))
+        .replace("{{SIMPLE_TELEX_SELECTED}}", selected(InputMethodType::SimpleTelex))
+        .replace("{{TOGGLE_HOTKEY}}", &escape_html(&config.hotkeys.toggle_vietnamese))
+        .replace("{{SWITCH_HOTKEY}}", &escape_html(&config.hotkeys.switch_input_method))
+        .replace("{{AUTO_START}}", checked(config.auto_start))
+        .replace("{{SHOW_STATUS_BAR}}", checked(config.show_status_bar))
+        .replace("{{SHOW_NOTIFICATIONS}}", checked(config.show_notifications))
+}
+
+/// `text` made safe inside an attribute value or element
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+const SETTINGS_TEMPLATE: &str = r#"<!DOCTYPE html>
 <html lang="en">
 <head>
     <meta charset="UTF-8">
@@ -141,9 +350,9 @@ impl GuiManager {
             <div class="form-group">
                 <label for="input-method">Select Input Method:</label>
                 <select id="input-method">
-                    <option value="telex" selected>Telex (aa → â, aw → ă)</option>
-                    <option value="vni">VNI (a8 → â, a6 → ă)</option>
-                    <option value="simple-telex">Simple Telex</option>
+                    <option value="telex" {{TELEX_SELECTED}}>Telex (aa → â, aw → ă)</option>
+                    <option value="vni" {{VNI_SELECTED}}>VNI (a6 → â, a8 → ă)</option>
+                    <option value="simple-telex" {{SIMPLE_TELEX_SELECTED}}>Simple Telex</option>
                 </select>
             </div>
         </div>
@@ -152,11 +361,11 @@ impl GuiManager {
             <h2>Hotkeys</h2>
             <div class="form-group">
                 <label for="toggle-key">Toggle Vietnamese Mode:</label>
-                <input type="text" id="toggle-key" value="Ctrl+Shift" readonly>
+                <input type="text" id="toggle-key" value="{{TOGGLE_HOTKEY}}" readonly>
             </div>
             <div class="form-group">
                 <label for="switch-key">Switch Input Method:</label>
-                <input type="text" id="switch-key" value="Ctrl+Alt+V" readonly>
+                <input type="text" id="switch-key" value="{{SWITCH_HOTKEY}}" readonly>
             </div>
         </div>
 
@@ -164,13 +373,19 @@ impl GuiManager {
             <h2>Preferences</h2>
             <div class="form-group">
                 <div class="checkbox-group">
-                    <input type="checkbox" id="auto-start">
+                    <input type="checkbox" id="auto-start" {{AUTO_START}}>
                     <label for="auto-start">Start VaixKey automatically at login</label>
                 </div>
             </div>
             <div class="form-group">
                 <div class="checkbox-group">
-                    <input type="checkbox" id="show-notifications" checked>
+                    <input type="checkbox" id="show-status-bar" {{SHOW_STATUS_BAR}}>
+                    <label for="show-status-bar">Show the menu bar icon</label>
+                </div>
+            </div>
+            <div class="form-group">
+                <div class="checkbox-group">
+                    <input type="checkbox" id="show-notifications" {{SHOW_NOTIFICATIONS}}>
                     <label for="show-notifications">Show status notifications</label>
                 </div>
             </div>
@@ -191,6 +406,7 @@ impl GuiManager {
             if (confirm('Reset all settings to defaults?')) {
                 document.getElementById('input-method').value = 'telex';
                 document.getElementById('auto-start').checked = false;
+                document.getElementById('show-status-bar').checked = true;
                 document.getElementById('show-notifications').checked = true;
                 alert('Settings reset to defaults!');
             }
@@ -203,22 +419,17 @@ impl GuiManager {
     </script>
 </body>
 </html>
-        "#;
+"#;
 
-        std::fs::write("/tmp/vaixkey_settings.html", html_content)?;
-        Ok(())
-    }
+fn send_notification(message: &str) -> std::io::Result<()> {
+    // Use macOS native notifications
+    Command::new("osascript")
+        .arg("-e")
+        .arg(format!(
+            "display notification \"{}\" with title \"VaixKey\"",
+            message
+        ))
+        .spawn()?;
 
-    async fn show_notification(&self, message: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // Use macOS native notifications
-        Command::new("osascript")
-            .arg("-e")
-            .arg(&format!(
-                "display notification \"{}\" with title \"VaixKey\"",
-                message
-            ))
-            .spawn()?;
-
-        Ok(())
-    }
-}
\ No newline at end of file
+    Ok(())
+}
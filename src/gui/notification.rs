@@ -0,0 +1,38 @@
+// Status notifications, coalesced: toggling the mode several times in quick
+// succession shows only the last state instead of a burst of banners.
+// Notification Center already holds banners back while a Focus mode is on;
+// `show_notifications = false` turns them off altogether.
+
+use std::time::{Duration, Instant};
+
+/// Notifications fired closer together than this are merged into the last one
+pub const COALESCE_WINDOW: Duration = Duration::from_millis(500);
+
+/// Trailing-edge debounce: each message restarts the window, and the last
+/// message is released once the window passes without a newer one
+#[derive(Debug, Default)]
+pub struct NotificationThrottle {
+    pending: Option<(String, Instant)>,
+}
+
+impl NotificationThrottle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `message`, replacing any that hasn't been shown yet. Returns
+    /// when it becomes due.
+    pub fn submit(&mut self, message: String, now: Instant) -> Instant {
+        let due = now + COALESCE_WINDOW;
+        self.pending = Some((message, due));
+        due
+    }
+
+    /// The queued message, if its window has passed
+    pub fn take_due(&mut self, now: Instant) -> Option<String> {
+        match &self.pending {
+            Some((_, due)) if now >= *due => self.pending.take().map(|(message, _)| message),
+            _ => None,
+        }
+    }
+}
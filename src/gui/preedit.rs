@@ -0,0 +1,209 @@
+// Pre-edit overlay: a small floating window under the caret that shows the
+// word being composed, underlined like other IMEs do.
+// AppKit objects may only be touched on the main thread, so every request is
+// queued onto the main dispatch queue; the main thread must be running
+// `run_event_loop` for the overlay to appear.
+
+use cocoa::appkit::{
+    NSApp, NSApplication, NSApplicationActivationPolicy, NSBackingStoreType, NSWindow,
+    NSWindowStyleMask,
+};
+use cocoa::base::{id, nil, NO, YES};
+use cocoa::foundation::{NSPoint, NSRect, NSSize, NSString};
+use core_foundation::base::{CFRelease, CFTypeRef, TCFType};
+use core_foundation::string::{CFString, CFStringRef};
+use core_graphics::geometry::{CGPoint, CGRect};
+use objc::{class, msg_send, sel, sel_impl};
+use std::cell::RefCell;
+use std::ffi::c_void;
+
+/// Floats above normal windows, like the system's own input panels
+const STATUS_WINDOW_LEVEL: i64 = 25;
+/// Panels with this style never take focus from the app being typed into
+const NONACTIVATING_PANEL_MASK: u64 = 1 << 7;
+/// Gap between the caret and the overlay, in points
+const CARET_GAP: f64 = 4.0;
+const FONT_SIZE: f64 = 15.0;
+
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn AXUIElementCreateSystemWide() -> CFTypeRef;
+    fn AXUIElementCopyAttributeValue(element: CFTypeRef, attribute: CFStringRef, value: *mut CFTypeRef) -> i32;
+    fn AXUIElementCopyParameterizedAttributeValue(
+        element: CFTypeRef,
+        attribute: CFStringRef,
+        parameter: CFTypeRef,
+        result: *mut CFTypeRef,
+    ) -> i32;
+    fn AXValueGetValue(value: CFTypeRef, value_type: u32, value_ptr: *mut c_void) -> bool;
+}
+
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {
+    static NSUnderlineStyleAttributeName: id;
+}
+
+extern "C" {
+    // What `dispatch_get_main_queue()` expands to
+    static _dispatch_main_q: c_void;
+    fn dispatch_async_f(queue: *const c_void, context: *mut c_void, work: extern "C" fn(*mut c_void));
+}
+
+const AX_SUCCESS: i32 = 0;
+const AX_VALUE_CGRECT_TYPE: u32 = 3;
+
+enum Request {
+    Show { text: String, caret: CGPoint },
+    Hide,
+}
+
+thread_local! {
+    // (panel, text field), created on first use; main thread only
+    static OVERLAY: RefCell<Option<(id, id)>> = const { RefCell::new(None) };
+}
+
+/// Show `text` just below `caret` (screen coordinates, origin at the top
+/// left of the main display, as reported by the Accessibility API)
+pub fn show(text: &str, caret: CGPoint) {
+    post(Request::Show { text: text.to_string(), caret });
+}
+
+pub fn hide() {
+    post(Request::Hide);
+}
+
+/// Bottom-left corner of the caret in the focused text field, if the focused
+/// app exposes it through the Accessibility API
+pub fn caret_position() -> Option<CGPoint> {
+    unsafe {
+        let system = AXUIElementCreateSystemWide();
+        if system.is_null() {
+            return None;
+        }
+        let focused = copy_attribute(system, "AXFocusedUIElement");
+        CFRelease(system);
+        let focused = focused?;
+
+        let range = copy_attribute(focused, "AXSelectedTextRange");
+        let bounds = range.and_then(|range| {
+            let name = CFString::from_static_string("AXBoundsForRange");
+            let mut value: CFTypeRef = std::ptr::null();
+            let status = AXUIElementCopyParameterizedAttributeValue(
+                focused,
+                name.as_concrete_TypeRef(),
+                range,
+                &mut value,
+            );
+            CFRelease(range);
+            (status == AX_SUCCESS && !value.is_null()).then_some(value)
+        });
+        CFRelease(focused);
+        let bounds = bounds?;
+
+        let mut rect = CGRect::default();
+        let ok = AXValueGetValue(bounds, AX_VALUE_CGRECT_TYPE, &mut rect as *mut CGRect as *mut c_void);
+        CFRelease(bounds);
+
+        // Some apps report an empty rect at the origin when they don't know
+        (ok && (rect.origin.x != 0.0 || rect.origin.y != 0.0))
+            .then(|| CGPoint::new(rect.origin.x, rect.origin.y + rect.size.height))
+    }
+}
+
+/// Run the AppKit event loop on the current (main) thread. Never returns.
+pub fn run_event_loop() {
+    unsafe {
+        let app = NSApplication::sharedApplication(nil);
+        // No Dock icon or menu bar: VaixKey stays a background agent
+        app.setActivationPolicy_(NSApplicationActivationPolicy::NSApplicationActivationPolicyAccessory);
+        NSApp().run();
+    }
+}
+
+unsafe fn copy_attribute(element: CFTypeRef, attribute: &'static str) -> Option<CFTypeRef> {
+    let name = CFString::from_static_string(attribute);
+    let mut value: CFTypeRef = std::ptr::null();
+    let status = AXUIElementCopyAttributeValue(element, name.as_concrete_TypeRef(), &mut value);
+    (status == AX_SUCCESS && !value.is_null()).then_some(value)
+}
+
+fn post(request: Request) {
+    let context = Box::into_raw(Box::new(request)) as *mut c_void;
+    unsafe {
+        dispatch_async_f(&_dispatch_main_q, context, handle_request);
+    }
+}
+
+extern "C" fn handle_request(context: *mut c_void) {
+    let request = unsafe { Box::from_raw(context as *mut Request) };
+    unsafe {
+        match *request {
+            Request::Show { text, caret } => show_on_main(&text, caret),
+            Request::Hide => {
+                OVERLAY.with(|overlay| {
+                    if let Some((panel, _)) = *overlay.borrow() {
+                        panel.orderOut_(nil);
+                    }
+                });
+            }
+        }
+    }
+}
+
+unsafe fn show_on_main(text: &str, caret: CGPoint) {
+    let (panel, field) = OVERLAY.with(|overlay| *overlay.borrow_mut().get_or_insert_with(|| create_overlay()));
+
+    // Underlined, like marked text in native input methods
+    let string = NSString::alloc(nil).init_str(text);
+    let one: id = msg_send![class!(NSNumber), numberWithInteger: 1i64];
+    let attributes: id = msg_send![class!(NSDictionary), dictionaryWithObject: one forKey: NSUnderlineStyleAttributeName];
+    let attributed: id = msg_send![class!(NSAttributedString), alloc];
+    let attributed: id = msg_send![attributed, initWithString: string attributes: attributes];
+    let _: () = msg_send![field, setAttributedStringValue: attributed];
+    let _: () = msg_send![attributed, release];
+    let _: () = msg_send![string, release];
+    let _: () = msg_send![field, sizeToFit];
+
+    // Accessibility coordinates start at the top left of the main display,
+    // AppKit's at the bottom left
+    let field_frame: NSRect = msg_send![field, frame];
+    let screens: id = msg_send![class!(NSScreen), screens];
+    let main_screen: id = msg_send![screens, objectAtIndex: 0usize];
+    let screen_frame: NSRect = msg_send![main_screen, frame];
+    let size = NSSize::new(field_frame.size.width + 8.0, field_frame.size.height + 4.0);
+    let origin = NSPoint::new(
+        caret.x,
+        screen_frame.size.height - caret.y - CARET_GAP - size.height,
+    );
+
+    let _: () = msg_send![field, setFrameOrigin: NSPoint::new(4.0, 2.0)];
+    panel.setFrame_display_(NSRect::new(origin, size), YES);
+    panel.orderFrontRegardless();
+}
+
+unsafe fn create_overlay() -> (id, id) {
+    let rect = NSRect::new(NSPoint::new(0.0, 0.0), NSSize::new(80.0, 24.0));
+    let panel: id = msg_send![class!(NSPanel), alloc];
+    let panel = panel.initWithContentRect_styleMask_backing_defer_(
+        rect,
+        NSWindowStyleMask::from_bits_truncate(NONACTIVATING_PANEL_MASK),
+        NSBackingStoreType::NSBackingStoreBuffered,
+        NO,
+    );
+    panel.setLevel_(STATUS_WINDOW_LEVEL);
+    panel.setIgnoresMouseEvents_(YES);
+    panel.setHasShadow_(YES);
+    let _: () = msg_send![panel, setHidesOnDeactivate: NO];
+    let background: id = msg_send![class!(NSColor), textBackgroundColor];
+    panel.setBackgroundColor_(background);
+
+    let content = panel.contentView();
+    let empty = NSString::alloc(nil).init_str("");
+    let field: id = msg_send![class!(NSTextField), labelWithString: empty];
+    let _: () = msg_send![empty, release];
+    let font: id = msg_send![class!(NSFont), systemFontOfSize: FONT_SIZE];
+    let _: () = msg_send![field, setFont: font];
+    let _: () = msg_send![content, addSubview: field];
+
+    (panel, field)
+}
@@ -0,0 +1,79 @@
+// "Convert Selection to Vietnamese" in the Services menu: macOS hands the
+// selected text to the provider registered here on a pasteboard, and the
+// converted text written back replaces the selection.
+// The menu item comes from the NSServices entry in the bundle's Info.plist
+// (see USAGE.md), so this only works when running from a `.app`. Requests
+// arrive through the AppKit event loop, which must be running on the main
+// thread.
+
+use super::clipboard;
+use cocoa::appkit::NSApplication;
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSAutoreleasePool, NSString};
+use log::{info, warn};
+use objc::declare::ClassDecl;
+use objc::runtime::{Object, Sel};
+use objc::{class, msg_send, sel, sel_impl};
+use std::ffi::c_void;
+use std::sync::OnceLock;
+
+/// Converts the text of a Services request
+pub type Converter = Box<dyn Fn(&str) -> String + Send + Sync>;
+
+static CONVERTER: OnceLock<Converter> = OnceLock::new();
+
+extern "C" {
+    fn NSUpdateDynamicServices();
+}
+
+/// Serve Services requests with `convert`. Call on the main thread, before
+/// `run_event_loop`; later calls keep the first converter.
+pub fn register(convert: Converter) {
+    if CONVERTER.set(convert).is_err() {
+        warn!("Services provider already registered");
+        return;
+    }
+    unsafe {
+        let mut decl = ClassDecl::new("VaixKeyServiceProvider", class!(NSObject))
+            .expect("VaixKeyServiceProvider is declared once");
+        // NSMessage "convertSelection" in Info.plist
+        decl.add_method(
+            sel!(convertSelection:userData:error:),
+            convert_selection as extern "C" fn(&Object, Sel, id, id, *mut c_void),
+        );
+        let provider: id = msg_send![decl.register(), new];
+
+        let app = NSApplication::sharedApplication(nil);
+        let _: () = msg_send![app, setServicesProvider: provider];
+        // Pick up the Info.plist entries without logging out
+        NSUpdateDynamicServices();
+    }
+    info!("Registered the Services provider");
+}
+
+/// `-convertSelection:userData:error:`: replace the pasteboard's text with
+/// its conversion, or report why not through `error`
+extern "C" fn convert_selection(_this: &Object, _cmd: Sel, pasteboard: id, _user_data: id, error: *mut c_void) {
+    let fail = |message: &str| {
+        warn!("Services request failed: {}", message);
+        if !error.is_null() {
+            unsafe {
+                let string = NSString::alloc(nil).init_str(message).autorelease();
+                *(error as *mut id) = string;
+            }
+        }
+    };
+
+    let Some(convert) = CONVERTER.get() else {
+        return fail("VaixKey has no converter registered");
+    };
+    let Some(text) = clipboard::read_string(pasteboard) else {
+        return fail("The selection holds no text");
+    };
+    let converted = convert(&text);
+    if let Err(e) = clipboard::write_string(pasteboard, &converted) {
+        return fail(&e);
+    }
+    info!("Converted a Services selection: {:?}", converted);
+}
+
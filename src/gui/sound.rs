@@ -0,0 +1,25 @@
+// Mode sounds for `toggle_feedback = "Sound"`: one system sound when
+// Vietnamese mode comes on and another when it goes off, so the mode can be
+// told by ear. Played with `afplay`, which ships with macOS.
+
+use std::process::Command;
+
+/// Played when Vietnamese mode is switched on
+pub const VIETNAMESE_SOUND: &str = "/System/Library/Sounds/Tink.aiff";
+/// Played when it is switched off
+pub const ENGLISH_SOUND: &str = "/System/Library/Sounds/Pop.aiff";
+
+/// The sound for switching to the given mode
+pub fn sound_for(is_vietnamese: bool) -> &'static str {
+    if is_vietnamese {
+        VIETNAMESE_SOUND
+    } else {
+        ENGLISH_SOUND
+    }
+}
+
+/// Start playing `path` and return without waiting for it to finish
+pub fn play(path: &str) -> std::io::Result<()> {
+    Command::new("afplay").arg(path).spawn()?;
+    Ok(())
+}
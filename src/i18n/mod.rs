@@ -0,0 +1,278 @@
+// Messages of `--status`, the permission guides and notifications in
+// English and Vietnamese (`language` in the config, `--lang` on the command
+// line). `t!("status.title")` looks a key up in the selected language; a key
+// missing there falls back to English, and one missing everywhere shows as
+// itself so it is easy to spot.
+
+use std::sync::Mutex;
+use vaixkey::config::Language;
+
+/// Language messages are shown in, set once at startup
+static LANGUAGE: Mutex<Language> = Mutex::new(Language::En);
+
+/// The message for `key` in the selected language
+macro_rules! t {
+    ($key:expr) => {
+        $crate::i18n::text($key)
+    };
+}
+pub(crate) use t;
+
+pub fn set_language(language: Language) {
+    if let Ok(mut current) = LANGUAGE.lock() {
+        *current = language;
+    }
+}
+
+pub fn language() -> Language {
+    LANGUAGE.lock().map(|language| *language).unwrap_or_default()
+}
+
+/// `t!` without the macro
+pub fn text(key: &'static str) -> &'static str {
+    lookup(language(), key)
+}
+
+/// The message for `key` in `language`, falling back to English and then to
+/// the key itself
+pub fn lookup(language: Language, key: &'static str) -> &'static str {
+    find(messages(language), key).or_else(|| find(EN, key)).unwrap_or(key)
+}
+
+/// Every message of `language`, by key
+pub fn messages(language: Language) -> &'static [(&'static str, &'static str)] {
+    match language {
+        Language::En => EN,
+        Language::Vi => VI,
+    }
+}
+
+fn find(messages: &[(&'static str, &'static str)], key: &str) -> Option<&'static str> {
+    messages.iter().find(|(k, _)| *k == key).map(|(_, message)| *message)
+}
+
+const EN: &[(&str, &str)] = &[
+    // --status
+    ("status.title", "🇻🇳 VaixKey Status Report"),
+    ("status.configuration", "📋 Configuration:"),
+    ("status.file", "File"),
+    ("status.input_method", "Input Method"),
+    ("status.auto_start", "Auto Start"),
+    ("status.show_status_bar", "Show Status Bar"),
+    ("status.show_notifications", "Show Notifications"),
+    ("status.toggle_hotkey", "Toggle Hotkey"),
+    ("status.switch_hotkey", "Switch Hotkey"),
+    ("status.engine", "🔧 Engine Status:"),
+    ("status.vietnamese_mode", "Vietnamese Mode"),
+    ("status.active", "✅ Active"),
+    ("status.inactive", "❌ Inactive"),
+    ("status.processing_test", "🧪 Vietnamese Processing Test:"),
+    ("status.engine_initialized", "Engine initialized"),
+    ("status.ready", "✅ VaixKey is properly configured and ready!"),
+    ("status.hint_test", "Run `cargo run -- --test` to test input processing"),
+    ("status.hint_settings", "Run `cargo run -- --settings` to open settings"),
+    // Permissions, shared by the guides
+    ("perm.input_monitoring", "Input Monitoring"),
+    ("perm.accessibility", "Accessibility"),
+    ("perm.granted", "✅ Granted"),
+    ("perm.not_granted", "❌ Not granted"),
+    ("perm.opening_accessibility", "📱 Opening System Settings → Accessibility..."),
+    ("perm.opening_input_monitoring", "📱 Opening System Settings → Input Monitoring..."),
+    ("perm.requesting", "🔐 Requesting Accessibility permission..."),
+    ("perm.already_granted", "✅ Permission already granted! VaixKey is ready."),
+    ("perm.request_sent_check", "⏳ Permission request sent. Check System Settings if no dialog appeared."),
+    ("perm.then_opening", "Opening System Settings → Accessibility..."),
+    // --permissions
+    ("check.title", "🔐 VaixKey Permission Status"),
+    ("check.checking", "📋 Checking macOS security permissions..."),
+    ("check.denied_capture", "❌ DENIED - Required for keystroke capture"),
+    ("check.denied_injection", "❌ DENIED - Required for text injection"),
+    ("check.ready", "🎯 VaixKey is ready for keyboard capture!"),
+    ("check.hint_debug", "Run `cargo run -- --debug` to test keystroke logging"),
+    ("check.cannot_function", "⚠️  VaixKey cannot function without these permissions"),
+    ("check.hint_setup", "Run `cargo run -- --setup-permissions` for setup instructions"),
+    // --security-status
+    ("security.title", "🔒 VaixKey Security Status Report"),
+    ("security.system", "💻 System Information:"),
+    ("security.permissions", "🔐 Permission Status:"),
+    ("security.granted", "✅ GRANTED"),
+    ("security.denied", "❌ DENIED"),
+    ("security.application", "📱 Application Status:"),
+    ("security.running_as", "Running as"),
+    ("security.unknown", "Unknown"),
+    ("security.process_id", "Process ID"),
+    ("security.required_actions", "📋 Required Actions:"),
+    ("security.grant_input_monitoring", "• Grant Input Monitoring permission in System Preferences"),
+    ("security.grant_accessibility", "• Grant Accessibility permission in System Preferences"),
+    ("security.hint_setup", "• Run `cargo run -- --setup-permissions` for detailed instructions"),
+    ("security.all_granted", "🎉 All permissions granted - VaixKey is ready!"),
+    // --setup-permissions
+    ("setup.title", "🔧 VaixKey Permission Setup Guide"),
+    ("setup.current", "📊 Current Permission Status:"),
+    ("setup.all_granted", "🎉 All permissions are already granted!"),
+    ("setup.ready", "VaixKey is ready to use."),
+    ("setup.hint_run", "Run `cargo run` to start VaixKey."),
+    ("setup.needs", "VaixKey needs macOS Accessibility permission to function."),
+    ("setup.same_permission", "(Both Input Monitoring and Accessibility use the same permission)"),
+    ("setup.options", "🚀 Quick Setup Options:"),
+    ("setup.option_list", "  1. [Recommended] Let VaixKey request permission automatically
+  2. Open System Settings → Privacy & Security → Accessibility
+  3. Open System Settings → Privacy & Security → Input Monitoring
+  4. Show manual setup instructions
+  5. Exit"),
+    ("setup.choose", "Choose an option (1-5): "),
+    ("setup.dialog", "   A system dialog should appear asking for permission."),
+    ("setup.granted", "✅ Permission granted! VaixKey is ready to use."),
+    ("setup.request_sent", "⏳ Permission request sent."),
+    ("setup.no_dialog", "📋 If no dialog appeared, please:
+   1. Open System Settings → Privacy & Security → Accessibility
+   2. Find and enable 'Terminal' (or 'vaixkey' if running the built app)
+   3. You may need to click the lock 🔒 icon first"),
+    ("setup.opening_settings", "Opening System Settings..."),
+    ("setup.in_settings", "📋 In System Settings:
+   1. Click the lock icon 🔒 if needed
+   2. Find 'Terminal' (or 'vaixkey') in the list
+   3. Toggle the switch to enable it
+   4. Restart VaixKey after granting permission"),
+    ("setup.exiting", "Exiting setup guide."),
+    ("setup.verify", "🧪 After granting permissions, verify with:"),
+    // Manual setup instructions
+    ("manual.title", "📋 Manual Setup Instructions"),
+    ("manual.ventura", "For macOS Ventura (13.0) and later:"),
+    ("manual.ventura_steps", "1. Click the Apple menu (🍎) → System Settings
+2. Click 'Privacy & Security' in the sidebar
+3. Scroll down and click 'Accessibility'
+4. Click the toggle next to 'Terminal' to enable it
+   (You may need to click the lock and enter your password)
+5. Also check 'Input Monitoring' and enable 'Terminal'"),
+    ("manual.monterey", "For macOS Monterey (12.0) and earlier:"),
+    ("manual.monterey_steps", "1. Click the Apple menu (🍎) → System Preferences
+2. Click 'Security & Privacy'
+3. Click the 'Privacy' tab
+4. Select 'Accessibility' from the left sidebar
+5. Click the lock icon 🔒 and enter your password
+6. Check the box next to 'Terminal'
+7. Select 'Input Monitoring' and check 'Terminal' there too"),
+    ("manual.tips", "💡 Tips:
+   • If 'Terminal' is not listed, run VaixKey once first
+   • If permission doesn't work, try toggling it off and on
+   • You may need to restart Terminal after granting permission
+   • For the built app, look for 'vaixkey' instead of 'Terminal'"),
+    // Notifications
+    ("notify.vietnamese_mode", "VaixKey: Vietnamese Mode"),
+    ("notify.english_mode", "VaixKey: English Mode"),
+    ("notify.injection_failed", "VaixKey: injection failed, buffer reset"),
+];
+
+const VI: &[(&str, &str)] = &[
+    // --status
+    ("status.title", "🇻🇳 Báo cáo trạng thái VaixKey"),
+    ("status.configuration", "📋 Cấu hình:"),
+    ("status.file", "Tệp"),
+    ("status.input_method", "Kiểu gõ"),
+    ("status.auto_start", "Tự khởi động"),
+    ("status.show_status_bar", "Hiện thanh trạng thái"),
+    ("status.show_notifications", "Hiện thông báo"),
+    ("status.toggle_hotkey", "Phím tắt bật/tắt"),
+    ("status.switch_hotkey", "Phím tắt đổi kiểu gõ"),
+    ("status.engine", "🔧 Trạng thái bộ gõ:"),
+    ("status.vietnamese_mode", "Chế độ tiếng Việt"),
+    ("status.active", "✅ Đang bật"),
+    ("status.inactive", "❌ Đang tắt"),
+    ("status.processing_test", "🧪 Thử bộ gõ tiếng Việt:"),
+    ("status.engine_initialized", "Bộ gõ đã khởi tạo"),
+    ("status.ready", "✅ VaixKey đã được cấu hình và sẵn sàng!"),
+    ("status.hint_test", "Chạy `cargo run -- --test` để kiểm tra bộ gõ"),
+    ("status.hint_settings", "Chạy `cargo run -- --settings` để mở phần cài đặt"),
+    // Permissions, shared by the guides
+    ("perm.input_monitoring", "Theo dõi đầu vào"),
+    ("perm.accessibility", "Trợ năng"),
+    ("perm.granted", "✅ Đã cấp"),
+    ("perm.not_granted", "❌ Chưa cấp"),
+    ("perm.opening_accessibility", "📱 Đang mở Cài đặt hệ thống → Trợ năng..."),
+    ("perm.opening_input_monitoring", "📱 Đang mở Cài đặt hệ thống → Theo dõi đầu vào..."),
+    ("perm.requesting", "🔐 Đang xin quyền Trợ năng..."),
+    ("perm.already_granted", "✅ Đã có quyền! VaixKey đã sẵn sàng."),
+    ("perm.request_sent_check", "⏳ Đã gửi yêu cầu cấp quyền. Nếu không thấy hộp thoại, hãy xem Cài đặt hệ thống."),
+    ("perm.then_opening", "Đang mở Cài đặt hệ thống → Trợ năng..."),
+    // --permissions
+    ("check.title", "🔐 Quyền truy cập của VaixKey"),
+    ("check.checking", "📋 Đang kiểm tra quyền bảo mật của macOS..."),
+    ("check.denied_capture", "❌ BỊ TỪ CHỐI - Cần để bắt phím gõ"),
+    ("check.denied_injection", "❌ BỊ TỪ CHỐI - Cần để gõ chữ vào ứng dụng"),
+    ("check.ready", "🎯 VaixKey đã sẵn sàng bắt phím!"),
+    ("check.hint_debug", "Chạy `cargo run -- --debug` để thử ghi lại phím gõ"),
+    ("check.cannot_function", "⚠️  VaixKey không thể hoạt động khi thiếu các quyền này"),
+    ("check.hint_setup", "Chạy `cargo run -- --setup-permissions` để xem hướng dẫn cấp quyền"),
+    // --security-status
+    ("security.title", "🔒 Báo cáo bảo mật của VaixKey"),
+    ("security.system", "💻 Thông tin hệ thống:"),
+    ("security.permissions", "🔐 Trạng thái quyền:"),
+    ("security.granted", "✅ ĐÃ CẤP"),
+    ("security.denied", "❌ BỊ TỪ CHỐI"),
+    ("security.application", "📱 Trạng thái ứng dụng:"),
+    ("security.running_as", "Đang chạy từ"),
+    ("security.unknown", "Không rõ"),
+    ("security.process_id", "Mã tiến trình"),
+    ("security.required_actions", "📋 Việc cần làm:"),
+    ("security.grant_input_monitoring", "• Cấp quyền Theo dõi đầu vào trong Cài đặt hệ thống"),
+    ("security.grant_accessibility", "• Cấp quyền Trợ năng trong Cài đặt hệ thống"),
+    ("security.hint_setup", "• Chạy `cargo run -- --setup-permissions` để xem hướng dẫn chi tiết"),
+    ("security.all_granted", "🎉 Đã cấp đủ quyền - VaixKey đã sẵn sàng!"),
+    // --setup-permissions
+    ("setup.title", "🔧 Hướng dẫn cấp quyền cho VaixKey"),
+    ("setup.current", "📊 Trạng thái quyền hiện tại:"),
+    ("setup.all_granted", "🎉 Đã cấp đủ quyền!"),
+    ("setup.ready", "VaixKey đã sẵn sàng để dùng."),
+    ("setup.hint_run", "Chạy `cargo run` để khởi động VaixKey."),
+    ("setup.needs", "VaixKey cần quyền Trợ năng của macOS để hoạt động."),
+    ("setup.same_permission", "(Theo dõi đầu vào và Trợ năng dùng chung một quyền)"),
+    ("setup.options", "🚀 Cấp quyền nhanh:"),
+    ("setup.option_list", "  1. [Khuyên dùng] Để VaixKey tự xin quyền
+  2. Mở Cài đặt hệ thống → Quyền riêng tư & Bảo mật → Trợ năng
+  3. Mở Cài đặt hệ thống → Quyền riêng tư & Bảo mật → Theo dõi đầu vào
+  4. Xem hướng dẫn cấp quyền thủ công
+  5. Thoát"),
+    ("setup.choose", "Chọn một mục (1-5): "),
+    ("setup.dialog", "   Hộp thoại của hệ thống sẽ hiện ra để hỏi quyền."),
+    ("setup.granted", "✅ Đã được cấp quyền! VaixKey đã sẵn sàng để dùng."),
+    ("setup.request_sent", "⏳ Đã gửi yêu cầu cấp quyền."),
+    ("setup.no_dialog", "📋 Nếu không thấy hộp thoại, hãy:
+   1. Mở Cài đặt hệ thống → Quyền riêng tư & Bảo mật → Trợ năng
+   2. Tìm và bật 'Terminal' (hoặc 'vaixkey' nếu chạy bản đã build)
+   3. Có thể cần bấm vào biểu tượng ổ khóa 🔒 trước"),
+    ("setup.opening_settings", "Đang mở Cài đặt hệ thống..."),
+    ("setup.in_settings", "📋 Trong Cài đặt hệ thống:
+   1. Bấm vào biểu tượng ổ khóa 🔒 nếu cần
+   2. Tìm 'Terminal' (hoặc 'vaixkey') trong danh sách
+   3. Gạt công tắc để bật
+   4. Khởi động lại VaixKey sau khi cấp quyền"),
+    ("setup.exiting", "Thoát hướng dẫn."),
+    ("setup.verify", "🧪 Sau khi cấp quyền, kiểm tra lại bằng:"),
+    // Manual setup instructions
+    ("manual.title", "📋 Hướng dẫn cấp quyền thủ công"),
+    ("manual.ventura", "Với macOS Ventura (13.0) trở lên:"),
+    ("manual.ventura_steps", "1. Bấm menu Apple (🍎) → Cài đặt hệ thống
+2. Bấm 'Quyền riêng tư & Bảo mật' ở thanh bên
+3. Cuộn xuống và bấm 'Trợ năng'
+4. Bấm công tắc cạnh 'Terminal' để bật
+   (Có thể cần bấm ổ khóa và nhập mật khẩu)
+5. Vào cả 'Theo dõi đầu vào' và bật 'Terminal'"),
+    ("manual.monterey", "Với macOS Monterey (12.0) trở về trước:"),
+    ("manual.monterey_steps", "1. Bấm menu Apple (🍎) → Tùy chọn hệ thống
+2. Bấm 'Bảo mật & Quyền riêng tư'
+3. Bấm thẻ 'Quyền riêng tư'
+4. Chọn 'Trợ năng' ở thanh bên trái
+5. Bấm biểu tượng ổ khóa 🔒 và nhập mật khẩu
+6. Đánh dấu ô cạnh 'Terminal'
+7. Chọn 'Theo dõi đầu vào' và cũng đánh dấu 'Terminal' ở đó"),
+    ("manual.tips", "💡 Mẹo:
+   • Nếu không thấy 'Terminal' trong danh sách, hãy chạy VaixKey một lần trước
+   • Nếu cấp quyền rồi mà không được, thử tắt đi rồi bật lại
+   • Có thể cần khởi động lại Terminal sau khi cấp quyền
+   • Với bản đã build, tìm 'vaixkey' thay cho 'Terminal'"),
+    // Notifications
+    ("notify.vietnamese_mode", "VaixKey: Chế độ tiếng Việt"),
+    ("notify.english_mode", "VaixKey: Chế độ tiếng Anh"),
+    ("notify.injection_failed", "VaixKey: không gõ được chữ, đã xóa từ đang gõ"),
+];
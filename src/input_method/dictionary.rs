@@ -0,0 +1,82 @@
+// Bundled Vietnamese word list for the dictionary revert check
+// (`engine.dictionary_revert`) and tone suggestions (`engine.suggestions`).
+// Only built with the `dictionary` feature.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+use unicode_normalization::UnicodeNormalization;
+
+const WORDS: &str = include_str!("vi_words.txt");
+
+static DICTIONARY: OnceLock<HashSet<String>> = OnceLock::new();
+static BY_BASE: OnceLock<HashMap<String, Vec<&'static str>>> = OnceLock::new();
+
+/// Combining tone marks: grave, acute, tilde, hook above, dot below
+const TONE_MARKS: [char; 5] = ['\u{300}', '\u{301}', '\u{303}', '\u{309}', '\u{323}'];
+
+/// Parse the word list on first use; later calls return the same set
+pub fn load() -> &'static HashSet<String> {
+    DICTIONARY.get_or_init(|| {
+        WORDS
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(str::split_whitespace)
+            .map(normalize)
+            .collect()
+    })
+}
+
+/// Whether `word` is a known Vietnamese syllable, ignoring case and where
+/// the tone mark sits
+pub fn contains(word: &str) -> bool {
+    load().contains(&normalize(word))
+}
+
+/// Other words spelled like `word` but for the tone, in word list order
+/// (most frequent first): `chao` gives `chào`, `chảo`, `cháo`, `chạo`
+pub fn tone_variants(word: &str) -> Vec<&'static str> {
+    let by_base = BY_BASE.get_or_init(|| {
+        let mut by_base: HashMap<String, Vec<&'static str>> = HashMap::new();
+        let words = WORDS
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(str::split_whitespace);
+        for entry in words {
+            let variants = by_base.entry(strip_tone(&normalize(entry))).or_default();
+            if !variants.iter().any(|v| normalize(v) == normalize(entry)) {
+                variants.push(entry);
+            }
+        }
+        by_base
+    });
+
+    let key = normalize(word);
+    by_base
+        .get(&strip_tone(&key))
+        .map(|variants| variants.iter().copied().filter(|v| normalize(v) != key).collect())
+        .unwrap_or_default()
+}
+
+/// A normalized word without its trailing tone mark
+fn strip_tone(key: &str) -> String {
+    key.trim_end_matches(TONE_MARKS).to_string()
+}
+
+/// Lowercase and decomposed, with the tone mark moved to the end so that
+/// old (hòa) and modern (hoà) placements compare equal
+fn normalize(word: &str) -> String {
+    let mut tone = None;
+    let mut key: String = word
+        .nfd()
+        .flat_map(char::to_lowercase)
+        .filter(|c| {
+            let is_tone = TONE_MARKS.contains(c);
+            if is_tone {
+                tone = Some(*c);
+            }
+            !is_tone
+        })
+        .collect();
+    key.extend(tone);
+    key
+}
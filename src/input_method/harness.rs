@@ -0,0 +1,196 @@
+// Headless harness for fuzzing the Unikey engine
+// Feeds raw bytes through `UnikeyEngine::process` and replays every result
+// against a model of the on-screen text, the same way the keyboard layer would.
+
+use super::unikey_engine::{ProcessResult, UnikeyEngine};
+
+/// Backspace as delivered to the engine
+const BACKSPACE: char = '\x08';
+
+/// Fuzzer bytes standing in for the Left and Right arrows
+const LEFT_ARROW: u8 = 0x01;
+const RIGHT_ARROW: u8 = 0x02;
+
+/// Known tricky sequences: undo escapes, diphthongs, prefixes and separators
+pub const SEED_CORPUS: &[&str] = &[
+    "mootj",
+    "Vieetj",
+    "nguwowif",
+    "nguoiwf",
+    "dduwowngf",
+    "twow",
+    "xooong",
+    "ddaays",
+    "quas",
+    "gias",
+    "tuaans",
+    "chuyeenj",
+    "aaa",
+    "ooo",
+    "ddd",
+    "ass",
+    "aww",
+    "ww",
+    "www",
+    "nam s",
+    "thi9s",
+    "abc.def",
+    "as\x08\x08s",
+    "\x08\x08s",
+    "viet\x08\x08\x08\x08\x08eej",
+    "tieng\x01\x01s",
+    "nguoi\x01w\x02\x02f",
+    "ab\x01\x01\x01aa",
+    "uwowwf",
+    "AAWS",
+    "wwwwwwwwwwwwwwwwwwwwwwwwwwwwwwwwwwwwwwwwwwwws",
+    "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaf",
+];
+
+/// Model of the text the user sees, rebuilt from the engine's results.
+/// Edits happen at the caret, which stays at the end unless moved.
+#[derive(Debug, Default)]
+pub struct ScreenModel {
+    text: Vec<char>,
+    caret: usize,
+}
+
+impl ScreenModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply a single processing result, failing if it would delete text
+    /// that is not on screen
+    pub fn apply(&mut self, result: &ProcessResult) -> Result<(), String> {
+        match result {
+            ProcessResult::PassThrough(c) => {
+                if *c == BACKSPACE {
+                    // A real backspace at the start of a field is a no-op
+                    if self.caret > 0 {
+                        self.caret -= 1;
+                        self.text.remove(self.caret);
+                    }
+                } else {
+                    self.insert(&[*c]);
+                }
+            }
+            ProcessResult::Output(text) => {
+                self.insert(&text.chars().collect::<Vec<_>>());
+            }
+            ProcessResult::Replace { backspaces, text } => {
+                if *backspaces > self.caret {
+                    return Err(format!(
+                        "{} backspaces requested but only {} characters before the caret",
+                        backspaces, self.caret
+                    ));
+                }
+                self.text.drain(self.caret - backspaces..self.caret);
+                self.caret -= backspaces;
+                self.insert(&text.chars().collect::<Vec<_>>());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn text(&self) -> String {
+        self.text.iter().collect()
+    }
+
+    /// Left arrow
+    pub fn move_caret_left(&mut self) {
+        self.caret = self.caret.saturating_sub(1);
+    }
+
+    /// Right arrow
+    pub fn move_caret_right(&mut self) {
+        self.caret = (self.caret + 1).min(self.text.len());
+    }
+
+    fn insert(&mut self, chars: &[char]) {
+        self.text.splice(self.caret..self.caret, chars.iter().copied());
+        self.caret += chars.len();
+    }
+
+    /// Check that the engine's composing buffer is what is actually on
+    /// screen around the caret, `after_cursor` of it right of the caret
+    fn holds_word(&self, buffer: &str, after_cursor: usize) -> bool {
+        let buffer: Vec<char> = buffer.chars().collect();
+        let Some(start) = (self.caret + after_cursor).checked_sub(buffer.len()) else {
+            return false;
+        };
+        self.text.get(start..self.caret + after_cursor) == Some(&buffer[..])
+    }
+}
+
+/// One fuzzer keystroke
+#[derive(Debug, Clone, Copy)]
+enum Keystroke {
+    Char(char),
+    Left,
+    Right,
+}
+
+/// Map a fuzzer byte onto a keystroke the engine can receive
+fn byte_to_key(b: u8) -> Option<Keystroke> {
+    match b {
+        LEFT_ARROW => Some(Keystroke::Left),
+        RIGHT_ARROW => Some(Keystroke::Right),
+        0x08 => Some(Keystroke::Char(BACKSPACE)),
+        0x20..=0x7e => Some(Keystroke::Char(b as char)),
+        _ => None,
+    }
+}
+
+/// Run one input through a fresh engine, returning a description of the
+/// first inconsistency between the engine and the screen model
+pub fn check_one(input: &[u8]) -> Result<String, String> {
+    let mut engine = UnikeyEngine::new();
+    let mut screen = ScreenModel::new();
+
+    for (step, key) in input.iter().filter_map(|&b| byte_to_key(b)).enumerate() {
+        match key {
+            Keystroke::Char(c) => {
+                let result = engine.process(c);
+                screen
+                    .apply(&result)
+                    .map_err(|e| format!("step {} ({:?}): {}", step, key, e))?;
+            }
+            // The keyboard layer drops the word once the caret leaves it
+            Keystroke::Left => {
+                screen.move_caret_left();
+                if !engine.cursor_left() {
+                    engine.clear_buf();
+                }
+            }
+            Keystroke::Right => {
+                screen.move_caret_right();
+                if !engine.cursor_right() {
+                    engine.clear_buf();
+                }
+            }
+        }
+
+        let buffer = engine.get_buffer();
+        if !screen.holds_word(&buffer, engine.after_cursor_len()) {
+            return Err(format!(
+                "step {} ({:?}): buffer '{}' is not at the caret of screen '{}'",
+                step,
+                key,
+                buffer,
+                screen.text()
+            ));
+        }
+    }
+
+    Ok(screen.text())
+}
+
+/// Fuzz entry point: panics on any engine/screen desync so that cargo-fuzz
+/// (or a plain loop) records the input as a failure
+#[allow(dead_code)] // Entry point for external fuzz targets
+pub fn fuzz_one(input: &[u8]) {
+    if let Err(e) = check_one(input) {
+        panic!("engine desync for input {:?}: {}", String::from_utf8_lossy(input), e);
+    }
+}
@@ -1,73 +1,391 @@
-use crate::config::{Config, InputMethodType};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use crate::config::{Config, EngineConfig, InjectionMode, InputMethodType, KeyboardLayout};
+use crate::lock;
+use crate::VaixError;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 pub mod telex;
 pub mod vni;
 pub mod vietnamese_engine;
 pub mod unikey_engine;
+pub mod harness;
+mod syllable_cache;
+#[cfg(feature = "dictionary")]
+pub mod dictionary;
 
-pub use unikey_engine::{UnikeyEngine, ProcessResult, InputMethod};
+pub use unikey_engine::{apply_results, UnikeyEngine, UnikeyEngineBuilder, ProcessResult, InputMethod};
+
+/// Most suggestions `InputMethodEngine::suggestions` returns
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/// What the engine is doing, for status queries
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EngineStatus {
+    pub vietnamese: bool,
+    /// Transforms are off until the word ends, after an undo
+    pub temporarily_off: bool,
+    pub method: InputMethodType,
+    /// The word being composed
+    pub buffer: String,
+}
 
 #[derive(Debug)]
 pub struct InputMethodEngine {
-    #[allow(dead_code)]
     config: Arc<Mutex<Config>>,
+    /// `config.input_method`, cached so keys don't lock the config
+    method: InputMethodType,
     unikey_engine: UnikeyEngine,
     is_vietnamese_mode: bool,
     current_buffer: String,
+    /// `engine.compose_on_commit`: keys go to the screen raw and the word
+    /// is converted when a separator commits it
+    compose_on_commit: bool,
+    /// In compose-on-commit mode, the raw keys of the word on screen
+    raw_word: String,
+    /// In compose-on-commit mode, what the word would look like had it been
+    /// transformed as typed
+    composed: harness::ScreenModel,
+    /// `engine.numpad_as_vni_tones`
+    numpad_as_vni_tones: bool,
+    /// `engine.tab_enter_commit`
+    tab_enter_commit: bool,
+    /// `engine.restore_previous_syllable`
+    restore_previous_syllable: bool,
+    /// `[symbols]`: trigger keys to the symbol that replaces them
+    symbols: BTreeMap<String, String>,
+    /// `[proper_nouns]`, keyed by the lowercase word
+    proper_nouns: BTreeMap<String, String>,
 }
 
 impl InputMethodEngine {
     pub fn new(config: Arc<Mutex<Config>>) -> Self {
-        Self {
+        let method = lock(&config).input_method;
+        let mut engine = Self {
             config,
+            method,
             unikey_engine: UnikeyEngine::new(),
             is_vietnamese_mode: true, // Start in Vietnamese mode
             current_buffer: String::new(),
+            compose_on_commit: false,
+            raw_word: String::new(),
+            composed: harness::ScreenModel::new(),
+            numpad_as_vni_tones: false,
+            tab_enter_commit: true,
+            restore_previous_syllable: false,
+            symbols: BTreeMap::new(),
+            proper_nouns: BTreeMap::new(),
+        };
+        engine.reload_config();
+        engine
+    }
+
+    /// Pick up changes to the shared config: the input method, the
+    /// [engine] options, the [separators], the [symbols] and the
+    /// [proper_nouns] apply from the next key
+    pub fn reload_config(&mut self) {
+        let config = lock(&self.config);
+        self.method = config.input_method;
+        let options = config.engine.clone();
+        let separators = config.separators.clone();
+        self.symbols = config.symbols.clone();
+        self.proper_nouns = config.proper_nouns.iter().map(|(word, noun)| (word.to_lowercase(), noun.clone())).collect();
+        drop(config);
+        self.apply_options(&options);
+        self.unikey_engine.set_separators(&separators.extra, &separators.exclude);
+    }
+
+    /// Switch the input method. The shared config is updated too, so a
+    /// later reload keeps it; the composing word is dropped.
+    pub fn set_method(&mut self, method: InputMethodType) {
+        lock(&self.config).input_method = method;
+        self.reload_config();
+        self.reset_buffer();
+    }
+
+    pub fn method(&self) -> InputMethodType {
+        self.method
+    }
+
+    pub fn status(&self) -> EngineStatus {
+        EngineStatus {
+            vietnamese: self.is_vietnamese_mode,
+            temporarily_off: self.is_temporarily_off(),
+            method: self.method,
+            buffer: self.current_buffer.clone(),
+        }
+    }
+
+    fn apply_options(&mut self, options: &EngineConfig) {
+        let input_method = match self.method {
+            InputMethodType::Telex | InputMethodType::SimpleTelex => InputMethod::Telex,
+            InputMethodType::Vni => InputMethod::Vni,
+        };
+        // Simple Telex is Telex with its shortcuts switched off, whatever the
+        // [engine] options say
+        let simple = self.method == InputMethodType::SimpleTelex;
+        self.unikey_engine.set_tone_at_end(options.tone_at_end || simple);
+        self.unikey_engine.set_standalone_w(!simple);
+        self.unikey_engine.set_bracket_shortcuts(options.bracket_shortcuts && !simple);
+        self.unikey_engine.set_dictionary_revert(options.dictionary_revert);
+        self.unikey_engine.set_allow_vni_in_telex(options.allow_vni_in_telex && !simple);
+        self.unikey_engine.set_english_double_letters(options.english_double_letters && !simple);
+        self.unikey_engine.set_max_syllable_len(options.max_syllable_len);
+        self.unikey_engine.set_max_after_vowel(options.max_after_vowel);
+        self.unikey_engine.set_strict_tones(options.strict_tones);
+        self.unikey_engine.set_oa_oe_uy_style(Some(options.oa_oe_uy_style));
+        self.unikey_engine.set_dd_initial_only(options.dd_initial_only);
+        self.unikey_engine.set_disabled_tone_keys(&options.disabled_tone_keys);
+        self.unikey_engine.set_z_key(options.z_key);
+        // A fresh cache: states cached under the old options don't hold
+        self.unikey_engine.set_syllable_cache(options.syllable_cache);
+        // Switching commits the engine's word, so forget our copy of it too
+        if !self.unikey_engine.set_input_method(input_method).is_empty() {
+            self.reset_buffer();
         }
+        self.compose_on_commit = options.compose_on_commit;
+        self.numpad_as_vni_tones = options.numpad_as_vni_tones;
+        self.tab_enter_commit = options.tab_enter_commit;
+        self.restore_previous_syllable = options.restore_previous_syllable;
+        self.unikey_engine.set_restore_previous_syllable(options.restore_previous_syllable);
     }
 
-    pub async fn process_keypress(&mut self, key_char: char) -> ProcessResult {
+    /// Run one key through the engine. Synchronous and lock-free, so the
+    /// keyboard grab thread can call it directly; settings come from the
+    /// last `reload_config`.
+    pub fn process_keypress(&mut self, key_char: char) -> ProcessResult {
         // Update the engine's Vietnamese mode
         if self.unikey_engine.is_vietnamese_mode() != self.is_vietnamese_mode {
             self.unikey_engine.set_vietnamese_mode(self.is_vietnamese_mode);
         }
 
-        // Update input method from config
-        let config = self.config.lock().await;
-        let input_method = match config.input_method {
-            InputMethodType::Telex | InputMethodType::SimpleTelex => InputMethod::Telex,
-            InputMethodType::Vni => InputMethod::Vni,
-        };
-        drop(config);
-        self.unikey_engine.set_input_method(input_method);
+        if self.compose_on_commit && self.is_vietnamese_mode {
+            return self.compose_keypress(key_char);
+        }
+
+        // A trigger on screen, replaced if this key ends the word
+        let symbol = self.pending_symbol().map(|symbol| (symbol, self.current_buffer.chars().count()));
+        // The word on screen, for [proper_nouns] once it is committed
+        let word = (!self.proper_nouns.is_empty()).then(|| self.current_buffer.clone());
 
         // Process the keypress
+        let was_off = self.unikey_engine.is_temporarily_off();
         let result = self.unikey_engine.process(key_char);
+        if self.unikey_engine.is_temporarily_off() != was_off {
+            log::debug!("Transforms {} for the rest of the word",
+                if was_off { "back on" } else { "off (undo)" });
+        }
 
         // Always sync current_buffer with engine's buffer after processing
         // This ensures separators (which clear the engine buffer) are reflected
         self.current_buffer = self.unikey_engine.get_buffer();
 
+        if let Some((symbol, backspaces)) = symbol {
+            if self.current_buffer.is_empty() && key_char != '\x08' {
+                // The symbol can't be edited back into its trigger
+                self.unikey_engine.clear_buf();
+                return ProcessResult::Replace { backspaces, text: format!("{}{}", symbol, key_char) };
+            }
+        }
+
+        if let Some(word) = word.filter(|word| !word.is_empty() && self.current_buffer.is_empty() && key_char != '\x08') {
+            // The word as this key commits it: deferred tones and reverts
+            // have been applied
+            let committed = apply_results(&word, std::slice::from_ref(&result));
+            if let Some(noun) = committed.strip_suffix(key_char).and_then(|committed| self.proper_noun(committed)) {
+                self.unikey_engine.clear_buf();
+                return ProcessResult::Replace { backspaces: word.chars().count(), text: format!("{}{}", noun, key_char) };
+            }
+        }
+
         result
     }
 
+    /// The `[proper_nouns]` entry for a committed word, in Vietnamese mode
+    fn proper_noun(&self, word: &str) -> Option<String> {
+        if !self.is_vietnamese_mode || word.is_empty() {
+            return None;
+        }
+        self.proper_nouns.get(&word.to_lowercase()).cloned()
+    }
+
+    /// The `[symbols]` entry whose trigger is the keys of the word being
+    /// composed, in Vietnamese mode. Without a word there is no trigger,
+    /// even for an empty one in the config, so a separator typed after
+    /// another passes through.
+    fn pending_symbol(&self) -> Option<String> {
+        if self.symbols.is_empty() || !self.is_vietnamese_mode || self.current_buffer.is_empty() {
+            return None;
+        }
+        let keys = self.unikey_engine.raw_keys().unwrap_or(&self.current_buffer);
+        self.symbols.get(keys).cloned()
+    }
+
+    /// `process_keypress` with `engine.compose_on_commit`: the key is typed
+    /// as is while the engine composes the word on the side. The separator
+    /// that ends the word erases exactly the raw keys on screen and types
+    /// the composed word in their place.
+    fn compose_keypress(&mut self, key_char: char) -> ProcessResult {
+        let result = self.unikey_engine.process(key_char);
+        let _ = self.composed.apply(&result);
+        self.current_buffer = self.unikey_engine.get_buffer();
+
+        if key_char == '\x08' {
+            // Rebuild the side copy from the raw keys that are left
+            self.raw_word.pop();
+            let raw_word = std::mem::take(&mut self.raw_word);
+            self.reset_buffer();
+            for c in raw_word.chars() {
+                self.compose_keypress(c);
+            }
+            return ProcessResult::PassThrough(key_char);
+        }
+
+        if !self.current_buffer.is_empty() {
+            self.raw_word.push(key_char);
+            return ProcessResult::PassThrough(key_char);
+        }
+
+        // The key ended the word
+        let backspaces = self.raw_word.chars().count();
+        if let Some(symbol) = self.symbols.get(&self.raw_word).filter(|_| backspaces > 0) {
+            let text = format!("{}{}", symbol, key_char);
+            self.raw_word.clear();
+            self.composed = harness::ScreenModel::new();
+            self.unikey_engine.clear_buf();
+            return ProcessResult::Replace { backspaces, text };
+        }
+        let composed = self.composed.text();
+        if let Some(noun) = composed.strip_suffix(key_char).and_then(|word| self.proper_noun(word)).filter(|_| backspaces > 0) {
+            self.raw_word.clear();
+            self.composed = harness::ScreenModel::new();
+            self.unikey_engine.clear_buf();
+            return ProcessResult::Replace { backspaces, text: format!("{}{}", noun, key_char) };
+        }
+        let unchanged = composed.strip_suffix(key_char) == Some(self.raw_word.as_str());
+        self.raw_word.clear();
+        self.composed = harness::ScreenModel::new();
+        if unchanged {
+            ProcessResult::PassThrough(key_char)
+        } else {
+            ProcessResult::Replace { backspaces, text: composed }
+        }
+    }
+
     pub fn toggle_vietnamese_mode(&mut self) {
         self.is_vietnamese_mode = !self.is_vietnamese_mode;
         self.unikey_engine.set_vietnamese_mode(self.is_vietnamese_mode);
         self.reset_buffer();
     }
 
+    /// Switch to another config profile at runtime. The new settings apply
+    /// from the next keypress; the composing word is dropped.
+    pub fn switch_profile(&mut self, name: &str) -> Result<(), VaixError> {
+        let config = Config::switch_profile(name)?;
+        *lock(&self.config) = config;
+        self.reload_config();
+        self.reset_buffer();
+        Ok(())
+    }
+
+    /// A key was pressed with Ctrl, Alt or Cmd held and went to the app as
+    /// a shortcut. Returns whether the composing word was dropped.
+    pub fn shortcut_pressed(&mut self) -> bool {
+        let reset = lock(&self.config).engine.reset_on_modifier;
+        if reset {
+            self.reset_buffer();
+        }
+        reset
+    }
+
+    /// Left arrow: move the cursor back within the word being composed.
+    /// Returns false if the caret left the word, which drops the buffer.
+    pub fn cursor_left(&mut self) -> bool {
+        // The raw keys on screen can only be swapped as a whole
+        let moved = !self.compose_on_commit && self.unikey_engine.cursor_left();
+        if !moved {
+            self.reset_buffer();
+        }
+        moved
+    }
+
+    /// Right arrow: the counterpart of `cursor_left`
+    pub fn cursor_right(&mut self) -> bool {
+        // The raw keys on screen can only be swapped as a whole
+        let moved = !self.compose_on_commit && self.unikey_engine.cursor_right();
+        if !moved {
+            self.reset_buffer();
+        }
+        moved
+    }
+
+    /// Whether numpad digits go through the engine (`engine.numpad_as_vni_tones`)
+    pub fn numpad_as_vni_tones(&self) -> bool {
+        self.numpad_as_vni_tones
+    }
+
+    /// Whether Tab and Enter finish the word like Space rather than drop it
+    /// (`engine.tab_enter_commit`)
+    pub fn tab_enter_commit(&self) -> bool {
+        self.tab_enter_commit
+    }
+
+    /// Whether Backspace goes through the engine, which can then reopen the
+    /// word before a separator (`engine.restore_previous_syllable`)
+    pub fn restore_previous_syllable(&self) -> bool {
+        self.restore_previous_syllable
+    }
+
+    /// How long the word being composed may sit idle before it is
+    /// committed (`engine.commit_timeout_ms`)
+    pub fn commit_timeout(&self) -> Option<Duration> {
+        lock(&self.config).engine.commit_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// The layout physical keys are read with when the OS reports no
+    /// character (`keyboard_layout`)
+    pub fn keyboard_layout(&self) -> KeyboardLayout {
+        lock(&self.config).keyboard_layout
+    }
+
+    /// How the keyboard layer types replacements (`injection_mode`)
+    pub fn injection_mode(&self) -> InjectionMode {
+        lock(&self.config).injection_mode
+    }
+
+    /// Whether failed Unicode injection falls back to key presses
+    /// (`injection_fallback`)
+    pub fn injection_fallback(&self) -> bool {
+        lock(&self.config).injection_fallback
+    }
+
+    /// How long the keyboard layer may spend on one key before it passes
+    /// the key through instead (`engine.max_process_us`)
+    pub fn process_budget(&self) -> Option<Duration> {
+        match lock(&self.config).engine.max_process_us {
+            0 => None,
+            us => Some(Duration::from_micros(us)),
+        }
+    }
+
     pub fn reset_buffer(&mut self) {
         self.current_buffer.clear();
         self.unikey_engine.clear_buf();
+        self.raw_word.clear();
+        self.composed = harness::ScreenModel::new();
     }
 
     pub fn is_vietnamese_mode(&self) -> bool {
         self.is_vietnamese_mode
     }
 
+    /// Whether the word being composed stays English after an undo, see
+    /// `UnikeyEngine::is_temporarily_off`
+    pub fn is_temporarily_off(&self) -> bool {
+        self.is_vietnamese_mode && self.unikey_engine.is_temporarily_off()
+    }
+
     pub fn backspace(&mut self) -> Option<String> {
         // Process backspace through the engine
         let result = self.unikey_engine.process('\x08');
@@ -94,4 +412,60 @@ impl InputMethodEngine {
     pub fn get_current_buffer(&self) -> &str {
         &self.current_buffer
     }
+
+    /// Common words the one being composed may have been meant as, differing
+    /// only in tone, most frequent first. Empty unless `engine.suggestions`
+    /// is on; nothing is applied.
+    pub fn suggestions(&self) -> Vec<String> {
+        if !lock(&self.config).engine.suggestions {
+            return Vec::new();
+        }
+        let Some(first) = self.current_buffer.chars().next() else {
+            return Vec::new();
+        };
+
+        #[cfg(feature = "dictionary")]
+        let variants = dictionary::tone_variants(&self.current_buffer);
+        #[cfg(not(feature = "dictionary"))]
+        let variants: Vec<&str> = Vec::new();
+
+        variants
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|word| {
+                // Follow the capital of the typed word: Chao → Chào
+                if first.is_uppercase() {
+                    let mut chars = word.chars();
+                    chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+                } else {
+                    word.to_string()
+                }
+            })
+            .collect()
+    }
+
+    /// `text` as it would come out typed with the current settings:
+    /// "Vieejt Nam" → "Việt Nam". Runs on a separate engine, so the word
+    /// being composed is left alone. The last word is committed as if a
+    /// space followed it.
+    pub fn convert(&self, text: &str) -> String {
+        let mut engine = InputMethodEngine::new(self.config.clone());
+        let mut screen = harness::ScreenModel::new();
+        for c in text.chars().chain(std::iter::once(' ')) {
+            let _ = screen.apply(&engine.process_keypress(c));
+        }
+        let mut converted = screen.text();
+        converted.pop();
+        converted
+    }
+
+    /// The engine's composing buffer and the keys that produced it, as one
+    /// line for bug reports: `"việ" (keys: "vieej")`
+    pub fn dump_buffer(&self) -> String {
+        let buffer = self.unikey_engine.get_buffer();
+        match self.unikey_engine.raw_keys() {
+            Some(keys) => format!("{:?} (keys: {:?})", buffer, keys),
+            None => format!("{:?} (keys: unknown after a backspace)", buffer),
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,68 @@
+// Least-recently-used cache for `engine.syllable_cache`, an experiment:
+// the engine maps the keys typed for a word so far to its state after the
+// last of them, so a syllable typed again is restored instead of recomputed.
+// Eviction scans for the oldest entry, which stays cheap at the few hundred
+// entries this is meant for.
+
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub struct SyllableCache<V> {
+    capacity: usize,
+    entries: HashMap<String, (V, u64)>,
+    /// Bumped on every use; an entry's copy says when it was last used
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl<V> SyllableCache<V> {
+    /// An empty cache holding at most `capacity` entries (at least one)
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::with_capacity(capacity),
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn get(&mut self, keys: &str) -> Option<&V> {
+        self.clock += 1;
+        match self.entries.get_mut(keys) {
+            Some((value, used)) => {
+                *used = self.clock;
+                self.hits += 1;
+                Some(value)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Store `value` for `keys`, evicting the least recently used entry when
+    /// full
+    pub fn insert(&mut self, keys: String, value: V) {
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&keys) {
+            let oldest = self.entries.iter().min_by_key(|(_, (_, used))| *used).map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        self.entries.insert(keys, (value, self.clock));
+    }
+
+    /// Drop every entry; hit and miss counts are kept
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Lookups that found an entry and lookups that didn't
+    pub fn stats(&self) -> (u64, u64) {
+        (self.hits, self.misses)
+    }
+}
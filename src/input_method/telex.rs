@@ -29,4 +29,10 @@ impl TelexProcessor {
         // Check if the input can be transformed using Telex rules
         false
     }
-}
\ No newline at end of file
+}
+
+impl Default for TelexProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
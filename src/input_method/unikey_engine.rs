@@ -3,19 +3,69 @@
 // Original copyright: Pham Kim Long (UniKey project)
 // Rust port for VaixKey
 
+use super::syllable_cache::SyllableCache;
+use crate::config::{TonePlacement, ZKey};
 use std::collections::HashMap;
 
 /// Maximum buffer size for storing typed characters
 const KEY_BUFSIZE: usize = 40;
+/// Default for `set_max_syllable_len`: no Vietnamese syllable needs more
+/// buffer positions than this
+const MAX_SYLLABLE_LEN: usize = 12;
 /// Number of characters to maintain when buffer is full
 const KEYS_MAINTAIN: usize = 20;
-/// Maximum characters after a vowel for tone placement
+/// Default for `set_max_after_vowel`: the longest codas (ch, ng, nh) have
+/// two letters
 const MAX_AFTER_VOWEL: usize = 2;
 /// Maximum vowel sequence length
 const MAX_VOWEL_SEQUENCE: usize = 3;
 /// Maximum length for character modification lookup
 const MAX_MODIFY_LENGTH: usize = 6;
 
+/// Three-vowel nuclei by vowel family (a=1, â=2, ă=3, e=4, ê=5, i=6, o=7,
+/// ô=8, ơ=9, u=10, ư=11, y=12) and the offset of the vowel taking the tone.
+/// Forms still missing their circumflex or horn are listed too, so a tone
+/// typed before the mark already lands where the mark will go.
+const TRIPHTHONGS: &[([u8; 3], usize)] = &[
+    ([6, 5, 10], 1),    // iêu: tiếu
+    ([6, 4, 10], 1),    // ieu
+    ([12, 5, 10], 1),   // yêu: yếu
+    ([12, 4, 10], 1),   // yeu
+    ([10, 12, 5], 2),   // uyê: chuyển, quyết
+    ([10, 12, 4], 2),   // uye
+    ([10, 12, 1], 1),   // uya: khuya
+    ([10, 12, 10], 1),  // uyu: khuỷu
+    ([7, 1, 6], 1),     // oai: hoài
+    ([7, 1, 12], 1),    // oay: xoáy
+    ([7, 1, 7], 1),     // oao: ngoáo
+    ([7, 4, 7], 1),     // oeo: ngoẻo
+    ([10, 1, 6], 1),    // uai: quái
+    ([10, 1, 12], 1),   // uay: quay
+    ([10, 2, 12], 1),   // uây: khuấy
+    ([10, 1, 7], 1),    // uao: quào
+    ([10, 5, 10], 1),   // uêu: nguều
+    ([10, 8, 6], 1),    // uôi: muối
+    ([10, 7, 6], 1),    // uoi
+    ([11, 9, 6], 1),    // ươi: người
+    ([10, 9, 6], 1),    // uơi, horn on o only
+    ([11, 7, 6], 1),    // ưoi, horn on u only
+    ([11, 9, 10], 1),   // ươu: rượu
+    ([10, 9, 10], 1),   // uơu
+    ([10, 7, 10], 1),   // uou
+    ([6, 11, 9], 2),    // giươ: giường
+    ([6, 10, 8], 2),    // giuô: giuộc
+];
+
+/// Characters that end a word unless `set_separators` says otherwise
+const DEFAULT_SEPARATORS: [char; 47] = [
+    ' ', '\n', '\r', '\t', '.', ',', ';', ':', '!', '?',
+    '(', ')', '[', ']', '{', '}', '<', '>', '/', '\\',
+    '"', '\'', '-', '_', '+', '=', '@', '#', '$', '%',
+    '^', '&', '*', '|', '`', '~', '0', '1', '2', '3',
+    '4', '5', '6', '7', '8', '9',
+    '\x08', // Backspace is routed to process_backspace
+];
+
 /// Input method types
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InputMethod {
@@ -53,6 +103,27 @@ struct CharAttr {
     current_tone: u8,     // Current tone on the character (0 = no tone)
 }
 
+/// A committed word, kept for `restore_previous_syllable`
+#[derive(Debug)]
+struct CommittedSyllable {
+    /// Letters with their case (`true` = lowercase)
+    letters: Vec<(char, bool)>,
+    raw_keys: Option<String>,
+}
+
+/// The word as a key left it, for `set_syllable_cache`
+#[derive(Debug)]
+struct WordSnapshot {
+    /// Letters with their case (`true` = lowercase)
+    letters: Vec<(char, bool)>,
+    last_w_converted: bool,
+    last_is_escape: bool,
+    temp_viet_off: bool,
+    deferred_tones: Vec<usize>,
+    last_tone_key: Option<(char, bool)>,
+    result: ProcessResult,
+}
+
 /// The main Vietnamese processing engine based on Unikey algorithm
 #[derive(Debug)]
 pub struct UnikeyEngine {
@@ -72,19 +143,71 @@ pub struct UnikeyEngine {
     free_marking: bool,
     tone_next_to_vowel: bool,
     modern_style: bool,
+    oa_oe_uy_style: Option<TonePlacement>,
+    tone_at_end: bool,
+    standalone_w: bool,
+    bracket_shortcuts: bool,
+    dictionary_revert: bool,
+    allow_vni_in_telex: bool,
+    restore_previous_syllable: bool,
+    english_double_letters: bool,
+    max_syllable_len: usize,
+    max_after_vowel: usize,
+    strict_tones: bool,
+    dd_initial_only: bool,
+    z_key: ZKey,
+    // Telex tone keys typed as plain letters, lowercase
+    disabled_tone_keys: Vec<char>,
+    // Custom rules from add_rule: trigger keys to the text they type
+    rules: HashMap<String, String>,
+
+    // The tone key the previous key press applied, so a repeat of it can
+    // keep both letters (english_double_letters)
+    last_tone_key: Option<(char, bool)>,
+
+    // The word a separator just committed, so a backspace over the
+    // separator can edit it again. Any other key drops it.
+    previous_syllable: Option<CommittedSyllable>,
+
+    // Keys typed for the current word, for the dictionary revert. None once
+    // a backspace or buffer overflow makes them unreliable.
+    raw_keys: Option<String>,
+
+    // Deferred tone keys (tone_at_end): buffer positions of the literal keys
+    deferred_tones: Vec<usize>,
+
+    // Letters right of the cursor after Left arrows, nearest last. Keys only
+    // ever edit `buf`, the part before the cursor; these are left alone.
+    after_cursor: Vec<(char, bool)>,
+
+    // Recently composed words by their raw keys (set_syllable_cache), and
+    // whether the word so far is exactly what typing its raw keys from an
+    // empty buffer gives, so a cached state may stand in for it
+    syllable_cache: Option<SyllableCache<WordSnapshot>>,
+    word_cacheable: bool,
     
     // Output
     keys_pushed: usize,
     backs: usize,
     output_buffer: String,
+    // Characters on screen from the start of the word to the caret, as the
+    // results so far left them: the most a Replace may erase without eating
+    // text typed before the word. Checked in debug builds.
+    emitted: usize,
     
     // Lookup tables
+    // DT is split: ASCII keys index an array directly, the Vietnamese
+    // letters live in the map
+    dt_ascii: [CharAttr; 128],
     dt: HashMap<char, CharAttr>,
     
     // Vietnamese character mappings
     // BD[vowel_index][tone_index] = toned character
     // Index 5 = base character without tone
     bd: [[char; 6]; 12],
+
+    // Uppercase counterpart of BD, looked up directly instead of case-mapping
+    bd_upper: [[char; 6]; 12],
     
     // BK: Double character results (a->â, e->ê, o->ô, d->đ)
     bk: [char; 8],
@@ -97,6 +220,11 @@ pub struct UnikeyEngine {
 }
 
 impl UnikeyEngine {
+    /// Configure an engine with chained calls instead of setters
+    pub fn builder() -> UnikeyEngineBuilder {
+        UnikeyEngineBuilder::new()
+    }
+
     pub fn new() -> Self {
         let mut engine = Self {
             keys: 0,
@@ -110,11 +238,36 @@ impl UnikeyEngine {
             free_marking: true,
             tone_next_to_vowel: false,
             modern_style: true,
+            oa_oe_uy_style: None,
+            tone_at_end: false,
+            standalone_w: true,
+            bracket_shortcuts: false,
+            dictionary_revert: false,
+            allow_vni_in_telex: false,
+            restore_previous_syllable: false,
+            english_double_letters: false,
+            max_syllable_len: MAX_SYLLABLE_LEN,
+            max_after_vowel: MAX_AFTER_VOWEL,
+            strict_tones: false,
+            dd_initial_only: false,
+            z_key: ZKey::Literal,
+            disabled_tone_keys: Vec::new(),
+            rules: HashMap::new(),
+            last_tone_key: None,
+            previous_syllable: None,
+            raw_keys: Some(String::new()),
+            deferred_tones: Vec::new(),
+            after_cursor: Vec::new(),
+            syllable_cache: None,
+            word_cacheable: true,
             keys_pushed: 0,
             backs: 0,
             output_buffer: String::new(),
+            emitted: 0,
+            dt_ascii: [CharAttr::default(); 128],
             dt: HashMap::new(),
             bd: [['\0'; 6]; 12],
+            bd_upper: [['\0'; 6]; 12],
             bk: ['\0'; 8],
             bw: ['\0'; 6],
             bt: ['\0'; 4],
@@ -132,6 +285,29 @@ impl UnikeyEngine {
         self.init_bt_table();
     }
 
+    /// Attributes of `c`. ASCII is served from an array so the common keys
+    /// never hash.
+    #[inline]
+    fn attr(&self, c: char) -> CharAttr {
+        if c.is_ascii() {
+            self.dt_ascii[c as usize]
+        } else {
+            self.dt.get(&c).copied().unwrap_or_default()
+        }
+    }
+
+    fn attr_mut(&mut self, c: char) -> &mut CharAttr {
+        if c.is_ascii() {
+            &mut self.dt_ascii[c as usize]
+        } else {
+            self.dt.entry(c).or_default()
+        }
+    }
+
+    fn set_attr(&mut self, c: char, attr: CharAttr) {
+        *self.attr_mut(c) = attr;
+    }
+
     /// Initialize the DT (character attributes) table
     fn init_dt_table(&mut self) {
         // Vowels with their indices
@@ -146,7 +322,7 @@ impl UnikeyEngine {
         ];
         
         for (c, idx) in vowel_base {
-            self.dt.insert(c, CharAttr {
+            self.set_attr(c, CharAttr {
                 vowel_index: idx,
                 dbchar_index: idx, // Can be doubled
                 ..Default::default()
@@ -189,58 +365,66 @@ impl UnikeyEngine {
         ];
         
         for (c, vowel_idx, tone_idx) in vn_vowels {
-            self.dt.insert(c, CharAttr {
+            self.set_attr(c, CharAttr {
                 vowel_index: vowel_idx,
                 current_tone: tone_idx,
                 ..Default::default()
             });
         }
 
-        // Telex tone keys
-        let tone_keys = [
-            ('s', 1), ('S', 1), // sắc
-            ('f', 2), ('F', 2), // huyền
-            ('r', 3), ('R', 3), // hỏi
-            ('x', 4), ('X', 4), // ngã
-            ('j', 5), ('J', 5), // nặng
-        ];
-        
-        for (c, tone) in tone_keys {
-            self.dt.insert(c, CharAttr {
-                tone_index: tone,
-                ..Default::default()
-            });
-        }
+        self.init_tone_keys();
 
         // Breve/horn keys (w for ă, ơ, ư)
-        self.dt.insert('w', CharAttr { is_breve: true, macro_index: 1, ..Default::default() });
-        self.dt.insert('W', CharAttr { is_breve: true, macro_index: 1, ..Default::default() });
+        self.set_attr('w', CharAttr { is_breve: true, macro_index: 1, ..Default::default() });
+        self.set_attr('W', CharAttr { is_breve: true, macro_index: 1, ..Default::default() });
 
         // Double character keys (Telex)
         // a, d, e, o can be doubled
-        if let Some(attr) = self.dt.get_mut(&'a') { attr.dbchar_index = 1; }
-        if let Some(attr) = self.dt.get_mut(&'A') { attr.dbchar_index = 1; }
-        self.dt.insert('d', CharAttr { dbchar_index: 2, ..Default::default() });
-        self.dt.insert('D', CharAttr { dbchar_index: 2, ..Default::default() });
-        if let Some(attr) = self.dt.get_mut(&'e') { attr.dbchar_index = 3; }
-        if let Some(attr) = self.dt.get_mut(&'E') { attr.dbchar_index = 3; }
-        if let Some(attr) = self.dt.get_mut(&'o') { attr.dbchar_index = 4; }
-        if let Some(attr) = self.dt.get_mut(&'O') { attr.dbchar_index = 4; }
+        self.attr_mut('a').dbchar_index = 1;
+        self.attr_mut('A').dbchar_index = 1;
+        self.set_attr('d', CharAttr { dbchar_index: 2, ..Default::default() });
+        self.set_attr('D', CharAttr { dbchar_index: 2, ..Default::default() });
+        self.attr_mut('e').dbchar_index = 3;
+        self.attr_mut('E').dbchar_index = 3;
+        self.attr_mut('o').dbchar_index = 4;
+        self.attr_mut('O').dbchar_index = 4;
 
         // Separators
-        let separators = [' ', '\n', '\r', '\t', '.', ',', ';', ':', '!', '?', 
-                         '(', ')', '[', ']', '{', '}', '<', '>', '/', '\\',
-                         '"', '\'', '-', '_', '+', '=', '@', '#', '$', '%',
-                         '^', '&', '*', '|', '`', '~', '0', '1', '2', '3',
-                         '4', '5', '6', '7', '8', '9'];
-        
-        for c in separators {
-            self.dt.insert(c, CharAttr { is_separator: true, ..Default::default() });
+        for c in DEFAULT_SEPARATORS {
+            self.set_attr(c, CharAttr { is_separator: true, ..Default::default() });
+        }
+
+        // Telex bracket shortcuts ([ → ơ, ] → ư), only while bracket_shortcuts is on
+        self.attr_mut('[').macro_index = 2;
+        self.attr_mut(']').macro_index = 3;
+
+        // VNI mark keys: 6 circumflex, 7 horn, 8 breve, 9 đ. Separators
+        // when there is nothing to mark; Telex borrows them with
+        // allow_vni_in_telex
+        for (i, c) in ['6', '7', '8', '9'].into_iter().enumerate() {
+            self.attr_mut(c).vni_double_index = i as u8 + 1;
         }
 
         // đ/Đ
-        self.dt.insert('đ', CharAttr { dbchar_index: 2, ..Default::default() });
-        self.dt.insert('Đ', CharAttr { dbchar_index: 2, ..Default::default() });
+        self.set_attr('đ', CharAttr { dbchar_index: 2, ..Default::default() });
+        self.set_attr('Đ', CharAttr { dbchar_index: 2, ..Default::default() });
+    }
+
+    /// Telex tone keys, except those in `disabled_tone_keys`, which stay
+    /// plain letters
+    fn init_tone_keys(&mut self) {
+        let tone_keys = [
+            ('s', 1), ('S', 1), // sắc
+            ('f', 2), ('F', 2), // huyền
+            ('r', 3), ('R', 3), // hỏi
+            ('x', 4), ('X', 4), // ngã
+            ('j', 5), ('J', 5), // nặng
+        ];
+        
+        for (c, tone) in tone_keys {
+            let disabled = self.disabled_tone_keys.contains(&c.to_ascii_lowercase());
+            self.attr_mut(c).tone_index = if disabled { 0 } else { tone };
+        }
     }
 
     /// Initialize BD table: vowel_index -> [acute, grave, hook, tilde, dot, base]
@@ -269,6 +453,80 @@ impl UnikeyEngine {
         self.bd[10] = ['ứ', 'ừ', 'ử', 'ữ', 'ự', 'ư'];
         // y family (index 11, corresponds to vowel_index 12)
         self.bd[11] = ['ý', 'ỳ', 'ỷ', 'ỹ', 'ỵ', 'y'];
+
+        // Uppercase rows, same layout
+        self.bd_upper[0] = ['Á', 'À', 'Ả', 'Ã', 'Ạ', 'A'];
+        self.bd_upper[1] = ['Ấ', 'Ầ', 'Ẩ', 'Ẫ', 'Ậ', 'Â'];
+        self.bd_upper[2] = ['Ắ', 'Ằ', 'Ẳ', 'Ẵ', 'Ặ', 'Ă'];
+        self.bd_upper[3] = ['É', 'È', 'Ẻ', 'Ẽ', 'Ẹ', 'E'];
+        self.bd_upper[4] = ['Ế', 'Ề', 'Ể', 'Ễ', 'Ệ', 'Ê'];
+        self.bd_upper[5] = ['Í', 'Ì', 'Ỉ', 'Ĩ', 'Ị', 'I'];
+        self.bd_upper[6] = ['Ó', 'Ò', 'Ỏ', 'Õ', 'Ọ', 'O'];
+        self.bd_upper[7] = ['Ố', 'Ồ', 'Ổ', 'Ỗ', 'Ộ', 'Ô'];
+        self.bd_upper[8] = ['Ớ', 'Ờ', 'Ở', 'Ỡ', 'Ợ', 'Ơ'];
+        self.bd_upper[9] = ['Ú', 'Ù', 'Ủ', 'Ũ', 'Ụ', 'U'];
+        self.bd_upper[10] = ['Ứ', 'Ừ', 'Ử', 'Ữ', 'Ự', 'Ư'];
+        self.bd_upper[11] = ['Ý', 'Ỳ', 'Ỷ', 'Ỹ', 'Ỵ', 'Y'];
+    }
+
+    /// Look up BD[vowel_index - 1][slot] in the requested case
+    fn bd_char(&self, vowel_index: usize, slot: usize, upper: bool) -> char {
+        if upper {
+            self.bd_upper[vowel_index - 1][slot]
+        } else {
+            self.bd[vowel_index - 1][slot]
+        }
+    }
+
+    /// Check that the tables agree with each other, to catch a typo in the
+    /// literals above: every BD row holds six distinct letters in both
+    /// cases, each letter's DT entry points back at its row and tone, and
+    /// every DT vowel index is in range. Lists every problem found.
+    pub fn self_check(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+
+        for (row, (lower, upper)) in self.bd.iter().zip(&self.bd_upper).enumerate() {
+            let vowel_index = row + 1;
+            for (slot, (&c, &upper_c)) in lower.iter().zip(upper).enumerate() {
+                if c.to_uppercase().ne(std::iter::once(upper_c)) {
+                    problems.push(format!("BD row {} slot {}: '{}' and '{}' are not the same letter", vowel_index, slot, c, upper_c));
+                }
+            }
+            for table in [lower, upper] {
+                let base = table[5];
+                for (slot, &c) in table.iter().enumerate() {
+                    if !c.is_alphabetic() || table[..slot].contains(&c) {
+                        problems.push(format!("BD row {}: '{}' is not a new letter", vowel_index, c));
+                        continue;
+                    }
+                    let tone = if slot == 5 { 0 } else { slot as u8 + 1 };
+                    let attr = self.attr(c);
+                    if attr.vowel_index as usize != vowel_index || attr.current_tone != tone {
+                        problems.push(format!(
+                            "'{}' is vowel {} tone {} in BD but vowel {} tone {} in DT",
+                            c, vowel_index, tone, attr.vowel_index, attr.current_tone
+                        ));
+                    } else if self.get_base_vowel(c) != base
+                        || (tone > 0 && self.apply_tone_to_base(base, tone) != c)
+                    {
+                        problems.push(format!("'{}' does not round-trip through its base '{}'", c, base));
+                    }
+                }
+            }
+        }
+
+        let ascii = self.dt_ascii.iter().enumerate().map(|(i, attr)| (i as u8 as char, attr));
+        for (c, attr) in ascii.chain(self.dt.iter().map(|(&c, attr)| (c, attr))) {
+            if attr.vowel_index as usize > self.bd.len() {
+                problems.push(format!("'{}' has vowel index {}, past the BD table", c, attr.vowel_index));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("; "))
+        }
     }
 
     /// Initialize BK table: double character results
@@ -291,15 +549,83 @@ impl UnikeyEngine {
     /// Clear the buffer
     pub fn clear_buf(&mut self) {
         self.keys = 0;
+        self.emitted = 0;
         self.last_w_converted = false;
         self.last_is_escape = false;
         self.temp_viet_off = false;
+        self.deferred_tones.clear();
+        self.after_cursor.clear();
+        self.raw_keys = Some(String::new());
         self.output_buffer.clear();
+        self.previous_syllable = None;
+        self.last_tone_key = None;
+        self.word_cacheable = true;
+    }
+
+    /// Commit the word on a separator, keeping it for a backspace over the
+    /// separator when `restore_previous_syllable` is on
+    fn commit_syllable(&mut self) {
+        let letters = (0..self.keys).map(|i| (self.buf[i], self.lower_case[i])).collect::<Vec<_>>();
+        let raw_keys = self.raw_keys.take();
+        let keep = self.restore_previous_syllable && !letters.is_empty() && self.after_cursor.is_empty();
+        self.clear_buf();
+        if keep {
+            self.previous_syllable = Some(CommittedSyllable { letters, raw_keys });
+        }
+    }
+
+    /// Bring back the committed word the separator followed; the backspace
+    /// itself deletes the separator on screen
+    fn restore_syllable(&mut self, syllable: CommittedSyllable) {
+        self.clear_buf();
+        for (c, is_lower) in syllable.letters {
+            self.put_char(c, is_lower);
+        }
+        self.raw_keys = syllable.raw_keys;
+        // Restored letters, not typed ones: the flags the keys set are gone
+        self.word_cacheable = false;
+    }
+
+    /// Move the cursor one letter left within the word (Left arrow), so the
+    /// next keys edit the syllable up to the cursor. Returns false when the
+    /// cursor is already at the start: the caret leaves the word and the
+    /// caller should clear the buffer.
+    pub fn cursor_left(&mut self) -> bool {
+        if self.keys == 0 {
+            return false;
+        }
+        self.keys -= 1;
+        self.emitted = self.emitted.saturating_sub(1);
+        self.after_cursor.push((self.buf[self.keys], self.lower_case[self.keys]));
+        self.deferred_tones.retain(|&pos| pos < self.keys);
+        self.last_w_converted = false;
+        // The keys no longer spell the word in order
+        self.raw_keys = None;
+        true
+    }
+
+    /// Move the cursor one letter right (Right arrow). Returns false at the
+    /// end of the word, where the caret leaves it.
+    pub fn cursor_right(&mut self) -> bool {
+        match self.after_cursor.pop() {
+            Some((c, is_lower)) => {
+                self.put_char(c, is_lower);
+                self.emitted += 1;
+                self.last_w_converted = false;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Letters between the cursor and the end of the word
+    pub fn after_cursor_len(&self) -> usize {
+        self.after_cursor.len()
     }
 
     /// Get key category based on input method
     fn key_category(&self, c: char) -> KeyCategory {
-        let attr = self.dt.get(&c).copied().unwrap_or_default();
+        let attr = self.attr(c);
 
         if attr.is_breve && self.input_method == InputMethod::Telex {
             return KeyCategory::BreveMark;
@@ -309,20 +635,43 @@ impl UnikeyEngine {
             return KeyCategory::ToneMark;
         }
 
+        // Telex z removes what `z_key` says, like VNI 0
+        if matches!(c, 'z' | 'Z') && self.z_key != ZKey::Literal && self.input_method == InputMethod::Telex {
+            return KeyCategory::ToneMark;
+        }
+
+        // VNI tones are digits, otherwise separators: 1-5 place a tone, 0
+        // removes it
+        if self.input_method == InputMethod::Vni && matches!(c, '0'..='5') {
+            return KeyCategory::ToneMark;
+        }
+
         if attr.dbchar_index > 0 && self.input_method == InputMethod::Telex {
             return KeyCategory::DoubleKey;
         }
 
-        if attr.macro_index > 0 {
+        // w is always a shortcut; brackets only with the bracket shortcuts
+        if attr.macro_index == 1
+            || (attr.macro_index > 1 && self.bracket_shortcuts && self.input_method == InputMethod::Telex)
+        {
             return KeyCategory::ShortKey;
         }
 
-        if attr.is_separator {
-            return KeyCategory::Separator;
+        // VNI 6-9 mark the syllable, or with nothing to mark stay in the
+        // word as typed, like 1-5. Telex with allow_vni_in_telex borrows them
+        // for a syllable no Telex key has touched yet, and otherwise they
+        // stay separators
+        if attr.vni_double_index > 0
+            && (self.input_method == InputMethod::Vni
+                || (self.allow_vni_in_telex
+                    && self.input_method == InputMethod::Telex
+                    && !self.vni_mark_edits(c).is_empty()))
+        {
+            return KeyCategory::VniDoubleMark;
         }
 
-        if attr.vni_double_index > 0 && self.input_method == InputMethod::Vni {
-            return KeyCategory::VniDoubleMark;
+        if attr.is_separator {
+            return KeyCategory::Separator;
         }
 
         if c == '\\' && self.input_method == InputMethod::Viqr {
@@ -355,14 +704,129 @@ impl UnikeyEngine {
                 self.lower_case[i] = self.lower_case[start + i];
             }
             self.keys = KEYS_MAINTAIN;
+            // Positions no longer line up with the shifted buffer
+            self.deferred_tones.clear();
+            self.raw_keys = None;
         }
     }
 
     /// Process a keypress and return the result
     pub fn process(&mut self, c: char) -> ProcessResult {
+        let (emitted, keys) = (self.emitted, self.keys);
+        let result = self.process_cached(c);
+        if let ProcessResult::Replace { backspaces, .. } = &result {
+            debug_assert!(
+                *backspaces <= emitted,
+                "'{}' erases {} characters, only {} are on screen since the word began",
+                c.escape_debug(), backspaces, emitted
+            );
+        }
+        self.emitted = match &result {
+            // A backspace over the separator brings the word before it back
+            ProcessResult::PassThrough('\x08') if keys == 0 => self.keys,
+            ProcessResult::PassThrough('\x08') => emitted.saturating_sub(1),
+            _ => self.emitted_after(emitted, &result),
+        };
+        result
+    }
+
+    /// `emitted` once `result` reaches the screen. A key that leaves the
+    /// buffer empty ended the word (or erased all of it), so nothing of the
+    /// word is before the caret.
+    fn emitted_after(&self, emitted: usize, result: &ProcessResult) -> usize {
+        if self.keys == 0 {
+            return 0;
+        }
+        match result {
+            ProcessResult::PassThrough(_) => emitted + 1,
+            ProcessResult::Output(text) => emitted + text.chars().count(),
+            ProcessResult::Replace { backspaces, text } => emitted.saturating_sub(*backspaces) + text.chars().count(),
+        }
+    }
+
+    /// `process` through the syllable cache when it is on
+    fn process_cached(&mut self, c: char) -> ProcessResult {
+        let Some(mut cache) = self.syllable_cache.take() else {
+            return self.process_key(c);
+        };
+        let keys = self.cache_key(c);
+        if let Some(snapshot) = keys.as_deref().and_then(|keys| cache.get(keys)) {
+            let result = self.restore_snapshot(snapshot);
+            self.raw_keys = keys;
+            self.syllable_cache = Some(cache);
+            return result;
+        }
+
+        // Only a clear_buf while processing makes the word cacheable again,
+        // unless the key just extends the raw keys
+        self.word_cacheable = false;
+        let result = self.process_key(c);
+        if let Some(keys) = keys.filter(|keys| self.raw_keys.as_ref() == Some(keys)) {
+            self.word_cacheable = true;
+            cache.insert(keys, self.snapshot(&result));
+        }
+        self.syllable_cache = Some(cache);
+        result
+    }
+
+    /// Raw keys of the word with `c` added, when the cache may serve it
+    fn cache_key(&self, c: char) -> Option<String> {
+        if !self.word_cacheable || !self.after_cursor.is_empty() {
+            return None;
+        }
+        let mut keys = self.raw_keys.clone()?;
+        keys.push(c);
+        Some(keys)
+    }
+
+    fn snapshot(&self, result: &ProcessResult) -> WordSnapshot {
+        WordSnapshot {
+            letters: (0..self.keys).map(|i| (self.buf[i], self.lower_case[i])).collect(),
+            last_w_converted: self.last_w_converted,
+            last_is_escape: self.last_is_escape,
+            temp_viet_off: self.temp_viet_off,
+            deferred_tones: self.deferred_tones.clone(),
+            last_tone_key: self.last_tone_key,
+            result: result.clone(),
+        }
+    }
+
+    /// Put the word back as `snapshot` recorded it; the caller sets the raw
+    /// keys
+    fn restore_snapshot(&mut self, snapshot: &WordSnapshot) -> ProcessResult {
+        self.keys = snapshot.letters.len();
+        for (i, &(c, is_lower)) in snapshot.letters.iter().enumerate() {
+            self.buf[i] = c;
+            self.lower_case[i] = is_lower;
+        }
+        self.last_w_converted = snapshot.last_w_converted;
+        self.last_is_escape = snapshot.last_is_escape;
+        self.temp_viet_off = snapshot.temp_viet_off;
+        self.deferred_tones.clone_from(&snapshot.deferred_tones);
+        self.last_tone_key = snapshot.last_tone_key;
+        self.previous_syllable = None;
+        self.word_cacheable = true;
+
+        self.output_buffer.clear();
+        self.backs = 0;
+        match &snapshot.result {
+            ProcessResult::Output(text) => self.output_buffer.push_str(text),
+            ProcessResult::Replace { backspaces, text } => {
+                self.backs = *backspaces;
+                self.output_buffer.push_str(text);
+            }
+            ProcessResult::PassThrough(_) => {}
+        }
+        self.keys_pushed = self.output_buffer.len();
+        snapshot.result.clone()
+    }
+
+    fn process_key(&mut self, c: char) -> ProcessResult {
         self.keys_pushed = 0;
         self.backs = 0;
         self.output_buffer.clear();
+        let previous_syllable = self.previous_syllable.take();
+        let last_tone_key = self.last_tone_key.take();
 
         let is_lower = c.is_lowercase();
         let c_lower = c.to_lowercase().next().unwrap_or(c);
@@ -372,6 +836,13 @@ impl UnikeyEngine {
             return ProcessResult::PassThrough(c);
         }
 
+        // Decomposed text sends the diacritic as its own code point after
+        // the letter (e, U+0302). Fold it into the letter so the word edits
+        // like precomposed text: the mark is blocked and the letter retyped.
+        if let Some(result) = self.compose_mark(c) {
+            return result;
+        }
+
         if self.temp_viet_off {
             if !c.is_alphabetic() {
                 self.temp_viet_off = false;
@@ -391,6 +862,30 @@ impl UnikeyEngine {
 
         let category = self.key_category(c);
 
+        // A run this long is no Vietnamese syllable (a URL, base64...): leave
+        // the rest of it alone until a separator
+        if self.max_syllable_len > 0 && self.keys >= self.max_syllable_len && category != KeyCategory::Separator {
+            self.temp_viet_off = true;
+            self.put_char(c, is_lower);
+            return ProcessResult::PassThrough(c);
+        }
+
+        if category != KeyCategory::Separator {
+            if let Some(raw) = &mut self.raw_keys {
+                raw.push(c);
+            }
+            // Custom rules come before every built-in transformation
+            if let Some(result) = self.apply_rule(c) {
+                return result;
+            }
+        }
+
+        // Only a w typed right after a standalone ư undoes it: any other
+        // key in between ("twow") lets the next w horn the vowels instead
+        if category != KeyCategory::BreveMark {
+            self.last_w_converted = false;
+        }
+
         match category {
             KeyCategory::BreveMark => {
                 if self.input_method == InputMethod::Telex && 
@@ -400,6 +895,7 @@ impl UnikeyEngine {
                 } else {
                     self.put_breve_mark(c, is_lower);
                     if self.input_method == InputMethod::Telex && 
+                       self.standalone_w &&
                        self.keys_pushed == 0 && 
                        self.backs == 0 && 
                        (c == 'w' || c == 'W') {
@@ -412,30 +908,62 @@ impl UnikeyEngine {
                 self.double_char(c, is_lower);
             }
             KeyCategory::ToneMark => {
-                self.put_tone_mark(c, is_lower);
+                if self.tone_at_end && self.has_vowel() {
+                    // Typed literally now, applied when the syllable is committed
+                    self.deferred_tones.push(self.keys);
+                } else {
+                    let was_off = self.temp_viet_off;
+                    self.put_tone_mark(c, is_lower);
+                    if self.temp_viet_off && !was_off {
+                        self.keep_doubled_tone_key(last_tone_key, c);
+                    } else if self.backs > 0 {
+                        self.last_tone_key = Some((c, is_lower));
+                    }
+                }
             }
             KeyCategory::ShortKey => {
                 self.short_key(c, is_lower);
             }
+            KeyCategory::VniDoubleMark => {
+                self.put_vni_mark(c);
+            }
             KeyCategory::Separator => {
                 if c == '\x08' { // Backspace
-                    self.process_backspace();
-                } else {
+                    match previous_syllable {
+                        Some(syllable) if self.keys == 0 => self.restore_syllable(syllable),
+                        _ => self.process_backspace(),
+                    }
+                } else if self.keys == 0 {
+                    // No word to commit, so nothing to rewrite: a run of
+                    // separators ("a  b", "a..") types as itself
                     self.clear_buf();
+                } else if !self.deferred_tones.is_empty() {
+                    return self.commit_deferred_tone(c);
+                } else if let Some(result) = self.revert_unknown_word(c) {
+                    return result;
+                } else {
+                    self.commit_syllable();
                 }
-                self.last_w_converted = false;
                 return ProcessResult::PassThrough(c);
             }
             _ => {
                 // Regular character
-                if category != KeyCategory::BreveMark {
-                    self.last_w_converted = false;
-                }
             }
         }
 
         if self.keys_pushed == 0 && self.backs == 0 {
             self.put_char(c, is_lower);
+
+            // A new vowel or coda consonant can change which vowel is the
+            // main one: re-place the tone
+            if c.is_alphabetic() && self.migrate_tone() {
+                // The new key is blocked, so it is part of the text but was
+                // never on screen
+                return ProcessResult::Replace {
+                    backspaces: self.backs - 1,
+                    text: self.output_buffer.clone(),
+                };
+            }
             return ProcessResult::PassThrough(c);
         }
 
@@ -449,11 +977,140 @@ impl UnikeyEngine {
         }
     }
 
+    /// Fire the custom rule whose trigger the word before the cursor plus
+    /// `c` ends with, the longest if several do: its letters are replaced
+    /// by the rule's output
+    fn apply_rule(&mut self, c: char) -> Option<ProcessResult> {
+        let (erased, output) = self
+            .rules
+            .iter()
+            .filter_map(|(trigger, output)| {
+                let mut before: Vec<char> = trigger.chars().collect();
+                if before.pop()? != c || before.len() > self.keys {
+                    return None;
+                }
+                let n = before.len();
+                (self.buf[self.keys - n..self.keys] == before[..]).then_some((n, output))
+            })
+            .max_by_key(|(n, _)| *n)?;
+        let output = output.clone();
+
+        self.keys -= erased;
+        self.deferred_tones.retain(|&pos| pos < self.keys);
+        for ch in output.chars() {
+            self.put_char(ch, ch.is_lowercase());
+        }
+        Some(if erased > 0 {
+            ProcessResult::Replace { backspaces: erased, text: output }
+        } else {
+            ProcessResult::Output(output)
+        })
+    }
+
+    /// Process `keys` in order, as if typed one by one, and return each
+    /// key's result. `apply_results` turns them into the text on screen.
+    pub fn process_sequence(&mut self, keys: &[char]) -> Vec<ProcessResult> {
+        keys.iter().map(|&c| self.process(c)).collect()
+    }
+
+    /// Combine a combining mark with the letter before it, if Unicode has a
+    /// precomposed form for the pair
+    fn compose_mark(&mut self, mark: char) -> Option<ProcessResult> {
+        if self.keys == 0 || !unicode_normalization::char::is_combining_mark(mark) {
+            return None;
+        }
+        let composed = unicode_normalization::char::compose(self.buf[self.keys - 1], mark)?;
+        self.buf[self.keys - 1] = composed;
+        // The raw keys no longer retype the word
+        self.raw_keys = None;
+        self.backs = 1;
+        self.output_buffer.push(composed);
+        Some(ProcessResult::Replace {
+            backspaces: 1,
+            text: self.output_buffer.clone(),
+        })
+    }
+
     /// Process backspace
     fn process_backspace(&mut self) {
+        self.raw_keys = None;
         if self.keys > 0 {
             self.keys -= 1;
             self.backs = 1;
+            // A deleted tone key no longer counts
+            self.deferred_tones.retain(|&pos| pos < self.keys);
+        }
+    }
+
+    /// Check whether the buffer holds a vowel a tone could land on
+    fn has_vowel(&self) -> bool {
+        self.buf[..self.keys]
+            .iter()
+            .any(|&c| self.attr(c).vowel_index > 0)
+    }
+
+    /// Dictionary revert: when the committed word was transformed into
+    /// something that isn't Vietnamese, retype the keys as they were pressed,
+    /// e.g. "ưindơ" goes back to "window"
+    fn revert_unknown_word(&mut self, separator: char) -> Option<ProcessResult> {
+        if !self.dictionary_revert {
+            return None;
+        }
+        let raw = self.raw_keys.as_ref()?;
+        let word: String = self.buf[..self.keys].iter().collect();
+
+        // Untransformed words are already what was typed
+        if word.is_ascii() || !is_plausible_english(raw) || is_known_word(&word) {
+            return None;
+        }
+
+        let mut text = raw.clone();
+        text.push(separator);
+        let backspaces = self.keys;
+        self.clear_buf();
+        Some(ProcessResult::Replace { backspaces, text })
+    }
+
+    /// Apply the deferred tone (tone_at_end) to the finished syllable.
+    /// The literal tone keys are removed and the last one is placed on the
+    /// main vowel; the separator is re-emitted after the composed syllable.
+    fn commit_deferred_tone(&mut self, separator: char) -> ProcessResult {
+        let typed: Vec<char> = self.buf[..self.keys].to_vec();
+        let tone_key = typed[*self.deferred_tones.last().unwrap_or(&0)];
+
+        // Rebuild the buffer without the tone keys
+        let stripped: Vec<(char, bool)> = (0..self.keys)
+            .filter(|pos| !self.deferred_tones.contains(pos))
+            .map(|pos| (self.buf[pos], self.lower_case[pos]))
+            .collect();
+        self.keys = 0;
+        for (c, is_lower) in stripped {
+            self.put_char(c, is_lower);
+        }
+
+        self.backs = 0;
+        self.keys_pushed = 0;
+        self.put_tone_mark(tone_key, tone_key.is_lowercase());
+        let composed: Vec<char> = if self.backs > 0 {
+            self.buf[..self.keys].to_vec()
+        } else {
+            // No vowel to carry the tone, keep what was typed
+            typed.to_vec()
+        };
+        self.clear_buf();
+
+        // Only rewrite the part of the syllable that actually changed
+        let common = typed
+            .iter()
+            .zip(&composed)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let mut text: String = composed[common..].iter().collect();
+        text.push(separator);
+
+        ProcessResult::Replace {
+            backspaces: typed.len() - common,
+            text,
         }
     }
 
@@ -470,26 +1127,29 @@ impl UnikeyEngine {
 
         while i >= left_most {
             let buf_char = self.buf[i as usize];
-            let attr = self.dt.get(&buf_char).copied().unwrap_or_default();
+            let attr = self.attr(buf_char);
             
             // Check if this is a vowel that can receive the breve/horn
             if attr.vowel_index > 0 {
+                // The result takes the case of the vowel being marked, not
+                // of the w: "Aw" → "Ă", "aW" → "ă"
+                let upper = !self.lower_case[i as usize];
                 // Determine which vowel family this belongs to and what transformation to apply
                 // vowel_index: a=1, â=2, ă=3, e=4, ê=5, i=6, o=7, ô=8, ơ=9, u=10, ư=11, y=12
                 let (target_char, can_apply) = match attr.vowel_index {
                     1 | 2 | 3 => {
                         // a, â, ă families -> apply breve to get ă
-                        let target = if buf_char.is_uppercase() { 'Ă' } else { 'ă' };
+                        let target = if upper { 'Ă' } else { 'ă' };
                         (target, true)
                     }
                     7 | 8 | 9 => {
                         // o, ô, ơ families -> apply horn to get ơ
-                        let target = if buf_char.is_uppercase() { 'Ơ' } else { 'ơ' };
+                        let target = if upper { 'Ơ' } else { 'ơ' };
                         (target, true)
                     }
                     10 | 11 => {
                         // u, ư families -> apply horn to get ư
-                        let target = if buf_char.is_uppercase() { 'Ư' } else { 'ư' };
+                        let target = if upper { 'Ư' } else { 'ư' };
                         (target, true)
                     }
                     _ => ('\0', false)
@@ -508,10 +1168,10 @@ impl UnikeyEngine {
                     let prev_raw = self.get_raw_base_vowel(prev_char);
                     if prev_raw == 'u' {
                         // "uo" pattern - apply horn to both vowels
-                        let prev_attr = self.dt.get(&prev_char).copied().unwrap_or_default();
+                        let prev_attr = self.attr(prev_char);
                         
                         // Transform 'u' to 'ư' preserving tone
-                        let u_target = if prev_char.is_uppercase() { 'Ư' } else { 'ư' };
+                        let u_target = if self.lower_case[(i - 1) as usize] { 'ư' } else { 'Ư' };
                         let new_u = if prev_attr.current_tone > 0 {
                             self.apply_tone_to_base(u_target, prev_attr.current_tone)
                         } else {
@@ -519,7 +1179,7 @@ impl UnikeyEngine {
                         };
                         
                         // Transform 'o' to 'ơ' preserving tone
-                        let o_target = if buf_char.is_uppercase() { 'Ơ' } else { 'ơ' };
+                        let o_target = if upper { 'Ơ' } else { 'ơ' };
                         let new_o = if attr.current_tone > 0 {
                             self.apply_tone_to_base(o_target, attr.current_tone)
                         } else {
@@ -537,9 +1197,9 @@ impl UnikeyEngine {
 
                 // Get the base character of the target family to check for undo
                 let target_base = match target_char.to_lowercase().next().unwrap_or(target_char) {
-                    'ă' => if buf_char.is_uppercase() { 'Ă' } else { 'ă' },
-                    'ơ' => if buf_char.is_uppercase() { 'Ơ' } else { 'ơ' },
-                    'ư' => if buf_char.is_uppercase() { 'Ư' } else { 'ư' },
+                    'ă' => if upper { 'Ă' } else { 'ă' },
+                    'ơ' => if upper { 'Ơ' } else { 'ơ' },
+                    'ư' => if upper { 'Ư' } else { 'ư' },
                     _ => target_char,
                 };
 
@@ -548,9 +1208,9 @@ impl UnikeyEngine {
                 if current_base.to_lowercase().next() == target_base.to_lowercase().next() {
                     // Already breve/horn, undo by reverting to plain vowel
                     let plain_vowel = match attr.vowel_index {
-                        1 | 2 | 3 => if buf_char.is_uppercase() { 'A' } else { 'a' },
-                        7 | 8 | 9 => if buf_char.is_uppercase() { 'O' } else { 'o' },
-                        10 | 11 => if buf_char.is_uppercase() { 'U' } else { 'u' },
+                        1 | 2 | 3 => if upper { 'A' } else { 'a' },
+                        7 | 8 | 9 => if upper { 'O' } else { 'o' },
+                        10 | 11 => if upper { 'U' } else { 'u' },
                         _ => buf_char,
                     };
                     // Apply current tone to plain vowel
@@ -623,7 +1283,10 @@ impl UnikeyEngine {
         }
     }
 
-    /// Process double character (aa, ee, oo, dd)
+    /// Process double character (aa, ee, oo, dd). The letter before may
+    /// already carry a tone, which the circumflex keeps: "asa" → "ấ", like
+    /// "aas"; a third letter undoes the circumflex and still keeps it
+    /// ("aafa" → "àa").
     fn double_char(&mut self, c: char, is_lower: bool) {
         if self.keys == 0 {
             return;
@@ -640,11 +1303,18 @@ impl UnikeyEngine {
             return;
         }
 
+        // đ only starts a syllable: the d of "add" or "dadd" stays a d
+        if c_lower == 'd' && self.dd_initial_only && self.keys > 1 {
+            return;
+        }
+
+        // The letter being modified keeps its case: "Ee" -> "Ê"
+        let target_lower = !last_char.is_uppercase();
         let target = match c_lower {
-            'a' => if is_lower { 'â' } else { 'Â' },
-            'e' => if is_lower { 'ê' } else { 'Ê' },
-            'o' => if is_lower { 'ô' } else { 'Ô' },
-            'd' => if is_lower { 'đ' } else { 'Đ' },
+            'a' => if target_lower { 'â' } else { 'Â' },
+            'e' => if target_lower { 'ê' } else { 'Ê' },
+            'o' => if target_lower { 'ô' } else { 'Ô' },
+            'd' => if target_lower { 'đ' } else { 'Đ' },
             _ => return,
         };
 
@@ -661,13 +1331,16 @@ impl UnikeyEngine {
         };
 
         if is_already_transformed {
-            // Undo: replace transformed char with original + add the new char
+            // Undo: replace transformed char with original + add the new char.
+            // A tone stays where it was: "coofo" → "còo"
             self.backs = 1;
-            let original = if self.lower_case[self.keys - 1] { 
+            let plain = if self.lower_case[self.keys - 1] { 
                 c_lower 
             } else { 
                 c_lower.to_uppercase().next().unwrap_or(c) 
             };
+            let tone = self.attr(last_char).current_tone;
+            let original = if tone > 0 { self.apply_tone_to_base(plain, tone) } else { plain };
             self.buf[self.keys - 1] = original;
             self.output_buffer.push(original);
             self.output_buffer.push(c);
@@ -678,7 +1351,7 @@ impl UnikeyEngine {
         }
 
         // Apply transformation (first double: oo → ô)
-        let last_attr = self.dt.get(&last_char).copied().unwrap_or_default();
+        let last_attr = self.attr(last_char);
         let new_char = if last_attr.current_tone > 0 {
             self.apply_tone_to_base(target, last_attr.current_tone)
         } else {
@@ -691,69 +1364,116 @@ impl UnikeyEngine {
         self.keys_pushed = 1;
     }
 
-    /// Put a tone mark (s, f, r, x, j in Telex)
-    fn put_tone_mark(&mut self, c: char, is_lower: bool) {
-        if self.keys == 0 {
-            return;
+    /// Letters the VNI mark key `c` would rewrite, as (position, new letter):
+    /// the rightmost a/e/o for 6, o/u for 7 (both letters of "uo"), a for 8,
+    /// d for 9, keeping any tone. Empty unless the syllable is all letters
+    /// with a vowel; in Telex they must be plain letters, and in VNI 9 needs
+    /// no vowel.
+    fn vni_mark_edits(&self, c: char) -> Vec<(usize, char)> {
+        let word = &self.buf[..self.keys];
+        let vni = self.input_method == InputMethod::Vni;
+        if !self.deferred_tones.is_empty()
+            || !word.iter().all(|ch| if vni { ch.is_alphabetic() } else { ch.is_ascii_alphabetic() })
+            || !(self.has_vowel() || (vni && c == '9'))
+        {
+            return Vec::new();
         }
 
-        let tone_index = match c.to_lowercase().next().unwrap_or(c) {
-            's' => 1, // acute (sắc)
-            'f' => 2, // grave (huyền)
-            'r' => 3, // hook (hỏi)
-            'x' => 4, // tilde (ngã)
-            'j' => 5, // dot (nặng)
-            _ => return,
+        let targets: &[char] = match c {
+            '6' => &['a', 'e', 'o'],
+            '7' => &['o', 'u'],
+            '8' => &['a'],
+            '9' => &['d'],
+            _ => return Vec::new(),
+        };
+        let Some(pos) = word
+            .iter()
+            .rposition(|&ch| targets.contains(&self.get_base_vowel(ch).to_ascii_lowercase()))
+        else {
+            return Vec::new();
         };
 
-        // Find the vowel to apply the tone to
-        let mut i = self.keys as i32 - 1;
-        let left_most = if self.tone_next_to_vowel { i } else { 0 };
-        let left_most = left_most.max(self.keys as i32 - 1 - MAX_AFTER_VOWEL as i32);
+        let mark = |letter: char| {
+            let base = self.get_base_vowel(letter);
+            let tone = (1..=5).find(|&tone| self.apply_tone_to_base(base, tone) == letter);
+            let marked = match (c, base.to_ascii_lowercase()) {
+                ('6', 'a') => 'â',
+                ('6', 'e') => 'ê',
+                ('6', 'o') => 'ô',
+                ('7', 'o') => 'ơ',
+                ('7', 'u') => 'ư',
+                ('8', _) => 'ă',
+                _ => 'đ',
+            };
+            let marked = if letter.is_uppercase() {
+                marked.to_uppercase().next().unwrap_or(marked)
+            } else {
+                marked
+            };
+            tone.map_or(marked, |tone| self.apply_tone_to_base(marked, tone))
+        };
 
-        // Find the first vowel from the right
-        while i >= left_most {
-            let attr = self.dt.get(&self.buf[i as usize]).copied().unwrap_or_default();
-            if attr.is_separator || attr.is_soft_sep || attr.vowel_index > 0 {
-                break;
-            }
-            i -= 1;
+        let mut edits = vec![(pos, mark(word[pos]))];
+        let plain = |i: usize| self.get_base_vowel(word[i]).to_ascii_lowercase();
+        if c == '7' && pos > 0 && plain(pos) == 'o' && plain(pos - 1) == 'u' {
+            edits.insert(0, (pos - 1, mark(word[pos - 1])));
         }
+        edits
+    }
 
-        if i < left_most {
+    /// Apply a VNI mark key, in VNI or borrowed by Telex (allow_vni_in_telex).
+    /// With nothing to mark the key is left to be typed as a letter
+    fn put_vni_mark(&mut self, c: char) {
+        let edits = self.vni_mark_edits(c);
+        let Some(&(from, _)) = edits.first() else {
             return;
+        };
+        for (pos, letter) in edits {
+            self.buf[pos] = letter;
         }
+        self.backs = self.keys - from;
+        self.rebuild_output(from);
+        self.keys_pushed = self.output_buffer.len();
+    }
 
-        let attr = self.dt.get(&self.buf[i as usize]).copied().unwrap_or_default();
-        if attr.vowel_index == 0 {
-            return;
+    /// Position of the vowel that should carry the tone for the syllable
+    /// currently in the buffer, if there is one
+    fn tone_target(&self) -> Option<usize> {
+        // All indices stay within buf[..keys]: scans run over ranges and
+        // step back only with checked arithmetic
+        let last = self.keys.checked_sub(1)?;
+
+        // Find the first vowel from the right, at most max_after_vowel
+        // letters back and not past a separator
+        let left_most = if self.tone_next_to_vowel { last } else { 0 };
+        let left_most = left_most.max(last.saturating_sub(self.max_after_vowel));
+        let end_pos = (left_most..=last).rev().find(|&i| {
+            let attr = self.attr(self.buf[i]);
+            attr.is_separator || attr.is_soft_sep || attr.vowel_index > 0
+        })?;
+        if self.attr(self.buf[end_pos]).vowel_index == 0 {
+            return None;
         }
 
         // Find the sequence of consecutive vowels
-        let end_pos = i;
-        let left_most = if self.tone_next_to_vowel { i } else { 0 };
-        let left_most = left_most.max(end_pos - MAX_VOWEL_SEQUENCE as i32 + 1);
-
-        while i >= left_most {
-            let attr = self.dt.get(&self.buf[i as usize]).copied().unwrap_or_default();
-            if attr.vowel_index == 0 {
-                break;
-            }
-            i -= 1;
-        }
-        // i now points to one position before the first vowel, adjust to first vowel
-        let start_pos = i + 1;
+        let left_most = if self.tone_next_to_vowel { end_pos } else { 0 };
+        let left_most = left_most.max((end_pos + 1).saturating_sub(MAX_VOWEL_SEQUENCE));
+        let start_pos = (left_most..=end_pos)
+            .rev()
+            .take_while(|&i| self.attr(self.buf[i]).vowel_index > 0)
+            .last()
+            .unwrap_or(end_pos);
 
         // Determine which vowel to apply the tone to
         // vowel_seq_len is the number of consecutive vowels
-        let vowel_seq_len = (end_pos - start_pos + 1) as usize;
+        let vowel_seq_len = end_pos - start_pos + 1;
         let target_pos = match vowel_seq_len {
             2 => {
                 // Two vowels: need to determine which one gets the tone
-                let v1 = self.buf[start_pos as usize];
-                let v2 = self.buf[end_pos as usize];
-                let v1_attr = self.dt.get(&v1).copied().unwrap_or_default();
-                let v2_attr = self.dt.get(&v2).copied().unwrap_or_default();
+                let v1 = self.buf[start_pos];
+                let v2 = self.buf[end_pos];
+                let v1_attr = self.attr(v1);
+                let v2_attr = self.attr(v2);
                 
                 // Get vowel family indices
                 // a=1, â=2, ă=3, e=4, ê=5, i=6, o=7, ô=8, ơ=9, u=10, ư=11, y=12
@@ -762,26 +1482,34 @@ impl UnikeyEngine {
                 
                 // Check for Q or GI prefix - tone goes on last vowel
                 let has_q_prefix = start_pos > 0 && {
-                    let prev_char = self.buf[(start_pos - 1) as usize].to_uppercase().next().unwrap_or(' ');
+                    let prev_char = self.buf[start_pos - 1].to_uppercase().next().unwrap_or(' ');
                     prev_char == 'Q'
                 };
                 let has_gi_prefix = start_pos > 0 && {
-                    let prev_char = self.buf[(start_pos - 1) as usize].to_uppercase().next().unwrap_or(' ');
-                    prev_char == 'G' && (start_pos as usize) < self.keys && {
-                        let next_char = self.buf[start_pos as usize].to_uppercase().next().unwrap_or(' ');
+                    let prev_char = self.buf[start_pos - 1].to_uppercase().next().unwrap_or(' ');
+                    prev_char == 'G' && start_pos < self.keys && {
+                        let next_char = self.buf[start_pos].to_uppercase().next().unwrap_or(' ');
                         next_char == 'I'
                     }
                 };
                 
                 // Check if there's a consonant after the vowel sequence
-                let has_consonant_after = (end_pos as usize) < self.keys - 1;
+                let has_consonant_after = end_pos < last;
                 
                 if has_q_prefix || has_gi_prefix {
                     // After Q or GI, tone goes on last vowel
-                    end_pos as usize
+                    end_pos
                 } else if has_consonant_after {
                     // If consonant follows (like "tuấn"), tone goes on last vowel
-                    end_pos as usize
+                    end_pos
+                } else if let Some(style) = self.oa_oe_uy_style.filter(|_| {
+                    (v1_family == 7 && (v2_family == 1 || v2_family == 4)) || (v1_family == 10 && v2_family == 12)
+                }) {
+                    // oa, oe, uy follow their own style
+                    match style {
+                        TonePlacement::Modern => end_pos,
+                        TonePlacement::Classic => start_pos,
+                    }
                 } else if self.modern_style {
                     // Special cases where tone goes on second vowel:
                     // oa, oe, uy -> second vowel
@@ -800,25 +1528,123 @@ impl UnikeyEngine {
                         (v1_family == 10 && v2_family == 8);
                     
                     if tone_on_second {
-                        end_pos as usize
+                        end_pos
                     } else {
                         // Most diphthongs: tone on first vowel (ôi, ai, ao, etc.)
-                        start_pos as usize
+                        start_pos
                     }
                 } else {
-                    start_pos as usize
+                    start_pos
                 }
             }
             3 => {
-                // Three vowels: tone goes on middle vowel
-                (start_pos + 1) as usize
+                // Known nuclei come from the table. Anything else: a vowel
+                // carrying a circumflex or horn is the main vowel, rightmost
+                // first, or failing that the middle one.
+                let families = [0, 1, 2].map(|k| self.attr(self.buf[start_pos + k]).vowel_index);
+                match TRIPHTHONGS.iter().find(|(nucleus, _)| *nucleus == families) {
+                    Some(&(_, offset)) => start_pos + offset,
+                    None => (start_pos..=end_pos)
+                        .rev()
+                        .find(|&pos| {
+                            let family = self.attr(self.buf[pos]).vowel_index;
+                            matches!(family, 2 | 3 | 5 | 8 | 9)
+                        })
+                        .unwrap_or(start_pos + 1),
+                }
             }
-            _ => end_pos as usize,
+            _ => end_pos,
         };
 
+        Some(target_pos)
+    }
+
+    /// Bounds (inclusive) of the run of consecutive vowels around `pos`
+    fn vowel_run(&self, pos: usize) -> (usize, usize) {
+        let is_vowel = |p: usize| self.attr(self.buf[p]).vowel_index > 0;
+        let mut start = pos;
+        while start > 0 && is_vowel(start - 1) {
+            start -= 1;
+        }
+        let mut end = pos;
+        while end + 1 < self.keys && is_vowel(end + 1) {
+            end += 1;
+        }
+        (start, end)
+    }
+
+    /// Whether the vowel run holding `pos` is followed by a stop coda: c,
+    /// ch, p or t. Only the first letter counts, so tone keys already typed
+    /// literally after the coda ("hocf" + "f") don't hide it.
+    fn ends_in_stop(&self, pos: usize) -> bool {
+        let (_, run_end) = self.vowel_run(pos);
+        self.buf[run_end + 1..self.keys]
+            .first()
+            .is_some_and(|c| matches!(c.to_ascii_lowercase(), 'c' | 'p' | 't'))
+    }
+
+    /// Move an existing tone when the syllable grows and the main vowel
+    /// changes ("muaf" + "n": mùa -> muàn, "thusy": thúy -> thuý). Returns true if the buffer
+    /// was rewritten; `backs` and `output_buffer` then cover the change.
+    fn migrate_tone(&mut self) -> bool {
+        let Some(target) = self.tone_target() else {
+            return false;
+        };
+
+        // The toned vowel must sit in the same vowel run as the target
+        let (run_start, run_end) = self.vowel_run(target);
+        let Some(toned) = (run_start..=run_end).find(|&pos| {
+            self.attr(self.buf[pos]).current_tone > 0
+        }) else {
+            return false;
+        };
+        if toned == target {
+            return false;
+        }
+
+        let tone = self.attr(self.buf[toned]).current_tone;
+        self.buf[toned] = self.get_base_vowel(self.buf[toned]);
+        self.buf[target] = self.apply_tone_to_base(self.buf[target], tone);
+
+        let from = toned.min(target);
+        self.backs = self.keys - from;
+        self.rebuild_output(from);
+        true
+    }
+
+    /// Put a tone mark (s, f, r, x, j in Telex, 1-5 in VNI; VNI 0 removes
+    /// it). With no vowel to carry it nothing is pushed, so `process`
+    /// buffers the key as a plain letter.
+    fn put_tone_mark(&mut self, c: char, is_lower: bool) {
+        if self.keys == 0 {
+            return;
+        }
+
+        let Some(tone_index) = self.tone_for_key(c) else {
+            return;
+        };
+        if tone_index == 0 {
+            if self.input_method == InputMethod::Telex && self.z_key == ZKey::RemoveMarks {
+                self.remove_marks();
+            } else {
+                self.remove_tone();
+            }
+            return;
+        }
+
+        let Some(target_pos) = self.tone_target() else {
+            return;
+        };
+
+        // Syllables ending in a stop take only sắc and nặng; strict, any
+        // other tone key is typed literally ("hocf")
+        if self.strict_tones && matches!(tone_index, 2..=4) && self.ends_in_stop(target_pos) {
+            return;
+        }
+
         // Get the vowel and apply the tone
         let vowel_char = self.buf[target_pos];
-        let vowel_attr = self.dt.get(&vowel_char).copied().unwrap_or_default();
+        let vowel_attr = self.attr(vowel_char);
         let vowel_idx = vowel_attr.vowel_index as usize;
         
         if vowel_idx == 0 || vowel_idx > 12 {
@@ -828,51 +1654,220 @@ impl UnikeyEngine {
         // Check for duplicate tone (undo)
         let current_tone = vowel_attr.current_tone;
         if current_tone == tone_index {
-            // Remove the tone
-            let base = self.bd[vowel_idx - 1][5];
-            let new_char = if vowel_char.is_uppercase() {
-                base.to_uppercase().next().unwrap_or(base)
-            } else {
-                base
-            };
+            // Remove the tone and type the key literally ("ass" → "as"). The
+            // key itself never reached the screen, so only the vowel onwards
+            // is erased; the rest of the word goes English
+            let new_char = self.bd_char(vowel_idx, 5, vowel_char.is_uppercase());
             self.backs = self.keys - target_pos;
             self.buf[target_pos] = new_char;
             self.rebuild_output(target_pos);
             self.output_buffer.push(c);
+            self.keys_pushed = self.output_buffer.len();
             self.put_char(c, is_lower);
             self.temp_viet_off = true;
             return;
         }
 
-        // Apply the tone
-        let base = self.get_base_vowel(vowel_char);
-        let base_attr = self.dt.get(&base).copied().unwrap_or_default();
+        self.apply_tone(target_pos, tone_index);
+    }
+
+    /// Put tone 1-5 on the vowel at `target_pos`, clearing it from the rest
+    /// of the vowel run, and push the rewritten tail of the word
+    fn apply_tone(&mut self, target_pos: usize, tone_index: u8) {
+        let Some(new_char) = self.toned_vowel(self.buf[target_pos], tone_index) else {
+            return;
+        };
+
+        // Only one vowel carries the tone: clear one placed on another vowel
+        // before the syllable grew ("thusy" + "r": thúy -> thuỷ)
+        let (run_start, run_end) = self.vowel_run(target_pos);
+        let mut from = target_pos;
+        for pos in run_start..=run_end {
+            let toned = self.attr(self.buf[pos]).current_tone > 0;
+            if pos != target_pos && toned {
+                self.buf[pos] = self.get_base_vowel(self.buf[pos]);
+                from = from.min(pos);
+            }
+        }
+
+        self.backs = self.keys - from;
+        self.buf[target_pos] = new_char;
+        self.rebuild_output(from);
+        self.keys_pushed = self.output_buffer.len();
+    }
+
+    /// `vowel` carrying `tone` (1-5) instead of the tone it has, or None for
+    /// a letter that takes no tone
+    fn toned_vowel(&self, vowel: char, tone: u8) -> Option<char> {
+        let vowel_idx = self.attr(vowel).vowel_index as usize;
+        let base_attr = self.attr(self.get_base_vowel(vowel));
         let base_idx = if base_attr.vowel_index > 0 { base_attr.vowel_index as usize } else { vowel_idx };
-        
-        if base_idx == 0 || base_idx > 12 {
+        (1..=12)
+            .contains(&base_idx)
+            .then(|| self.bd_char(base_idx, tone as usize - 1, vowel.is_uppercase()))
+    }
+
+    /// Tone on the syllable being typed: 1-5 for sắc, huyền, hỏi, ngã,
+    /// nặng, or None when it has no tone or no vowel
+    pub fn current_syllable_tone(&self) -> Option<u8> {
+        let target = self.tone_target()?;
+        let (run_start, run_end) = self.vowel_run(target);
+        (run_start..=run_end)
+            .map(|pos| self.attr(self.buf[pos]).current_tone)
+            .find(|&tone| tone > 0)
+    }
+
+    /// Put `tone` (1-5, or 0 to remove it) on the syllable being typed, as
+    /// if its tone key were pressed, but without the undo: setting the tone
+    /// it already has changes nothing. Returns the edit for the screen; an
+    /// empty `Output` when nothing changed.
+    pub fn set_tone(&mut self, tone: u8) -> ProcessResult {
+        self.keys_pushed = 0;
+        self.backs = 0;
+        self.output_buffer.clear();
+        self.last_tone_key = None;
+
+        if self.vietnamese_mode && !self.temp_viet_off && tone <= 5 {
+            if tone == 0 {
+                self.remove_tone();
+            } else if self.current_syllable_tone() != Some(tone) {
+                if let Some(target) = self.tone_target() {
+                    self.apply_tone(target, tone);
+                }
+            }
+        }
+
+        if self.backs == 0 {
+            return ProcessResult::Output(String::new());
+        }
+        // The keys typed no longer give this word
+        self.raw_keys = None;
+        let result = ProcessResult::Replace {
+            backspaces: self.backs,
+            text: self.output_buffer.clone(),
+        };
+        self.emitted = self.emitted_after(self.emitted, &result);
+        result
+    }
+
+    /// The whole word as `set_tone(tone)` would leave it, without changing
+    /// anything, so a tone picker can show every variant at once: `ca` →
+    /// "cá" for 1, "ca" for 0. The word as it is when the tone can't go on it.
+    pub fn preview_tone(&self, tone: u8) -> String {
+        let mut word = self.buf[..self.keys].to_vec();
+        let target = self.tone_target().filter(|_| self.vietnamese_mode && !self.temp_viet_off && tone <= 5);
+        if let Some(target) = target {
+            let toned = if tone == 0 { None } else { self.toned_vowel(word[target], tone) };
+            if tone == 0 || toned.is_some() {
+                let (run_start, run_end) = self.vowel_run(target);
+                for c in &mut word[run_start..=run_end] {
+                    *c = self.get_base_vowel(*c);
+                }
+                if let Some(toned) = toned {
+                    word[target] = toned;
+                }
+            }
+        }
+        word.into_iter()
+            .chain(self.after_cursor.iter().rev().map(|&(c, _)| c))
+            .collect()
+    }
+
+    /// With `english_double_letters`, a tone key that undoes the tone the
+    /// same key placed on the previous press keeps that first key too:
+    /// "clas" + "s" → "class" instead of "clas"
+    fn keep_doubled_tone_key(&mut self, last_tone_key: Option<(char, bool)>, c: char) {
+        if !self.english_double_letters || self.input_method != InputMethod::Telex {
             return;
         }
+        let Some((first, first_lower)) = last_tone_key.filter(|(k, _)| k.eq_ignore_ascii_case(&c)) else {
+            return;
+        };
+        // The undo put `c` last in both the buffer and the output
+        let Some(last) = self.keys.checked_sub(1) else {
+            return;
+        };
+        let (repeat, repeat_lower) = (self.buf[last], self.lower_case[last]);
+        self.keys = last;
+        self.put_char(first, first_lower);
+        self.put_char(repeat, repeat_lower);
+        self.output_buffer.pop();
+        self.output_buffer.push(first);
+        self.output_buffer.push(repeat);
+    }
 
-        let new_char = self.bd[base_idx - 1][tone_index as usize - 1];
-        let new_char = if vowel_char.is_uppercase() {
-            new_char.to_uppercase().next().unwrap_or(new_char)
-        } else {
-            new_char
+    /// Tone a key applies in the current input method: 1-5 for sắc, huyền,
+    /// hỏi, ngã, nặng, or 0 to remove the tone
+    fn tone_for_key(&self, c: char) -> Option<u8> {
+        match self.input_method {
+            InputMethod::Vni => c.to_digit(10).filter(|&d| d <= 5).map(|d| d as u8),
+            _ => match c.to_lowercase().next().unwrap_or(c) {
+                's' => Some(1), // acute (sắc)
+                'f' => Some(2), // grave (huyền)
+                'r' => Some(3), // hook (hỏi)
+                'x' => Some(4), // tilde (ngã)
+                'j' => Some(5), // dot (nặng)
+                'z' if self.z_key != ZKey::Literal => Some(0), // remove
+                _ => None,
+            },
+        }
+    }
+
+    /// Strip the tone from the syllable being typed (VNI 0). Whichever vowel
+    /// carries it goes back to its base form; with no tone nothing is
+    /// pushed and the key stays a literal.
+    fn remove_tone(&mut self) {
+        let Some(target) = self.tone_target() else {
+            return;
+        };
+        let (run_start, run_end) = self.vowel_run(target);
+        let Some(toned) = (run_start..=run_end).find(|&pos| {
+            self.attr(self.buf[pos]).current_tone > 0
+        }) else {
+            return;
         };
 
-        self.backs = self.keys - target_pos;
-        self.buf[target_pos] = new_char;
-        self.rebuild_output(target_pos);
+        self.buf[toned] = self.get_base_vowel(self.buf[toned]);
+        self.backs = self.keys - toned;
+        self.rebuild_output(toned);
+        self.keys_pushed = self.output_buffer.len();
+    }
+
+    /// Strip the tone and every mark from the word being typed (Telex `z`
+    /// with `ZKey::RemoveMarks`): each letter goes back to the plain one its
+    /// keys started from, â/ă → a, ơ → o, ư → u, đ → d. With nothing to
+    /// strip nothing is pushed and the key stays a literal.
+    fn remove_marks(&mut self) {
+        let Some(from) = (0..self.keys).find(|&pos| self.plain_letter(self.buf[pos]) != self.buf[pos]) else {
+            return;
+        };
+        for pos in from..self.keys {
+            self.buf[pos] = self.plain_letter(self.buf[pos]);
+        }
+        self.backs = self.keys - from;
+        self.rebuild_output(from);
         self.keys_pushed = self.output_buffer.len();
     }
 
+    /// `c` without tone or mark, through the BK and BW tables in reverse
+    fn plain_letter(&self, c: char) -> char {
+        let base = self.get_base_vowel(c);
+        if let Some(i) = self.bk.iter().position(|&k| k == base) {
+            return ['d', 'D', 'a', 'A', 'e', 'E', 'o', 'O'][i];
+        }
+        if let Some(i) = self.bw.iter().position(|&w| w == base) {
+            return ['a', 'A', 'o', 'O', 'u', 'U'][i];
+        }
+        base
+    }
+
     /// Process a shortcut key
     fn short_key(&mut self, c: char, is_lower: bool) {
         // For 'w' alone, output ư
         let new_char = match c.to_lowercase().next().unwrap_or(c) {
             'w' => if is_lower { 'ư' } else { 'Ư' },
             '[' => 'ơ',
-            ']' => 'Ơ',
+            ']' => 'ư',
             _ => return,
         };
 
@@ -887,38 +1882,28 @@ impl UnikeyEngine {
         }
 
         self.output_buffer.push(new_char);
-        self.put_char(new_char, is_lower);
+        self.put_char(new_char, new_char.is_lowercase());
         self.keys_pushed = 1;
     }
 
     /// Get the base vowel (without tone) for a character
     fn get_base_vowel(&self, c: char) -> char {
-        let attr = self.dt.get(&c).copied().unwrap_or_default();
+        let attr = self.attr(c);
         if attr.vowel_index == 0 || attr.vowel_index > 12 {
             return c;
         }
         
-        let base = self.bd[attr.vowel_index as usize - 1][5];
-        if c.is_uppercase() {
-            base.to_uppercase().next().unwrap_or(base)
-        } else {
-            base
-        }
+        self.bd_char(attr.vowel_index as usize, 5, c.is_uppercase())
     }
 
     /// Apply a tone to a base vowel
     fn apply_tone_to_base(&self, base: char, tone: u8) -> char {
-        let attr = self.dt.get(&base).copied().unwrap_or_default();
+        let attr = self.attr(base);
         if attr.vowel_index == 0 || attr.vowel_index > 12 || tone == 0 || tone > 5 {
             return base;
         }
 
-        let toned = self.bd[attr.vowel_index as usize - 1][tone as usize - 1];
-        if base.is_uppercase() {
-            toned.to_uppercase().next().unwrap_or(toned)
-        } else {
-            toned
-        }
+        self.bd_char(attr.vowel_index as usize, tone as usize - 1, base.is_uppercase())
     }
 
     /// Rebuild output from a position in the buffer
@@ -931,8 +1916,19 @@ impl UnikeyEngine {
 
     // Public API methods
     
-    pub fn set_input_method(&mut self, method: InputMethod) {
+    /// Switch the input method. The word being composed is committed first,
+    /// as a separator would, so the new method never edits keys typed under
+    /// the old one; returns that word, empty when there was none or the
+    /// method is unchanged. Deferred tones (`tone_at_end`) are dropped.
+    pub fn set_input_method(&mut self, method: InputMethod) -> String {
+        if method == self.input_method {
+            return String::new();
+        }
+        self.forget_syllables();
+        let committed = self.get_buffer();
+        self.clear_buf();
         self.input_method = method;
+        committed
     }
 
     pub fn set_vietnamese_mode(&mut self, enabled: bool) {
@@ -950,17 +1946,234 @@ impl UnikeyEngine {
         self.vietnamese_mode
     }
 
+    /// Whether an undo ("aaa", "ass") switched transforms off for the rest
+    /// of the word. The next separator turns them back on.
+    pub fn is_temporarily_off(&self) -> bool {
+        self.temp_viet_off
+    }
+
+    /// The whole word, including any letters right of the cursor
     pub fn get_buffer(&self) -> String {
-        self.buf[..self.keys].iter().collect()
+        self.buf[..self.keys]
+            .iter()
+            .copied()
+            .chain(self.after_cursor.iter().rev().map(|&(c, _)| c))
+            .collect()
+    }
+
+    /// Keys typed for the current word, or None once a backspace made them
+    /// unreliable
+    pub fn raw_keys(&self) -> Option<&str> {
+        self.raw_keys.as_deref()
     }
 
     pub fn set_free_marking(&mut self, enabled: bool) {
+        self.forget_syllables();
         self.free_marking = enabled;
     }
 
     pub fn set_modern_style(&mut self, enabled: bool) {
+        self.forget_syllables();
         self.modern_style = enabled;
     }
+
+    /// Place tones on oa, oe and uy this way instead of by `modern_style`.
+    /// None follows `modern_style`.
+    pub fn set_oa_oe_uy_style(&mut self, style: Option<TonePlacement>) {
+        self.forget_syllables();
+        self.oa_oe_uy_style = style;
+    }
+
+    /// Telex: type ư for a `w` that has no vowel to horn ("w" → "ư"). When
+    /// off, such a `w` stays a literal letter.
+    pub fn set_standalone_w(&mut self, enabled: bool) {
+        self.forget_syllables();
+        self.standalone_w = enabled;
+    }
+
+    /// Type ơ for `[` and ư for `]` in Telex instead of treating them as
+    /// separators
+    pub fn set_bracket_shortcuts(&mut self, enabled: bool) {
+        self.forget_syllables();
+        self.bracket_shortcuts = enabled;
+    }
+
+    /// Revert transformed words that aren't in the bundled Vietnamese word
+    /// list when they are committed. Needs the `dictionary` feature.
+    pub fn set_dictionary_revert(&mut self, enabled: bool) {
+        self.forget_syllables();
+        if enabled && !self.dictionary_revert {
+            // Parse the word list now rather than on the first commit
+            #[cfg(feature = "dictionary")]
+            super::dictionary::load();
+        }
+        self.dictionary_revert = enabled;
+    }
+
+    /// Telex: let the VNI keys 6-9 mark a syllable typed without any Telex
+    /// mark ("a6" → "â", "tam8" → "tăm", "huo7" → "hươ"). 1-5 stay separators.
+    pub fn set_allow_vni_in_telex(&mut self, enabled: bool) {
+        self.forget_syllables();
+        self.allow_vni_in_telex = enabled;
+    }
+
+    /// Keep the word a separator committed, so a backspace right after the
+    /// separator makes it editable again ("vieet " ⌫ "j" → "việt")
+    pub fn set_restore_previous_syllable(&mut self, enabled: bool) {
+        self.forget_syllables();
+        self.restore_previous_syllable = enabled;
+        if !enabled {
+            self.previous_syllable = None;
+        }
+    }
+
+    /// Make `extra` end words too and `exclude` ordinary characters that a
+    /// word can contain ("e-mail" as one word), starting from the default
+    /// set each time. Letters never become separators and backspace always
+    /// stays one.
+    pub fn set_separators(&mut self, extra: &[char], exclude: &[char]) {
+        self.forget_syllables();
+        for attr in self.dt_ascii.iter_mut().chain(self.dt.values_mut()) {
+            attr.is_separator = false;
+        }
+        let separators = DEFAULT_SEPARATORS
+            .iter()
+            .filter(|&&c| c == '\x08' || !exclude.contains(&c))
+            .chain(extra.iter().filter(|c| !c.is_alphabetic()));
+        for &c in separators {
+            self.attr_mut(c).is_separator = true;
+        }
+    }
+
+    /// Telex: leave these tone keys as plain letters, e.g. `x` so "max"
+    /// isn't "mã". Case doesn't matter; keys that aren't tone keys are
+    /// ignored.
+    pub fn set_disabled_tone_keys(&mut self, keys: &[char]) {
+        self.forget_syllables();
+        self.disabled_tone_keys = keys.iter().map(|c| c.to_ascii_lowercase()).collect();
+        self.init_tone_keys();
+    }
+
+    /// Add a custom composition rule, or replace the one with this trigger:
+    /// when the keys of `trigger` end the word before the cursor (as shown,
+    /// "oe" but not "ôe"), they become `output`. Rules are checked before
+    /// the built-in Telex and VNI handling, so "aa" → "ä" overrides â, and
+    /// the longest matching trigger wins. They fire only in Vietnamese mode.
+    /// A trigger must be non-empty and can't contain a separator, which
+    /// would end the word first.
+    pub fn add_rule(&mut self, trigger: &str, output: &str) -> Result<(), String> {
+        if trigger.is_empty() {
+            return Err("empty trigger".to_string());
+        }
+        if let Some(c) = trigger.chars().find(|&c| self.attr(c).is_separator) {
+            return Err(format!("trigger {:?} contains the separator {:?}", trigger, c));
+        }
+        self.forget_syllables();
+        self.rules.insert(trigger.to_string(), output.to_string());
+        Ok(())
+    }
+
+    /// Remove every rule added with `add_rule`
+    pub fn clear_rules(&mut self) {
+        self.forget_syllables();
+        self.rules.clear();
+    }
+
+    /// Telex: a tone key typed twice in a row types both letters instead of
+    /// undoing the tone to a single one ("class", "pass", "staff")
+    pub fn set_english_double_letters(&mut self, enabled: bool) {
+        self.forget_syllables();
+        self.english_double_letters = enabled;
+    }
+
+    /// Stop transforming a word once it has `len` letters, until the next
+    /// separator. 0 never stops.
+    pub fn set_max_syllable_len(&mut self, len: usize) {
+        self.forget_syllables();
+        self.max_syllable_len = len;
+    }
+
+    /// How many letters may follow the vowels for a tone key to still
+    /// reach them: "hocj" → "học" needs 1, "manhj" → "mạnh" 2, which covers
+    /// every Vietnamese coda. More lets tones cross letters no syllable
+    /// ends with; 0 only tones a vowel right before the key.
+    pub fn set_max_after_vowel(&mut self, letters: usize) {
+        self.forget_syllables();
+        self.max_after_vowel = letters.min(KEY_BUFSIZE);
+    }
+
+    /// Strict tones: a syllable ending in c, ch, p or t takes only sắc and
+    /// nặng, so huyền, hỏi and ngã keys are typed literally there ("hocf")
+    pub fn set_strict_tones(&mut self, enabled: bool) {
+        self.forget_syllables();
+        self.strict_tones = enabled;
+    }
+
+    /// Telex: only a `dd` that starts the syllable types đ ("dda" → "đa");
+    /// elsewhere the second d is literal ("add"). Off, any `dd` doubles.
+    pub fn set_dd_initial_only(&mut self, enabled: bool) {
+        self.forget_syllables();
+        self.dd_initial_only = enabled;
+    }
+
+    /// Telex: what `z` removes from the word being typed, the tone or the
+    /// tone and the marks; `ZKey::Literal` types it as a letter
+    pub fn set_z_key(&mut self, action: ZKey) {
+        self.forget_syllables();
+        self.z_key = action;
+    }
+
+    /// Tones wait for the separator and go on the finished syllable:
+    /// "toans " → "toán ". Only the last tone key typed counts; earlier
+    /// ones are dropped ("toansf " → "toàn ").
+    pub fn set_tone_at_end(&mut self, enabled: bool) {
+        self.forget_syllables();
+        if self.tone_at_end != enabled {
+            self.tone_at_end = enabled;
+            self.deferred_tones.clear();
+        }
+    }
+
+    /// Experimental: remember the word as each key left it, keyed by the
+    /// raw keys so far, and restore it when the same keys come again instead
+    /// of recomputing it. Keeps the `capacity` most recently used entries
+    /// (one per key of a word); 0 turns it off. Every other setter empties
+    /// the cache.
+    pub fn set_syllable_cache(&mut self, capacity: usize) {
+        self.syllable_cache = (capacity > 0).then(|| SyllableCache::new(capacity));
+        // Nothing vouched for a word typed while the cache was off
+        self.word_cacheable = self.keys == 0 && self.raw_keys.as_deref() == Some("") && !self.temp_viet_off;
+    }
+
+    /// Hits and misses of the syllable cache, or None when it is off
+    pub fn syllable_cache_stats(&self) -> Option<(u64, u64)> {
+        self.syllable_cache.as_ref().map(SyllableCache::stats)
+    }
+
+    /// Cached states are only valid for the settings they were made with
+    fn forget_syllables(&mut self) {
+        if let Some(cache) = &mut self.syllable_cache {
+            cache.clear();
+        }
+    }
+}
+
+/// Raw keys that could be an English word: letters only, with a vowel
+fn is_plausible_english(raw: &str) -> bool {
+    raw.len() >= 2
+        && raw.chars().all(|c| c.is_ascii_alphabetic())
+        && raw.chars().any(|c| "aeiouyAEIOUY".contains(c))
+}
+
+#[cfg(feature = "dictionary")]
+fn is_known_word(word: &str) -> bool {
+    super::dictionary::contains(word)
+}
+
+/// Without the word list every word counts as known, so nothing is reverted
+#[cfg(not(feature = "dictionary"))]
+fn is_known_word(_word: &str) -> bool {
+    true
 }
 
 impl Default for UnikeyEngine {
@@ -969,8 +2182,93 @@ impl Default for UnikeyEngine {
     }
 }
 
-/// Result of processing a keypress
+/// Chainable setup for a `UnikeyEngine`, for embedders that would otherwise
+/// follow `new()` with a row of setters:
+/// `UnikeyEngine::builder().method(InputMethod::Vni).modern_style(false).build()`
 #[derive(Debug, Clone)]
+pub struct UnikeyEngineBuilder {
+    method: InputMethod,
+    free_marking: bool,
+    modern_style: bool,
+    oa_oe_uy_style: Option<TonePlacement>,
+    tone_next_to_vowel: bool,
+    vietnamese_mode: bool,
+}
+
+impl UnikeyEngineBuilder {
+    pub fn new() -> Self {
+        Self {
+            method: InputMethod::Telex,
+            free_marking: true,
+            modern_style: true,
+            oa_oe_uy_style: None,
+            tone_next_to_vowel: false,
+            vietnamese_mode: true,
+        }
+    }
+
+    /// Input method. Default: Telex
+    pub fn method(mut self, method: InputMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Let a horn or breve key mark a vowel earlier in the word
+    /// ("tuongw" → "tương"), not only the letter right before it.
+    /// Default: on
+    pub fn free_marking(mut self, enabled: bool) -> Self {
+        self.free_marking = enabled;
+        self
+    }
+
+    /// New-style tone placement (hoà, thuý); off places it the old way
+    /// (hòa, thúy). Default: on
+    pub fn modern_style(mut self, enabled: bool) -> Self {
+        self.modern_style = enabled;
+        self
+    }
+
+    /// Tone placement on oa, oe and uy alone, overriding `modern_style` for
+    /// them. Default: follow `modern_style`
+    pub fn oa_oe_uy_style(mut self, style: TonePlacement) -> Self {
+        self.oa_oe_uy_style = Some(style);
+        self
+    }
+
+    /// Only let a tone key mark the vowel typed right before it ("as" →
+    /// "á", but "ans" stays). Default: off
+    pub fn tone_next_to_vowel(mut self, enabled: bool) -> Self {
+        self.tone_next_to_vowel = enabled;
+        self
+    }
+
+    /// Start composing Vietnamese; off passes every key through until
+    /// toggled. Default: on
+    pub fn vietnamese_mode(mut self, enabled: bool) -> Self {
+        self.vietnamese_mode = enabled;
+        self
+    }
+
+    pub fn build(self) -> UnikeyEngine {
+        let mut engine = UnikeyEngine::new();
+        engine.set_input_method(self.method);
+        engine.set_free_marking(self.free_marking);
+        engine.set_modern_style(self.modern_style);
+        engine.set_oa_oe_uy_style(self.oa_oe_uy_style);
+        engine.tone_next_to_vowel = self.tone_next_to_vowel;
+        engine.set_vietnamese_mode(self.vietnamese_mode);
+        engine
+    }
+}
+
+impl Default for UnikeyEngineBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Result of processing a keypress
+#[derive(Debug, Clone, PartialEq)]
 pub enum ProcessResult {
     /// Pass the character through unchanged
     PassThrough(char),
@@ -982,3 +2280,33 @@ pub enum ProcessResult {
         text: String,
     },
 }
+
+/// The text a host ends up with after applying `results` in order to
+/// `start`, editing at its end. This is the contract for each result:
+/// - `PassThrough(c)`: type `c`; a backspace (`'\x08'`) deletes the last
+///   character instead
+/// - `Output(text)`: append `text`
+/// - `Replace { backspaces, text }`: delete `backspaces` characters, then
+///   append `text`
+///
+/// Characters are Unicode scalar values, not bytes. Deleting stops at the
+/// start of the text, like Backspace at the start of a field.
+pub fn apply_results(start: &str, results: &[ProcessResult]) -> String {
+    let mut text = start.to_string();
+    for result in results {
+        match result {
+            ProcessResult::PassThrough('\x08') => {
+                text.pop();
+            }
+            ProcessResult::PassThrough(c) => text.push(*c),
+            ProcessResult::Output(output) => text.push_str(output),
+            ProcessResult::Replace { backspaces, text: output } => {
+                for _ in 0..*backspaces {
+                    text.pop();
+                }
+                text.push_str(output);
+            }
+        }
+    }
+    text
+}
@@ -272,6 +272,12 @@ impl VietnameseEngine {
     }
 }
 
+impl Default for VietnameseEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[allow(dead_code)] // Will be used when input processing is fully implemented
 #[derive(Debug, Clone, Copy)]
 enum ToneMark {
@@ -28,4 +28,10 @@ impl VniProcessor {
         // Check if the input can be transformed using VNI rules
         false
     }
-}
\ No newline at end of file
+}
+
+impl Default for VniProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,212 @@
+// Platform backends for capturing keystrokes and injecting composed text
+// KeyboardMonitor talks to a backend only through InputBackend, so the same
+// GrabAction semantics (block the key, send backspaces, type the replacement)
+// work on every platform.
+
+use rdev::{simulate, Event, EventType, GrabError, Key, SimulateError};
+use std::io;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use unicode_normalization::UnicodeNormalization;
+use vaixkey::VaixError;
+
+/// Capture and injection primitives for one platform
+pub trait InputBackend: Send + Sync {
+    /// Short name for logs and `--status`
+    fn name(&self) -> &'static str;
+
+    /// Intercept keyboard events until the process exits. Returning `None`
+    /// from the callback blocks the original event.
+    fn grab(&self, callback: fn(Event) -> Option<Event>) -> Result<(), VaixError>;
+
+    /// Make a running `grab` return. Grabs that can't be interrupted keep
+    /// running, with the callback passing every key through once the monitor
+    /// has stopped.
+    fn stop_grab(&self) {}
+
+    /// Delete `count` characters before the caret
+    fn send_backspaces(&self, count: usize) -> Result<(), VaixError>;
+
+    /// Type `text` at the caret. Stops at the first character that can't be
+    /// sent.
+    fn send_text(&self, text: &str) -> Result<(), VaixError>;
+
+    /// Type `text` with key presses only, for apps that drop Unicode strings
+    /// (`injection_fallback`). One key per character, so the engine's later
+    /// backspaces still line up; backends without key events fail.
+    fn send_keystrokes(&self, _text: &str) -> Result<(), VaixError> {
+        Err(VaixError::Backend { backend: self.name(), message: "cannot send key events".to_string() })
+    }
+
+    /// The clipboard's text, or None if it holds none. Backends without
+    /// clipboard access fail, and paste injection types the text instead.
+    fn read_clipboard(&self) -> Result<Option<String>, VaixError> {
+        Err(VaixError::Backend { backend: self.name(), message: "no clipboard access".to_string() })
+    }
+
+    /// Replace the clipboard's contents with `text`
+    fn write_clipboard(&self, _text: &str) -> Result<(), VaixError> {
+        Err(VaixError::Backend { backend: self.name(), message: "no clipboard access".to_string() })
+    }
+
+    /// Paste the clipboard at the caret, as Cmd+V does
+    fn send_paste(&self) -> Result<(), VaixError> {
+        Err(VaixError::Backend { backend: self.name(), message: "cannot paste".to_string() })
+    }
+}
+
+/// Backend built on `rdev::grab` and `rdev::simulate`. Only characters with
+/// a physical key can be typed; other platforms provide their own backend for
+/// Unicode injection.
+#[cfg_attr(target_os = "macos", allow(dead_code))] // macOS uses MacosBackend
+pub struct RdevBackend;
+
+impl InputBackend for RdevBackend {
+    fn name(&self) -> &'static str {
+        "rdev"
+    }
+
+    fn grab(&self, callback: fn(Event) -> Option<Event>) -> Result<(), VaixError> {
+        rdev::grab(callback).map_err(|e| grab_error(self.name(), e))
+    }
+
+    fn send_backspaces(&self, count: usize) -> Result<(), VaixError> {
+        for _ in 0..count {
+            send_key(Key::Backspace).map_err(|_| inject_error(self.name(), "Backspace"))?;
+            thread::sleep(Duration::from_millis(2));
+        }
+        Ok(())
+    }
+
+    fn send_text(&self, text: &str) -> Result<(), VaixError> {
+        for c in text.chars() {
+            let sent = send_ascii_char(c).map_err(|_| inject_error(self.name(), format!("'{}'", c)))?;
+            if !sent {
+                return Err(inject_error(self.name(), format!("'{}' (no key)", c)));
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+        Ok(())
+    }
+
+    fn send_keystrokes(&self, text: &str) -> Result<(), VaixError> {
+        send_keystroke_text(self.name(), text, |_| None)
+    }
+}
+
+/// Type `text` as real key presses: each character's base letter (`ộ` → o,
+/// `đ` → d) on its key, after the dead key for its mark when `dead_key` has
+/// one and the mark is the only one. Other marks are dropped, leaving one
+/// character on screen per character of `text`.
+pub(super) fn send_keystroke_text(
+    backend: &'static str,
+    text: &str,
+    dead_key: fn(char) -> Option<Key>,
+) -> Result<(), VaixError> {
+    for c in text.chars() {
+        let mut parts = std::iter::once(c).nfd();
+        let base = match parts.next() {
+            Some('đ') => 'd',
+            Some('Đ') => 'D',
+            Some(base) => base,
+            None => continue,
+        };
+        let marks: Vec<char> = parts.collect();
+        if let [mark] = marks[..] {
+            if let Some(key) = dead_key(mark) {
+                simulate(&EventType::KeyPress(Key::Alt)).map_err(|_| inject_error(backend, "Option"))?;
+                thread::sleep(Duration::from_millis(1));
+                let sent = send_key(key).map_err(|_| inject_error(backend, format!("the dead key for '{}'", c)));
+                // Released even when the key failed, so Option doesn't stay down
+                let _ = simulate(&EventType::KeyRelease(Key::Alt));
+                sent?;
+            }
+        }
+        if !send_ascii_char(base).map_err(|_| inject_error(backend, format!("'{}'", base)))? {
+            return Err(inject_error(backend, format!("'{}' (no key)", c)));
+        }
+        thread::sleep(Duration::from_millis(2));
+    }
+    Ok(())
+}
+
+/// Map an rdev grab failure. A refused event tap or unreadable input
+/// devices mean the permission is missing.
+pub(super) fn grab_error(backend: &'static str, error: GrabError) -> VaixError {
+    match error {
+        GrabError::EventTapError => VaixError::PermissionDenied(
+            "cannot create the event tap; grant Accessibility permission and restart the terminal".to_string(),
+        ),
+        GrabError::IoError(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            VaixError::PermissionDenied(format!("cannot read the input devices: {}", e))
+        }
+        e => VaixError::Backend { backend, message: format!("{:?}", e) },
+    }
+}
+
+/// A character or key `backend` could not type
+pub(super) fn inject_error(backend: &'static str, what: impl std::fmt::Display) -> VaixError {
+    VaixError::Backend { backend, message: format!("cannot type {}", what) }
+}
+
+/// Send a single key press and release
+pub(super) fn send_key(key: Key) -> Result<(), SimulateError> {
+    simulate(&EventType::KeyPress(key))?;
+    thread::sleep(Duration::from_millis(1));
+    simulate(&EventType::KeyRelease(key))
+}
+
+/// Type an ASCII letter or separator through direct key simulation, pressing
+/// the key that types it on the configured layout. Returns false if the
+/// character has no physical key.
+pub(super) fn send_ascii_char(c: char) -> Result<bool, SimulateError> {
+    if !(c.is_ascii_alphabetic() || super::separator_to_key(c).is_some()) {
+        return Ok(false);
+    }
+    let Some(key) = super::char_to_layout_key(c, super::active_layout()) else {
+        return Ok(false);
+    };
+
+    let needs_shift = c.is_uppercase();
+    if needs_shift {
+        simulate(&EventType::KeyPress(Key::ShiftLeft))?;
+        thread::sleep(Duration::from_millis(1));
+    }
+    let sent = send_key(key);
+    if needs_shift {
+        // Released even when the key failed, so Shift doesn't stay down
+        let _ = simulate(&EventType::KeyRelease(Key::ShiftLeft));
+    }
+    sent.map(|()| true)
+}
+
+/// Check whether we are running inside a Wayland session
+#[cfg(target_os = "linux")]
+fn is_wayland_session() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/// Pick the backend for the current session
+pub fn select_backend(debug_mode: bool) -> Arc<dyn InputBackend> {
+    #[cfg(target_os = "macos")]
+    {
+        Arc::new(super::macos::MacosBackend::new(debug_mode))
+    }
+
+    // XTest still reaches XWayland apps, but native Wayland ones ignore it
+    #[cfg(all(target_os = "linux", feature = "wayland"))]
+    if is_wayland_session() {
+        match super::wayland::WaylandBackend::connect(debug_mode) {
+            Ok(backend) => return Arc::new(backend),
+            Err(e) => log::error!("{}; only XWayland apps will receive typed text", e),
+        }
+    }
+    #[cfg(all(target_os = "linux", not(feature = "wayland")))]
+    if is_wayland_session() {
+        log::error!("Built without the wayland feature; only XWayland apps will receive typed text");
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    Arc::new(RdevBackend)
+}
@@ -0,0 +1,61 @@
+// Key dump (`vaixkey --dump-keys`): print every key event as rdev reports it,
+// with the character VaixKey would read from it, until Ctrl+C. It listens
+// instead of grabbing, so nothing is blocked, transformed or injected; for
+// "my s key isn't recognized" reports, where the question is what reaches
+// VaixKey at all.
+
+use std::time::{Duration, SystemTime};
+
+use rdev::{listen, Event, EventType, ListenError};
+use vaixkey::config::KeyboardLayout;
+use vaixkey::VaixError;
+
+use super::{is_modifier_key, key_to_char, typed_char};
+
+/// One line for `event`, `elapsed` after the dump started, or None for
+/// mouse movement, which would drown the keys
+pub fn describe_event(event: &Event, layout: KeyboardLayout, elapsed: Duration) -> Option<String> {
+    let (kind, what) = match event.event_type {
+        EventType::KeyPress(key) | EventType::KeyRelease(key) => {
+            let kind = if matches!(event.event_type, EventType::KeyPress(_)) { "press" } else { "release" };
+            let what = if is_modifier_key(&key) {
+                format!("{:?} (modifier)", key)
+            } else {
+                let typed = typed_char(event).map_or("none".to_string(), |c| format!("{:?}", c));
+                let from_key = match key_to_char(&key, layout) {
+                    Some(c) => format!("{:?}", c),
+                    None => "None (the key alone gives the engine nothing)".to_string(),
+                };
+                format!("{:?} typed {} key_to_char {}", key, typed, from_key)
+            };
+            (kind, what)
+        }
+        EventType::ButtonPress(button) => ("press", format!("{:?} (mouse)", button)),
+        EventType::ButtonRelease(button) => ("release", format!("{:?} (mouse)", button)),
+        EventType::Wheel { delta_x, delta_y } => ("wheel", format!("{} {}", delta_x, delta_y)),
+        EventType::MouseMove { .. } => return None,
+    };
+    Some(format!("{:>9.3}s  {:<7}  {}", elapsed.as_secs_f64(), kind, what))
+}
+
+/// Print events until the process is interrupted. Returns only if the
+/// listener can't start.
+pub fn dump_keys(layout: KeyboardLayout) -> Result<(), VaixError> {
+    let start = SystemTime::now();
+    listen(move |event| {
+        let elapsed = event.time.duration_since(start).unwrap_or_default();
+        if let Some(line) = describe_event(&event, layout, elapsed) {
+            println!("{}", line);
+        }
+    })
+    .map_err(listen_error)
+}
+
+fn listen_error(error: ListenError) -> VaixError {
+    match error {
+        ListenError::EventTapError => VaixError::PermissionDenied(
+            "cannot create the event tap; grant Input Monitoring permission and restart the terminal".to_string(),
+        ),
+        e => VaixError::Backend { backend: "rdev", message: format!("cannot listen: {:?}", e) },
+    }
+}
@@ -0,0 +1,173 @@
+// Focused text field tracking: the composing buffer belongs to one field, so
+// it is dropped whenever focus moves (Tab, a mouse click, a dialog opening,
+// switching apps). Otherwise the next tone key would backspace into text the
+// engine never typed.
+// On macOS an AXObserver watches the frontmost app for focused-element
+// changes. An observer only covers one process, so a slow poll re-targets it
+// when another app comes to the front. Elsewhere nothing is observed and only
+// `notify` raises the flag.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static FOCUS_CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// Record that the focused field changed; the monitor resets the buffer
+/// before handling the next key
+pub fn notify() {
+    FOCUS_CHANGED.store(true, Ordering::SeqCst);
+}
+
+/// Whether focus moved since the last call
+pub fn take_changed() -> bool {
+    FOCUS_CHANGED.swap(false, Ordering::SeqCst)
+}
+
+#[cfg(target_os = "macos")]
+pub use observer::{start, stop};
+
+#[cfg(not(target_os = "macos"))]
+pub fn start() {}
+
+#[cfg(not(target_os = "macos"))]
+pub fn stop() {}
+
+#[cfg(target_os = "macos")]
+mod observer {
+    use super::notify;
+    use core_foundation::base::{CFRelease, CFTypeRef, TCFType};
+    use core_foundation::runloop::{kCFRunLoopDefaultMode, CFRunLoop, CFRunLoopSource, CFRunLoopSourceRef};
+    use core_foundation::string::{CFString, CFStringRef};
+    use log::debug;
+    use std::ffi::c_void;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    type AXObserverCallback = extern "C" fn(CFTypeRef, CFTypeRef, CFStringRef, *mut c_void);
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn AXIsProcessTrusted() -> bool;
+        fn AXUIElementCreateSystemWide() -> CFTypeRef;
+        fn AXUIElementCreateApplication(pid: i32) -> CFTypeRef;
+        fn AXUIElementCopyAttributeValue(element: CFTypeRef, attribute: CFStringRef, value: *mut CFTypeRef) -> i32;
+        fn AXUIElementGetPid(element: CFTypeRef, pid: *mut i32) -> i32;
+        fn AXObserverCreate(pid: i32, callback: AXObserverCallback, observer: *mut CFTypeRef) -> i32;
+        fn AXObserverAddNotification(
+            observer: CFTypeRef,
+            element: CFTypeRef,
+            notification: CFStringRef,
+            refcon: *mut c_void,
+        ) -> i32;
+        fn AXObserverGetRunLoopSource(observer: CFTypeRef) -> CFRunLoopSourceRef;
+    }
+
+    const AX_SUCCESS: i32 = 0;
+    /// How often the frontmost app is checked
+    const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+    static RUNNING: AtomicBool = AtomicBool::new(false);
+
+    /// Observer registered on one app, with the run loop source it feeds
+    struct AppObserver {
+        pid: i32,
+        observer: CFTypeRef,
+        source: CFRunLoopSource,
+    }
+
+    impl Drop for AppObserver {
+        fn drop(&mut self) {
+            CFRunLoop::get_current().remove_source(&self.source, unsafe { kCFRunLoopDefaultMode });
+            unsafe { CFRelease(self.observer) };
+        }
+    }
+
+    /// Start watching focus on a background thread. Without Accessibility
+    /// permission nothing is observed and keys still work, just without the
+    /// per-field reset.
+    pub fn start() {
+        if !unsafe { AXIsProcessTrusted() } {
+            debug!("Accessibility not trusted, focus changes will not reset the buffer");
+            return;
+        }
+        if RUNNING.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        std::thread::spawn(|| {
+            let mut current: Option<AppObserver> = None;
+            while RUNNING.load(Ordering::SeqCst) {
+                let pid = frontmost_pid();
+                if pid != current.as_ref().map(|app| app.pid) {
+                    // Switching apps moves focus too
+                    if current.is_some() {
+                        notify();
+                    }
+                    current = pid.and_then(observe);
+                }
+
+                if current.is_some() {
+                    CFRunLoop::run_in_mode(unsafe { kCFRunLoopDefaultMode }, POLL_INTERVAL, false);
+                } else {
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+            }
+        });
+    }
+
+    /// Ask the observer thread to finish; it exits within one poll interval
+    pub fn stop() {
+        RUNNING.store(false, Ordering::SeqCst);
+    }
+
+    extern "C" fn focus_changed(_observer: CFTypeRef, _element: CFTypeRef, _name: CFStringRef, _refcon: *mut c_void) {
+        notify();
+    }
+
+    fn frontmost_pid() -> Option<i32> {
+        unsafe {
+            let system = AXUIElementCreateSystemWide();
+            if system.is_null() {
+                return None;
+            }
+            let name = CFString::from_static_string("AXFocusedApplication");
+            let mut app: CFTypeRef = std::ptr::null();
+            let status = AXUIElementCopyAttributeValue(system, name.as_concrete_TypeRef(), &mut app);
+            CFRelease(system);
+            if status != AX_SUCCESS || app.is_null() {
+                return None;
+            }
+
+            let mut pid = 0;
+            let status = AXUIElementGetPid(app, &mut pid);
+            CFRelease(app);
+            (status == AX_SUCCESS).then_some(pid)
+        }
+    }
+
+    /// Register for focused-element changes in `pid` on this thread's run
+    /// loop. Some apps (and sandboxed helpers) refuse observers; they are
+    /// skipped.
+    fn observe(pid: i32) -> Option<AppObserver> {
+        unsafe {
+            let mut observer: CFTypeRef = std::ptr::null();
+            if AXObserverCreate(pid, focus_changed, &mut observer) != AX_SUCCESS || observer.is_null() {
+                debug!("Cannot observe focus in pid {}", pid);
+                return None;
+            }
+
+            let app = AXUIElementCreateApplication(pid);
+            let name = CFString::from_static_string("AXFocusedUIElementChanged");
+            let status = AXObserverAddNotification(observer, app, name.as_concrete_TypeRef(), std::ptr::null_mut());
+            CFRelease(app);
+            if status != AX_SUCCESS {
+                debug!("Cannot observe focus in pid {} (AXError {})", pid, status);
+                CFRelease(observer);
+                return None;
+            }
+
+            let source = CFRunLoopSource::wrap_under_get_rule(AXObserverGetRunLoopSource(observer));
+            CFRunLoop::get_current().add_source(&source, kCFRunLoopDefaultMode);
+            debug!("Observing focus changes in pid {}", pid);
+            Some(AppObserver { pid, observer, source })
+        }
+    }
+}
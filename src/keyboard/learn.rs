@@ -0,0 +1,158 @@
+// Learn mode (`vaixkey --learn`): catch transforms the user undoes by hand.
+// A transform erased with Backspace within `RETYPE_WINDOW`, then retyped
+// differently, was probably wrong; when the word ends, the keys that produced
+// it, the output and what the user settled on are appended to a log that can
+// be sent along with a bug report.
+//
+// Log format, one correction per line after the header comment:
+//   keys<TAB>bad output<TAB>corrected output
+// e.g. `mas	má	mas`: typing "mas" gave "má", the user wanted "mas".
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::GrabAction;
+
+/// Where `--learn` appends corrections
+pub const LEARN_LOG_PATH: &str = "/tmp/vaixkey_learn.log";
+
+/// How soon after a transform the Backspaces must come to count as undoing
+/// it
+pub const RETYPE_WINDOW: Duration = Duration::from_millis(1500);
+
+const HEADER: &str = "# vaixkey learn log v1: keys, output, corrected";
+
+/// A word as typed, a transform the user erased in it and what replaced it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Correction {
+    pub keys: String,
+    pub bad: String,
+    pub corrected: String,
+}
+
+impl Correction {
+    fn to_line(&self) -> String {
+        format!("{}\t{}\t{}", self.keys, self.bad, self.corrected)
+    }
+}
+
+/// The last transform of the word, while Backspaces may still undo it
+struct Transform {
+    keys: String,
+    output: String,
+    /// Characters it typed; this many Backspaces erase it
+    injected: usize,
+    at: Instant,
+    erased: usize,
+}
+
+/// The word being typed, as it stands on screen
+#[derive(Default)]
+struct Word {
+    keys: String,
+    text: Vec<char>,
+    transform: Option<Transform>,
+    /// The transform the user erased, waiting for the word to end
+    undone: Option<(String, String)>,
+}
+
+/// Watches keys and what the monitor did with them, and logs corrections
+pub struct Learner {
+    file: Mutex<File>,
+    word: Mutex<Word>,
+}
+
+impl Learner {
+    /// Append to the log at `path`, creating it with a header if needed
+    pub fn create(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let new = !path.exists();
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if new {
+            writeln!(file, "{}", HEADER)?;
+        }
+        Ok(Self { file: Mutex::new(file), word: Mutex::new(Word::default()) })
+    }
+
+    /// `ch` reached the engine (Backspace as `'\x08'`) and the monitor
+    /// answered with `action`
+    pub fn key(&self, ch: char, action: &GrabAction, at: Instant) {
+        let Ok(mut word) = self.word.lock() else { return };
+        let backspace = ch == '\x08';
+        if backspace {
+            word.keys.pop();
+        } else {
+            word.keys.push(ch);
+        }
+
+        match action {
+            GrabAction::PassThrough if backspace => {
+                word.text.pop();
+            }
+            GrabAction::PassThrough => word.text.push(ch),
+            GrabAction::Block => {}
+            GrabAction::BlockAndInject { backspaces, text } => {
+                let keep = word.text.len().saturating_sub(*backspaces);
+                word.text.truncate(keep);
+                word.text.extend(text.chars());
+            }
+        }
+
+        if backspace {
+            let erased = match word.transform.as_mut() {
+                Some(transform) if at.duration_since(transform.at) <= RETYPE_WINDOW => {
+                    transform.erased += 1;
+                    transform.erased >= transform.injected
+                }
+                _ => {
+                    word.transform = None;
+                    false
+                }
+            };
+            if erased {
+                if let Some(transform) = word.transform.take() {
+                    word.undone = Some((transform.keys, transform.output));
+                }
+            }
+        } else if let GrabAction::BlockAndInject { text, .. } = action {
+            word.transform = Some(Transform {
+                keys: word.keys.clone(),
+                output: word.text.iter().collect(),
+                injected: text.chars().count(),
+                at,
+                erased: 0,
+            });
+        } else {
+            // Erasing now would erase this key first, not the transform
+            word.transform = None;
+        }
+    }
+
+    /// A separator ended the word. Logs and returns the correction if a
+    /// transform in it was undone and the word came out different.
+    pub fn commit(&self) -> Option<Correction> {
+        let word = std::mem::take(&mut *self.word.lock().ok()?);
+        let (keys, bad) = word.undone?;
+        // Without the separator that ended the word
+        let corrected = word.text.into_iter().collect::<String>().trim_end().to_string();
+        if corrected.is_empty() || corrected == bad {
+            return None;
+        }
+
+        let correction = Correction { keys, bad, corrected };
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(file, "{}", correction.to_line());
+        }
+        Some(correction)
+    }
+
+    /// The word was dropped (arrows, focus change...); what follows can't be
+    /// compared with it
+    pub fn reset(&self) {
+        if let Ok(mut word) = self.word.lock() {
+            *word = Word::default();
+        }
+    }
+}
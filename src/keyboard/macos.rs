@@ -0,0 +1,143 @@
+// macOS backend: rdev's event tap for capture, CGEvent for injection
+// ASCII letters and separators are typed as real key presses so shortcuts
+// and autocomplete in the target app see normal keys; everything else is
+// posted as a Unicode string on a keyboard event (or, with
+// `injection_fallback`, as dead keys and letters once that fails).
+
+use super::backend::{grab_error, inject_error, send_ascii_char, send_key, send_keystroke_text, InputBackend};
+use crate::gui::clipboard;
+use core_foundation::runloop::CFRunLoop;
+use core_graphics::event::{CGEvent, CGEventTapLocation};
+use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+use log::debug;
+use rdev::{simulate, Event, EventType, Key};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use vaixkey::VaixError;
+
+pub struct MacosBackend {
+    debug_mode: bool,
+    /// Run loop the event tap is attached to while `grab` is running
+    grab_loop: Mutex<Option<CFRunLoop>>,
+}
+
+impl MacosBackend {
+    pub fn new(debug_mode: bool) -> Self {
+        Self {
+            debug_mode,
+            grab_loop: Mutex::new(None),
+        }
+    }
+
+    /// Post a character as the Unicode string of a synthetic keyboard event
+    fn send_unicode_char(&self, c: char) -> Result<(), VaixError> {
+        // Create event source
+        let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+            .map_err(|_| inject_error(self.name(), format!("'{}' (no CGEventSource)", c)))?;
+
+        // Create a keyboard event
+        let event = CGEvent::new_keyboard_event(source.clone(), 0, true)
+            .map_err(|_| inject_error(self.name(), format!("'{}' (no CGEvent)", c)))?;
+
+        // Set the Unicode string for this event
+        let chars: Vec<u16> = c.encode_utf16(&mut [0u16; 2]).to_vec();
+        event.set_string_from_utf16_unchecked(&chars);
+
+        // Post the event. CGEventPost returns nothing, so an app dropping
+        // it goes unnoticed and injection_fallback never starts here
+        event.post(CGEventTapLocation::HID);
+
+        // Send key up
+        if let Ok(up_event) = CGEvent::new_keyboard_event(source, 0, false) {
+            up_event.post(CGEventTapLocation::HID);
+        }
+
+        if self.debug_mode {
+            debug!("📤 Injected Unicode: '{}'", c);
+        }
+        Ok(())
+    }
+}
+
+impl InputBackend for MacosBackend {
+    fn name(&self) -> &'static str {
+        "macos"
+    }
+
+    fn grab(&self, callback: fn(Event) -> Option<Event>) -> Result<(), VaixError> {
+        // rdev adds its event tap to this thread's run loop and runs it until
+        // the loop is stopped
+        if let Ok(mut grab_loop) = self.grab_loop.lock() {
+            *grab_loop = Some(CFRunLoop::get_current());
+        }
+        let result = rdev::grab(callback).map_err(|e| grab_error(self.name(), e));
+        if let Ok(mut grab_loop) = self.grab_loop.lock() {
+            *grab_loop = None;
+        }
+        result
+    }
+
+    fn stop_grab(&self) {
+        if let Some(grab_loop) = self.grab_loop.lock().ok().and_then(|mut l| l.take()) {
+            grab_loop.stop();
+        }
+    }
+
+    fn send_backspaces(&self, count: usize) -> Result<(), VaixError> {
+        for _ in 0..count {
+            send_key(Key::Backspace).map_err(|_| inject_error(self.name(), "Backspace"))?;
+            thread::sleep(Duration::from_millis(2));
+        }
+        Ok(())
+    }
+
+    fn send_text(&self, text: &str) -> Result<(), VaixError> {
+        for c in text.chars() {
+            let sent = send_ascii_char(c).map_err(|_| inject_error(self.name(), format!("'{}'", c)))?;
+            if !sent {
+                self.send_unicode_char(c)?;
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+        Ok(())
+    }
+
+    fn send_keystrokes(&self, text: &str) -> Result<(), VaixError> {
+        send_keystroke_text(self.name(), text, dead_key)
+    }
+
+    fn read_clipboard(&self) -> Result<Option<String>, VaixError> {
+        clipboard::paste().map_err(|message| VaixError::Backend { backend: self.name(), message })
+    }
+
+    fn write_clipboard(&self, text: &str) -> Result<(), VaixError> {
+        clipboard::copy(text).map_err(|message| VaixError::Backend { backend: self.name(), message })
+    }
+
+    fn send_paste(&self) -> Result<(), VaixError> {
+        // Cmd plus the key that types v on the configured layout, as the
+        // shortcut follows the layout
+        let v = super::char_to_layout_key('v', super::active_layout()).unwrap_or(Key::KeyV);
+        simulate(&EventType::KeyPress(Key::MetaLeft)).map_err(|_| inject_error(self.name(), "Cmd"))?;
+        thread::sleep(Duration::from_millis(1));
+        let sent = send_key(v).map_err(|_| inject_error(self.name(), "Cmd+V"));
+        // Released even when V failed, so Cmd doesn't stay down
+        let _ = simulate(&EventType::KeyRelease(Key::MetaLeft));
+        sent
+    }
+}
+
+/// The key that, with Option, starts a dead-key composition of `mark` on
+/// the standard macOS layouts: acute, grave, tilde and circumflex. Hook,
+/// dot below, breve and horn have none.
+fn dead_key(mark: char) -> Option<Key> {
+    let letter = match mark {
+        '\u{301}' => 'e',
+        '\u{300}' => return Some(Key::BackQuote),
+        '\u{303}' => 'n',
+        '\u{302}' => 'i',
+        _ => return None,
+    };
+    super::char_to_layout_key(letter, super::active_layout())
+}
@@ -1,24 +1,50 @@
-use crate::input_method::{InputMethodEngine, ProcessResult};
-use log::{info, debug, error};
-use rdev::{grab, simulate, Event, EventType, Key};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+mod backend;
+mod dump;
+mod focus;
+mod learn;
+mod power;
+#[cfg(target_os = "macos")]
+mod macos;
+mod recording;
+#[cfg(all(target_os = "linux", feature = "wayland"))]
+mod wayland;
+
+use crate::gui::SETTINGS_HTML_PATH;
+use crate::input_method::{EngineStatus, InputMethodEngine, ProcessResult};
+use crate::replay::{Entry, Recorder};
+use vaixkey::config::{Hotkey, InjectionMode, KeyboardLayout};
+pub use backend::InputBackend;
+pub use dump::{describe_event, dump_keys};
+pub use learn::{Learner, LEARN_LOG_PATH, RETYPE_WINDOW};
+pub use power::PowerEvent;
+pub use recording::{BackendCall, RecordingBackend};
+use log::{info, debug, error, warn};
+use rdev::{Event, EventType, Key};
+use std::sync::{Arc, Mutex};
+use vaixkey::{lock, VaixError};
 use std::sync::mpsc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /// Flag to track if we're currently injecting text (to avoid feedback loop)
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 static INJECTING: AtomicBool = AtomicBool::new(false);
 
+/// Injections that may fail in a row before the buffer is dropped
+const INJECT_FAILURE_LIMIT: usize = 3;
+static INJECT_FAILURES: AtomicUsize = AtomicUsize::new(0);
+/// Why injection kept failing, until the monitor drops the buffer
+static INJECTION_BROKEN: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
+
 /// Track modifier key state to pass through Ctrl+X, Alt+X, Cmd+X combinations
 static CTRL_HELD: AtomicBool = AtomicBool::new(false);
 static ALT_HELD: AtomicBool = AtomicBool::new(false);
 static META_HELD: AtomicBool = AtomicBool::new(false);  // Command key on macOS
+static SHIFT_HELD: AtomicBool = AtomicBool::new(false);  // Hotkeys, arrows and untyped letters
 
 /// Struct to hold processing result from engine
-#[derive(Clone, Debug)]
-enum GrabAction {
+#[derive(Clone, Debug, PartialEq)]
+pub enum GrabAction {
     PassThrough,
     Block,
     BlockAndInject { backspaces: usize, text: String },
@@ -26,9 +52,41 @@ enum GrabAction {
 
 pub struct KeyboardMonitor {
     engine: Arc<Mutex<InputMethodEngine>>,
+    backend: Arc<dyn InputBackend>,
     debug_mode: bool,
+    secure_input_active: AtomicBool,
+    status_listener: Option<StatusListener>,
+    recorder: Option<Recorder>,
+    dump_hotkey: Option<(Hotkey, BufferListener)>,
+    convert_hotkey: Option<(Hotkey, ShortcutListener)>,
+    failure_listener: Option<FailureListener>,
+    /// When the last key reached the engine, for `engine.commit_timeout_ms`
+    last_key_at: Mutex<Option<Instant>>,
+    process_hook: Option<ProcessHook>,
+    learner: Option<Learner>,
+    /// Whether the grab `start` spawned is still running
+    grab_running: Arc<AtomicBool>,
+    stopped: AtomicBool,
 }
 
+/// Called with the composing buffer, e.g. for `hotkeys.dump_buffer`
+pub type BufferListener = Box<dyn Fn(&str) + Send + Sync>;
+
+/// Called with the engine's status after every key, e.g. to draw a pre-edit
+/// overlay; an empty buffer means the word was committed
+pub type StatusListener = Box<dyn Fn(&EngineStatus) + Send + Sync>;
+
+/// Called when a hotkey that needs no engine state is pressed
+pub type ShortcutListener = Box<dyn Fn() + Send + Sync>;
+
+/// Run inside the timed part of every key the engine processes, e.g. to
+/// simulate a slow engine against `engine.max_process_us`
+pub type ProcessHook = Box<dyn Fn() + Send + Sync>;
+
+/// Called with the last error once injection has failed repeatedly and the
+/// buffer was dropped
+pub type FailureListener = Box<dyn Fn(&str) + Send + Sync>;
+
 #[derive(Debug, Clone)]
 pub struct KeystrokeInfo {
     pub key: String,
@@ -39,14 +97,33 @@ pub struct KeystrokeInfo {
     pub vietnamese_mode: bool,
 }
 
-// Channel for communicating between grab callback and async processor
-use std::sync::OnceLock;
-static KEY_CHANNEL: OnceLock<(
-    std::sync::Mutex<mpsc::Sender<(Key, bool)>>,  // key, is_press
-    std::sync::Mutex<mpsc::Receiver<GrabAction>>,
-)> = OnceLock::new();
+// Channel for communicating between grab callback and the processing loop.
+// Cleared by `KeyboardMonitor::stop`, after which the callback passes every
+// key through.
+type KeyChannel = (
+    mpsc::Sender<(Key, Option<char>, Option<Hotkey>)>,  // key, character it typed, combo if part of a shortcut
+    mpsc::Receiver<GrabAction>,
+);
+static KEY_CHANNEL: std::sync::Mutex<Option<KeyChannel>> = std::sync::Mutex::new(None);
 
-static DEBUG_MODE: AtomicBool = AtomicBool::new(false);
+/// Layout of the running monitor, for the grab callback and injection
+static KEYBOARD_LAYOUT: std::sync::Mutex<KeyboardLayout> = std::sync::Mutex::new(KeyboardLayout::Qwerty);
+
+/// Injection mode of the running monitor, for the grab callback
+static INJECTION_MODE: std::sync::Mutex<InjectionMode> = std::sync::Mutex::new(InjectionMode::Simulate);
+
+/// `injection_fallback` of the running monitor, for the grab callback
+static INJECTION_FALLBACK: AtomicBool = AtomicBool::new(false);
+/// Set once a Unicode string was rejected with `injection_fallback` on:
+/// replacements are typed with key presses until the monitor stops
+static KEYSTROKES_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// How long the clipboard keeps a pasted replacement before it is restored.
+/// The app reads it while handling Cmd+V, after the key has been posted.
+const PASTE_RESTORE_DELAY: Duration = Duration::from_millis(50);
+
+/// Backend used by the grab callback to inject replacements
+static INJECT_BACKEND: std::sync::Mutex<Option<Arc<dyn InputBackend>>> = std::sync::Mutex::new(None);
 
 fn grab_callback(event: Event) -> Option<Event> {
     // If we're injecting, let all events through
@@ -56,23 +133,25 @@ fn grab_callback(event: Event) -> Option<Event> {
 
     match event.event_type {
         EventType::KeyPress(key) => {
-            let debug = DEBUG_MODE.load(Ordering::SeqCst);
-            
             // Track modifier state
             match key {
                 Key::ControlLeft | Key::ControlRight => {
                     CTRL_HELD.store(true, Ordering::SeqCst);
-                    if debug { eprintln!("🔑 CTRL pressed, state=true"); }
+                    debug!("CTRL pressed");
                     return Some(event);
                 }
                 Key::Alt | Key::AltGr => {
                     ALT_HELD.store(true, Ordering::SeqCst);
-                    if debug { eprintln!("🔑 ALT pressed, state=true"); }
+                    debug!("ALT pressed");
                     return Some(event);
                 }
                 Key::MetaLeft | Key::MetaRight => {
                     META_HELD.store(true, Ordering::SeqCst);
-                    if debug { eprintln!("🔑 META pressed, state=true"); }
+                    debug!("META pressed");
+                    return Some(event);
+                }
+                Key::ShiftLeft | Key::ShiftRight => {
+                    SHIFT_HELD.store(true, Ordering::SeqCst);
                     return Some(event);
                 }
                 _ => {}
@@ -83,9 +162,7 @@ fn grab_callback(event: Event) -> Option<Event> {
             let alt = ALT_HELD.load(Ordering::SeqCst);
             let meta = META_HELD.load(Ordering::SeqCst);
             
-            if debug {
-                eprintln!("🔑 Key {:?} - modifiers: ctrl={}, alt={}, meta={}", key, ctrl, alt, meta);
-            }
+            debug!("Key {:?}, modifiers: ctrl={}, alt={}, meta={}", key, ctrl, alt, meta);
             
             // Always pass through modifier keys (Shift, CapsLock, etc.)
             if is_modifier_key(&key) {
@@ -93,57 +170,58 @@ fn grab_callback(event: Event) -> Option<Event> {
             }
             
             // If any modifier (Ctrl/Alt/Cmd) is held, pass through the key
-            // This allows Ctrl+C, Cmd+V, Alt+Tab, etc. to work
-            if CTRL_HELD.load(Ordering::SeqCst) || 
-               ALT_HELD.load(Ordering::SeqCst) || 
-               META_HELD.load(Ordering::SeqCst) {
-                return Some(event);
-            }
-            
-            // Check if this is a separator key that should clear the buffer
-            if is_separator_key(&key) {
-                // Notify the engine to clear its buffer, but let the key through
-                if let Some((tx_mutex, rx_mutex)) = KEY_CHANNEL.get() {
-                    if let Ok(tx) = tx_mutex.lock() {
-                        let _ = tx.send((key, true));
-                    }
-                    // Wait for response but always pass through
-                    if let Ok(rx) = rx_mutex.lock() {
-                        let _ = rx.recv_timeout(Duration::from_millis(50));
+            // This allows Ctrl+C, Cmd+V, Alt+Tab, etc. to work. The monitor
+            // is told so it can drop the word: Cmd+A, Cmd+Left and the like
+            // move the caret away from it. No reply is expected.
+            if ctrl || alt || meta {
+                let combo = Hotkey {
+                    ctrl,
+                    alt,
+                    shift: SHIFT_HELD.load(Ordering::SeqCst),
+                    meta,
+                    key: hotkey_key_name(&key),
+                };
+                if let Ok(channel) = KEY_CHANNEL.lock() {
+                    if let Some((tx, _)) = channel.as_ref() {
+                        let _ = tx.send((key, None, Some(combo)));
                     }
                 }
                 return Some(event);
             }
             
-            // Check if this is a character key we should process
-            if let Some(_ch) = key_to_char(&key) {
-                // Send to processor and wait for response
-                if let Some((tx_mutex, rx_mutex)) = KEY_CHANNEL.get() {
-                    if let Ok(tx) = tx_mutex.lock() {
-                        let _ = tx.send((key, true));
-                    }
-                    if let Ok(rx) = rx_mutex.lock() {
-                        // Wait for action with timeout
-                        match rx.recv_timeout(Duration::from_millis(50)) {
-                            Ok(GrabAction::PassThrough) => {
-                                return Some(event);
-                            }
-                            Ok(GrabAction::Block) => {
-                                return None;
-                            }
-                            Ok(GrabAction::BlockAndInject { backspaces, text }) => {
-                                // Block the event and inject our replacement
-                                let debug = DEBUG_MODE.load(Ordering::SeqCst);
+            // Every other key goes to the processor: characters are composed,
+            // separators may commit a deferred tone (tone_at_end), and keys
+            // it can't read (F-keys, OEM and international keys the layout
+            // gives no character) end the word
+            let typed = typed_char(&event);
+            // Send to processor and wait for response
+            if let Ok(channel) = KEY_CHANNEL.lock() {
+                if let Some((tx, rx)) = channel.as_ref() {
+                    let _ = tx.send((key, typed, None));
+                    // Wait for action with timeout
+                    match rx.recv_timeout(Duration::from_millis(50)) {
+                        Ok(GrabAction::PassThrough) => {
+                            return Some(event);
+                        }
+                        Ok(GrabAction::Block) => {
+                            return None;
+                        }
+                        Ok(GrabAction::BlockAndInject { backspaces, text }) => {
+                            // Block the event and inject our replacement
+                            let backend = INJECT_BACKEND.lock().ok().and_then(|b| b.clone());
+                            let mode = INJECTION_MODE.lock().map(|mode| *mode).unwrap_or_default();
+                            let fallback = INJECTION_FALLBACK.load(Ordering::SeqCst);
+                            if let Some(backend) = backend {
                                 // Inject in a separate thread to not block
                                 std::thread::spawn(move || {
-                                    inject_replacement(backspaces, &text, debug);
+                                    inject_tracked(backend.as_ref(), mode, fallback, backspaces, &text);
                                 });
-                                return None;
-                            }
-                            Err(_) => {
-                                // Timeout, let event through
-                                return Some(event);
                             }
+                            return None;
+                        }
+                        Err(_) => {
+                            // Timeout, let event through
+                            return Some(event);
                         }
                     }
                 }
@@ -162,6 +240,9 @@ fn grab_callback(event: Event) -> Option<Event> {
                 Key::MetaLeft | Key::MetaRight => {
                     META_HELD.store(false, Ordering::SeqCst);
                 }
+                Key::ShiftLeft | Key::ShiftRight => {
+                    SHIFT_HELD.store(false, Ordering::SeqCst);
+                }
                 _ => {}
             }
             // Let key releases through
@@ -183,7 +264,66 @@ fn is_modifier_key(key: &Key) -> bool {
     )
 }
 
-/// Check if this key is a separator that should clear the buffer
+/// The key's name as written in a hotkey combo ("B", "7", "Space"), or
+/// None for keys a combo can't name
+fn hotkey_key_name(key: &Key) -> Option<String> {
+    let named = match key {
+        Key::Space => "Space",
+        Key::Tab => "Tab",
+        Key::Return => "Enter",
+        Key::Escape => "Escape",
+        Key::Backspace => "Backspace",
+        Key::F1 => "F1",
+        Key::F2 => "F2",
+        Key::F3 => "F3",
+        Key::F4 => "F4",
+        Key::F5 => "F5",
+        Key::F6 => "F6",
+        Key::F7 => "F7",
+        Key::F8 => "F8",
+        Key::F9 => "F9",
+        Key::F10 => "F10",
+        Key::F11 => "F11",
+        Key::F12 => "F12",
+        // A combo names the top-row digits only
+        _ if is_numpad_digit(key) => return None,
+        _ => {
+            return key_to_char(key, active_layout())
+                .filter(char::is_ascii_alphanumeric)
+                .map(|c| c.to_ascii_uppercase().to_string())
+        }
+    };
+    Some(named.to_string())
+}
+
+/// Layout set by the running monitor's config (QWERTY when none runs)
+pub(super) fn active_layout() -> KeyboardLayout {
+    KEYBOARD_LAYOUT.lock().map(|layout| *layout).unwrap_or_default()
+}
+
+/// How a key that ends the word treats it
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Separator {
+    /// Goes through the engine as this character, which finishes the word
+    /// first: deferred tones, symbols and compose_on_commit apply
+    Commit(char),
+    /// Drops the word as it stands on screen
+    Clear,
+}
+
+/// How `key` ends the word, if it does. Tab and Enter commit like Space
+/// unless `engine.tab_enter_commit` is off.
+fn separator_kind(key: &Key, tab_enter_commit: bool) -> Option<Separator> {
+    match key {
+        Key::Return | Key::Tab if !tab_enter_commit => Some(Separator::Clear),
+        _ => match separator_to_char(key) {
+            Some(ch) => Some(Separator::Commit(ch)),
+            None => is_separator_key(key).then_some(Separator::Clear),
+        },
+    }
+}
+
+/// Check if this key ends the word, by committing or clearing it
 fn is_separator_key(key: &Key) -> bool {
     matches!(key, 
         Key::Space | Key::Return | Key::Tab | 
@@ -194,89 +334,234 @@ fn is_separator_key(key: &Key) -> bool {
     )
 }
 
+/// Check whether CapsLock is on. Only needed when the OS doesn't report the
+/// typed character, which already carries the case.
+#[cfg(target_os = "macos")]
+fn is_caps_lock_on() -> bool {
+    const HID_SYSTEM_STATE: i32 = 1;
+    const FLAG_MASK_ALPHA_SHIFT: u64 = 0x0001_0000;
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceFlagsState(state_id: i32) -> u64;
+    }
+
+    unsafe { CGEventSourceFlagsState(HID_SYSTEM_STATE) & FLAG_MASK_ALPHA_SHIFT != 0 }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn is_caps_lock_on() -> bool {
+    false
+}
+
+/// The character of a key the OS gave no character for. `key_to_char` is
+/// lowercase; Shift or CapsLock (not both) makes letters uppercase, so
+/// all-caps typing still reaches the engine as capitals.
+fn untyped_char(key: &Key, layout: KeyboardLayout) -> Option<char> {
+    let c = key_to_char(key, layout)?;
+    if SHIFT_HELD.load(Ordering::SeqCst) != is_caps_lock_on() {
+        Some(c.to_ascii_uppercase())
+    } else {
+        Some(c)
+    }
+}
+
+/// Check whether macOS secure event input is on (password fields, Terminal's
+/// "Secure Keyboard Entry"). Transforms must never run while it is enabled.
+#[cfg(target_os = "macos")]
+fn is_secure_input_enabled() -> bool {
+    #[link(name = "Carbon", kind = "framework")]
+    extern "C" {
+        fn IsSecureEventInputEnabled() -> u8;
+    }
+
+    unsafe { IsSecureEventInputEnabled() != 0 }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn is_secure_input_enabled() -> bool {
+    false
+}
+
 impl KeyboardMonitor {
     pub fn new(engine: Arc<Mutex<InputMethodEngine>>) -> Self {
+        let debug_mode = std::env::var("VAIXKEY_DEBUG").is_ok();
         Self {
             engine,
-            debug_mode: std::env::var("VAIXKEY_DEBUG").is_ok(),
+            backend: backend::select_backend(debug_mode),
+            debug_mode,
+            secure_input_active: AtomicBool::new(false),
+            status_listener: None,
+            recorder: None,
+            dump_hotkey: None,
+            convert_hotkey: None,
+            failure_listener: None,
+            last_key_at: Mutex::new(None),
+            process_hook: None,
+            learner: None,
+            grab_running: Arc::new(AtomicBool::new(false)),
+            stopped: AtomicBool::new(false),
         }
     }
 
     pub fn new_with_debug(engine: Arc<Mutex<InputMethodEngine>>) -> Self {
         Self {
             engine,
+            backend: backend::select_backend(true),
             debug_mode: true,
+            secure_input_active: AtomicBool::new(false),
+            status_listener: None,
+            recorder: None,
+            dump_hotkey: None,
+            convert_hotkey: None,
+            failure_listener: None,
+            last_key_at: Mutex::new(None),
+            process_hook: None,
+            learner: None,
+            grab_running: Arc::new(AtomicBool::new(false)),
+            stopped: AtomicBool::new(false),
         }
     }
 
-    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
-        DEBUG_MODE.store(self.debug_mode, Ordering::SeqCst);
-        
+    /// Monitor with an explicit backend, e.g. a RecordingBackend in tests
+    pub fn with_backend(engine: Arc<Mutex<InputMethodEngine>>, backend: Arc<dyn InputBackend>) -> Self {
+        Self {
+            engine,
+            backend,
+            debug_mode: false,
+            secure_input_active: AtomicBool::new(false),
+            status_listener: None,
+            recorder: None,
+            dump_hotkey: None,
+            convert_hotkey: None,
+            failure_listener: None,
+            last_key_at: Mutex::new(None),
+            process_hook: None,
+            learner: None,
+            grab_running: Arc::new(AtomicBool::new(false)),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    pub fn set_status_listener(&mut self, listener: StatusListener) {
+        self.status_listener = Some(listener);
+    }
+
+    /// Call `listener` with `InputMethodEngine::dump_buffer` whenever
+    /// `hotkey` is pressed
+    pub fn set_dump_hotkey(&mut self, hotkey: Hotkey, listener: BufferListener) {
+        self.dump_hotkey = Some((hotkey, listener));
+    }
+
+    /// Call `listener` whenever `hotkey` is pressed, to convert the
+    /// clipboard
+    pub fn set_convert_hotkey(&mut self, hotkey: Hotkey, listener: ShortcutListener) {
+        self.convert_hotkey = Some((hotkey, listener));
+    }
+
+    /// Call `listener` when injection keeps failing (the app rejects
+    /// synthetic events) and the composing buffer was dropped
+    pub fn set_failure_listener(&mut self, listener: FailureListener) {
+        self.failure_listener = Some(listener);
+    }
+
+    /// Call `hook` while each key is processed, inside its time budget
+    pub fn set_process_hook(&mut self, hook: ProcessHook) {
+        self.process_hook = Some(hook);
+    }
+
+    /// Log every key the engine sees, for `--replay`
+    pub fn set_recorder(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Watch for transforms the user undoes, for `--learn`
+    pub fn set_learner(&mut self, learner: Learner) {
+        self.learner = Some(learner);
+    }
+
+    fn learn(&self, f: impl FnOnce(&Learner)) {
+        if let Some(learner) = &self.learner {
+            f(learner);
+        }
+    }
+
+    fn record(&self, entry: Entry) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(entry);
+        }
+    }
+
+    /// Grab the keyboard and process keys until `stop` is called. Blocks
+    /// the calling thread.
+    pub fn start(&self) -> Result<(), VaixError> {
+        self.stopped.store(false, Ordering::SeqCst);
+
         if self.debug_mode {
-            println!("🔍 Starting keyboard monitor with DEBUG logging enabled");
-            println!("📝 Debug logs include: key events, buffer state, processing results");
+            info!("Starting keyboard monitor with debug logging (key events, buffer state, processing results)");
         } else {
-            info!("Starting keyboard monitor (set VAIXKEY_DEBUG=1 for detailed logging)");
+            info!("Starting keyboard monitor (set RUST_LOG=vaixkey::keyboard=debug for detailed logging)");
         }
 
-        println!("🎯 Real keyboard GRAB starting (events will be intercepted)...");
-        println!("⌨️  Type something to see keystroke capture in action!");
-        println!("   Press Ctrl+C to exit\n");
+        info!("Keyboard grab starting, events will be intercepted (Ctrl+C to exit)");
 
         // Create bidirectional channels for communication with grab callback
-        let (key_tx, key_rx) = mpsc::channel::<(Key, bool)>();
+        let (key_tx, key_rx) = mpsc::channel::<(Key, Option<char>, Option<Hotkey>)>();
         let (action_tx, action_rx) = mpsc::channel::<GrabAction>();
         
         // Store channels in global state
-        KEY_CHANNEL.get_or_init(|| (
-            std::sync::Mutex::new(key_tx),
-            std::sync::Mutex::new(action_rx),
-        ));
+        if let Ok(mut channel) = KEY_CHANNEL.lock() {
+            *channel = Some((key_tx, action_rx));
+        }
         
-        // Start the grab in a separate thread
-        std::thread::spawn(move || {
-            if let Err(e) = grab(grab_callback) {
-                eprintln!("❌ Failed to start keyboard grab: {:?}", e);
-                eprintln!("");
-                eprintln!("This usually means:");
-                eprintln!("   • Accessibility permission not granted to Terminal");
-                eprintln!("   • Need to restart Terminal after granting permission");
-                eprintln!("");
-                eprintln!("💡 Try: cargo run -- --open-accessibility");
-            }
-        });
+        // Drop whatever was composed before the monitor started
+        focus::take_changed();
+        focus::start();
+        power::take_woke();
+        power::start();
+
+        info!("Using {} input backend", self.backend.name());
+        if let Ok(mut backend) = INJECT_BACKEND.lock() {
+            *backend = Some(self.backend.clone());
+        }
+        if let Ok(mut layout) = KEYBOARD_LAYOUT.lock() {
+            *layout = lock(&self.engine).keyboard_layout();
+        }
+        if let Ok(mut mode) = INJECTION_MODE.lock() {
+            *mode = lock(&self.engine).injection_mode();
+        }
+        INJECTION_FALLBACK.store(lock(&self.engine).injection_fallback(), Ordering::SeqCst);
+
+        self.spawn_grab();
 
         // Process key events and send back actions
-        let debug_mode = self.debug_mode;
-        let engine = self.engine.clone();
-        
         loop {
             // Check for key events with a timeout
             match key_rx.recv_timeout(std::time::Duration::from_millis(100)) {
-                Ok((key, _is_press)) => {
-                    // Check if it's a separator key
-                    if is_separator_key(&key) {
-                        // Clear the engine buffer
-                        let mut eng = engine.lock().await;
-                        if debug_mode {
-                            println!("📤 Separator key, clearing buffer: '{}'", eng.get_current_buffer());
-                            println!("─────────────────────────────────────");
-                        }
-                        eng.reset_buffer();
-                        drop(eng);
-                        let _ = action_tx.send(GrabAction::PassThrough);
-                    } else if let Some(ch) = key_to_char(&key) {
-                        let action = self.process_key(ch, debug_mode, &engine).await;
-                        let _ = action_tx.send(action);
-                    } else {
-                        let _ = action_tx.send(GrabAction::PassThrough);
+                Ok((_, _, Some(combo))) => {
+                    self.handle_shortcut(&combo);
+                }
+                Ok((key, typed, None)) => {
+                    let action = self.handle_key(key, typed);
+                    let _ = action_tx.send(action);
+                    if let Some(listener) = &self.status_listener {
+                        let status = lock(&self.engine).status();
+                        listener(&status);
                     }
                 }
                 Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if power::take_woke() {
+                        self.recover_after_wake();
+                    }
+                    self.check_injection();
+                    self.commit_if_idle(Instant::now());
                     continue;
                 }
                 Err(mpsc::RecvTimeoutError::Disconnected) => {
-                    error!("Key channel disconnected");
+                    // `stop` drops the sender along with the channel
+                    if !self.stopped.load(Ordering::SeqCst) {
+                        error!("Key channel disconnected");
+                    }
                     break;
                 }
             }
@@ -285,31 +570,334 @@ impl KeyboardMonitor {
         Ok(())
     }
 
-    async fn process_key(&self, ch: char, debug_mode: bool, engine: &Arc<Mutex<InputMethodEngine>>) -> GrabAction {
-        let mut eng = engine.lock().await;
-        let _vietnamese_mode = eng.is_vietnamese_mode();
-        let buffer_before = eng.get_current_buffer().to_string();
-        
-        if debug_mode {
-            println!("🔴 KEY: '{}' (buffer: '{}')", ch, buffer_before);
+    /// Shut the monitor down: end the grab where the backend supports it,
+    /// drop the key channel so `start` returns, and reset the global state a
+    /// later `start` relies on
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.backend.stop_grab();
+        focus::stop();
+        power::stop();
+
+        if let Ok(mut channel) = KEY_CHANNEL.lock() {
+            *channel = None;
+        }
+        if let Ok(mut backend) = INJECT_BACKEND.lock() {
+            *backend = None;
+        }
+        if let Ok(mut layout) = KEYBOARD_LAYOUT.lock() {
+            *layout = KeyboardLayout::Qwerty;
+        }
+        if let Ok(mut mode) = INJECTION_MODE.lock() {
+            *mode = InjectionMode::Simulate;
+        }
+        INJECTION_FALLBACK.store(false, Ordering::SeqCst);
+        KEYSTROKES_ONLY.store(false, Ordering::SeqCst);
+        INJECTING.store(false, Ordering::SeqCst);
+        INJECT_FAILURES.store(0, Ordering::SeqCst);
+        if let Ok(mut broken) = INJECTION_BROKEN.lock() {
+            *broken = None;
+        }
+        CTRL_HELD.store(false, Ordering::SeqCst);
+        ALT_HELD.store(false, Ordering::SeqCst);
+        META_HELD.store(false, Ordering::SeqCst);
+        SHIFT_HELD.store(false, Ordering::SeqCst);
+
+        match std::fs::remove_file(SETTINGS_HTML_PATH) {
+            Ok(()) => debug!("Removed {}", SETTINGS_HTML_PATH),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => error!("Failed to remove {}: {}", SETTINGS_HTML_PATH, e),
+        }
+        info!("Keyboard monitor stopped");
+    }
+
+    /// Run one key press through the engine and decide what the grab
+    /// callback should do with the original event. `typed` is the character
+    /// the OS produced for it, if known; otherwise it is derived from the key.
+    fn handle_key(&self, key: Key, typed: Option<char>) -> GrabAction {
+        let engine = &self.engine;
+        self.check_injection();
+        self.commit_if_idle(Instant::now());
+
+        // Never compose or inject into password fields
+        if is_secure_input_enabled() {
+            if !self.secure_input_active.swap(true, Ordering::SeqCst) {
+                debug!("Secure input enabled, passing keys through untouched");
+            }
+            lock(engine).reset_buffer();
+            self.record(Entry::Reset);
+            self.learn(|learner| learner.reset());
+            return GrabAction::PassThrough;
+        } else if self.secure_input_active.swap(false, Ordering::SeqCst) {
+            debug!("Secure input disabled, resuming Vietnamese processing");
+        }
+
+        // A word never continues into another text field
+        if focus::take_changed() {
+            debug!("Focused element changed, resetting buffer");
+            lock(engine).reset_buffer();
+            self.record(Entry::Reset);
+            self.learn(|learner| learner.reset());
+        }
+
+        // Nothing typed before sleep or a screen lock is still on screen
+        if power::take_woke() {
+            self.recover_after_wake();
+        }
+
+        // Separators that end a word are committed through the engine
+        let separator = separator_kind(&key, lock(engine).tab_enter_commit());
+        if let Some(Separator::Commit(ch)) = separator {
+            let action = self.process_key(ch, engine);
+            self.learn(|learner| {
+                learner.key(ch, &action, Instant::now());
+                if let Some(correction) = learner.commit() {
+                    info!("Learned: '{}' typed '{}', corrected to '{}'",
+                        correction.keys, correction.bad, correction.corrected);
+                }
+            });
+            action
+        } else if matches!(key, Key::LeftArrow | Key::RightArrow) && !SHIFT_HELD.load(Ordering::SeqCst) {
+            // Arrows move within the word; stepping out of it (or selecting
+            // with Shift) drops the buffer
+            let left = matches!(key, Key::LeftArrow);
+            let mut eng = lock(engine);
+            let moved = if left { eng.cursor_left() } else { eng.cursor_right() };
+            debug!("Cursor {}, buffer: '{}' (still in word: {})",
+                if left { "left" } else { "right" }, eng.get_current_buffer(), moved);
+            drop(eng);
+            self.record(if left { Entry::CursorLeft } else { Entry::CursorRight });
+            self.learn(|learner| learner.reset());
+            GrabAction::PassThrough
+        } else if matches!(key, Key::Backspace) && lock(engine).restore_previous_syllable() {
+            // The engine edits its buffer, or reopens the word before a
+            // separator it is deleting
+            let action = self.process_key('\x08', engine);
+            self.learn(|learner| learner.key('\x08', &action, Instant::now()));
+            action
+        } else if separator == Some(Separator::Clear) {
+            // Clear the engine buffer
+            let mut eng = lock(engine);
+            debug!("Separator key, clearing buffer: '{}'", eng.get_current_buffer());
+            eng.reset_buffer();
+            self.record(Entry::Reset);
+            // Backspace right after a transform may be undoing it
+            if matches!(key, Key::Backspace) {
+                self.learn(|learner| learner.key('\x08', &GrabAction::PassThrough, Instant::now()));
+            } else {
+                self.learn(|learner| learner.reset());
+            }
+            GrabAction::PassThrough
+        } else if is_numpad_digit(&key) && !lock(engine).numpad_as_vni_tones() {
+            // Typed by the app, not the engine: the word can't continue
+            // past it
+            lock(engine).reset_buffer();
+            self.record(Entry::Reset);
+            self.learn(|learner| learner.reset());
+            GrabAction::PassThrough
+        } else if let Some(ch) = typed.or_else(|| untyped_char(&key, lock(engine).keyboard_layout())) {
+            let action = self.process_key(ch, engine);
+            self.learn(|learner| learner.key(ch, &action, Instant::now()));
+            action
+        } else if is_modifier_key(&key) {
+            GrabAction::PassThrough
+        } else {
+            // A key with no character the engine can compose (an OEM or
+            // international key, F1...) typed something the word can't
+            // continue past, like a separator
+            let mut eng = lock(engine);
+            debug!("Unknown key {:?}, clearing buffer: '{}'", key, eng.get_current_buffer());
+            eng.reset_buffer();
+            drop(eng);
+            self.record(Entry::Reset);
+            self.learn(|learner| learner.reset());
+            GrabAction::PassThrough
+        }
+    }
+
+    /// Run the backend's grab on its own thread, keeping `grab_running` up
+    /// to date
+    fn spawn_grab(&self) {
+        let backend = self.backend.clone();
+        let running = self.grab_running.clone();
+        running.store(true, Ordering::SeqCst);
+        std::thread::spawn(move || {
+            match backend.grab(grab_callback) {
+                Ok(()) => {}
+                Err(e @ VaixError::PermissionDenied(_)) => {
+                    error!(
+                        "Failed to start keyboard grab: {}. Accessibility is probably not granted to \
+                         Terminal, or Terminal needs a restart after granting it; try `vaixkey --open-accessibility`",
+                        e
+                    );
+                }
+                Err(e) => error!("Failed to start keyboard grab: {}", e),
+            }
+            running.store(false, Ordering::SeqCst);
+        });
+    }
+
+    /// After a wake or unlock: drop the stale word and modifier state, check
+    /// Accessibility is still granted and restart the grab if it ended
+    fn recover_after_wake(&self) {
+        info!("Woke up, resetting buffer: '{}'", lock(&self.engine).get_current_buffer());
+        lock(&self.engine).reset_buffer();
+        self.record(Entry::Reset);
+        self.learn(|learner| learner.reset());
+        for held in [&CTRL_HELD, &ALT_HELD, &META_HELD, &SHIFT_HELD] {
+            held.store(false, Ordering::SeqCst);
+        }
+
+        if !power::accessibility_trusted() {
+            warn!("Accessibility is no longer granted; keys can't be grabbed until it is");
+            return;
+        }
+        // Only a monitor that was started has a grab to restart
+        let started = KEY_CHANNEL.lock().is_ok_and(|channel| channel.is_some());
+        if started && !self.stopped.load(Ordering::SeqCst) && !self.grab_running.load(Ordering::SeqCst) {
+            info!("Keyboard grab ended while asleep, restarting it");
+            self.spawn_grab();
+        }
+    }
+
+    /// Drop the buffer once injection has failed `INJECT_FAILURE_LIMIT` times
+    /// in a row: the screen no longer matches the engine, and every later
+    /// replacement would backspace into the wrong text
+    fn check_injection(&self) {
+        let Some(reason) = INJECTION_BROKEN.lock().ok().and_then(|mut broken| broken.take()) else {
+            return;
+        };
+        error!("Injection failed {} times in a row, resetting buffer: {}", INJECT_FAILURE_LIMIT, reason);
+        lock(&self.engine).reset_buffer();
+        self.record(Entry::Reset);
+        if let Some(listener) = &self.failure_listener {
+            listener(&reason);
+        }
+    }
+
+    /// Commit the word being composed if no key reached the engine for
+    /// `engine.commit_timeout_ms` before `now`. Returns whether it did. The
+    /// processing loop calls this whenever it wakes up without a key.
+    pub fn commit_if_idle(&self, now: Instant) -> bool {
+        let Some(last_key_at) = *lock(&self.last_key_at) else {
+            return false;
+        };
+        let mut eng = lock(&self.engine);
+        let Some(timeout) = eng.commit_timeout() else {
+            return false;
+        };
+        if eng.get_current_buffer().is_empty() || now.duration_since(last_key_at) < timeout {
+            return false;
+        }
+
+        let committed = eng.commit_current_text();
+        drop(eng);
+        debug!("Idle for {:?}, committed '{}'", timeout, committed);
+        self.record(Entry::Reset);
+        *lock(&self.last_key_at) = None;
+        true
+    }
+
+    /// A key pressed with Ctrl, Alt or Cmd held. It always reaches the app
+    /// untouched. The dump hotkey hands the buffer to its listener and leaves
+    /// it alone; for anything else the engine decides whether the word is
+    /// dropped (`engine.reset_on_modifier`).
+    fn handle_shortcut(&self, combo: &Hotkey) {
+        let mut eng = lock(&self.engine);
+        debug!("Shortcut {}, buffer: '{}'", combo, eng.get_current_buffer());
+
+        if let Some((hotkey, listener)) = &self.dump_hotkey {
+            if hotkey == combo {
+                let dump = eng.dump_buffer();
+                drop(eng);
+                listener(&dump);
+                return;
+            }
+        }
+
+        if let Some((hotkey, listener)) = &self.convert_hotkey {
+            if hotkey == combo {
+                drop(eng);
+                listener();
+                return;
+            }
+        }
+
+        if eng.shortcut_pressed() {
+            self.record(Entry::Reset);
+        }
+    }
+
+    /// Drive a shortcut (Ctrl, Alt or Cmd held) the way the grab callback
+    /// reports it
+    pub fn dispatch_shortcut(&self, combo: &Hotkey) {
+        self.handle_shortcut(combo);
+    }
+
+    /// Tell the monitor the focused text field changed, as the focus observer
+    /// does; the composing buffer is dropped before the next key
+    pub fn notify_focus_changed(&self) {
+        focus::notify();
+    }
+
+    /// Report a sleep, wake or screen lock, as the power observer does
+    pub fn notify_power_event(&self, event: PowerEvent) {
+        power::notify(event);
+    }
+
+    /// Drive a key press through the same grab -> action -> inject path as a
+    /// real keystroke, injecting synchronously through this monitor's backend.
+    /// Modifier tracking in the grab callback is not involved.
+    pub fn dispatch_key(&self, key: Key, typed: Option<char>) -> GrabAction {
+        let action = self.handle_key(key, typed);
+        if let GrabAction::BlockAndInject { backspaces, text } = &action {
+            let (mode, fallback) = {
+                let engine = lock(&self.engine);
+                (engine.injection_mode(), engine.injection_fallback())
+            };
+            inject_tracked(self.backend.as_ref(), mode, fallback, *backspaces, text);
+            self.check_injection();
+        }
+        action
+    }
+
+    fn process_key(&self, ch: char, engine: &Arc<Mutex<InputMethodEngine>>) -> GrabAction {
+        let mut eng = lock(engine);
+        debug!("Key '{}' (buffer: '{}')", ch, eng.get_current_buffer());
+
+        // Time the engine and the hook only, not the wait for the lock
+        let started = Instant::now();
+        let result = eng.process_keypress(ch);
+        if eng.is_temporarily_off() {
+            debug!("Undo: typing English until the word ends");
+        }
+        if let Some(hook) = &self.process_hook {
+            hook();
+        }
+
+        // Typing must never lag: past the budget the key reaches the app as
+        // typed, and the word the engine was composing is dropped with it
+        let elapsed = started.elapsed();
+        if eng.process_budget().is_some_and(|budget| elapsed > budget) {
+            warn!("Key '{}' took {:?} to process, over engine.max_process_us; passing it through", ch, elapsed);
+            eng.reset_buffer();
+            drop(eng);
+            self.record(Entry::Key(ch));
+            self.record(Entry::Reset);
+            *lock(&self.last_key_at) = Some(Instant::now());
+            return GrabAction::PassThrough;
         }
-        
-        let result = eng.process_keypress(ch).await;
         drop(eng);
+        self.record(Entry::Key(ch));
+        *lock(&self.last_key_at) = Some(Instant::now());
         
         match &result {
             ProcessResult::PassThrough(c) => {
-                if debug_mode {
-                    println!("🔤 Pass through: '{}'", c);
-                    println!("─────────────────────────────────────");
-                }
+                debug!("Pass through: '{}'", c);
                 GrabAction::PassThrough
             }
             ProcessResult::Output(text) => {
-                if debug_mode {
-                    println!("🔤 Output: '{}' (blocking original, injecting)", text);
-                    println!("─────────────────────────────────────");
-                }
+                debug!("Output: '{}' (blocking original, injecting)", text);
                 // Block the key and inject text
                 GrabAction::BlockAndInject {
                     backspaces: 0,
@@ -317,11 +905,7 @@ impl KeyboardMonitor {
                 }
             }
             ProcessResult::Replace { backspaces, text } => {
-                if debug_mode {
-                    println!("🔤 Replace: {} backspaces, then '{}'", backspaces, text);
-                    println!("✨ Vietnamese transformation applied!");
-                    println!("─────────────────────────────────────");
-                }
+                debug!("Replace: {} backspaces, then '{}'", backspaces, text);
                 // Block the key, send backspaces, then inject new text
                 GrabAction::BlockAndInject {
                     backspaces: *backspaces,
@@ -332,10 +916,36 @@ impl KeyboardMonitor {
     }
 }
 
-/// Inject replacement text: send backspaces then type new text
-fn inject_replacement(backspaces: usize, text: &str, debug_mode: bool) {
+/// Start a grab that passes every key through and stop it after `wait`.
+/// Returns the backend's error if it gives up before then.
+pub fn probe_grab(wait: Duration) -> Result<(), VaixError> {
+    let backend = backend::select_backend(false);
+    let (done_tx, done_rx) = mpsc::channel();
+    let grabbing = backend.clone();
+    thread::spawn(move || {
+        let _ = done_tx.send(grabbing.grab(Some));
+    });
+
+    match done_rx.recv_timeout(wait) {
+        Ok(result) => result,
+        Err(_) => {
+            // Still grabbing, so the listener works
+            backend.stop_grab();
+            Ok(())
+        }
+    }
+}
+
+/// Inject replacement text: send backspaces then type or paste new text
+fn inject_replacement(
+    backend: &dyn InputBackend,
+    mode: InjectionMode,
+    fallback: bool,
+    backspaces: usize,
+    text: &str,
+) -> Result<(), VaixError> {
     if backspaces == 0 && text.is_empty() {
-        return;
+        return Ok(());
     }
 
     INJECTING.store(true, Ordering::SeqCst);
@@ -343,144 +953,119 @@ fn inject_replacement(backspaces: usize, text: &str, debug_mode: bool) {
     // Small delay to let grab callback return
     thread::sleep(Duration::from_millis(5));
     
-    // Send backspaces to delete the original characters
-    for _ in 0..backspaces {
-        send_key(Key::Backspace, debug_mode);
-        thread::sleep(Duration::from_millis(2));
-    }
-    
-    // Type the new text using Unicode input
-    for c in text.chars() {
-        send_unicode_char(c, debug_mode);
-        thread::sleep(Duration::from_millis(2));
-    }
+    // Send backspaces to delete the original characters, then type or paste
+    // the new text
+    let result = match mode {
+        InjectionMode::Paste if !text.is_empty() => paste_replacement(backend, fallback, backspaces, text),
+        _ => type_replacement(backend, fallback, backspaces, text),
+    };
     
     INJECTING.store(false, Ordering::SeqCst);
+    result
 }
 
-/// Send a single key press and release
-fn send_key(key: Key, debug_mode: bool) {
-    if let Err(e) = simulate(&EventType::KeyPress(key)) {
-        if debug_mode {
-            eprintln!("⚠️  Failed to simulate key press: {:?}", e);
-        }
+/// Send the backspaces, then type `text`. With `fallback` the text goes one
+/// character at a time, so when one is rejected it and the rest are typed
+/// with key presses instead, as is every replacement after it.
+fn type_replacement(backend: &dyn InputBackend, fallback: bool, backspaces: usize, text: &str) -> Result<(), VaixError> {
+    backend.send_backspaces(backspaces)?;
+    if !fallback {
+        return backend.send_text(text);
+    }
+    if KEYSTROKES_ONLY.load(Ordering::SeqCst) {
+        return backend.send_keystrokes(text);
     }
-    thread::sleep(Duration::from_millis(1));
-    if let Err(e) = simulate(&EventType::KeyRelease(key)) {
-        if debug_mode {
-            eprintln!("⚠️  Failed to simulate key release: {:?}", e);
+    for (i, c) in text.char_indices() {
+        if let Err(e) = backend.send_text(&text[i..i + c.len_utf8()]) {
+            warn!("Unicode injection failed ({}), typing key presses until VaixKey restarts", e);
+            KEYSTROKES_ONLY.store(true, Ordering::SeqCst);
+            return backend.send_keystrokes(&text[i..]);
         }
     }
+    Ok(())
 }
 
-/// Send a Unicode character using the platform's Unicode input method
-/// On macOS, we use the CGEvent API which rdev wraps
-fn send_unicode_char(c: char, debug_mode: bool) {
-    // For ASCII characters, we can use direct key simulation
-    if c.is_ascii_alphabetic() {
-        let key = char_to_key(c);
-        if let Some(k) = key {
-            let needs_shift = c.is_uppercase();
-            if needs_shift {
-                let _ = simulate(&EventType::KeyPress(Key::ShiftLeft));
-                thread::sleep(Duration::from_millis(1));
-            }
-            send_key(k, debug_mode);
-            if needs_shift {
-                let _ = simulate(&EventType::KeyRelease(Key::ShiftLeft));
+/// Put `text` on the clipboard, send the backspaces and paste it, then put
+/// back the text the clipboard held. A clipboard without text (an image,
+/// files) couldn't be put back, so the replacement is typed instead.
+fn paste_replacement(backend: &dyn InputBackend, fallback: bool, backspaces: usize, text: &str) -> Result<(), VaixError> {
+    let Ok(Some(saved)) = backend.read_clipboard() else {
+        return type_replacement(backend, fallback, backspaces, text);
+    };
+    backend.write_clipboard(text)?;
+    let pasted = backend
+        .send_backspaces(backspaces)
+        .and_then(|()| backend.send_paste());
+    thread::sleep(PASTE_RESTORE_DELAY);
+    let restored = backend.write_clipboard(&saved);
+    pasted.and(restored)
+}
+
+/// Inject a replacement and count consecutive failures. At
+/// `INJECT_FAILURE_LIMIT` the monitor is asked to drop the buffer.
+fn inject_tracked(backend: &dyn InputBackend, mode: InjectionMode, fallback: bool, backspaces: usize, text: &str) {
+    match inject_replacement(backend, mode, fallback, backspaces, text) {
+        Ok(()) => INJECT_FAILURES.store(0, Ordering::SeqCst),
+        Err(e) => {
+            warn!("Injection failed: {}", e);
+            if INJECT_FAILURES.fetch_add(1, Ordering::SeqCst) + 1 >= INJECT_FAILURE_LIMIT {
+                INJECT_FAILURES.store(0, Ordering::SeqCst);
+                if let Ok(mut broken) = INJECTION_BROKEN.lock() {
+                    *broken = Some(e.to_string());
+                }
             }
-            return;
-        }
-    }
-    
-    // For Vietnamese/Unicode characters, use platform-specific method
-    #[cfg(target_os = "macos")]
-    {
-        send_unicode_char_macos(c, debug_mode);
-    }
-    
-    #[cfg(not(target_os = "macos"))]
-    {
-        // Fallback: try rdev's Unknown key with the unicode value
-        if debug_mode {
-            eprintln!("⚠️  Unicode injection not fully implemented on this platform");
         }
     }
 }
 
-#[cfg(target_os = "macos")]
-fn send_unicode_char_macos(c: char, debug_mode: bool) {
-    use core_graphics::event::{CGEvent, CGEventTapLocation};
-    use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
-    
-    // Create event source
-    let source = match CGEventSource::new(CGEventSourceStateID::HIDSystemState) {
-        Ok(s) => s,
-        Err(_) => {
-            if debug_mode {
-                eprintln!("⚠️  Failed to create CGEventSource");
-            }
-            return;
-        }
-    };
-    
-    // Create a keyboard event
-    let event = match CGEvent::new_keyboard_event(source.clone(), 0, true) {
-        Ok(e) => e,
-        Err(_) => {
-            if debug_mode {
-                eprintln!("⚠️  Failed to create CGEvent");
-            }
-            return;
-        }
-    };
-    
-    // Set the Unicode string for this event
-    let chars: Vec<u16> = c.encode_utf16(&mut [0u16; 2]).to_vec();
-    event.set_string_from_utf16_unchecked(&chars);
-    
-    // Post the event
-    event.post(CGEventTapLocation::HID);
-    
-    // Send key up
-    if let Ok(up_event) = CGEvent::new_keyboard_event(source, 0, false) {
-        up_event.post(CGEventTapLocation::HID);
+/// The character an event typed according to the OS keyboard layout. This
+/// covers shifted letters, dead-key compositions and layouts with Vietnamese
+/// letters on their own keys (â, ư, đ...), which the physical key can't tell.
+fn typed_char(event: &Event) -> Option<char> {
+    let mut chars = event.name.as_deref()?.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !c.is_control() => Some(c),
+        _ => None,
     }
-    
-    if debug_mode {
-        debug!("📤 Injected Unicode: '{}'", c);
+}
+
+/// The physical keys a layout may put a letter on, row by row, in the order
+/// of the `layout_chars` strings
+const LAYOUT_KEYS: [Key; 35] = [
+    Key::KeyQ, Key::KeyW, Key::KeyE, Key::KeyR, Key::KeyT, Key::KeyY, Key::KeyU, Key::KeyI, Key::KeyO,
+    Key::KeyP, Key::LeftBracket, Key::RightBracket,
+    Key::KeyA, Key::KeyS, Key::KeyD, Key::KeyF, Key::KeyG, Key::KeyH, Key::KeyJ, Key::KeyK, Key::KeyL,
+    Key::SemiColon, Key::Quote,
+    Key::KeyZ, Key::KeyX, Key::KeyC, Key::KeyV, Key::KeyB, Key::KeyN, Key::KeyM, Key::Comma, Key::Dot,
+    Key::Slash,
+    Key::Minus, Key::Equal,
+];
+
+/// What each of `LAYOUT_KEYS` types, unshifted, on `layout`
+fn layout_chars(layout: KeyboardLayout) -> &'static str {
+    match layout {
+        KeyboardLayout::Qwerty => "qwertyuiop[]asdfghjkl;'zxcvbnm,./-=",
+        KeyboardLayout::Dvorak => "',.pyfgcrl/=aoeuidhtns-;qjkxbmwvz[]",
+        KeyboardLayout::Colemak => "qwfpgjluy;[]arstdhneio'zxcvbkm,./-=",
     }
 }
 
-/// Convert rdev Key to a character
-fn key_to_char(key: &Key) -> Option<char> {
+/// Characters the engine gets from a physical key: letters, and brackets
+/// (separators by default, ơ/ư with the Telex bracket shortcuts). Other
+/// punctuation reaches the app without touching the buffer.
+fn is_layout_char(c: char) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, '[' | ']')
+}
+
+/// Convert rdev Key to a character as `layout` types it. Only used when the
+/// OS doesn't report the character (`typed_char`), which already follows
+/// whatever layout is active.
+fn key_to_char(key: &Key, layout: KeyboardLayout) -> Option<char> {
+    if let Some(pos) = LAYOUT_KEYS.iter().position(|k| k == key) {
+        return layout_chars(layout).chars().nth(pos).filter(|&c| is_layout_char(c));
+    }
+
     match key {
-        Key::KeyA => Some('a'),
-        Key::KeyB => Some('b'),
-        Key::KeyC => Some('c'),
-        Key::KeyD => Some('d'),
-        Key::KeyE => Some('e'),
-        Key::KeyF => Some('f'),
-        Key::KeyG => Some('g'),
-        Key::KeyH => Some('h'),
-        Key::KeyI => Some('i'),
-        Key::KeyJ => Some('j'),
-        Key::KeyK => Some('k'),
-        Key::KeyL => Some('l'),
-        Key::KeyM => Some('m'),
-        Key::KeyN => Some('n'),
-        Key::KeyO => Some('o'),
-        Key::KeyP => Some('p'),
-        Key::KeyQ => Some('q'),
-        Key::KeyR => Some('r'),
-        Key::KeyS => Some('s'),
-        Key::KeyT => Some('t'),
-        Key::KeyU => Some('u'),
-        Key::KeyV => Some('v'),
-        Key::KeyW => Some('w'),
-        Key::KeyX => Some('x'),
-        Key::KeyY => Some('y'),
-        Key::KeyZ => Some('z'),
         Key::Num0 => Some('0'),
         Key::Num1 => Some('1'),
         Key::Num2 => Some('2'),
@@ -491,39 +1076,69 @@ fn key_to_char(key: &Key) -> Option<char> {
         Key::Num7 => Some('7'),
         Key::Num8 => Some('8'),
         Key::Num9 => Some('9'),
+        // The same digits on the numpad, when `engine.numpad_as_vni_tones`
+        // lets them through
+        Key::Kp0 => Some('0'),
+        Key::Kp1 => Some('1'),
+        Key::Kp2 => Some('2'),
+        Key::Kp3 => Some('3'),
+        Key::Kp4 => Some('4'),
+        Key::Kp5 => Some('5'),
+        Key::Kp6 => Some('6'),
+        Key::Kp7 => Some('7'),
+        Key::Kp8 => Some('8'),
+        Key::Kp9 => Some('9'),
+        _ => None,
+    }
+}
+
+/// Check if this key is a digit on the numpad
+fn is_numpad_digit(key: &Key) -> bool {
+    matches!(key,
+        Key::Kp0 | Key::Kp1 | Key::Kp2 | Key::Kp3 | Key::Kp4 |
+        Key::Kp5 | Key::Kp6 | Key::Kp7 | Key::Kp8 | Key::Kp9
+    )
+}
+
+/// Convert a word-ending separator key to the character the engine sees
+fn separator_to_char(key: &Key) -> Option<char> {
+    match key {
+        Key::Space => Some(' '),
+        Key::Return => Some('\n'),
+        Key::Tab => Some('\t'),
+        _ => None,
+    }
+}
+
+/// Convert a separator character back to its rdev Key
+fn separator_to_key(c: char) -> Option<Key> {
+    match c {
+        ' ' => Some(Key::Space),
+        '\n' => Some(Key::Return),
+        '\t' => Some(Key::Tab),
         _ => None,
     }
 }
 
-/// Convert a character to rdev Key (lowercase)
-fn char_to_key(c: char) -> Option<Key> {
-    match c.to_ascii_lowercase() {
-        'a' => Some(Key::KeyA),
-        'b' => Some(Key::KeyB),
-        'c' => Some(Key::KeyC),
-        'd' => Some(Key::KeyD),
-        'e' => Some(Key::KeyE),
-        'f' => Some(Key::KeyF),
-        'g' => Some(Key::KeyG),
-        'h' => Some(Key::KeyH),
-        'i' => Some(Key::KeyI),
-        'j' => Some(Key::KeyJ),
-        'k' => Some(Key::KeyK),
-        'l' => Some(Key::KeyL),
-        'm' => Some(Key::KeyM),
-        'n' => Some(Key::KeyN),
-        'o' => Some(Key::KeyO),
-        'p' => Some(Key::KeyP),
-        'q' => Some(Key::KeyQ),
-        'r' => Some(Key::KeyR),
-        's' => Some(Key::KeyS),
-        't' => Some(Key::KeyT),
-        'u' => Some(Key::KeyU),
-        'v' => Some(Key::KeyV),
-        'w' => Some(Key::KeyW),
-        'x' => Some(Key::KeyX),
-        'y' => Some(Key::KeyY),
-        'z' => Some(Key::KeyZ),
+/// Convert a character to the rdev Key that types it on QWERTY (lowercase)
+pub fn char_to_key(c: char) -> Option<Key> {
+    char_to_layout_key(c, KeyboardLayout::Qwerty)
+}
+
+/// Convert a character to the rdev Key that types it on `layout`
+/// (lowercase)
+pub fn char_to_layout_key(c: char, layout: KeyboardLayout) -> Option<Key> {
+    if let Some(key) = separator_to_key(c) {
+        return Some(key);
+    }
+
+    let c = c.to_ascii_lowercase();
+    if c.is_ascii_alphabetic() {
+        let pos = layout_chars(layout).chars().position(|l| l == c)?;
+        return Some(LAYOUT_KEYS[pos]);
+    }
+
+    match c {
         '0' => Some(Key::Num0),
         '1' => Some(Key::Num1),
         '2' => Some(Key::Num2),
@@ -536,4 +1151,21 @@ fn char_to_key(c: char) -> Option<Key> {
         '9' => Some(Key::Num9),
         _ => None,
     }
-}
\ No newline at end of file
+}
+
+/// Convert a digit to its numpad key; `char_to_key` gives the top-row one
+pub fn char_to_numpad_key(c: char) -> Option<Key> {
+    match c {
+        '0' => Some(Key::Kp0),
+        '1' => Some(Key::Kp1),
+        '2' => Some(Key::Kp2),
+        '3' => Some(Key::Kp3),
+        '4' => Some(Key::Kp4),
+        '5' => Some(Key::Kp5),
+        '6' => Some(Key::Kp6),
+        '7' => Some(Key::Kp7),
+        '8' => Some(Key::Kp8),
+        '9' => Some(Key::Kp9),
+        _ => None,
+    }
+}
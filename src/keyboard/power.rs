@@ -0,0 +1,227 @@
+// Sleep, wake and screen lock: whatever was being composed before the Mac
+// slept is stale afterwards, and the event tap may have died with it. Every
+// event is logged so users can line problems up with it; wake and unlock
+// raise a flag the monitor checks before the next key, to reset the buffer,
+// re-check Accessibility and restart the grab if it ended.
+// On macOS, sleep and wake come from IOKit's system power notifications and
+// the lock state from the `com.apple.screenIsLocked`/`Unlocked` distributed
+// notifications. Both are delivered on a run loop of our own, unlike
+// NSWorkspace's, which need AppKit's main loop. Elsewhere nothing is observed
+// and only `notify` raises the flag.
+
+use log::info;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static WOKE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PowerEvent {
+    Sleep,
+    Wake,
+    ScreenLocked,
+    ScreenUnlocked,
+}
+
+/// Log `event`; after a wake or unlock the monitor resets before the next
+/// key
+pub fn notify(event: PowerEvent) {
+    info!("System event: {:?}", event);
+    if matches!(event, PowerEvent::Wake | PowerEvent::ScreenUnlocked) {
+        WOKE.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Whether the system woke or was unlocked since the last call
+pub fn take_woke() -> bool {
+    WOKE.swap(false, Ordering::SeqCst)
+}
+
+#[cfg(target_os = "macos")]
+pub use observer::{accessibility_trusted, start, stop};
+
+#[cfg(not(target_os = "macos"))]
+pub fn start() {}
+
+#[cfg(not(target_os = "macos"))]
+pub fn stop() {}
+
+#[cfg(not(target_os = "macos"))]
+pub fn accessibility_trusted() -> bool {
+    true
+}
+
+#[cfg(target_os = "macos")]
+mod observer {
+    use super::{notify, PowerEvent};
+    use core_foundation::base::{CFIndex, CFTypeRef, TCFType};
+    use core_foundation::runloop::{kCFRunLoopDefaultMode, CFRunLoop, CFRunLoopSource, CFRunLoopSourceRef};
+    use core_foundation::string::{CFString, CFStringRef};
+    use log::{debug, warn};
+    use std::ffi::c_void;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::time::Duration;
+
+    type IOServiceInterestCallback = extern "C" fn(*mut c_void, u32, u32, *mut c_void);
+    type CFNotificationCallback = extern "C" fn(CFTypeRef, *const c_void, CFStringRef, *const c_void, CFTypeRef);
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IORegisterForSystemPower(
+            refcon: *mut c_void,
+            port: *mut *mut c_void,
+            callback: IOServiceInterestCallback,
+            notifier: *mut u32,
+        ) -> u32;
+        fn IONotificationPortGetRunLoopSource(port: *mut c_void) -> CFRunLoopSourceRef;
+        fn IOAllowPowerChange(kernel_port: u32, notification_id: isize) -> i32;
+        fn IODeregisterForSystemPower(notifier: *mut u32) -> i32;
+        fn IONotificationPortDestroy(port: *mut c_void);
+        fn IOServiceClose(connect: u32) -> i32;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFNotificationCenterGetDistributedCenter() -> CFTypeRef;
+        fn CFNotificationCenterAddObserver(
+            center: CFTypeRef,
+            observer: *const c_void,
+            callback: CFNotificationCallback,
+            name: CFStringRef,
+            object: *const c_void,
+            suspension_behavior: CFIndex,
+        );
+        fn CFNotificationCenterRemoveObserver(
+            center: CFTypeRef,
+            observer: *const c_void,
+            name: CFStringRef,
+            object: *const c_void,
+        );
+    }
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn AXIsProcessTrusted() -> bool;
+    }
+
+    // IOKit power messages (IOMessage.h)
+    const CAN_SYSTEM_SLEEP: u32 = 0xE000_0270;
+    const SYSTEM_WILL_SLEEP: u32 = 0xE000_0280;
+    const SYSTEM_HAS_POWERED_ON: u32 = 0xE000_0300;
+    /// CFNotificationSuspensionBehaviorDeliverImmediately
+    const DELIVER_IMMEDIATELY: CFIndex = 4;
+    const SCREEN_LOCKED: &str = "com.apple.screenIsLocked";
+    const SCREEN_UNLOCKED: &str = "com.apple.screenIsUnlocked";
+    /// How often the observer thread checks whether it should stop
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    static RUNNING: AtomicBool = AtomicBool::new(false);
+    /// Connection sleep notices must be acknowledged on
+    static ROOT_PORT: AtomicU32 = AtomicU32::new(0);
+
+    /// Whether the process still has Accessibility permission
+    pub fn accessibility_trusted() -> bool {
+        unsafe { AXIsProcessTrusted() }
+    }
+
+    /// Start observing on a background thread
+    pub fn start() {
+        if RUNNING.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        std::thread::spawn(|| {
+            let run_loop = CFRunLoop::get_current();
+            let mut port: *mut c_void = std::ptr::null_mut();
+            let mut notifier = 0;
+            let mut power_source = None;
+            // Distinct per thread, so a stop/start pair can't remove the
+            // next thread's observers
+            let token = Box::new(0u8);
+            let observer = &*token as *const u8 as *const c_void;
+            let center = unsafe { CFNotificationCenterGetDistributedCenter() };
+            let root_port =
+                unsafe { IORegisterForSystemPower(std::ptr::null_mut(), &mut port, power_changed, &mut notifier) };
+
+            unsafe {
+                if root_port == 0 || port.is_null() {
+                    warn!("Cannot register for sleep and wake notifications");
+                } else {
+                    ROOT_PORT.store(root_port, Ordering::SeqCst);
+                    let source = CFRunLoopSource::wrap_under_get_rule(IONotificationPortGetRunLoopSource(port));
+                    run_loop.add_source(&source, kCFRunLoopDefaultMode);
+                    power_source = Some(source);
+                }
+
+                for name in [SCREEN_LOCKED, SCREEN_UNLOCKED] {
+                    let name = CFString::from_static_string(name);
+                    CFNotificationCenterAddObserver(
+                        center,
+                        observer,
+                        screen_lock_changed,
+                        name.as_concrete_TypeRef(),
+                        std::ptr::null(),
+                        DELIVER_IMMEDIATELY,
+                    );
+                }
+            }
+            debug!("Observing sleep/wake (registered: {}) and screen lock", power_source.is_some());
+
+            while RUNNING.load(Ordering::SeqCst) {
+                CFRunLoop::run_in_mode(unsafe { kCFRunLoopDefaultMode }, POLL_INTERVAL, false);
+            }
+
+            // Undo the registrations on the thread that made them
+            unsafe {
+                for name in [SCREEN_LOCKED, SCREEN_UNLOCKED] {
+                    let name = CFString::from_static_string(name);
+                    CFNotificationCenterRemoveObserver(center, observer, name.as_concrete_TypeRef(), std::ptr::null());
+                }
+                if let Some(source) = power_source {
+                    run_loop.remove_source(&source, kCFRunLoopDefaultMode);
+                    IODeregisterForSystemPower(&mut notifier);
+                    IONotificationPortDestroy(port);
+                    IOServiceClose(root_port);
+                    let _ = ROOT_PORT.compare_exchange(root_port, 0, Ordering::SeqCst, Ordering::SeqCst);
+                }
+            }
+            debug!("Stopped observing sleep/wake and screen lock");
+        });
+    }
+
+    /// Ask the observer thread to finish; it deregisters and exits within
+    /// one poll interval
+    pub fn stop() {
+        RUNNING.store(false, Ordering::SeqCst);
+    }
+
+    /// Sleep has to be acknowledged, or the system waits 30 seconds for us
+    extern "C" fn power_changed(_refcon: *mut c_void, _service: u32, message: u32, argument: *mut c_void) {
+        match message {
+            CAN_SYSTEM_SLEEP => allow_power_change(argument),
+            SYSTEM_WILL_SLEEP => {
+                notify(PowerEvent::Sleep);
+                allow_power_change(argument);
+            }
+            SYSTEM_HAS_POWERED_ON => notify(PowerEvent::Wake),
+            _ => {}
+        }
+    }
+
+    fn allow_power_change(argument: *mut c_void) {
+        unsafe { IOAllowPowerChange(ROOT_PORT.load(Ordering::SeqCst), argument as isize) };
+    }
+
+    extern "C" fn screen_lock_changed(
+        _center: CFTypeRef,
+        _observer: *const c_void,
+        name: CFStringRef,
+        _object: *const c_void,
+        _user_info: CFTypeRef,
+    ) {
+        let name = unsafe { CFString::wrap_under_get_rule(name) }.to_string();
+        match name.as_str() {
+            SCREEN_LOCKED => notify(PowerEvent::ScreenLocked),
+            SCREEN_UNLOCKED => notify(PowerEvent::ScreenUnlocked),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,112 @@
+// Test double that records injections instead of sending them to the OS
+// Lets `--test` drive KeyboardMonitor with scripted keys and check exactly
+// what would have been typed into the focused application.
+
+use super::backend::InputBackend;
+use rdev::Event;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use vaixkey::VaixError;
+
+/// One call made to the backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendCall {
+    Backspaces(usize),
+    Text(String),
+    /// The clipboard was set to this text
+    Clipboard(String),
+    Paste,
+    /// Typed with key presses only (`injection_fallback`)
+    Keystrokes(String),
+}
+
+#[derive(Debug, Default)]
+pub struct RecordingBackend {
+    calls: Mutex<Vec<BackendCall>>,
+    clipboard: Mutex<Option<String>>,
+    failing: AtomicBool,
+    refusing_unicode: AtomicBool,
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every call recorded so far, in order
+    pub fn calls(&self) -> Vec<BackendCall> {
+        self.calls.lock().map(|c| c.clone()).unwrap_or_default()
+    }
+
+    /// What the clipboard holds, as a user copied it or injection left it
+    pub fn clipboard(&self) -> Option<String> {
+        self.clipboard.lock().ok().and_then(|c| c.clone())
+    }
+
+    pub fn set_clipboard(&self, text: Option<&str>) {
+        if let Ok(mut clipboard) = self.clipboard.lock() {
+            *clipboard = text.map(str::to_string);
+        }
+    }
+
+    /// Make every later call fail without recording, like an app that
+    /// rejects synthetic events
+    pub fn set_failing(&self, failing: bool) {
+        self.failing.store(failing, Ordering::SeqCst);
+    }
+
+    /// Make typed text with non-ASCII characters fail, like a game or remote
+    /// desktop client that drops Unicode strings but takes key presses
+    pub fn set_refusing_unicode(&self, refusing: bool) {
+        self.refusing_unicode.store(refusing, Ordering::SeqCst);
+    }
+
+    fn record(&self, call: BackendCall) -> Result<(), VaixError> {
+        if self.failing.load(Ordering::SeqCst) {
+            return Err(VaixError::Backend { backend: self.name(), message: "injection refused".to_string() });
+        }
+        if let Ok(mut calls) = self.calls.lock() {
+            calls.push(call);
+        }
+        Ok(())
+    }
+}
+
+impl InputBackend for RecordingBackend {
+    fn name(&self) -> &'static str {
+        "recording"
+    }
+
+    fn grab(&self, _callback: fn(Event) -> Option<Event>) -> Result<(), VaixError> {
+        Err(VaixError::Backend { backend: self.name(), message: "cannot capture keys".to_string() })
+    }
+
+    fn send_backspaces(&self, count: usize) -> Result<(), VaixError> {
+        self.record(BackendCall::Backspaces(count))
+    }
+
+    fn send_text(&self, text: &str) -> Result<(), VaixError> {
+        if self.refusing_unicode.load(Ordering::SeqCst) && !text.is_ascii() {
+            return Err(VaixError::Backend { backend: self.name(), message: format!("cannot type '{}'", text) });
+        }
+        self.record(BackendCall::Text(text.to_string()))
+    }
+
+    fn send_keystrokes(&self, text: &str) -> Result<(), VaixError> {
+        self.record(BackendCall::Keystrokes(text.to_string()))
+    }
+
+    fn read_clipboard(&self) -> Result<Option<String>, VaixError> {
+        Ok(self.clipboard())
+    }
+
+    fn write_clipboard(&self, text: &str) -> Result<(), VaixError> {
+        self.record(BackendCall::Clipboard(text.to_string()))?;
+        self.set_clipboard(Some(text));
+        Ok(())
+    }
+
+    fn send_paste(&self) -> Result<(), VaixError> {
+        self.record(BackendCall::Paste)
+    }
+}
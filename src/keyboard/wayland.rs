@@ -0,0 +1,217 @@
+// Wayland backend (`wayland` feature, Linux)
+// rdev's Linux grab reads evdev devices directly, so capture keeps working
+// under Wayland (the user needs read access to /dev/input, usually via the
+// `input` group). rdev's simulate goes through XTest, which Wayland clients
+// ignore, so injection speaks the virtual keyboard protocol
+// (zwp_virtual_keyboard_v1) to the compositor itself.
+//
+// The virtual keyboard types keycodes through a keymap it supplies, so each
+// character gets a key of its own in a keymap we generate: Backspace first,
+// then every character typed so far. A new character means uploading the
+// keymap again; after that, typing it is two key events on the existing
+// connection.
+//
+// Compositors known to implement the virtual keyboard protocol: Sway,
+// Hyprland, river and Wayfire (wlroots based). GNOME (Mutter) does not, and
+// `select_backend` falls back to rdev there.
+
+use super::backend::{grab_error, InputBackend};
+use log::debug;
+use rdev::Event;
+use std::fs::File;
+use std::io::Write;
+use std::os::fd::AsFd;
+use std::sync::Mutex;
+use std::time::Instant;
+use vaixkey::{lock, VaixError};
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_keyboard, wl_registry, wl_seat::WlSeat};
+use wayland_client::{delegate_noop, Connection, Dispatch, EventQueue, QueueHandle};
+use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1;
+use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1;
+
+/// xkb keycodes are evdev codes plus 8; the protocol takes evdev codes
+const XKB_KEYCODE_OFFSET: u32 = 8;
+/// Highest xkb keycode
+const XKB_KEYCODE_MAX: u32 = 255;
+/// Evdev code of Backspace in our keymap; characters follow it
+const BACKSPACE_CODE: u32 = 1;
+/// Characters one keymap holds after Backspace
+const MAX_CHARS: usize = (XKB_KEYCODE_MAX - XKB_KEYCODE_OFFSET - BACKSPACE_CODE) as usize;
+
+pub struct WaylandBackend {
+    keyboard: Mutex<VirtualKeyboard>,
+    debug_mode: bool,
+}
+
+impl WaylandBackend {
+    /// Connect to the compositor and create the virtual keyboard. Fails when
+    /// the compositor doesn't offer the protocol.
+    pub fn connect(debug_mode: bool) -> Result<Self, VaixError> {
+        let keyboard = VirtualKeyboard::connect()
+            .map_err(|message| VaixError::Backend { backend: "wayland", message })?;
+        Ok(Self { keyboard: Mutex::new(keyboard), debug_mode })
+    }
+
+    /// Type `keys`, `None` being Backspace
+    fn type_keys(&self, keys: &[Option<char>]) -> Result<(), VaixError> {
+        lock(&self.keyboard)
+            .type_keys(keys)
+            .map_err(|message| VaixError::Backend { backend: self.name(), message })
+    }
+}
+
+impl InputBackend for WaylandBackend {
+    fn name(&self) -> &'static str {
+        "wayland"
+    }
+
+    fn grab(&self, callback: fn(Event) -> Option<Event>) -> Result<(), VaixError> {
+        rdev::grab(callback).map_err(|e| grab_error(self.name(), e))
+    }
+
+    fn send_backspaces(&self, count: usize) -> Result<(), VaixError> {
+        if count == 0 {
+            return Ok(());
+        }
+        self.type_keys(&vec![None; count])
+    }
+
+    fn send_text(&self, text: &str) -> Result<(), VaixError> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        if self.debug_mode {
+            debug!("📤 Injecting via virtual keyboard: '{}'", text);
+        }
+        self.type_keys(&text.chars().map(Some).collect::<Vec<_>>())
+    }
+
+    /// Every character is already a key press here
+    fn send_keystrokes(&self, text: &str) -> Result<(), VaixError> {
+        self.send_text(text)
+    }
+}
+
+struct State;
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_noop!(State: ignore WlSeat);
+delegate_noop!(State: ZwpVirtualKeyboardManagerV1);
+delegate_noop!(State: ZwpVirtualKeyboardV1);
+
+/// A virtual keyboard on the compositor's seat, with the keymap it last
+/// uploaded
+struct VirtualKeyboard {
+    queue: EventQueue<State>,
+    keyboard: ZwpVirtualKeyboardV1,
+    /// Characters in the keymap; `keys[i]` has evdev code
+    /// `BACKSPACE_CODE + 1 + i`
+    keys: Vec<char>,
+    started: Instant,
+}
+
+impl VirtualKeyboard {
+    /// Connect to the compositor named by `WAYLAND_DISPLAY`
+    fn connect() -> Result<Self, String> {
+        let conn = Connection::connect_to_env().map_err(|e| format!("cannot connect to the compositor: {}", e))?;
+        let (globals, queue) =
+            registry_queue_init::<State>(&conn).map_err(|e| format!("cannot list compositor globals: {}", e))?;
+        let qh = queue.handle();
+        let seat: WlSeat = globals.bind(&qh, 1..=1, ()).map_err(|e| format!("no seat: {}", e))?;
+        let manager: ZwpVirtualKeyboardManagerV1 = globals
+            .bind(&qh, 1..=1, ())
+            .map_err(|e| format!("the compositor has no virtual keyboard protocol: {}", e))?;
+        let keyboard = manager.create_virtual_keyboard(&seat, &qh, ());
+        let mut keyboard = Self { queue, keyboard, keys: Vec::new(), started: Instant::now() };
+        // Keys sent before any keymap are a protocol error, so Backspace
+        // alone goes up front
+        keyboard.upload_keymap()?;
+        Ok(keyboard)
+    }
+
+    /// Press and release the key for each item, `None` being Backspace, and
+    /// wait for the compositor to take them
+    fn type_keys(&mut self, keys: &[Option<char>]) -> Result<(), String> {
+        let mut needed: Vec<char> = Vec::new();
+        for &c in keys.iter().flatten() {
+            if !needed.contains(&c) {
+                needed.push(c);
+            }
+        }
+        if needed.len() > MAX_CHARS {
+            return Err(format!("more than {} different characters in one edit", MAX_CHARS));
+        }
+        if needed.iter().any(|c| !self.keys.contains(c)) {
+            // Grow the keymap, or start over with just these when it's full
+            let mut grown = self.keys.clone();
+            grown.extend(needed.iter().filter(|c| !self.keys.contains(c)));
+            self.keys = if grown.len() > MAX_CHARS { needed } else { grown };
+            self.upload_keymap()?;
+        }
+
+        for key in keys {
+            let code = match key {
+                None => BACKSPACE_CODE,
+                Some(c) => BACKSPACE_CODE + 1 + self.keys.iter().position(|k| k == c).unwrap_or(0) as u32,
+            };
+            let time = self.started.elapsed().as_millis() as u32;
+            self.keyboard.key(time, code, wl_keyboard::KeyState::Pressed.into());
+            self.keyboard.key(time, code, wl_keyboard::KeyState::Released.into());
+        }
+        self.queue.roundtrip(&mut State).map_err(|e| format!("compositor connection lost: {}", e))?;
+        Ok(())
+    }
+
+    /// Send the keymap for `self.keys`
+    fn upload_keymap(&mut self) -> Result<(), String> {
+        let keymap = keymap(&self.keys);
+        let mut file = keymap_file().map_err(|e| format!("cannot create the keymap file: {}", e))?;
+        // The size covers a NUL terminator, as for wl_keyboard keymaps
+        file.write_all(keymap.as_bytes())
+            .and_then(|()| file.write_all(&[0]))
+            .map_err(|e| format!("cannot write the keymap file: {}", e))?;
+        self.keyboard.keymap(wl_keyboard::KeymapFormat::XkbV1.into(), file.as_fd(), keymap.len() as u32 + 1);
+        Ok(())
+    }
+}
+
+/// xkb keymap text: Backspace on the first key, then one key per character,
+/// named by its Unicode keysym (`U1EC7` for ệ)
+fn keymap(keys: &[char]) -> String {
+    let first = XKB_KEYCODE_OFFSET + BACKSPACE_CODE;
+    let mut codes = format!("minimum = {};\nmaximum = {};\n", XKB_KEYCODE_OFFSET, XKB_KEYCODE_MAX);
+    let mut symbols = String::new();
+    let names = std::iter::once("BackSpace".to_string()).chain(keys.iter().map(|&c| format!("U{:04X}", c as u32)));
+    for (i, name) in names.enumerate() {
+        codes.push_str(&format!("<K{}> = {};\n", i, first + i as u32));
+        symbols.push_str(&format!("key <K{}> {{ [ {} ] }};\n", i, name));
+    }
+    format!(
+        "xkb_keymap {{\nxkb_keycodes \"vaixkey\" {{\n{}}};\n\
+         xkb_types \"vaixkey\" {{ include \"complete\" }};\n\
+         xkb_compatibility \"vaixkey\" {{ include \"complete\" }};\n\
+         xkb_symbols \"vaixkey\" {{\n{}}};\n}};\n",
+        codes, symbols
+    )
+}
+
+/// An unlinked file in the runtime directory for the compositor to map
+fn keymap_file() -> std::io::Result<File> {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR").map_or_else(std::env::temp_dir, Into::into);
+    let path = dir.join(format!("vaixkey-keymap-{}", std::process::id()));
+    let file = File::options().read(true).write(true).create(true).truncate(true).open(&path)?;
+    std::fs::remove_file(&path)?;
+    Ok(file)
+}
@@ -0,0 +1,21 @@
+// VaixKey library: the Vietnamese processing engine and its configuration,
+// shared by the macOS app and the C ABI (cdylib) for embedding elsewhere.
+// Nothing here touches the keyboard, the GUI or macOS: those modules belong
+// to the binary, which needs the `app` feature. Build with
+// `--no-default-features --features dictionary` for the engine alone.
+
+pub mod config;
+pub mod error;
+pub mod ffi;
+pub mod input_method;
+
+pub use config::{Config, EngineConfig, FeedbackKind, InjectionMode, InputMethodType, KeyboardLayout, Language, TonePlacement, ZKey};
+pub use error::VaixError;
+pub use input_method::{apply_results, InputMethod, InputMethodEngine, ProcessResult, UnikeyEngine, UnikeyEngineBuilder};
+
+/// Lock a mutex shared with the keyboard grab thread. A panic while it was
+/// held leaves the data usable (at worst the composing word is stale), so
+/// poisoning is ignored rather than taking typing down with it.
+pub fn lock<T>(mutex: &std::sync::Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+}
@@ -1,65 +1,236 @@
-mod config;
-mod input_method;
 mod keyboard;
 mod gui;
+mod bench;
+mod cache;
+mod i18n;
+mod doctor;
+mod explain;
+mod replay;
+mod repl;
+mod rpc;
+mod uninstall;
 
 use log::{info, error};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
-use config::Config;
-use input_method::InputMethodEngine;
-use keyboard::KeyboardMonitor;
+use vaixkey::{config, input_method, lock, VaixError};
+
+use config::{Config, EngineConfig, FeedbackKind, InjectionMode, Language, TonePlacement, ZKey};
+use input_method::{harness, InputMethod, InputMethodEngine, ProcessResult, UnikeyEngine};
+use keyboard::{BackendCall, GrabAction, KeyboardMonitor, RecordingBackend};
 use gui::GuiManager;
+use i18n::t;
+
+// Counts heap allocations for `--benchmark`; otherwise just the system allocator
+#[global_allocator]
+static ALLOCATOR: bench::CountingAllocator = bench::CountingAllocator;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    env_logger::init();
+    init_logging();
     info!("Starting VaixKey Vietnamese Input Method");
 
+    // A typo in the engine's character tables would garble words; refuse to
+    // start instead
+    if let Err(e) = UnikeyEngine::new().self_check() {
+        error!("Engine table self-check failed: {}", e);
+        return Err(format!("engine table self-check failed: {}", e).into());
+    }
+
+    // `--config <path>` can appear anywhere and is removed before the
+    // command is matched
+    let mut args: Vec<String> = std::env::args().collect();
+    if let Some(pos) = args.iter().position(|a| a == "--config") {
+        if pos + 1 >= args.len() {
+            return Err("--config requires a path".into());
+        }
+        let path = args.remove(pos + 1);
+        args.remove(pos);
+        Config::set_path_override(path.into());
+    }
+
+    // `--profile <name>` selects a profile and remembers it for later runs
+    if let Some(pos) = args.iter().position(|a| a == "--profile") {
+        if pos + 1 >= args.len() {
+            return Err("--profile requires a name".into());
+        }
+        let name = args.remove(pos + 1);
+        args.remove(pos);
+        Config::set_current_profile(Some(&name))?;
+        info!("Using profile '{}'", name);
+    }
+
+    // `--lang <en|vi>` overrides the config's language for this run
+    let mut lang_override = None;
+    if let Some(pos) = args.iter().position(|a| a == "--lang") {
+        let lang = args.get(pos + 1).ok_or("--lang requires a language (en or vi)")?;
+        lang_override = Some(lang.parse::<Language>()?);
+        args.drain(pos..pos + 2);
+    }
+
+    // `--rpc-port <port>` serves the engine over localhost while running
+    let mut rpc_port = None;
+    if let Some(pos) = args.iter().position(|a| a == "--rpc-port") {
+        let port = args.get(pos + 1).and_then(|p| p.parse::<u16>().ok()).ok_or("--rpc-port requires a port number")?;
+        args.drain(pos..pos + 2);
+        rpc_port = Some(port);
+    }
+
+    // `--learn` logs transforms the user undoes by hand
+    let learn = match args.iter().position(|a| a == "--learn") {
+        Some(pos) => {
+            args.remove(pos);
+            true
+        }
+        None => false,
+    };
+
+    // `--no-cache` parses the config file even when cache.bin matches it
+    let use_cache = match args.iter().position(|a| a == "--no-cache") {
+        Some(pos) => {
+            args.remove(pos);
+            false
+        }
+        None => true,
+    };
+
+    // Uninstalling must not load (and so recreate) the config first
+    if args.get(1).map(String::as_str) == Some("--uninstall") {
+        let assume_yes = args.iter().any(|a| a == "--yes" || a == "-y");
+        return uninstall::run(assume_yes);
+    }
+
+    // Both work on a config too broken to load
+    if args.get(1).map(String::as_str) == Some("--print-default-config") {
+        print!("{}", Config::default_toml()?);
+        return Ok(());
+    }
+    if args.get(1).map(String::as_str) == Some("--reset-config") {
+        let assume_yes = args.iter().any(|a| a == "--yes" || a == "-y");
+        return reset_config(assume_yes);
+    }
+
+    // The doctor reports a broken config instead of failing to load it
+    if args.get(1).map(String::as_str) == Some("--doctor") {
+        std::process::exit(doctor::run());
+    }
+
     // Load configuration
-    let config = Arc::new(Mutex::new(Config::load()?));
+    let config = Arc::new(Mutex::new(if use_cache { cache::load()? } else { Config::load()? }));
     info!("Configuration loaded successfully");
+    i18n::set_language(lang_override.unwrap_or(lock(&config).language));
 
     // Initialize input method engine
     let engine = Arc::new(Mutex::new(InputMethodEngine::new(config.clone())));
 
     // Initialize GUI manager
-    let gui_manager = GuiManager::new();
+    let mut gui_manager = GuiManager::new();
+    gui_manager.set_show_notifications(lock(&config).show_notifications);
 
     // Show initial status
-    {
-        let engine = engine.lock().await;
-        gui_manager.show_status_indicator(engine.is_vietnamese_mode()).await?;
-    }
+    let is_vietnamese = lock(&engine).is_vietnamese_mode();
+    gui_manager.show_status_indicator(is_vietnamese).await?;
+
+    // Sound or notification each time the mode is switched
+    let mut toggle_feedback = GuiManager::new();
+    toggle_feedback.set_show_notifications(lock(&config).show_notifications);
+    toggle_feedback.set_toggle_feedback(lock(&config).toggle_feedback);
+    toggle_feedback.watch_mode(engine.clone());
 
     // Start keyboard monitor
-    let keyboard_monitor = if std::env::var("VAIXKEY_DEBUG").is_ok() {
+    let mut keyboard_monitor = if std::env::var("VAIXKEY_DEBUG").is_ok() {
         KeyboardMonitor::new_with_debug(engine.clone())
     } else {
         KeyboardMonitor::new(engine.clone())
     };
 
+    #[cfg(target_os = "macos")]
+    let show_preedit = lock(&config).ui.show_preedit;
+    #[cfg(target_os = "macos")]
+    if show_preedit {
+        keyboard_monitor.set_status_listener(Box::new(|status| {
+            let gui_manager = GuiManager::new();
+            match gui::caret_position() {
+                // After an undo the rest of the word is typed as is
+                Some(caret) if status.temporarily_off => {
+                    gui_manager.show_preedit(&format!("{} · EN", status.buffer), caret)
+                }
+                Some(caret) if !status.buffer.is_empty() => gui_manager.show_preedit(&status.buffer, caret),
+                _ => gui_manager.hide_preedit(),
+            }
+        }));
+    }
+
+    // Injection the app keeps rejecting drops the word; say so
+    let show_notifications = lock(&config).show_notifications;
+    keyboard_monitor.set_failure_listener(Box::new(move |_reason| {
+        let mut gui_manager = GuiManager::new();
+        gui_manager.set_show_notifications(show_notifications);
+        gui_manager.show_alert(t!("notify.injection_failed"));
+    }));
+
+    // Copy the composing buffer to the clipboard on demand, for bug reports
+    let dump_hotkey = lock(&config).hotkeys.dump_buffer.clone();
+    if let Some(hotkey) = dump_hotkey.and_then(|combo| combo.parse().ok()) {
+        keyboard_monitor.set_dump_hotkey(hotkey, Box::new(|dump| {
+            match GuiManager::new().copy_to_clipboard(dump) {
+                Ok(()) => info!("Copied buffer to the clipboard: {}", dump),
+                Err(e) => error!("Failed to copy buffer {}: {}", dump, e),
+            }
+        }));
+    }
+
+    // Convert whatever was copied, as if it had been typed
+    let convert_hotkey = lock(&config).hotkeys.convert_clipboard.clone();
+    if let Some(hotkey) = convert_hotkey.and_then(|combo| combo.parse().ok()) {
+        let engine = engine.clone();
+        keyboard_monitor.set_convert_hotkey(hotkey, Box::new(move || {
+            match GuiManager::new().convert_clipboard(|text| lock(&engine).convert(text)) {
+                Ok(Some(text)) => info!("Converted the clipboard: {:?}", text),
+                Ok(None) => info!("Clipboard holds no text; nothing to convert"),
+                Err(e) => error!("Failed to convert the clipboard: {}", e),
+            }
+        }));
+    }
+
+    if learn {
+        let log_path = std::path::Path::new(keyboard::LEARN_LOG_PATH);
+        keyboard_monitor.set_learner(keyboard::Learner::create(log_path)?);
+        println!("📚 Learn mode: transforms you erase and retype are logged to {}", log_path.display());
+    }
+
     // Start the application
     info!("VaixKey is now running. Press Ctrl+C to exit or run with --settings to open settings.");
 
     // Check command line arguments
-    let args: Vec<String> = std::env::args().collect();
     if args.len() > 1 {
         match args[1].as_str() {
             "--settings" => {
-                gui_manager.show_settings().await?;
+                let current = lock(&config).clone();
+                gui_manager.show_settings(&current).await?;
             }
             "--test" => {
-                return run_test_mode(engine.clone()).await;
+                return run_test_mode(engine.clone());
             }
             "--status" => {
-                return show_status(config.clone(), engine.clone()).await;
+                return show_status(config.clone(), engine.clone());
             }
             "--debug" => {
                 info!("🔍 Starting VaixKey in DEBUG mode with comprehensive keystroke logging");
-                return run_debug_mode(engine.clone()).await;
+                return run_debug_mode(engine.clone());
+            }
+            "--replay" => {
+                let path = args.get(2).ok_or("--replay requires a log file")?;
+                let mut engine = lock(&engine);
+                return replay::run(&mut engine, std::path::Path::new(path));
+            }
+            "--explain" => {
+                let word = args.get(2).ok_or("--explain requires a word")?;
+                explain::run(&mut lock(&engine), word);
+                return Ok(());
+            }
+            "--repl" => {
+                return repl::run(&mut lock(&engine));
             }
             "--permissions" | "--check-permissions" => {
                 return check_permissions().await;
@@ -71,23 +242,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 return setup_permissions_guide().await;
             }
             "--open-accessibility" => {
-                println!("📱 Opening System Settings → Accessibility...");
+                println!("{}", t!("perm.opening_accessibility"));
                 open_system_settings("accessibility");
                 return Ok(());
             }
             "--open-input-monitoring" => {
-                println!("📱 Opening System Settings → Input Monitoring...");
+                println!("{}", t!("perm.opening_input_monitoring"));
                 open_system_settings("input_monitoring");
                 return Ok(());
             }
             "--request-permission" => {
-                println!("🔐 Requesting Accessibility permission...");
+                println!("{}", t!("perm.requesting"));
                 let granted = request_accessibility_permission();
                 if granted {
-                    println!("✅ Permission already granted! VaixKey is ready.");
+                    println!("{}", t!("perm.already_granted"));
                 } else {
-                    println!("⏳ Permission request sent. Check System Settings if no dialog appeared.");
-                    println!("   Opening System Settings → Accessibility...");
+                    println!("{}", t!("perm.request_sent_check"));
+                    println!("   {}", t!("perm.then_opening"));
                     open_system_settings("accessibility");
                 }
                 return Ok(());
@@ -95,14 +266,50 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "--test-capture" => {
                 return test_real_keyboard_capture(engine.clone()).await;
             }
+            "--dump-keys" => {
+                let layout = lock(&config).keyboard_layout;
+                println!("⌨️  Key events as VaixKey sees them ({:?} layout); nothing is transformed. Press Ctrl+C to stop.", layout);
+                println!("{:>10}  {:<7}  key, character the OS typed, key_to_char", "time", "event");
+                keyboard::dump_keys(layout)?;
+                return Ok(());
+            }
+            "--benchmark" => {
+                let keystrokes = args.get(2).and_then(|n| n.parse().ok()).unwrap_or(bench::DEFAULT_KEYSTROKES);
+                bench::run(keystrokes);
+                return Ok(());
+            }
+            "--convert-clipboard" => {
+                match gui_manager.convert_clipboard(|text| lock(&engine).convert(text))? {
+                    Some(text) => println!("📋 Clipboard converted: {}", text),
+                    None => println!("📋 The clipboard holds no text; left unchanged"),
+                }
+                return Ok(());
+            }
+            "--list-methods" => {
+                list_methods(&lock(&config));
+                return Ok(());
+            }
+            "--set-method" => {
+                let name = args.get(2).ok_or("--set-method requires a method name")?;
+                return set_method(&config, name, rpc_port);
+            }
+            "--list-profiles" => {
+                return list_profiles();
+            }
+            "--fuzz" => {
+                let iterations = args.get(2).and_then(|n| n.parse().ok()).unwrap_or(10_000);
+                return run_fuzz_mode(iterations);
+            }
             _ => {
-                println!("Usage: vaixkey [--settings|--test|--status|--debug|--permissions]");
+                println!("Usage: vaixkey [--config <path>] [--profile <name>] [--settings|--test|--status|--debug|--permissions]");
                 println!("");
                 println!("Commands:");
                 println!("  --settings             Open settings interface");
                 println!("  --test                 Test Vietnamese processing engine");
                 println!("  --status               Show configuration and engine status");
-                println!("  --debug                Run with comprehensive keystroke logging");
+                println!("  --debug                Run with comprehensive keystroke logging (recorded to /tmp/vaixkey_session.log)");
+                println!("  --replay <file>        Feed a recorded keystroke log through the engine");
+                println!("  --explain <word>       Type a word key by key, bracketing what each key changed");
                 println!("  --permissions          Check macOS security permissions");
                 println!("  --security-status      Show detailed security status");
                 println!("  --setup-permissions    Interactive guide for setting up permissions");
@@ -110,69 +317,149 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 println!("  --open-accessibility   Open System Settings → Accessibility");
                 println!("  --open-input-monitoring Open System Settings → Input Monitoring");
                 println!("  --test-capture         Test real keyboard capture (requires permissions)");
+                println!("  --dump-keys            Print every key event and the character read from it, until Ctrl+C");
+                println!("  --doctor               Check permissions, config and keyboard grab; exits 1 on a critical failure");
+                println!("  --fuzz [iterations]    Fuzz the engine with random keystrokes (default 10000)");
+                println!("  --convert-clipboard    Convert the clipboard text as if typed, and copy the result back");
+                println!("  --list-profiles        List config profiles");
+                println!("  --list-methods         List input methods");
+                println!("  --set-method <name>    Switch the input method in the config (telex, vni, simple-telex); with --rpc-port, in the running instance too");
+                println!("  --benchmark [keys]     Measure engine throughput on a Telex corpus (default 1000000)");
+                println!("  --uninstall [--yes]    Remove config, LaunchAgent and temp files (--yes skips the prompt)");
+                println!("  --print-default-config Print the default config as TOML");
+                println!("  --reset-config [--yes] Overwrite the config file with the defaults (--yes skips the prompt)");
+                println!();
+                println!("Options:");
+                println!("  --config <path>        Use this config file instead of ~/.config/vaixkey/config.toml");
+                println!("  --rpc-port <port>      Serve the engine as newline-delimited JSON-RPC on 127.0.0.1:<port>");
+                println!("  --learn                Log transforms erased and retyped within {}ms to {}",
+                    keyboard::RETYPE_WINDOW.as_millis(), keyboard::LEARN_LOG_PATH);
+                println!("  --profile <name>       Switch to ~/.config/vaixkey/profiles/<name>.toml (\"default\" for config.toml)");
+                println!("  --lang <en|vi>         Show status, permission guides and notifications in this language");
+                println!("  --no-cache             Parse the config file even when ~/.config/vaixkey/{} matches it", cache::CACHE_FILE);
                 println!("");
                 println!("Environment Variables:");
                 println!("  VAIXKEY_DEBUG=1  Enable debug logging in normal mode");
+                println!("  RUST_LOG         Log levels per module, e.g. vaixkey::keyboard=debug");
+                println!("  VAIXKEY_CONFIG   Path to the config file (overridden by --config)");
                 return Ok(());
             }
         }
     }
 
-    // Run keyboard monitoring in background
-    let monitor_handle = tokio::spawn(async move {
-        if let Err(e) = keyboard_monitor.start().await {
-            error!("Keyboard monitor error: {}", e);
-        }
-    });
+    if let Some(port) = rpc_port {
+        let addr = rpc::serve(port, engine.clone())?;
+        println!("🛰️  JSON-RPC on {}", addr);
+    }
+
+    // Run keyboard monitoring on its own thread; tokio only waits for Ctrl+C
+    let keyboard_monitor = Arc::new(keyboard_monitor);
+    let monitor_thread = {
+        let keyboard_monitor = keyboard_monitor.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = keyboard_monitor.start() {
+                error!("Keyboard monitor error: {}", e);
+            }
+        })
+    };
+
+    // "Convert Selection to Vietnamese" in the Services menu
+    #[cfg(target_os = "macos")]
+    let services = lock(&config).ui.services;
+    #[cfg(target_os = "macos")]
+    if services {
+        let engine = engine.clone();
+        gui_manager.register_services(move |text| lock(&engine).convert(text));
+    }
+
+    // The overlay and the Services menu need AppKit's event loop on the main
+    // thread, so Ctrl+C is handled from a task instead
+    #[cfg(target_os = "macos")]
+    if show_preedit || services {
+        tokio::spawn(async move {
+            let _ = tokio::signal::ctrl_c().await;
+            shutdown(&keyboard_monitor, monitor_thread);
+            std::process::exit(0);
+        });
+        gui::run_event_loop();
+        return Ok(());
+    }
 
     // Wait for shutdown signal
     tokio::signal::ctrl_c().await?;
+    shutdown(&keyboard_monitor, monitor_thread);
+    Ok(())
+}
+
+/// Set up `log` output. `RUST_LOG` picks levels per module as usual
+/// (`RUST_LOG=vaixkey::keyboard=debug`); without it only errors are shown,
+/// or everything VaixKey logs at debug level with `VAIXKEY_DEBUG` or `--debug`.
+fn init_logging() {
+    let debug = std::env::var("VAIXKEY_DEBUG").is_ok() || std::env::args().any(|arg| arg == "--debug");
+    let default_filter = if debug { "vaixkey=debug" } else { "error" };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_filter)).init();
+}
+
+/// Stop the monitor and give its loop a moment to notice; a thread still
+/// busy after that is left to end with the process
+fn shutdown(keyboard_monitor: &KeyboardMonitor, monitor_thread: std::thread::JoinHandle<()>) {
     info!("Shutting down VaixKey");
+    keyboard_monitor.stop();
 
-    monitor_handle.abort();
-    Ok(())
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(1);
+    while !monitor_thread.is_finished() && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    if monitor_thread.is_finished() {
+        let _ = monitor_thread.join();
+    }
 }
 
-async fn show_status(
+fn show_status(
     config: Arc<Mutex<Config>>,
     engine: Arc<Mutex<InputMethodEngine>>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("🇻🇳 VaixKey Status Report");
+    println!("{}", t!("status.title"));
     println!("========================");
 
     // Configuration status
-    let config = config.lock().await;
-    println!("📋 Configuration:");
-    println!("   Input Method: {:?}", config.input_method);
-    println!("   Auto Start: {}", config.auto_start);
-    println!("   Show Status Bar: {}", config.show_status_bar);
-    println!("   Toggle Hotkey: {}", config.hotkeys.toggle_vietnamese);
-    println!("   Switch Hotkey: {}", config.hotkeys.switch_input_method);
+    let config = lock(&config);
+    println!("{}", t!("status.configuration"));
+    if let Ok(path) = Config::config_path() {
+        println!("   {}: {}", t!("status.file"), path.display());
+    }
+    println!("   {}: {:?}", t!("status.input_method"), config.input_method);
+    println!("   {}: {}", t!("status.auto_start"), config.auto_start);
+    println!("   {}: {}", t!("status.show_status_bar"), config.show_status_bar);
+    println!("   {}: {}", t!("status.show_notifications"), config.show_notifications);
+    println!("   {}: {}", t!("status.toggle_hotkey"), config.hotkeys.toggle_vietnamese);
+    println!("   {}: {}", t!("status.switch_hotkey"), config.hotkeys.switch_input_method);
 
     // Engine status
-    let engine = engine.lock().await;
-    println!("\n🔧 Engine Status:");
-    println!("   Vietnamese Mode: {}", if engine.is_vietnamese_mode() { "✅ Active" } else { "❌ Inactive" });
+    let engine = lock(&engine);
+    println!("\n{}", t!("status.engine"));
+    println!("   {}: {}", t!("status.vietnamese_mode"),
+        if engine.is_vietnamese_mode() { t!("status.active") } else { t!("status.inactive") });
 
     // Test Vietnamese processing
-    println!("\n🧪 Vietnamese Processing Test:");
-    test_vietnamese_processing(&*engine).await;
+    println!("\n{}", t!("status.processing_test"));
+    test_vietnamese_processing(&engine);
 
-    println!("\n✅ VaixKey is properly configured and ready!");
-    println!("   Run `cargo run -- --test` to test input processing");
-    println!("   Run `cargo run -- --settings` to open settings");
+    println!("\n{}", t!("status.ready"));
+    println!("   {}", t!("status.hint_test"));
+    println!("   {}", t!("status.hint_settings"));
 
     Ok(())
 }
 
-async fn run_test_mode(
+fn run_test_mode(
     engine: Arc<Mutex<InputMethodEngine>>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     println!("🧪 VaixKey Test Mode");
     println!("===================");
     println!("Testing Vietnamese input processing...\n");
 
-    let mut engine = engine.lock().await;
+    let mut engine = lock(&engine);
 
     // Test cases
     let test_cases = vec![
@@ -197,7 +484,7 @@ async fn run_test_mode(
 
         // Process each character
         for ch in input.chars() {
-            engine.process_keypress(ch).await;
+            engine.process_keypress(ch);
         }
         let result = engine.get_current_buffer().to_string();
 
@@ -216,13 +503,100 @@ async fn run_test_mode(
     for (input, expected, description) in &undo_tests {
         engine.reset_buffer();
         for ch in input.chars() {
-            engine.process_keypress(ch).await;
+            engine.process_keypress(ch);
         }
         let result = engine.get_current_buffer().to_string();
         let status = if result == *expected { "✅" } else { "❌" };
         println!("   {} {} → {} (expected: {}) - {}", status, input, result, expected, description);
     }
 
+    // A tone key pressed again undoes its tone and is typed itself; the
+    // rest of the word is English until a separator
+    println!("\n↩️  Tone Key Undo Tests:");
+    let tone_undo_tests = vec![
+        ("as", "á", false, ProcessResult::Replace { backspaces: 1, text: "á".to_string() }),
+        ("ass", "as", true, ProcessResult::Replace { backspaces: 1, text: "as".to_string() }),
+        ("ASS", "AS", true, ProcessResult::Replace { backspaces: 1, text: "AS".to_string() }),
+        ("asss", "ass", true, ProcessResult::PassThrough('s')),
+        ("vieetjj", "viêtj", true, ProcessResult::Replace { backspaces: 2, text: "êtj".to_string() }),
+        ("tieengss", "tiêngs", true, ProcessResult::Replace { backspaces: 3, text: "êngs".to_string() }),
+        ("nguwowiff", "ngươif", true, ProcessResult::Replace { backspaces: 2, text: "ơif".to_string() }),
+        ("hoass", "hoas", true, ProcessResult::Replace { backspaces: 1, text: "as".to_string() }),
+        ("cassf", "casf", true, ProcessResult::PassThrough('f')),      // No new tone
+        ("cassee", "casee", true, ProcessResult::PassThrough('e')),    // No circumflex
+        ("ass as", "as á", false, ProcessResult::Replace { backspaces: 1, text: "á".to_string() }),
+    ];
+
+    for (input, expected, expected_off, expected_last) in &tone_undo_tests {
+        let mut undo_engine = UnikeyEngine::new();
+        let results: Vec<ProcessResult> = input.chars().map(|ch| undo_engine.process(ch)).collect();
+        let screen = input_method::apply_results("", &results);
+        let word = input.rsplit(' ').next().unwrap_or_default();
+        let buffer_matches = undo_engine.get_buffer() == expected.rsplit(' ').next().unwrap_or_default();
+        let off = undo_engine.is_temporarily_off();
+        let ok = screen == *expected && buffer_matches && off == *expected_off && results.last() == Some(expected_last);
+        let status = if ok { "✅" } else { "❌" };
+        println!("   {} '{}' → '{}', last {:?}, English for the rest of '{}': {} (expected: '{}')",
+            status, input, screen, results.last(), word, off, expected);
+    }
+
+    // Doubles next to other letters: only the letter right before doubles,
+    // and undoing a circumflex keeps the tone it carried
+    println!("\n🧷 Double-Char Adjacency Tests:");
+    let adjacency_tests = vec![
+        ("ddaays", "đấy"),
+        ("dda", "đa"),
+        ("dddd", "ddd"),
+        ("dsd", "dsd"),
+        ("ddoo", "đô"),
+        ("coo", "cô"),
+        ("cooo", "coo"),
+        ("coofo", "còo"),       // Undo keeps the tone on the first o
+        ("cooso", "cóo"),
+        ("coso", "cố"),         // o after a toned o still doubles
+        ("tooto", "tôto"),      // Only the letter right before doubles
+    ];
+
+    for (input, expected) in &adjacency_tests {
+        engine.reset_buffer();
+        for ch in input.chars() {
+            engine.process_keypress(ch);
+        }
+        let result = engine.get_current_buffer().to_string();
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} {} → {} (expected: {})", status, input, result, expected);
+    }
+
+    // The circumflex and the tone combine in either order; the tone stays
+    // through undoing the circumflex and a later tone key replaces it
+    println!("\n🎩 Circumflex With Tone Tests:");
+    let circumflex_tone_tests = vec![
+        ("aas", "ấ"),
+        ("asa", "ấ"),           // Tone first, then the doubling key
+        ("aasf", "ầ"),          // A later tone replaces the first
+        ("asaf", "ầ"),
+        ("afa", "ầ"),
+        ("oso", "ố"),
+        ("eje", "ệ"),
+        ("tasa", "tấ"),
+        ("mosot", "mốt"),
+        ("Asa", "Ấ"),
+        ("ASA", "Ấ"),
+        ("aafa", "àa"),         // Third a undoes â, the tone stays
+        ("asaa", "áa"),
+        ("esa", "éa"),          // A different vowel doesn't double
+    ];
+
+    for (input, expected) in &circumflex_tone_tests {
+        engine.reset_buffer();
+        for ch in input.chars() {
+            engine.process_keypress(ch);
+        }
+        let result = engine.get_current_buffer().to_string();
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} {} → {} (expected: {})", status, input, result, expected);
+    }
+
     println!("\n🎯 Tone Mark Processing:");
     let tone_tests = vec![
         ("as", "a + sắc tone"),
@@ -235,7 +609,7 @@ async fn run_test_mode(
     for (input, description) in &tone_tests {
         engine.reset_buffer();
         for ch in input.chars() {
-            engine.process_keypress(ch).await;
+            engine.process_keypress(ch);
         }
         let result = engine.get_current_buffer().to_string();
         println!("   {} → {} ({})", input, result, description);
@@ -258,18 +632,334 @@ async fn run_test_mode(
         ("tuaans", "tuấn"),   // t + u + â + n + s = tuấn (aa for â)
         ("quas", "quá"),      // qu + a + s = quá (Q prefix exception)
         ("gias", "giá"),      // gi + a + s = giá (GI prefix exception)
+        ("nguyeenj", "nguyện"), // uyê: tone on ê, not the middle y
+        ("chuyeenr", "chuyển"),
+        ("quyeets", "quyết"), // qu + yê + t
+        ("khuyar", "khuỷa"),  // uya: no circumflex, tone on middle y
+        ("giuwowngf", "giường"), // gi + ươ + ng: tone on ơ
     ];
 
     for (input, expected) in &word_tests {
         engine.reset_buffer();
         for ch in input.chars() {
-            engine.process_keypress(ch).await;
+            engine.process_keypress(ch);
         }
         let result = engine.get_current_buffer().to_string();
         let status = if result == *expected { "✅" } else { "❌" };
         println!("   {} {} → {} (expected: {})", status, input, result, expected);
     }
 
+    // Tones typed before the syllable is complete move to the main vowel
+    println!("\n🔁 Tone Migration Tests:");
+    let migration_tests = vec![
+        ("hoafn", "hoàn"),   // Tone already on the main vowel stays put
+        ("tuasn", "tuán"),   // Coda moves the tone from u to a
+        ("muafn", "muàn"),
+        ("hosa", "hoá"),     // Second vowel of oa takes the tone
+        ("thusy", "thuý"),
+        ("thusyr", "thuỷ"),  // A new tone replaces the misplaced one
+        ("quafn", "quàn"),   // Q prefix already tones the last vowel
+    ];
+
+    for (input, expected) in &migration_tests {
+        let result = type_on_screen(&mut UnikeyEngine::new(), input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} {} → {} (expected: {})", status, input, result, expected);
+    }
+
+    // ươ from any key order, with the tone landing on ơ and staying there
+    // through the coda
+    println!("\n🪝 Horned ươ Tests:");
+    let horn_tests = vec![
+        ("nuowcs", "nước"),      // w on o horns both vowels
+        ("nuwowcs", "nước"),     // Each vowel horned separately
+        ("nuocws", "nước"),      // Horn after the coda
+        ("dduwowcj", "được"),    // With đ
+        ("dduowcj", "được"),
+        ("nguwowif", "người"),
+        ("nguowif", "người"),
+        ("wowcs", "ước"),        // Standalone w for ư
+        ("twow", "tươ"),         // A key between two w's: horn, not undo
+        ("ddwowcj", "được"),
+        ("DDUWOWCJ", "ĐƯỢC"),
+    ];
+
+    for (input, expected) in &horn_tests {
+        let result = type_on_screen(&mut UnikeyEngine::new(), input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} {} → {} (expected: {})", status, input, result, expected);
+    }
+
+    // Three-vowel nuclei put the tone where the triphthong table says
+    println!("\n🎯 Triphthong Tone Tests:");
+    let triphthong_tests = vec![
+        ("hoaif", "hoài"),
+        ("xoays", "xoáy"),
+        ("nguoiwf", "người"),
+        ("ruwowuj", "rượu"),
+        ("muoois", "muối"),
+        ("chuyeenr", "chuyển"),
+        ("quyeets", "quyết"),
+        ("tieeus", "tiếu"),
+        ("khuaays", "khuấy"),
+        ("khuyur", "khuỷu"),
+        ("ngoeor", "ngoẻo"),
+        ("quaof", "quào"),
+        ("giuwowngf", "giường"),
+        ("chuyeren", "chuyển"),  // Tone typed before the circumflex
+        ("khoer", "khoẻ"),       // oe: tone on e
+    ];
+
+    for (input, expected) in &triphthong_tests {
+        let result = type_on_screen(&mut UnikeyEngine::new(), input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} {} → {} (expected: {})", status, input, result, expected);
+    }
+
+    // Left/Right move within the word; keys then edit the syllable up to
+    // the cursor and leave the rest alone
+    println!("\n↔️  Cursor Editing Tests:");
+    let cursor_tests = vec![
+        ("tieeng←←s", "tiếng"),      // Tone on the vowel before the cursor
+        ("toan←s", "toán"),
+        ("nguoi←w→f", "người"),      // Horn mid-word, tone back at the end
+        ("hoa←←f", "hfoa"),          // No vowel before the cursor: literal
+        ("ab←←←aa", "âab"),          // Leaving the word drops it
+        ("vieet←j", "việt"),
+    ];
+
+    for (input, expected) in &cursor_tests {
+        let result = type_on_screen(&mut UnikeyEngine::new(), input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} {} → {} (expected: {})", status, input, result, expected);
+    }
+
+    // Every vowel family with every tone, typed in both cases
+    println!("\n🔠 Toned Vowels, Both Cases:");
+    let vowel_families = [
+        ("a", "áàảãạ", "ÁÀẢÃẠ"),
+        ("aa", "ấầẩẫậ", "ẤẦẨẪẬ"),
+        ("aw", "ắằẳẵặ", "ẮẰẲẴẶ"),
+        ("e", "éèẻẽẹ", "ÉÈẺẼẸ"),
+        ("ee", "ếềểễệ", "ẾỀỂỄỆ"),
+        ("i", "íìỉĩị", "ÍÌỈĨỊ"),
+        ("o", "óòỏõọ", "ÓÒỎÕỌ"),
+        ("oo", "ốồổỗộ", "ỐỒỔỖỘ"),
+        ("ow", "ớờởỡợ", "ỚỜỞỠỢ"),
+        ("u", "úùủũụ", "ÚÙỦŨỤ"),
+        ("uw", "ứừửữự", "ỨỪỬỮỰ"),
+        ("y", "ýỳỷỹỵ", "ÝỲỶỸỴ"),
+    ];
+    let mut toned_failures = Vec::new();
+    for (keys, lower, upper) in &vowel_families {
+        for (tone, (expected_lower, expected_upper)) in "sfrxj".chars().zip(lower.chars().zip(upper.chars())) {
+            // Lowercase, all caps, and a capital vowel with lowercase modifier keys
+            let capitalized = {
+                let mut chars = keys.chars();
+                let first = chars.next().map(|c| c.to_ascii_uppercase()).unwrap_or_default();
+                format!("{}{}{}", first, chars.as_str(), tone)
+            };
+            let cases = [
+                (format!("{}{}", keys, tone), expected_lower),
+                (format!("{}{}", keys, tone).to_uppercase(), expected_upper),
+                (capitalized, expected_upper),
+            ];
+            for (input, expected) in cases {
+                let result = type_on_screen(&mut UnikeyEngine::new(), &input);
+                if result != expected.to_string() {
+                    toned_failures.push(format!("{} → {} (expected: {})", input, result, expected));
+                }
+            }
+        }
+    }
+    if toned_failures.is_empty() {
+        println!("   ✅ {} families × 5 tones × 3 casings", vowel_families.len());
+    } else {
+        for failure in &toned_failures {
+            println!("   ❌ {}", failure);
+        }
+    }
+
+    // The breve/horn follows the case of the vowel, not of the w
+    println!("\n🔠 Breve Case Tests:");
+    let breve_case_tests = vec![
+        ("Aw", "Ă"),
+        ("Ow", "Ơ"),
+        ("Uw", "Ư"),
+        ("aW", "ă"),
+        ("Asw", "Ắ"),          // Tone first, then the breve
+        ("Ufw", "Ừ"),
+        ("Uow", "Ươ"),
+        ("uOw", "ưƠ"),         // Each vowel of "uo" keeps its own case
+        ("Awfw", "Àw"),        // Undo keeps the case and the tone
+    ];
+    for (input, expected) in &breve_case_tests {
+        let result = type_on_screen(&mut UnikeyEngine::new(), input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} {} → {} (expected: {})", status, input, result, expected);
+    }
+
+    // CapsLock or Shift held for the whole word: every mark and tone lands
+    // on a capital, through the engine and through the keyboard monitor
+    println!("\n⇪ All-Caps Tests:");
+    let all_caps_tests = vec![
+        ("VIEETJ", "VIỆT"),
+        ("DDAAYS", "ĐẤY"),
+        ("NAAWNG", "NĂNG"),
+        ("NGUWOWIF", "NGƯỜI"),
+        ("QUYEETS", "QUYẾT"),
+        ("TRAAFN", "TRẦN"),
+        ("HUYR", "HUỶ"),
+        ("AAA", "AA"),          // Undo keeps the capitals
+        ("VIEETJJ", "VIÊTJ"),
+    ];
+    for (input, expected) in &all_caps_tests {
+        let result = type_on_screen(&mut UnikeyEngine::new(), input);
+        let (through_monitor, _) = type_through_monitor(input, false);
+        let status = if result == *expected && through_monitor == *expected { "✅" } else { "❌" };
+        println!("   {} {} → {} (monitor: {}, expected: {})", status, input, result, through_monitor, expected);
+    }
+
+    // On Dvorak the physical keys differ from the letters they type. The
+    // character the OS reports always wins; without one, keyboard_layout
+    // says what the key typed
+    println!("\n🗺️  Keyboard Layout Tests:");
+    for (layout, reported, input, expected) in [
+        (config::KeyboardLayout::Dvorak, false, "vieetj", "việt"),  // v . i g e d t k j c
+        (config::KeyboardLayout::Dvorak, false, "dduwowngf", "đường"),
+        (config::KeyboardLayout::Colemak, false, "ngux", "ngũ"),
+        (config::KeyboardLayout::Qwerty, false, "vieetj", "việt"),
+        (config::KeyboardLayout::Qwerty, true, "vieetj", "việt"),   // A Dvorak typist: the OS is right
+    ] {
+        let config = Config { keyboard_layout: layout, ..Config::default() };
+        let engine = Arc::new(Mutex::new(InputMethodEngine::new(Arc::new(Mutex::new(config)))));
+        let monitor = KeyboardMonitor::with_backend(engine, Arc::new(RecordingBackend::new()));
+        let mut screen = harness::ScreenModel::new();
+        for ch in input.chars() {
+            // The key pressed for `ch`: on the configured layout, or on
+            // Dvorak when the OS reports the character
+            let typist_layout = if reported { config::KeyboardLayout::Dvorak } else { layout };
+            let key = keyboard::char_to_layout_key(ch, typist_layout).unwrap_or(rdev::Key::Unknown(0));
+            let result = match monitor.dispatch_key(key, reported.then_some(ch)) {
+                GrabAction::BlockAndInject { backspaces, text } => ProcessResult::Replace { backspaces, text },
+                // The app types what the layout says, whatever VaixKey thought
+                _ => ProcessResult::PassThrough(ch),
+            };
+            let _ = screen.apply(&result);
+        }
+        let text = screen.text();
+        let status = if text == expected { "✅" } else { "❌" };
+        println!("   {} {:?}, OS reports the character: {}, {} → {} (expected: {})",
+            status, layout, reported, input, text, expected);
+    }
+
+    // y as the nucleus, alone or after u, in both tone placement styles
+    println!("\n🇾 Y Nucleus Tests:");
+    let y_nucleus_tests = vec![
+        ("myf", "mỳ", "mỳ"),
+        ("kyr", "kỷ", "kỷ"),
+        ("ys", "ý", "ý"),
+        ("thuys", "thuý", "thúy"),      // uy: the style decides
+        ("huyr", "huỷ", "hủy"),
+        ("quys", "quý", "quý"),         // qu: u is part of the initial
+        ("quynhf", "quỳnh", "quỳnh"),   // Coda: tone on y
+        ("quyeenf", "quyền", "quyền"),  // uyê: tone on ê
+        ("yeenf", "yền", "yền"),
+        ("ngayf", "ngày", "ngày"),      // ay: tone on a
+    ];
+    for (input, expected_modern, expected_old) in &y_nucleus_tests {
+        let modern = type_on_screen(&mut UnikeyEngine::new(), input);
+        let old = type_on_screen(&mut UnikeyEngine::builder().modern_style(false).build(), input);
+        let status = if modern == *expected_modern && old == *expected_old { "✅" } else { "❌" };
+        println!("   {} {} → modern: {}, old: {} (expected: {}, {})",
+            status, input, modern, old, expected_modern, expected_old);
+    }
+
+    // gh and ngh only come before e, ê and i; the tone skips the initial
+    println!("\n🇬 gh/ngh Initial Tests:");
+    let gh_tests = vec![
+        ("nghix", "nghĩ"),
+        ("ghes", "ghé"),
+        ("ghees", "ghế"),
+        ("ghetj", "ghẹt"),
+        ("nghieengs", "nghiếng"),    // iê: tone on ê
+        ("nghiax", "nghĩa"),         // ia: tone on i
+        ("NGHIEENGJ", "NGHIỆNG"),
+    ];
+    for (input, expected) in &gh_tests {
+        let result = type_on_screen(&mut UnikeyEngine::new(), input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} {} → {} (expected: {})", status, input, result, expected);
+    }
+
+    // The character tables agree with each other, as checked at startup
+    println!("\n🧮 Table Self-Check:");
+    match UnikeyEngine::new().self_check() {
+        Ok(()) => println!("   ✅ BD, DT and tone round trips are consistent"),
+        Err(e) => println!("   ❌ {}", e),
+    }
+
+    // oa, oe and uy can take the other style's placement while every other
+    // nucleus keeps following modern_style
+    println!("\n🎯 oa/oe/uy Style Tests:");
+    let oa_oe_uy_tests = vec![
+        // input, modern + Classic override, classic + Modern override
+        ("hoas", "hóa", "hoá"),
+        ("khoer", "khỏe", "khoẻ"),
+        ("thuys", "thúy", "thuý"),
+        ("hoanf", "hoàn", "hoàn"),     // Coda: always on the second vowel
+        ("quys", "quý", "quý"),
+        ("ngayf", "ngày", "ngày"),     // Other diphthongs: unaffected
+        ("cuar", "của", "của"),
+    ];
+    for (input, expected_classic, expected_modern) in &oa_oe_uy_tests {
+        let classic = type_on_screen(&mut UnikeyEngine::builder().oa_oe_uy_style(TonePlacement::Classic).build(), input);
+        let modern = type_on_screen(
+            &mut UnikeyEngine::builder().modern_style(false).oa_oe_uy_style(TonePlacement::Modern).build(),
+            input,
+        );
+        let status = if classic == *expected_classic && modern == *expected_modern { "✅" } else { "❌" };
+        println!("   {} {} → classic oa/oe/uy: {}, modern oa/oe/uy: {} (expected: {}, {})",
+            status, input, classic, modern, expected_classic, expected_modern);
+    }
+    let mut config = Config::default();
+    config.engine.oa_oe_uy_style = TonePlacement::Classic;
+    let result = type_with_config(config, "hoaf khoer ");
+    let status = if result == "hòa khỏe " { "✅" } else { "❌" };
+    println!("   {} engine.oa_oe_uy_style = Classic: 'hoaf khoer ' → '{}'", status, result);
+
+    // Engines configured through the builder
+    println!("\n🏗️  Builder Tests:");
+    let builder_tests = vec![
+        (UnikeyEngine::builder().build(), "ans tuongw", "án tương", "defaults"),
+        (UnikeyEngine::builder().tone_next_to_vowel(true).build(), "as ans", "á ans", "tone_next_to_vowel"),
+        (UnikeyEngine::builder().free_marking(false).build(), "tuongw", "tuongư", "free_marking off"),
+        (UnikeyEngine::builder().vietnamese_mode(false).build(), "tuongw", "tuongw", "vietnamese_mode off"),
+        (UnikeyEngine::builder().method(InputMethod::Vni).modern_style(false).build(), "hoa2", "hòa", "VNI, old style"),
+    ];
+    for (mut engine, input, expected, description) in builder_tests {
+        let result = type_on_screen(&mut engine, input);
+        let status = if result == expected { "✅" } else { "❌" };
+        println!("   {} {}: {} → {} (expected: {})", status, description, input, result, expected);
+    }
+
+    // Combining marks after a letter fold into the precomposed letter, so
+    // decomposed text can be edited like typed text
+    println!("\n🧩 Decomposed Input Tests:");
+    let decomposed_tests = vec![
+        ("vie\u{302}\u{301}t", "viết", "e + ◌̂ + ◌́"),
+        ("vie\u{302}\u{301}tf", "viềt", "re-toned after the mark"),
+        ("vie\u{302}\u{301}\x08", "vi", "one backspace removes ế"),
+        ("vie\u{302}\u{301}\x08ees", "viế", "retyped after the backspace"),
+        ("a\u{306}s", "ắ", "ă from a + ◌̆, then a tone"),
+        ("\u{301}a", "\u{301}a", "a mark with no letter stays as is"),
+    ];
+    for (input, expected, description) in &decomposed_tests {
+        let result = type_on_screen(&mut UnikeyEngine::new(), input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} {:?} → {} (expected: {}) - {}", status, input, result, expected, description);
+    }
+
     // Test separator handling - "nam s" should NOT become "naám s"
     println!("\n🔀 Separator Handling Tests:");
     let separator_tests = vec![
@@ -281,150 +971,2286 @@ async fn run_test_mode(
     for (input, expected_buffer) in &separator_tests {
         engine.reset_buffer();
         for ch in input.chars() {
-            engine.process_keypress(ch).await;
+            engine.process_keypress(ch);
         }
         let result = engine.get_current_buffer().to_string();
         let status = if result == *expected_buffer { "✅" } else { "❌" };
         println!("   {} '{}' → buffer: '{}' (expected: '{}')", status, input, result, expected_buffer);
     }
 
-    println!("\n�🔄 Mode Toggle Test:");
-    println!("   Current mode: {}", if engine.is_vietnamese_mode() { "Vietnamese" } else { "English" });
-    engine.toggle_vietnamese_mode();
-    println!("   After toggle: {}", if engine.is_vietnamese_mode() { "Vietnamese" } else { "English" });
-
-    println!("\n✅ Test complete! VaixKey engine is working properly.");
-    println!("   Note: This tests the processing engine only.");
-    println!("   Keyboard capture is not yet implemented.");
+    // A tone key with no vowel before it is a plain letter: typed as is and
+    // buffered, so a later vowel never picks it up as a tone
+    println!("\n🔡 Leading Tone Key Tests:");
+    let leading_tone_tests = vec![
+        ("s", "s", "s"),
+        ("sa", "sa", "sa"),           // Not "á"
+        ("ska", "ska", "ska"),
+        ("nam sa", "nam sa", "sa"),   // Same after a separator reset
+        ("nam ska", "nam ska", "ska"),
+    ];
 
-    Ok(())
-}
+    for (input, expected_screen, expected_buffer) in &leading_tone_tests {
+        let mut unikey = UnikeyEngine::new();
+        let screen = type_on_screen(&mut unikey, input);
+        let buffer = unikey.get_buffer();
+        let status = if screen == *expected_screen && buffer == *expected_buffer { "✅" } else { "❌" };
+        println!("   {} '{}' → '{}', buffer: '{}' (expected: '{}', '{}')",
+            status, input, screen, buffer, expected_screen, expected_buffer);
+    }
 
-async fn run_debug_mode(
-    engine: Arc<Mutex<InputMethodEngine>>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    println!("🔍 VaixKey Debug Mode");
-    println!("====================");
-    println!("📝 Comprehensive keystroke capture and processing logging");
-    println!("");
-    println!("🎯 What you'll see:");
-    println!("   🔴 KEY PRESS events with timestamps");
-    println!("   🔵 KEY RELEASE events");
-    println!("   📊 Engine state (Vietnamese mode, buffer contents)");
-    println!("   🔤 Character processing details");
-    println!("   ✨ Vietnamese transformation results");
-    println!("   📋 Complete keystroke information");
-    println!("");
-    println!("⌨️  Start typing to see real-time keystroke capture...");
-    println!("   (Press Ctrl+C to exit)");
-    println!("");
+    // set_tone places a tone like its key would, minus the undo
+    println!("\n🎚️  Tone API Tests:");
+    let tone_api_tests = vec![
+        ("ca", 1, "cá", Some(1)),
+        ("cas", 1, "cá", Some(1)),     // Same tone again is not an undo
+        ("cas", 2, "cà", Some(2)),
+        ("cas", 0, "ca", None),
+        ("vieet", 5, "việt", Some(5)),
+        ("c", 1, "c", None),           // No vowel to carry it
+    ];
 
-    // Create keyboard monitor in debug mode
-    let keyboard_monitor = KeyboardMonitor::new_with_debug(engine.clone());
+    for (input, tone, expected_screen, expected_tone) in &tone_api_tests {
+        let mut engine = UnikeyEngine::new();
+        let mut screen = harness::ScreenModel::new();
+        for ch in input.chars() {
+            let _ = screen.apply(&engine.process(ch));
+        }
+        let _ = screen.apply(&engine.set_tone(*tone));
+        let result = screen.text();
+        let current = engine.current_syllable_tone();
+        let status = if result == *expected_screen && current == *expected_tone { "✅" } else { "❌" };
+        println!("   {} '{}' + set_tone({}) → '{}', tone: {:?} (expected: '{}', {:?})",
+            status, input, tone, result, current, expected_screen, expected_tone);
+    }
 
-    // Start monitoring
-    keyboard_monitor.start().await?;
+    // preview_tone shows what set_tone would do and leaves the word alone
+    let preview_tests = vec![
+        ("ca", 1, "cá"),
+        ("ca", 0, "ca"),
+        ("cas", 2, "cà"),
+        ("cas", 0, "ca"),
+        ("thuys", 3, "thuỷ"),
+        ("vieet", 5, "việt"),
+        ("VIEET", 4, "VIỄT"),
+        ("c", 1, "c"),
+    ];
+    for (input, tone, expected) in &preview_tests {
+        let mut engine = UnikeyEngine::new();
+        let mut toned = UnikeyEngine::new();
+        for ch in input.chars() {
+            engine.process(ch);
+            toned.process(ch);
+        }
+        let before = engine.get_buffer();
+        let preview = engine.preview_tone(*tone);
+        let after = engine.get_buffer();
+        toned.set_tone(*tone);
+        let ok = preview == *expected && after == before && toned.get_buffer() == preview;
+        let status = if ok { "✅" } else { "❌" };
+        println!("   {} '{}' preview_tone({}) → '{}', buffer still '{}' (expected: '{}')",
+            status, input, tone, preview, after, expected);
+    }
 
-    Ok(())
-}
+    // A whole key sequence at once, replayed with apply_results
+    println!("\n🧮 Key Sequence Tests:");
+    let sequence_tests = vec![
+        ("", "vieetj nam", "việt nam"),
+        ("", "nguwowif ddaays", "người đấy"),
+        ("Xin ", "chaof banj", "Xin chào bạn"),
+        ("", "tieengs Vieetj", "tiếng Việt"),
+        ("", "ass", "as"),
+        ("", "vieet\x08\x08eej", "việ"),
+        ("ok", "\x08\x08\x08as", "á"),      // Backspace stops at the start
+    ];
+    for (start, keys, expected) in &sequence_tests {
+        let keys: Vec<char> = keys.chars().collect();
+        let results = UnikeyEngine::new().process_sequence(&keys);
+        let result = input_method::apply_results(start, &results);
+        let status = if result == *expected && results.len() == keys.len() { "✅" } else { "❌" };
+        println!("   {} {:?} + {:?} → '{}' (expected: '{}')",
+            status, start, keys.iter().collect::<String>(), result, expected);
+    }
 
-async fn test_vietnamese_processing(engine: &InputMethodEngine) {
-    // This is a simple test of the Vietnamese engine
-    // Note: We can't easily test the async methods here due to borrowing,
-    // so we'll just show that the engine exists and is configured
-    println!("   Engine initialized: ✅");
-    println!("   Vietnamese mode: {}", if engine.is_vietnamese_mode() { "✅" } else { "❌" });
-}
+    // Rules added with add_rule fire before the built-in transformations;
+    // built-ins they don't match keep working
+    println!("\n🧩 Custom Rule Tests:");
+    let rule_tests = vec![
+        ("oe", "œ"),
+        ("coe ", "cœ "),
+        ("ooe", "ôe"),                  // ô is not o
+        ("vieetj ", "việt "),
+        ("aa", "ä"),                    // Overrides aa → â
+        ("ddeens", "đến"),
+        ("az", "aʒ"),
+        ("dz", "ʣ"),                    // Longest trigger wins
+    ];
+    let mut rules = UnikeyEngine::new();
+    let rejected = rules.add_rule("", "x").is_err() && rules.add_rule("a b", "x").is_err();
+    let status = if rejected { "✅" } else { "❌" };
+    println!("   {} add_rule rejects an empty trigger and one with a space", status);
+    for (input, expected) in &rule_tests {
+        let mut engine = UnikeyEngine::new();
+        for (trigger, output) in [("oe", "œ"), ("aa", "ä"), ("z", "ʒ"), ("dz", "ʣ")] {
+            let _ = engine.add_rule(trigger, output);
+        }
+        let mut screen = harness::ScreenModel::new();
+        for ch in input.chars() {
+            let _ = screen.apply(&engine.process(ch));
+        }
+        let result = screen.text();
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} '{}' → '{}' (expected: '{}')", status, input, result, expected);
+    }
 
-async fn check_permissions() -> Result<(), Box<dyn std::error::Error>> {
-    println!("🔐 VaixKey Permission Status");
-    println!("============================");
+    // engine.syllable_cache: replaying cached words must type what
+    // recomputing them does, also after settings change
+    println!("\n🗃️  Syllable Cache Tests:");
+    let cache_inputs = [
+        "vieetj nam vieetj nam ",
+        "nguwowif ddaays nguwowif ddaays ",
+        "ass ass class class ",
+        "thuys thuys toans. toans.",
+        "viet\x08j viet\x08j chaof chaof",
+        "aaa aaa www www ",
+    ];
+    for (input, capacity) in cache_inputs.iter().flat_map(|input| [(input, 1), (input, 64)]) {
+        let mut cached = UnikeyEngine::new();
+        cached.set_syllable_cache(capacity);
+        let expected = type_on_screen(&mut UnikeyEngine::new(), input);
+        let result = type_on_screen(&mut cached, input);
+        let (hits, _) = cached.syllable_cache_stats().unwrap_or_default();
+        let status = if result == expected && (capacity == 1 || hits > 0) { "✅" } else { "❌" };
+        println!("   {} {:?} (capacity {}) → '{}', {} hits (expected: '{}')",
+            status, input, capacity, result, hits, expected);
+    }
 
-    // For now, show what permissions we would check
-    // In a real implementation, this would use macOS APIs to check actual permissions
-    println!("📋 Checking macOS security permissions...");
-    println!("");
+    let mut cache_config = Config::default();
+    cache_config.engine.syllable_cache = 64;
+    let mut switching = InputMethodEngine::new(Arc::new(Mutex::new(cache_config)));
+    let mut screen = harness::ScreenModel::new();
+    for ch in "as a1 ".chars() {
+        let _ = screen.apply(&switching.process_keypress(ch));
+    }
+    switching.set_method(config::InputMethodType::Vni);
+    for ch in "as a1 ".chars() {
+        let _ = screen.apply(&switching.process_keypress(ch));
+    }
+    let result = screen.text();
+    let status = if result == "á a1 as á " { "✅" } else { "❌" };
+    println!("   {} Telex then VNI with a cache → '{}' (expected: 'á a1 as á ')", status, result);
+
+    // Deferred tones only rewrite the syllable once a separator commits it
+    println!("\n⏳ Deferred Tone Tests (tone_at_end):");
+    let deferred_tests = vec![
+        ("toans", "toán", "toans"),      // Before commit: tone key still literal
+        ("toans ", "toán ", "toán "),    // Space commits the tone onto a
+        ("vieetj ", "việt ", "việt "),
+        ("hoafn.", "hoàn.", "hoàn."),    // Any separator commits
+        ("sa ", "sa ", "sa "),           // Leading s is a consonant, not a tone
+    ];
 
-    // Simulate permission checking (in real implementation, use CGPreflightScreenCaptureAccess, etc.)
-    let input_monitoring = check_input_monitoring_permission().await;
-    let accessibility = check_accessibility_permission().await;
+    for (input, expected_immediate, expected_deferred) in &deferred_tests {
+        let mut immediate = UnikeyEngine::new();
+        let mut deferred = UnikeyEngine::new();
+        deferred.set_tone_at_end(true);
 
-    println!("🔍 Input Monitoring: {}",
-        if input_monitoring { "✅ Granted" } else { "❌ DENIED - Required for keystroke capture" });
-    println!("🔧 Accessibility: {}",
-        if accessibility { "✅ Granted" } else { "❌ DENIED - Required for text injection" });
+        let immediate_result = type_on_screen(&mut immediate, input);
+        let deferred_result = type_on_screen(&mut deferred, input);
+        let status = if immediate_result == *expected_immediate && deferred_result == *expected_deferred {
+            "✅"
+        } else {
+            "❌"
+        };
+        println!("   {} '{}' → immediate: '{}', deferred: '{}' (expected: '{}', '{}')",
+            status, input, immediate_result, deferred_result, expected_immediate, expected_deferred);
+    }
 
-    println!("");
+    // [ and ] are separators unless the Telex bracket shortcuts are on
+    println!("\n🔣 Bracket Shortcut Tests:");
+    let bracket_tests = vec![
+        ("m[f", true, "mờ"),
+        ("t]", true, "tư"),
+        ("d][c", true, "dươc"),
+        ("[[", true, "["),          // Pressing again restores the bracket
+        ("m[f", false, "m[f"),      // Disabled: a literal bracket ends the word
+        ("a[s", false, "a[s"),
+    ];
 
-    if input_monitoring && accessibility {
-        println!("🎯 VaixKey is ready for keyboard capture!");
-        println!("   Run `cargo run -- --debug` to test keystroke logging");
-    } else {
-        println!("⚠️  VaixKey cannot function without these permissions");
-        println!("   Run `cargo run -- --setup-permissions` for setup instructions");
+    for (input, enabled, expected) in &bracket_tests {
+        let mut engine = UnikeyEngine::new();
+        engine.set_bracket_shortcuts(*enabled);
+        let result = type_on_screen(&mut engine, input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} '{}' ({}) → '{}' (expected: '{}')",
+            status, input, if *enabled { "on" } else { "off" }, result, expected);
     }
 
-    Ok(())
-}
-
-async fn show_security_status() -> Result<(), Box<dyn std::error::Error>> {
-    println!("🔒 VaixKey Security Status Report");
-    println!("=================================");
+    // Simple Telex drops the shortcuts and defers tones; words still compose
+    println!("\n🐣 Simple Telex Tests:");
+    let simple_tests = vec![
+        ("w ", "ư ", "w "),               // Leading w: ư only in Telex
+        ("wa ", "ưa ", "wa "),
+        ("thuw ", "thư ", "thư "),       // w after a vowel horns it in both
+        ("[ ", "ơ ", "[ "),               // Brackets stay literal even when enabled
+        ("toans", "toán", "toans"),       // Tone waits for the commit
+        ("nuowcs ", "nước ", "nước "),
+    ];
 
-    // System information
-    println!("💻 System Information:");
-    let output = std::process::Command::new("sw_vers").output()?;
-    if output.status.success() {
-        let version_info = String::from_utf8_lossy(&output.stdout);
-        for line in version_info.lines() {
-            println!("   {}", line);
-        }
+    for (input, expected_telex, expected_simple) in &simple_tests {
+        let mut telex = Config::default();
+        telex.engine.bracket_shortcuts = true;
+        let mut simple = telex.clone();
+        simple.input_method = config::InputMethodType::SimpleTelex;
+
+        let telex_result = type_with_config(telex, input);
+        let simple_result = type_with_config(simple, input);
+        let status = if telex_result == *expected_telex && simple_result == *expected_simple { "✅" } else { "❌" };
+        println!("   {} '{}' → Telex: '{}', Simple Telex: '{}' (expected: '{}', '{}')",
+            status, input, telex_result, simple_result, expected_telex, expected_simple);
     }
 
-    println!("");
+    // VNI: digits 1-5 place a tone, 0 takes it off again
+    println!("\n🔢 VNI Tone Tests:");
+    let vni_tone_tests = vec![
+        ("a1 ", "á "),
+        ("xe2 ", "xè "),
+        ("hoa2 ", "hoà "),
+        ("a10 ", "a "),         // Tone then remove
+        ("nga20 ", "nga "),
+        ("hoa20 ", "hoa "),
+        ("a0 ", "a0 "),         // Nothing to remove: literal 0
+        ("toan0 ", "toan0 "),
+        ("a11 ", "a1 "),        // Same tone twice undoes it
+    ];
 
-    // Permission status
-    println!("🔐 Permission Status:");
-    let input_monitoring = check_input_monitoring_permission().await;
+    for (input, expected) in &vni_tone_tests {
+        let result = type_on_screen(&mut UnikeyEngine::builder().method(InputMethod::Vni).build(), input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} '{}' → '{}' (expected: '{}')", status, input, result, expected);
+    }
+
+    // A VNI digit with nothing to act on stays in the word as typed, 6-9 the
+    // same as 1-5
+    println!("\n🔢 VNI Literal Digit Tests:");
+    let vni_literal_tests = vec![
+        ("b1", "b1", "b1"),
+        ("b6", "b6", "b6"),
+        ("b61", "b61", "b61"),
+        ("b9", "b9", "b9"),
+    ];
+
+    for (input, expected_screen, expected_buffer) in &vni_literal_tests {
+        let mut unikey = UnikeyEngine::builder().method(InputMethod::Vni).build();
+        let screen = type_on_screen(&mut unikey, input);
+        let buffer = unikey.get_buffer();
+        let status = if screen == *expected_screen && buffer == *expected_buffer { "✅" } else { "❌" };
+        println!("   {} '{}' → '{}', buffer: '{}' (expected: '{}', '{}')",
+            status, input, screen, buffer, expected_screen, expected_buffer);
+    }
+
+    // VNI marks: 6 circumflex, 7 horn, 8 breve, 9 đ; a digit with nothing
+    // to mark is typed as is
+    println!("\n🔣 VNI Mark Tests:");
+    let vni_mark_tests = vec![
+        ("a6 ", "â "),
+        ("a8 ", "ă "),
+        ("duo7ng2 ", "dường "),
+        ("d9 ", "đ "),
+        ("Tie6ng1 ", "Tiếng "),
+        ("a18 ", "ắ "),         // The mark keeps the tone
+        ("a66 ", "â6 "),        // Nothing left to mark: literal 6
+        ("a9 ", "a9 "),
+    ];
+
+    for (input, expected) in &vni_mark_tests {
+        let result = type_on_screen(&mut UnikeyEngine::builder().method(InputMethod::Vni).build(), input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} '{}' → '{}' (expected: '{}')", status, input, result, expected);
+    }
+
+    // engine.allow_vni_in_telex: 6-9 mark plain Telex syllables, 1-5 and
+    // digits after a Telex mark stay literal
+    println!("\n🔀 VNI Marks in Telex Tests:");
+    let vni_in_telex_tests = vec![
+        ("a8 ", true, "ă "),
+        ("a6 ", true, "â "),
+        ("tam8 ", true, "tăm "),
+        ("huo7 ", true, "hươ "),
+        ("di9 ", true, "đi "),
+        ("Tu7 ", true, "Tư "),
+        ("a8s ", true, "ắ "),          // Telex tone on the VNI mark
+        ("a1 ", true, "a1 "),          // 1-5 are still separators
+        ("aa8 ", true, "â8 "),         // Telex already marked the syllable
+        ("e8 ", true, "e8 "),          // Nothing for 8 to mark
+        ("a8 ", false, "a8 "),         // Off by default
+    ];
+
+    for (input, allow, expected) in &vni_in_telex_tests {
+        let mut config = Config::default();
+        config.engine.allow_vni_in_telex = *allow;
+        let result = type_with_config(config, input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} '{}' (allow_vni_in_telex={}) → '{}' (expected: '{}')", status, input, allow, result, expected);
+    }
+
+    // set_method switches the cached method without reloading the config
+    println!("\n🔁 Input Method Switch Tests:");
+    let mut switching = InputMethodEngine::new(Arc::new(Mutex::new(Config::default())));
+    for (method, input, expected) in [
+        (config::InputMethodType::Telex, "as ", "á "),
+        (config::InputMethodType::Vni, "as ", "as "),
+        (config::InputMethodType::Vni, "a1 ", "á "),
+        (config::InputMethodType::Telex, "a1 ", "a1 "),
+    ] {
+        switching.set_method(method);
+        let mut screen = harness::ScreenModel::new();
+        for ch in input.chars() {
+            let _ = screen.apply(&switching.process_keypress(ch));
+        }
+        let result = screen.text();
+        let status = if result == expected && switching.method() == method { "✅" } else { "❌" };
+        println!("   {} {:?}: '{}' → '{}' (expected: '{}')", status, method, input, result, expected);
+    }
+
+    // Switching mid-word commits the word: later keys start a new one
+    for (from, to, before, after, expected, expected_committed) in [
+        (InputMethod::Telex, InputMethod::Vni, "vie", "a1", "vieá", "vie"),
+        (InputMethod::Vni, InputMethod::Telex, "vie", "as", "vieá", "vie"),
+        (InputMethod::Telex, InputMethod::Vni, "vieej", "t", "việt", "việ"),
+        (InputMethod::Telex, InputMethod::Telex, "vie", "ej", "việ", ""),   // Same method: the word goes on
+    ] {
+        let mut engine = UnikeyEngine::builder().method(from).build();
+        let mut screen = harness::ScreenModel::new();
+        for ch in before.chars() {
+            let _ = screen.apply(&engine.process(ch));
+        }
+        let committed = engine.set_input_method(to);
+        for ch in after.chars() {
+            let _ = screen.apply(&engine.process(ch));
+        }
+        let result = screen.text();
+        let status = if result == expected && committed == expected_committed { "✅" } else { "❌" };
+        println!("   {} {:?} '{}' → {:?} '{}' → '{}', committed: '{}' (expected: '{}', '{}')",
+            status, from, before, to, after, result, committed, expected, expected_committed);
+    }
+
+    // Raw keys stay on screen until a separator converts the word
+    println!("\n⏳ Compose On Commit Tests:");
+    let mut deferred_config = Config::default();
+    deferred_config.engine.compose_on_commit = true;
+    let mut deferred = InputMethodEngine::new(Arc::new(Mutex::new(deferred_config)));
+    for (input, expected_last, expected) in [
+        ("vieetj ", ProcessResult::Replace { backspaces: 6, text: "việt ".to_string() }, "việt "),
+        ("hello ", ProcessResult::PassThrough(' '), "hello "),
+        ("vieetj\x08 ", ProcessResult::Replace { backspaces: 5, text: "viêt ".to_string() }, "viêt "),
+    ] {
+        let mut screen = harness::ScreenModel::new();
+        let results: Vec<_> = input.chars().map(|ch| deferred.process_keypress(ch)).collect();
+        for result in &results {
+            let _ = screen.apply(result);
+        }
+        let raw_until_commit = results[..results.len() - 1]
+            .iter()
+            .all(|r| matches!(r, ProcessResult::PassThrough(_)));
+        let result = screen.text();
+        let ok = raw_until_commit && results.last() == Some(&expected_last) && result == expected;
+        println!("   {} {:?} → '{}' (expected: '{}', last: {:?})",
+                 if ok { "✅" } else { "❌" }, input, result, expected, results.last());
+    }
+
+    // Backspace over a space reopens the word before it
+    println!("\n⌫ Previous Syllable Tests:");
+    let previous_syllable_tests = vec![
+        ("vieet \x08j", true, "việt"),
+        ("viet \x08s", true, "viét"),
+        ("Nguoi \x08w ", true, "Người "),
+        ("vieet \x08j", false, "viêtj"),   // Option off: a new word starts
+        ("viet x\x08\x08s", true, "viets"), // Only right after the separator
+    ];
+    for (input, restore, expected) in &previous_syllable_tests {
+        let mut config = Config::default();
+        config.engine.restore_previous_syllable = *restore;
+        let mut engine = InputMethodEngine::new(Arc::new(Mutex::new(config)));
+        let mut screen = harness::ScreenModel::new();
+        for ch in input.chars() {
+            let _ = screen.apply(&engine.process_keypress(ch));
+        }
+        let result = screen.text();
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} {:?} ({}) → '{}' (expected: '{}')",
+                 status, input, if *restore { "on" } else { "off" }, result, expected);
+    }
+
+    // [separators] decides which characters end the word being composed
+    println!("\n✂️  Separator Config Tests:");
+    let separator_tests = vec![
+        ("viet-", "", "", "viet-", ""),             // - ends the word by default
+        ("viet-", "", "-", "viet-", "viet-"),       // Excluded: the word goes on
+        ("vieetj-nam ", "", "-", "việt-nam ", ""),
+        ("a“s", "", "", "á“", "á“"),               // “ is an ordinary character...
+        ("a“s", "“", "", "a“s", "s"),             // ...unless made a separator
+        ("as", "a", "", "á", "á"),                  // Letters never end a word
+    ];
+    for (input, extra, exclude, expected, expected_buffer) in &separator_tests {
+        let mut config = Config::default();
+        config.separators.extra = extra.chars().collect();
+        config.separators.exclude = exclude.chars().collect();
+        let mut engine = InputMethodEngine::new(Arc::new(Mutex::new(config)));
+        let mut screen = harness::ScreenModel::new();
+        for ch in input.chars() {
+            let _ = screen.apply(&engine.process_keypress(ch));
+        }
+        let result = screen.text();
+        let buffer = engine.get_current_buffer();
+        let status = if result == *expected && buffer == *expected_buffer { "✅" } else { "❌" };
+        println!("   {} '{}' (extra: {:?}, exclude: {:?}) → '{}', buffer '{}' (expected: '{}', '{}')",
+                 status, input, extra, exclude, result, buffer, expected, expected_buffer);
+    }
+
+    // An undo leaves the rest of the word untransformed; a separator ends that
+    println!("\n⏸️  Temporary English Tests:");
+    let temp_off_tests = vec![
+        ("as", false),
+        ("ass", true),          // Tone undone
+        ("aaa", true),          // Circumflex undone
+        ("asst", true),         // Still off for the rest of the word
+        ("ass ", false),        // Separator turns it back on
+        ("ass tas", false),
+    ];
+    for (input, expected) in &temp_off_tests {
+        let mut engine = InputMethodEngine::new(Arc::new(Mutex::new(Config::default())));
+        for ch in input.chars() {
+            engine.process_keypress(ch);
+        }
+        let off = engine.is_temporarily_off();
+        let status = if off == *expected && engine.status().temporarily_off == off { "✅" } else { "❌" };
+        println!("   {} '{}' → temporarily off: {} (expected: {})", status, input, off, expected);
+    }
+
+    // engine.dd_initial_only: on, đ only starts a syllable; off (the
+    // default), a dd after the first letter doubles too
+    println!("\n🔡 dd Position Tests:");
+    let dd_tests = vec![
+        ("add ", true, "add "),
+        ("odd ", true, "odd "),
+        ("dadd ", true, "dadd "),
+        ("dda ", true, "đa "),
+        ("add ", false, "ađ "),
+        ("dadd ", false, "dađ "),
+        ("dda ", false, "đa "),
+    ];
+    for (input, dd_initial_only, expected) in &dd_tests {
+        let mut config = Config::default();
+        config.engine.dd_initial_only = *dd_initial_only;
+        let result = type_with_config(config, input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} '{}' (dd_initial_only={}) → '{}' (expected: '{}')",
+                 status, input, dd_initial_only, result, expected);
+    }
+
+    // engine.disabled_tone_keys: those keys stay letters, the rest still mark
+    println!("\n🚫 Disabled Tone Key Tests:");
+    let disabled_tone_tests = vec![
+        ("max ", vec!['x'], "max "),
+        ("maxs ", vec!['x'], "máx "),   // An ordinary letter after the vowel
+        ("mas ", vec!['x'], "má "),
+        ("MAX ", vec!['x'], "MAX "),
+        ("max ", vec![], "mã "),
+        ("vieetj ", vec!['s', 'f'], "việt "),
+    ];
+    for (input, disabled, expected) in &disabled_tone_tests {
+        let mut config = Config::default();
+        config.engine.disabled_tone_keys = disabled.clone();
+        let result = type_with_config(config, input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} '{}' (disabled: {:?}) → '{}' (expected: '{}')",
+                 status, input, disabled, result, expected);
+    }
+
+    // engine.z_key: z removes the tone, or the tone and every mark, and is
+    // a letter when there is nothing to remove
+    println!("\n🧽 z Key Tests:");
+    let z_tests = vec![
+        ("aaz ", ZKey::RemoveMarks, "a "),
+        ("owz ", ZKey::RemoveMarks, "o "),
+        ("ddz ", ZKey::RemoveMarks, "d "),
+        ("uwz ", ZKey::RemoveMarks, "u "),
+        ("awz ", ZKey::RemoveMarks, "a "),
+        ("nguwowfiz ", ZKey::RemoveMarks, "nguoi "),
+        ("DDAAYZ ", ZKey::RemoveMarks, "DAY "),
+        ("pizza ", ZKey::RemoveMarks, "pizza "),
+        ("asz ", ZKey::RemoveTone, "a "),
+        ("aasz ", ZKey::RemoveTone, "â "),
+        ("aaz ", ZKey::RemoveTone, "âz "),
+        ("asz ", ZKey::Literal, "áz "),
+    ];
+    for (input, z_key, expected) in &z_tests {
+        let mut config = Config::default();
+        config.engine.z_key = *z_key;
+        let result = type_with_config(config, input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} '{}' (z_key={:?}) → '{}' (expected: '{}')",
+                 status, input, z_key, result, expected);
+    }
+
+    // Literal tone letters in Telex: with no vowel before them they never
+    // mark anything; doubled, they undo the tone and, with
+    // english_double_letters, keep both letters
+    println!("\n🔤 Literal Tone Key Tests:");
+    let literal_tone_tests = vec![
+        ("s ", false, "s "),            // No vowel: literal
+        ("fs ", false, "fs "),
+        ("as ", false, "á "),
+        ("ass ", false, "as "),         // Doubled: undo, one letter
+        ("class ", false, "clas "),
+        ("class ", true, "class "),     // Doubled: both letters
+        ("pass ", true, "pass "),
+        ("staff ", true, "staff "),
+        ("classes ", true, "classes "), // Rest of the word stays English
+        ("CLASS ", true, "CLASS "),
+        ("ass ", true, "ass "),
+        ("asf ", true, "à "),           // A different tone key still retones
+        ("viets ", true, "viét "),
+    ];
+    for (input, english_double_letters, expected) in &literal_tone_tests {
+        let mut config = Config::default();
+        config.engine.english_double_letters = *english_double_letters;
+        let result = type_with_config(config, input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} '{}' (english_double_letters={}) → '{}' (expected: '{}')",
+                 status, input, english_double_letters, result, expected);
+    }
+
+    // A run without separators stops transforming at max_syllable_len and
+    // types its tail literally, up to the next separator
+    println!("\n📏 Max Syllable Length Tests:");
+    let max_len_tests = vec![
+        ("abcdefghijklmnopqras ", 12, "abcdèghịklmnopqras "),       // 20 keys
+        ("abcdefghijklmnopqras ", 0, "abcdèghịklmnỏpqá "),          // No limit
+        ("abcdefghijklmnopqras ", 5, "abcdefghijklmnopqras "),
+        ("abcdefghijklmnopqras viets ", 12, "abcdèghịklmnopqras viét "), // Next word composes
+        ("nghieengf ", 12, "nghiềng "),
+    ];
+    for (input, max_syllable_len, expected) in &max_len_tests {
+        let mut config = Config::default();
+        config.engine.max_syllable_len = *max_syllable_len;
+        let result = type_with_config(config, input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} '{}' (max_syllable_len={}) → '{}' (expected: '{}')",
+                 status, input, max_syllable_len, result, expected);
+    }
+
+    // A tone key reaches back over at most max_after_vowel letters after the
+    // vowels; two covers every coda
+    println!("\n🎯 Tone Reach Tests:");
+    let reach_tests = vec![
+        ("hocj ", 2, "học "),
+        ("bangf ", 2, "bàng "),
+        ("manhj ", 2, "mạnh "),
+        ("nghieengs ", 2, "nghiếng "),
+        ("mangts ", 2, "mangts "),      // Three letters back: out of reach
+        ("mangts ", 3, "mángt "),
+        ("hocj ", 0, "hocj "),
+    ];
+    for (input, max_after_vowel, expected) in &reach_tests {
+        let mut config = Config::default();
+        config.engine.max_after_vowel = *max_after_vowel;
+        let result = type_with_config(config, input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} '{}' (max_after_vowel={}) → '{}' (expected: '{}')",
+                 status, input, max_after_vowel, result, expected);
+    }
+
+    // engine.strict_tones: stop codas (c, ch, p, t) take only sắc and nặng
+    println!("\n📐 Strict Tone Tests:");
+    let strict_tests = vec![
+        ("hocf ", true, "hocf "),
+        ("hocj ", true, "học "),
+        ("hocs ", true, "hóc "),
+        ("sachx ", true, "sachx "),
+        ("hopr ", true, "hopr "),
+        ("hocff ", true, "hocff "),
+        ("hocf ", false, "hòc "),
+        ("hangf ", true, "hàng "),
+    ];
+    for (input, strict_tones, expected) in &strict_tests {
+        let mut config = Config::default();
+        config.engine.strict_tones = *strict_tones;
+        let result = type_with_config(config, input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} '{}' (strict_tones={}) → '{}' (expected: '{}')",
+                 status, input, strict_tones, result, expected);
+    }
+
+    // A word typed as a [symbols] trigger becomes its symbol when it ends;
+    // anything else composes as usual
+    println!("\n💱 Symbol Tests:");
+    let mut custom_symbols = Config::default();
+    custom_symbols.symbols.insert("ddg".to_string(), "₫".to_string());
+    let mut compose_symbols = Config::default();
+    compose_symbols.engine.compose_on_commit = true;
+    let symbol_tests = vec![
+        (Config::default(), "vnd ", "₫ ", "default trigger"),
+        (Config::default(), "100vnd.", "100₫.", "after digits"),
+        (Config::default(), "vieetj vnd ", "việt ₫ ", "next to Vietnamese"),
+        (Config::default(), "Vnd ", "Vnd ", "case matters"),
+        (Config::default(), "vnds ", "vnds ", "longer word"),
+        (Config::default(), "vnd", "vnd", "not ended yet"),
+        (custom_symbols.clone(), "ddg ", "₫ ", "custom trigger, by keys"),
+        (custom_symbols, "ddgs ", "đgs ", "custom trigger, longer word"),
+        (compose_symbols, "vnd vieetj ", "₫ việt ", "compose_on_commit"),
+    ];
+    for (config, input, expected, description) in symbol_tests {
+        let result = type_with_config(config, input);
+        let status = if result == expected { "✅" } else { "❌" };
+        println!("   {} {}: {:?} → {:?} (expected: {:?})", status, description, input, result, expected);
+    }
+
+    // A word that composes to a [proper_nouns] entry is rewritten when it
+    // ends, whatever its case; longer words and unfinished ones are not
+    println!("\n🏙️  Proper Noun Tests:");
+    let mut nouns = Config::default();
+    nouns.proper_nouns.insert("vietnam".to_string(), "Việt Nam".to_string());
+    nouns.proper_nouns.insert("hanoi".to_string(), "Hà Nội".to_string());
+    nouns.proper_nouns.insert("hue".to_string(), "Huế".to_string());
+    let compose_nouns = Config {
+        engine: EngineConfig { compose_on_commit: true, ..EngineConfig::default() },
+        ..nouns.clone()
+    };
+    let tone_at_end_nouns = Config {
+        engine: EngineConfig { tone_at_end: true, ..EngineConfig::default() },
+        ..nouns.clone()
+    };
+    let proper_noun_tests = vec![
+        (nouns.clone(), "vietnam ", "Việt Nam ", "plain word"),
+        (nouns.clone(), "toi o hanoi.", "toi o Hà Nội.", "ended by punctuation"),
+        (nouns.clone(), "Hanoi ", "Hà Nội ", "any case"),
+        (nouns.clone(), "hanoian ", "hanoian ", "longer word"),
+        (nouns.clone(), "hanoi", "hanoi", "not ended yet"),
+        (nouns.clone(), "hano\x08oi ", "Hà Nội ", "after a backspace"),
+        (nouns.clone(), "hue ", "Huế ", "short entry"),
+        (nouns.clone(), "hues ", "húe ", "composes to another word"),
+        (compose_nouns, "vietnam vieetj ", "Việt Nam việt ", "compose_on_commit"),
+        (tone_at_end_nouns, "vietnam ", "Việt Nam ", "tone_at_end"),
+    ];
+    for (config, input, expected, description) in proper_noun_tests {
+        let result = type_with_config(config, input);
+        let status = if result == expected { "✅" } else { "❌" };
+        println!("   {} {}: {:?} → {:?} (expected: {:?})", status, description, input, result, expected);
+    }
+    let mut english = InputMethodEngine::new(Arc::new(Mutex::new(nouns)));
+    english.toggle_vietnamese_mode();
+    let result = vaixkey::apply_results("", &"hanoi ".chars().map(|c| english.process_keypress(c)).collect::<Vec<_>>());
+    let status = if result == "hanoi " { "✅" } else { "❌" };
+    println!("   {} English mode: \"hanoi \" → {:?} (expected: \"hanoi \")", status, result);
+
+    // A client composing a word over the JSON-RPC socket
+    println!("\n🛰️  JSON-RPC Tests:");
+    let rpc_engine = Arc::new(Mutex::new(InputMethodEngine::new(Arc::new(Mutex::new(Config::default())))));
+    let result = (|| -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error>> {
+        use std::io::{BufRead, Write};
+        let addr = rpc::serve(0, rpc_engine.clone())?;
+        let stream = std::net::TcpStream::connect(addr)?;
+        stream.set_read_timeout(Some(std::time::Duration::from_secs(2)))?;
+        let mut writer = stream.try_clone()?;
+        let mut reader = std::io::BufReader::new(stream);
+        let mut responses = Vec::new();
+        let mut requests: Vec<String> = "vieejt"
+            .chars()
+            .enumerate()
+            .map(|(id, c)| format!(r#"{{"jsonrpc":"2.0","id":{},"method":"process","params":{{"char":"{}"}}}}"#, id, c))
+            .collect();
+        requests.push(r#"{"jsonrpc":"2.0","id":"s","method":"status"}"#.to_string());
+        requests.push("{not json".to_string());
+        requests.push(r#"{"jsonrpc":"2.0","id":7,"method":"fly"}"#.to_string());
+        requests.push(r#"{"jsonrpc":"2.0","id":8,"method":"set_method","params":{"method":"viqr"}}"#.to_string());
+        requests.push(r#"{"jsonrpc":"2.0","id":9,"method":"convert","params":{"text":"Tooi ddi hocj"}}"#.to_string());
+        for request in requests {
+            writeln!(writer, "{}", request)?;
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            responses.push(serde_json::from_str(&line)?);
+        }
+        Ok(responses)
+    })();
+    match result {
+        Ok(responses) => {
+            let status = &responses[6]["result"];
+            let rpc_checks = [
+                ("'t' replaces nothing", responses[5]["result"]["action"] == "pass_through"),
+                ("status buffer", status["buffer"] == "việt" && status["method"] == "Telex"),
+                ("malformed line", responses[7]["error"]["code"] == -32700),
+                ("unknown method", responses[8]["error"]["code"] == -32601 && responses[8]["id"] == 7),
+                ("bad method name", responses[9]["error"]["code"] == -32602),
+                ("convert", responses[10]["result"]["text"] == "Tôi đi học"),
+            ];
+            for (description, ok) in rpc_checks {
+                println!("   {} {}", if ok { "✅" } else { "❌" }, description);
+            }
+            println!("   buffer after the word: {}", status["buffer"]);
+        }
+        Err(e) => println!("   ❌ RPC round trip failed: {}", e),
+    }
+    // The client side, which --set-method --rpc-port uses to switch a
+    // running instance
+    let client = rpc::serve(0, rpc_engine.clone()).map_err(|e| e.to_string()).and_then(|addr| {
+        let switched = rpc::request(addr.port(), "set_method", serde_json::json!({ "method": "vni" }))?;
+        let refused = rpc::request(addr.port(), "set_method", serde_json::json!({ "method": "viqr" }));
+        Ok((switched, refused))
+    });
+    match client {
+        Ok((switched, refused)) => {
+            let ok = switched["method"] == "Vni"
+                && lock(&rpc_engine).status().method == config::InputMethodType::Vni
+                && refused.is_err();
+            println!("   {} request() switches the serving engine to VNI and reports errors", if ok { "✅" } else { "❌" });
+        }
+        Err(e) => println!("   ❌ request() failed: {}", e),
+    }
+
+    // switch_profile over JSON-RPC, in a temp HOME so the real profiles
+    // and current_profile pointer are left alone
+    println!("\n🗂️  Profile Switch Tests:");
+    let temp_home = std::env::temp_dir().join(format!("vaixkey-profile-home-{}", std::process::id()));
+    let previous_home = std::env::var_os("HOME");
+    std::env::set_var("HOME", &temp_home);
+    let result = (|| -> Result<(Vec<serde_json::Value>, String), Box<dyn std::error::Error>> {
+        Config::set_current_profile(Some("vni"))?;
+        if !Config::config_path()?.starts_with(&temp_home) {
+            return Err("--config or VAIXKEY_CONFIG is set, which overrides profiles".into());
+        }
+        Config { input_method: config::InputMethodType::Vni, ..Config::default() }.save()?;
+        Config::set_current_profile(None)?;
+        let engine = Mutex::new(InputMethodEngine::new(Arc::new(Mutex::new(Config::default()))));
+        let responses = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"switch_profile","params":{"name":"vni"}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"switch_profile","params":{"name":"../vni"}}"#,
+        ]
+        .iter()
+        .map(|request| serde_json::from_str(&rpc::handle_line(&engine, request)))
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+        let typed = vaixkey::apply_results("", &"a1".chars().map(|c| lock(&engine).process_keypress(c)).collect::<Vec<_>>());
+        Ok((responses, typed))
+    })();
+    match previous_home {
+        Some(home) => std::env::set_var("HOME", home),
+        None => std::env::remove_var("HOME"),
+    }
+    let _ = std::fs::remove_dir_all(&temp_home);
+    match result {
+        Ok((responses, typed)) => {
+            let profile_checks = [
+                ("status reports the profile's method", responses[0]["result"]["method"] == "Vni"),
+                ("engine types with it: a1 → á", typed == "á"),
+                ("bad profile name", responses[1]["error"]["code"] == -32602),
+            ];
+            for (description, ok) in profile_checks {
+                println!("   {} {}", if ok { "✅" } else { "❌" }, description);
+            }
+        }
+        Err(e) => println!("   ❌ profile switch failed: {}", e),
+    }
+
+    // Whole texts through convert(), as the clipboard conversion does
+    println!("\n📋 Convert Tests:");
+    let convert_tests = vec![
+        ("Vieejt Nam", false, "Việt Nam"),
+        ("xin chaof\nbanj", false, "xin chào\nbạn"),   // Newlines are separators
+        ("toans", true, "toán"),                       // Last word is committed
+        ("Tooi ddi hocj.", false, "Tôi đi học."),
+        ("123 abc", false, "123 abc"),
+        ("", false, ""),
+    ];
+    for (input, tone_at_end, expected) in &convert_tests {
+        let mut config = Config::default();
+        config.engine.tone_at_end = *tone_at_end;
+        let converter = InputMethodEngine::new(Arc::new(Mutex::new(config)));
+        let result = converter.convert(input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} {:?} → {:?} (expected: {:?})", status, input, result, expected);
+    }
+
+    // Transformed words that aren't Vietnamese go back to the typed keys
+    println!("\n📖 Dictionary Revert Tests:");
+    if cfg!(feature = "dictionary") {
+        let revert_tests = vec![
+            ("chaof ", true, "chào "),
+            ("nguwowif ", true, "người "),
+            ("hoaf ", true, "hoà "),        // Tone placement doesn't matter
+            ("emails ", true, "emails "),   // "emaíl" isn't a word
+            ("window ", true, "window "),
+            ("after ", true, "after "),
+            ("tesst ", true, "test "),      // Undo result is kept as typed
+            ("window ", false, "ưindơ "),
+        ];
+
+        for (input, enabled, expected) in &revert_tests {
+            let mut engine = UnikeyEngine::new();
+            engine.set_dictionary_revert(*enabled);
+            let result = type_on_screen(&mut engine, input);
+            let status = if result == *expected { "✅" } else { "❌" };
+            println!("   {} '{}' ({}) → '{}' (expected: '{}')",
+                status, input, if *enabled { "on" } else { "off" }, result, expected);
+        }
+    } else {
+        println!("   ⏭️  Skipped: built without the `dictionary` feature");
+    }
+
+    // Other tones of the word being composed, offered but never applied
+    println!("\n💡 Suggestion Tests:");
+    if cfg!(feature = "dictionary") {
+        let suggestion_tests = vec![
+            ("chao", true, vec!["chào", "cháo"]),
+            ("Chao", true, vec!["Chào", "Cháo"]),   // Capital follows the typed word
+            ("chaof", true, vec!["chao", "cháo"]),  // A toned word offers the others
+            ("xyz", true, vec![]),
+            ("chao", false, vec![]),                // Off unless engine.suggestions
+        ];
+
+        for (input, enabled, expected) in &suggestion_tests {
+            let mut config = Config::default();
+            config.engine.suggestions = *enabled;
+            let mut engine = InputMethodEngine::new(Arc::new(Mutex::new(config)));
+            for ch in input.chars() {
+                engine.process_keypress(ch);
+            }
+            let suggestions = engine.suggestions();
+            let ok = suggestions.len() <= input_method::MAX_SUGGESTIONS
+                && expected.iter().all(|word| suggestions.iter().any(|s| s == word))
+                && (!expected.is_empty() || suggestions.is_empty());
+            let status = if ok { "✅" } else { "❌" };
+            println!("   {} '{}' ({}) → {:?} (expected to include: {:?})",
+                status, input, if *enabled { "on" } else { "off" }, suggestions, expected);
+        }
+    } else {
+        println!("   ⏭️  Skipped: built without the `dictionary` feature");
+    }
+
+    // Drive the monitor's grab -> action -> inject path with a recording backend
+    println!("\n🔌 Keyboard Wiring Tests (recording backend):");
+    let wiring_tests = vec![
+        ("mootj", false, "một", Some(vec![
+            BackendCall::Backspaces(1), BackendCall::Text("ô".to_string()),
+            BackendCall::Backspaces(2), BackendCall::Text("ột".to_string()),
+        ])),
+        ("ddaays", false, "đấy", None),
+        ("aaa", false, "aa", None),                 // Undo restores the literal keys
+        ("nam s", false, "nam s", Some(vec![])),    // Plain text never injects
+        ("as\x08s", false, "s", None),              // Backspace clears the composition
+        ("toans ", true, "toán ", None),            // tone_at_end commits on space
+        ("âs", false, "ấ", None),                   // Precomposed â, then a tone key
+        ("Tươngf", false, "Tường", None),           // Shifted letter, layout-typed ư and ơ
+        ("tieng\x0cs", false, "tiengs", Some(vec![])), // Focus moved to another field
+        ("tieeng←←s", false, "tiếng", Some(vec![         // Left arrows keep the word
+            BackendCall::Backspaces(1), BackendCall::Text("ê".to_string()),
+            BackendCall::Backspaces(1), BackendCall::Text("ế".to_string()),
+        ])),
+    ];
+
+    for (input, tone_at_end, expected, expected_calls) in &wiring_tests {
+        let (screen, calls) = type_through_monitor(input, *tone_at_end);
+        let calls_ok = expected_calls.as_ref().is_none_or(|expected| calls == *expected);
+        let status = if screen == *expected && calls_ok { "✅" } else { "❌" };
+        println!("   {} {:?} → '{}' (expected: '{}'), {} backend calls",
+            status, input, screen, expected, calls.len());
+    }
+
+    // injection_mode = Paste: each replacement goes through the clipboard,
+    // which gets its text back afterwards; without text there it is typed
+    println!("\n📋 Paste Injection Tests:");
+    let paste_tests = vec![
+        ("mootj", Some("copied"), "một", vec![
+            BackendCall::Clipboard("ô".to_string()), BackendCall::Backspaces(1), BackendCall::Paste,
+            BackendCall::Clipboard("copied".to_string()),
+            BackendCall::Clipboard("ột".to_string()), BackendCall::Backspaces(2), BackendCall::Paste,
+            BackendCall::Clipboard("copied".to_string()),
+        ]),
+        ("mootj", None, "một", vec![
+            BackendCall::Backspaces(1), BackendCall::Text("ô".to_string()),
+            BackendCall::Backspaces(2), BackendCall::Text("ột".to_string()),
+        ]),
+        ("nam s", Some("copied"), "nam s", vec![]),
+    ];
+    for (input, clipboard, expected, expected_calls) in &paste_tests {
+        let config = Config { injection_mode: InjectionMode::Paste, ..Config::default() };
+        let backend = Arc::new(RecordingBackend::new());
+        backend.set_clipboard(*clipboard);
+        let (screen, calls) = type_through_monitor_with_backend(config, backend.clone(), input);
+        let restored = backend.clipboard().as_deref() == *clipboard;
+        let status = if screen == *expected && calls == *expected_calls && restored { "✅" } else { "❌" };
+        println!("   {} {:?} (clipboard {:?}) → '{}' (expected: '{}'), {} backend calls, clipboard {:?} after",
+            status, input, clipboard, screen, expected, calls.len(), backend.clipboard());
+    }
+
+    // injection_fallback: once the app rejects a Unicode string, that
+    // character, the rest of the replacement and every later one are typed
+    // with key presses
+    println!("\n🛟 Injection Fallback Tests:");
+    let keystrokes = |text: &str| BackendCall::Keystrokes(text.to_string());
+    let fallback_tests = vec![
+        ("vieetj aa", true, false, true, "việt â", vec![
+            BackendCall::Backspaces(1), keystrokes("ê"),
+            BackendCall::Backspaces(2), keystrokes("ệt"),
+            BackendCall::Backspaces(1), keystrokes("â"),
+        ]),
+        ("vieetj ", true, true, true, "việt ", vec![
+            BackendCall::Backspaces(6), BackendCall::Text("v".to_string()), BackendCall::Text("i".to_string()),
+            keystrokes("ệt "),
+        ]),
+        ("vieetj", false, false, true, "v", vec![BackendCall::Backspaces(1), BackendCall::Backspaces(2)]),
+        ("vieetj", true, false, false, "việt", vec![
+            BackendCall::Backspaces(1), BackendCall::Text("ê".to_string()),
+            BackendCall::Backspaces(2), BackendCall::Text("ệ".to_string()), BackendCall::Text("t".to_string()),
+        ]),
+    ];
+    for (input, fallback, compose_on_commit, refusing, expected, expected_calls) in &fallback_tests {
+        let mut config = Config { injection_fallback: *fallback, ..Config::default() };
+        config.engine.compose_on_commit = *compose_on_commit;
+        let backend = Arc::new(RecordingBackend::new());
+        backend.set_refusing_unicode(*refusing);
+        let (screen, calls) = type_through_monitor_with_backend(config, backend, input);
+        let status = if screen == *expected && calls == *expected_calls { "✅" } else { "❌" };
+        println!("   {} {:?} (fallback {}, Unicode refused {}) → '{}' (expected: '{}'), calls {:?}",
+            status, input, fallback, refusing, screen, expected, calls);
+    }
+
+    // Waking from sleep or unlocking the screen drops the word; going to
+    // sleep alone doesn't (the keys after it come after the wake)
+    println!("\n😴 Sleep/Wake Tests:");
+    for (events, expected) in [
+        (vec![], "tiếng"),
+        (vec![keyboard::PowerEvent::Sleep], "tiếng"),
+        (vec![keyboard::PowerEvent::Sleep, keyboard::PowerEvent::Wake], "tiêngs"),
+        (vec![keyboard::PowerEvent::ScreenLocked, keyboard::PowerEvent::ScreenUnlocked], "tiêngs"),
+    ] {
+        let engine = Arc::new(Mutex::new(InputMethodEngine::new(Arc::new(Mutex::new(Config::default())))));
+        let monitor = KeyboardMonitor::with_backend(engine, Arc::new(RecordingBackend::new()));
+        let mut screen = harness::ScreenModel::new();
+        let mut type_keys = |keys: &str| {
+            for ch in keys.chars() {
+                let key = keyboard::char_to_key(ch).unwrap_or(rdev::Key::Unknown(0));
+                let result = match monitor.dispatch_key(key, Some(ch)) {
+                    GrabAction::BlockAndInject { backspaces, text } => ProcessResult::Replace { backspaces, text },
+                    _ => ProcessResult::PassThrough(ch),
+                };
+                let _ = screen.apply(&result);
+            }
+        };
+        type_keys("tieeng");
+        for event in &events {
+            monitor.notify_power_event(*event);
+        }
+        type_keys("s");
+        let text = screen.text();
+        let status = if text == expected { "✅" } else { "❌" };
+        println!("   {} 'tieeng', {:?}, 's' → '{}' (expected: '{}')", status, events, text, expected);
+    }
+
+    // Keys pressed with Cmd held are shortcuts: they never reach the engine
+    // and, with reset_on_modifier, end the word being composed
+    let shortcut_tests = vec![
+        ("vie", vec!["Cmd+A"], "", true, ""),
+        ("vieet", vec!["Cmd+C", "Cmd+V"], "", true, ""),
+        ("vie", vec!["Cmd+A"], "es", true, "é"),          // New word after Cmd+A
+        ("vie", vec!["Cmd+B"], "es", false, "viế"),       // Option off: word kept
+    ];
+
+    for (before, shortcuts, after, reset_on_modifier, expected) in &shortcut_tests {
+        let mut config = Config::default();
+        config.engine.reset_on_modifier = *reset_on_modifier;
+        let engine = Arc::new(Mutex::new(InputMethodEngine::new(Arc::new(Mutex::new(config)))));
+        let monitor = KeyboardMonitor::with_backend(engine.clone(), Arc::new(RecordingBackend::new()));
+
+        for ch in before.chars() {
+            monitor.dispatch_key(keyboard::char_to_key(ch).unwrap_or(rdev::Key::Unknown(0)), Some(ch));
+        }
+        for combo in shortcuts {
+            monitor.dispatch_shortcut(&combo.parse().unwrap_or_default());
+        }
+        for ch in after.chars() {
+            monitor.dispatch_key(keyboard::char_to_key(ch).unwrap_or(rdev::Key::Unknown(0)), Some(ch));
+        }
+
+        let buffer = lock(&engine).get_current_buffer().to_string();
+        let status = if buffer == *expected { "✅" } else { "❌" };
+        println!("   {} '{}' + {} + '{}' (reset_on_modifier: {}) → buffer '{}' (expected: '{}')",
+            status, before, shortcuts.join(", "), after, reset_on_modifier, buffer, expected);
+    }
+
+    // VNI digits from the numpad: composed only with numpad_as_vni_tones,
+    // otherwise they pass through and end the word
+    println!("\n🔢 Numpad Tests:");
+    let numpad_tests = vec![
+        ("a1", true, "á", ""),
+        ("viet65", true, "việt", ""),
+        ("a1", false, "a1", ""),
+        ("viet65", false, "viet65", ""),
+        ("a1a", false, "a1a", "a"),      // A new word starts after the digit
+    ];
+
+    for (input, numpad_as_vni_tones, expected, expected_buffer) in &numpad_tests {
+        let mut config = Config::default();
+        config.input_method = config::InputMethodType::Vni;
+        config.engine.numpad_as_vni_tones = *numpad_as_vni_tones;
+        let engine = Arc::new(Mutex::new(InputMethodEngine::new(Arc::new(Mutex::new(config)))));
+        let backend = Arc::new(RecordingBackend::new());
+        let monitor = KeyboardMonitor::with_backend(engine.clone(), backend.clone());
+
+        let mut screen = harness::ScreenModel::new();
+        for ch in input.chars() {
+            let key = keyboard::char_to_numpad_key(ch)
+                .or_else(|| keyboard::char_to_key(ch))
+                .unwrap_or(rdev::Key::Unknown(0));
+            let before = backend.calls().len();
+            if let GrabAction::PassThrough = monitor.dispatch_key(key, Some(ch)) {
+                let _ = screen.apply(&ProcessResult::PassThrough(ch));
+            }
+            apply_backend_calls(&mut screen, &backend.calls()[before..]);
+        }
+
+        let result = screen.text();
+        let buffer = lock(&engine).get_current_buffer().to_string();
+        let status = if result == *expected && buffer == *expected_buffer { "✅" } else { "❌" };
+        println!("   {} '{}' on the numpad ({}) → '{}' (expected: '{}'), buffer '{}'",
+            status, input, if *numpad_as_vni_tones { "on" } else { "off" }, result, expected, buffer);
+    }
+
+    // A separator right after another has no word to commit: it always
+    // passes through, whatever rewrites the first one did
+    println!("\n⎵ Separator Run Tests:");
+    let separator_run_tests = vec![
+        ("a  b", "a  b"),
+        ("a.. b", "a.. b"),
+        ("toans..  ", "toán..  "),
+        ("vieetj , nam", "việt , nam"),
+    ];
+    let mut tone_at_end = Config::default();
+    tone_at_end.engine.tone_at_end = true;
+    let mut compose_on_commit = Config::default();
+    compose_on_commit.engine.compose_on_commit = true;
+    let mut dictionary_revert = Config::default();
+    dictionary_revert.engine.dictionary_revert = true;
+    let mut empty_symbol = Config::default();
+    empty_symbol.symbols.insert(String::new(), "?".to_string());
+    let separator_configs = [
+        ("default", Config::default()),
+        ("tone_at_end", tone_at_end),
+        ("compose_on_commit", compose_on_commit),
+        ("dictionary_revert", dictionary_revert),
+        ("empty symbol trigger", empty_symbol),
+    ];
+    for (input, expected) in &separator_run_tests {
+        for (name, config) in &separator_configs {
+            let mut engine = InputMethodEngine::new(Arc::new(Mutex::new(config.clone())));
+            let mut screen = harness::ScreenModel::new();
+            let mut stray = Vec::new();
+            let mut previous = None;
+            for ch in input.chars() {
+                let result = engine.process_keypress(ch);
+                let after_separator = previous.is_some_and(|p: char| !p.is_alphanumeric());
+                if !ch.is_alphanumeric() && after_separator && result != ProcessResult::PassThrough(ch) {
+                    stray.push(result.clone());
+                }
+                let _ = screen.apply(&result);
+                previous = Some(ch);
+            }
+            let result = screen.text();
+            let status = if result == *expected && stray.is_empty() { "✅" } else { "❌" };
+            println!("   {} {:?} ({}) → {:?} (expected: {:?}), rewrites after a separator: {:?}",
+                status, input, name, result, expected, stray);
+        }
+    }
+
+    // engine.tab_enter_commit: Tab and Enter finish the word like Space, or
+    // drop it as typed
+    println!("\n↩️  Tab/Enter Tests:");
+    let tab_enter_tests = vec![
+        ("toans\t", true, false, true, "toán\t"),
+        ("toans\t", true, false, false, "toans\t"),
+        ("toans ", true, false, false, "toán "),       // Space always commits
+        ("vieetj\n", false, true, true, "việt\n"),
+        ("vieetj\n", false, true, false, "vieetj\n"),
+        ("vieetj\nas", false, false, false, "việt\ná"), // A new word after it
+    ];
+
+    for (input, tone_at_end, compose_on_commit, tab_enter_commit, expected) in &tab_enter_tests {
+        let mut config = Config::default();
+        config.engine.tone_at_end = *tone_at_end;
+        config.engine.compose_on_commit = *compose_on_commit;
+        config.engine.tab_enter_commit = *tab_enter_commit;
+        let (result, _) = type_through_monitor_with_config(config, input);
+        let status = if result == *expected { "✅" } else { "❌" };
+        println!("   {} {:?} ({}) → {:?} (expected: {:?})",
+            status, input, if *tab_enter_commit { "commit" } else { "clear" }, result, expected);
+    }
+
+    // The dump hotkey reports the buffer and its keys without touching it
+    {
+        let engine = Arc::new(Mutex::new(InputMethodEngine::new(Arc::new(Mutex::new(Config::default())))));
+        let backend = Arc::new(RecordingBackend::new());
+        let mut monitor = KeyboardMonitor::with_backend(engine.clone(), backend.clone());
+        let dumps = Arc::new(Mutex::new(Vec::new()));
+        let sink = dumps.clone();
+        let hotkey: config::Hotkey = "Ctrl+Alt+B".parse().unwrap_or_default();
+        monitor.set_dump_hotkey(hotkey.clone(), Box::new(move |dump| lock(&sink).push(dump.to_string())));
+
+        for ch in "vieej".chars() {
+            monitor.dispatch_key(keyboard::char_to_key(ch).unwrap_or(rdev::Key::Unknown(0)), Some(ch));
+        }
+        let calls_before = backend.calls().len();
+        monitor.dispatch_shortcut(&hotkey);
+
+        let dumps = lock(&dumps).clone();
+        let buffer = lock(&engine).get_current_buffer().to_string();
+        let expected = r#""việ" (keys: "vieej")"#;
+        let ok = dumps == [expected] && buffer == "việ" && backend.calls().len() == calls_before;
+        let status = if ok { "✅" } else { "❌" };
+        println!("   {} 'vieej' + Ctrl+Alt+B → dumped {:?}, buffer kept: '{}', nothing injected",
+            status, dumps, buffer);
+    }
+
+    // stop() must leave nothing behind and be safe to repeat
+    {
+        let config = Arc::new(Mutex::new(Config::default()));
+        let engine = Arc::new(Mutex::new(InputMethodEngine::new(config)));
+        let monitor = KeyboardMonitor::with_backend(engine, Arc::new(RecordingBackend::new()));
+        let _ = std::fs::write(gui::SETTINGS_HTML_PATH, "<html></html>");
+        monitor.stop();
+        monitor.stop();
+        let removed = !std::path::Path::new(gui::SETTINGS_HTML_PATH).exists();
+        let status = if removed { "✅" } else { "❌" };
+        println!("   {} stop() twice → settings page removed: {}", status, removed);
+    }
+
+    // Injection that keeps failing drops the buffer instead of compounding
+    println!("\n⏲️  Commit Timeout Tests:");
+    for (timeout_ms, idle_ms, expected_committed, expected) in [
+        (Some(1000), 1500, true, "caf"),      // Paused too long: f starts a new word
+        (Some(1000), 500, false, "cà"),       // Still within the timeout
+        (None, 60_000, false, "cà"),          // Off by default
+    ] {
+        let mut config = Config::default();
+        config.engine.commit_timeout_ms = timeout_ms;
+        let engine = Arc::new(Mutex::new(InputMethodEngine::new(Arc::new(Mutex::new(config)))));
+        let backend = Arc::new(RecordingBackend::new());
+        let monitor = KeyboardMonitor::with_backend(engine.clone(), backend.clone());
+        let mut screen = harness::ScreenModel::new();
+        let mut type_key = |ch: char| {
+            let key = keyboard::char_to_key(ch).unwrap_or(rdev::Key::Unknown(0));
+            let result = match monitor.dispatch_key(key, Some(ch)) {
+                GrabAction::BlockAndInject { backspaces, text } => ProcessResult::Replace { backspaces, text },
+                _ => ProcessResult::PassThrough(ch),
+            };
+            let _ = screen.apply(&result);
+        };
+
+        type_key('c');
+        type_key('a');
+        let later = std::time::Instant::now() + std::time::Duration::from_millis(idle_ms);
+        let committed = monitor.commit_if_idle(later);
+        type_key('f');
+        let text = screen.text();
+        let status = if committed == expected_committed && text == expected { "✅" } else { "❌" };
+        println!("   {} timeout {:?}, idle {}ms → committed: {}, 'ca' + 'f' → '{}' (expected: '{}')",
+            status, timeout_ms, idle_ms, committed, text, expected);
+    }
+
+    // --learn: a transform erased right away and retyped is logged with
+    // what the user typed instead; one erased too late is not
+    println!("\n📚 Learn Mode Tests:");
+    for (backspace_after_ms, expected) in [
+        (200, Some(("mas", "má", "mas"))),
+        (3000, None),                      // Past RETYPE_WINDOW: an edit, not a correction
+    ] {
+        let log_path = std::env::temp_dir().join(format!("vaixkey-learn-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&log_path);
+        let learner = keyboard::Learner::create(&log_path)?;
+        let mut engine = InputMethodEngine::new(Arc::new(Mutex::new(Config::default())));
+        let start = std::time::Instant::now();
+        let mut type_keys = |keys: &str, at: std::time::Instant| {
+            for ch in keys.chars() {
+                let action = match engine.process_keypress(ch) {
+                    ProcessResult::Replace { backspaces, text } => GrabAction::BlockAndInject { backspaces, text },
+                    ProcessResult::Output(text) => GrabAction::BlockAndInject { backspaces: 0, text },
+                    ProcessResult::PassThrough(_) => GrabAction::PassThrough,
+                };
+                learner.key(ch, &action, at);
+            }
+        };
+        type_keys("mas", start);
+        type_keys("\x08", start + std::time::Duration::from_millis(backspace_after_ms));
+        type_keys("ass ", start + std::time::Duration::from_millis(backspace_after_ms + 300));
+        let correction = learner.commit();
+        let logged = std::fs::read_to_string(&log_path).unwrap_or_default();
+        let _ = std::fs::remove_file(&log_path);
+        let got = correction.as_ref().map(|c| (c.keys.as_str(), c.bad.as_str(), c.corrected.as_str()));
+        let line_ok = match expected {
+            Some((keys, bad, corrected)) => logged.lines().nth(1) == Some(&format!("{}\t{}\t{}", keys, bad, corrected)),
+            None => logged.lines().count() == 1,
+        };
+        let status = if got == expected && line_ok { "✅" } else { "❌" };
+        println!("   {} 'mas', ⌫ after {}ms, 'ass ' → {:?}", status, backspace_after_ms, got);
+    }
+
+    // The same through the keyboard monitor, Backspace key and all
+    {
+        let log_path = std::env::temp_dir().join(format!("vaixkey-learn-monitor-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&log_path);
+        let engine = Arc::new(Mutex::new(InputMethodEngine::new(Arc::new(Mutex::new(Config::default())))));
+        let mut monitor = KeyboardMonitor::with_backend(engine, Arc::new(RecordingBackend::new()));
+        monitor.set_learner(keyboard::Learner::create(&log_path)?);
+        for ch in "mas\x08ass ".chars() {
+            let key = if ch == '\x08' { rdev::Key::Backspace } else { keyboard::char_to_key(ch).unwrap_or(rdev::Key::Unknown(0)) };
+            monitor.dispatch_key(key, (!ch.is_control()).then_some(ch));
+        }
+        let logged = std::fs::read_to_string(&log_path).unwrap_or_default();
+        let _ = std::fs::remove_file(&log_path);
+        let line = logged.lines().nth(1).unwrap_or("");
+        let status = if line == "mas\tmá\tmas" { "✅" } else { "❌" };
+        println!("   {} monitor: 'mas' ⌫ 'ass ' → logged {:?}", status, line);
+    }
+
+    // A key over engine.max_process_us reaches the app as typed
+    println!("\n🐢 Process Budget Tests:");
+    for (max_process_us, expected_action, expected_buffer) in [
+        (1000, GrabAction::PassThrough, ""),                                       // Slow: key typed as is, word dropped
+        (0, GrabAction::BlockAndInject { backspaces: 1, text: "ê".to_string() }, "viê"), // No budget: always waits
+    ] {
+        let mut config = Config::default();
+        config.engine.max_process_us = max_process_us;
+        let engine = Arc::new(Mutex::new(InputMethodEngine::new(Arc::new(Mutex::new(config)))));
+        let backend = Arc::new(RecordingBackend::new());
+        let mut monitor = KeyboardMonitor::with_backend(engine.clone(), backend.clone());
+        let slow = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let hook_slow = slow.clone();
+        monitor.set_process_hook(Box::new(move || {
+            if hook_slow.load(std::sync::atomic::Ordering::SeqCst) {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+        }));
+        for ch in "vie".chars() {
+            monitor.dispatch_key(keyboard::char_to_key(ch).unwrap_or(rdev::Key::Unknown(0)), Some(ch));
+        }
+        slow.store(true, std::sync::atomic::Ordering::SeqCst);
+        let action = monitor.dispatch_key(rdev::Key::KeyE, Some('e'));
+        let buffer = lock(&engine).get_current_buffer().to_string();
+        let status = if action == expected_action && buffer == expected_buffer { "✅" } else { "❌" };
+        println!("   {} budget {}µs, 'vie' + slow 'e' → {:?}, buffer '{}'",
+            status, max_process_us, action, buffer);
+    }
+
+    // A key with no character the engine can use (an OEM or international
+    // key the layout doesn't report, F1) ends the word; a modifier doesn't
+    println!("\n❓ Unknown Key Tests:");
+    for (key, expected_buffer, expected_screen) in [
+        (rdev::Key::Unknown(0x2a), "", "viêtj"),
+        (rdev::Key::IntlBackslash, "", "viêtj"),
+        (rdev::Key::F1, "", "viêtj"),
+        (rdev::Key::ShiftLeft, "viêt", "việt"),
+    ] {
+        let engine = Arc::new(Mutex::new(InputMethodEngine::new(Arc::new(Mutex::new(Config::default())))));
+        let backend = Arc::new(RecordingBackend::new());
+        let monitor = KeyboardMonitor::with_backend(engine.clone(), backend.clone());
+        let mut screen = harness::ScreenModel::new();
+        let mut type_key = |key: rdev::Key, typed: Option<char>| {
+            let before = backend.calls().len();
+            match monitor.dispatch_key(key, typed) {
+                GrabAction::PassThrough => {
+                    if let Some(ch) = typed {
+                        let _ = screen.apply(&ProcessResult::PassThrough(ch));
+                    }
+                }
+                GrabAction::Block => {}
+                GrabAction::BlockAndInject { .. } => apply_backend_calls(&mut screen, &backend.calls()[before..]),
+            }
+        };
+        for ch in "vieet".chars() {
+            type_key(keyboard::char_to_key(ch).unwrap_or(rdev::Key::Unknown(0)), Some(ch));
+        }
+        type_key(key, None);
+        let buffer = lock(&engine).get_current_buffer().to_string();
+        type_key(rdev::Key::KeyJ, Some('j'));
+        let ok = buffer == expected_buffer && screen.text() == expected_screen;
+        let status = if ok { "✅" } else { "❌" };
+        println!("   {} 'vieet' + {:?} → buffer '{}', then 'j' → '{}' (expected: '{}', '{}')",
+            status, key, buffer, screen.text(), expected_buffer, expected_screen);
+    }
+
+    println!("\n🚧 Injection Failure Tests:");
+    {
+        let engine = Arc::new(Mutex::new(InputMethodEngine::new(Arc::new(Mutex::new(Config::default())))));
+        let backend = Arc::new(RecordingBackend::new());
+        let mut monitor = KeyboardMonitor::with_backend(engine.clone(), backend.clone());
+        let failures = Arc::new(Mutex::new(Vec::new()));
+        let sink = failures.clone();
+        monitor.set_failure_listener(Box::new(move |reason| lock(&sink).push(reason.to_string())));
+        let type_keys = |keys: &str| {
+            for ch in keys.chars() {
+                monitor.dispatch_key(keyboard::char_to_key(ch).unwrap_or(rdev::Key::Unknown(0)), Some(ch));
+            }
+        };
+
+        backend.set_failing(true);
+        type_keys("vieetj");
+        let buffer = lock(&engine).get_current_buffer().to_string();
+        let ok = buffer == "việt" && lock(&failures).is_empty();
+        let status = if ok { "✅" } else { "❌" };
+        println!("   {} two failed injections → buffer '{}' kept, failures reported: {}", status, buffer, lock(&failures).len());
+
+        type_keys("s");
+        let buffer = lock(&engine).get_current_buffer().to_string();
+        let ok = buffer.is_empty() && lock(&failures).len() == 1;
+        let status = if ok { "✅" } else { "❌" };
+        println!("   {} third failure → buffer reset ('{}'), failures reported: {:?}", status, buffer, lock(&failures));
+
+        backend.set_failing(false);
+        type_keys(" aa");
+        let calls = backend.calls();
+        let expected = vec![BackendCall::Backspaces(1), BackendCall::Text("â".to_string())];
+        let status = if calls == expected { "✅" } else { "❌" };
+        println!("   {} injection recovers → {:?}", status, calls);
+        monitor.stop();
+    }
+
+    // A recorded log must parse back to the same keys and replay to the same text
+    println!("\n📼 Replay Log Tests:");
+    {
+        let entries: Vec<replay::Entry> = "vieetj# ".chars().map(replay::Entry::Key)
+            .chain([replay::Entry::Reset])
+            .chain("namw\n".chars().map(replay::Entry::Key))
+            .collect();
+        let log_path = std::env::temp_dir().join(format!("vaixkey-replay-{}.log", std::process::id()));
+        let parsed = replay::Recorder::create(&log_path)
+            .map(|recorder| entries.iter().for_each(|&entry| recorder.record(entry)))
+            .and_then(|_| Ok(std::fs::read_to_string(&log_path)?))
+            .map_err(|e| e.to_string())
+            .and_then(|log| replay::parse(&log));
+        let _ = std::fs::remove_file(&log_path);
+        let round_trip = parsed.as_ref().is_ok_and(|parsed| *parsed == entries);
+        let status = if round_trip { "✅" } else { "❌" };
+        println!("   {} {} entries written and parsed back: {}", status, entries.len(), round_trip);
+
+        let mut replay_engine = InputMethodEngine::new(Arc::new(Mutex::new(Config::default())));
+        let mut screen = harness::ScreenModel::new();
+        for (_, result) in replay::replay(&mut replay_engine, &entries) {
+            if let Some(result) = result {
+                let _ = screen.apply(&result);
+            }
+        }
+        let expected = "việt# năm\n";
+        let status = if screen.text() == expected { "✅" } else { "❌" };
+        println!("   {} replayed → {:?} (expected: {:?})", status, screen.text(), expected);
+
+        let status = if replay::parse("ab\n").is_err() { "✅" } else { "❌" };
+        println!("   {} multi-character line is rejected", status);
+    }
+
+    // --explain brackets the characters each key rewrote
+    println!("\n🔎 Explain Tests:");
+    let explain_tests = vec![
+        ("nguyeenj", "nguy[ê]", "nguy[ệ]n"),  // Mark on the 2nd e, tone on the j
+        ("hoaf", "hoa", "ho[à]"),
+        ("dd", "d", "[đ]"),
+        ("ass", "[á]", "[a]s"),                // Undo rewrites the vowel too
+    ];
+    let mut explain_engine = InputMethodEngine::new(Arc::new(Mutex::new(Config::default())));
+    for (word, expected_before, expected_last) in &explain_tests {
+        let steps = explain::explain(&mut explain_engine, word);
+        let lines: Vec<String> = steps.iter().map(explain::Step::highlighted).collect();
+        let last = lines.last().cloned().unwrap_or_default();
+        let ok = last == *expected_last && lines.iter().any(|line| line == expected_before);
+        let status = if ok { "✅" } else { "❌" };
+        println!("   {} '{}' → {} (expected: '{}' then '{}')", status, word, lines.join(" "), expected_before, expected_last);
+    }
+
+    // Rapid mode toggles collapse into one notification showing the last state
+    println!("\n🔔 Notification Throttle Tests:");
+    {
+        let start = std::time::Instant::now();
+        let step = gui::COALESCE_WINDOW / 5;
+        let mut throttle = gui::NotificationThrottle::new();
+        throttle.submit("Vietnamese".to_string(), start);
+        throttle.submit("English".to_string(), start + step);
+        let last_due = throttle.submit("Vietnamese".to_string(), start + step * 2);
+
+        let early = throttle.take_due(start + gui::COALESCE_WINDOW);
+        let status = if early.is_none() { "✅" } else { "❌" };
+        println!("   {} nothing shown while toggles keep coming: {:?}", status, early);
+
+        let shown = throttle.take_due(last_due);
+        let again = throttle.take_due(last_due + gui::COALESCE_WINDOW);
+        let ok = shown.as_deref() == Some("Vietnamese") && again.is_none();
+        let status = if ok { "✅" } else { "❌" };
+        println!("   {} 3 toggles → one notification {:?}, then {:?}", status, shown, again);
+    }
+
+    // toggle_feedback: parsed from the config, and what a toggle then gives
+    println!("\n🔊 Toggle Feedback Tests:");
+    for (line, expected) in [
+        ("", Some(FeedbackKind::None)),
+        ("toggle_feedback = \"Sound\"\n", Some(FeedbackKind::Sound)),
+        ("toggle_feedback = \"Notification\"\n", Some(FeedbackKind::Notification)),
+        ("toggle_feedback = \"Beep\"\n", None),
+    ] {
+        let toml = format!("{}{}", line, Config::default_toml()?.replace("toggle_feedback = \"None\"\n", ""));
+        let parsed = Config::from_toml(&toml).ok().map(|(config, _)| config.toggle_feedback);
+        let status = if parsed == expected { "✅" } else { "❌" };
+        println!("   {} {:?} → {:?}", status, line.trim(), parsed);
+    }
+    for (kind, show_notifications, expected) in [
+        (FeedbackKind::None, true, FeedbackKind::None),
+        (FeedbackKind::Sound, true, FeedbackKind::Sound),
+        (FeedbackKind::Sound, false, FeedbackKind::Sound),
+        (FeedbackKind::Notification, true, FeedbackKind::Notification),
+        (FeedbackKind::Notification, false, FeedbackKind::None),
+    ] {
+        let mut feedback = GuiManager::new();
+        feedback.set_show_notifications(show_notifications);
+        feedback.set_toggle_feedback(kind);
+        let given = feedback.feedback_for_toggle();
+        let status = if given == expected { "✅" } else { "❌" };
+        println!("   {} {:?} with show_notifications = {} → {:?}", status, kind, show_notifications, given);
+    }
+    let sounds = (gui::sound::sound_for(true), gui::sound::sound_for(false));
+    let status = if sounds.0 != sounds.1 { "✅" } else { "❌" };
+    println!("   {} a different sound for each mode: {:?}", status, sounds);
+
+    // The settings page shows the config it was opened with
+    println!("\n🖼️  Settings Page Tests:");
+    let custom = Config {
+        input_method: config::InputMethodType::Vni,
+        hotkeys: config::HotkeyConfig {
+            toggle_vietnamese: "Ctrl+Space".to_string(),
+            ..Config::default().hotkeys
+        },
+        auto_start: true,
+        show_status_bar: false,
+        show_notifications: false,
+        ..Config::default()
+    };
+    for (config, expected, unexpected) in [
+        (
+            Config::default(),
+            vec![r#"value="telex" selected"#, r#"value="Ctrl+Shift""#, r#"value="Ctrl+Alt+V""#, r#"id="show-status-bar" checked"#],
+            vec![r#"value="vni" selected"#, r#"id="auto-start" checked"#],
+        ),
+        (
+            custom,
+            vec![r#"value="vni" selected"#, r#"value="Ctrl+Space""#, r#"id="auto-start" checked"#],
+            vec![r#"value="telex" selected"#, r#"id="show-status-bar" checked"#, r#"id="show-notifications" checked"#],
+        ),
+    ] {
+        let html = gui::settings_html(&config);
+        let missing: Vec<_> = expected.iter().filter(|part| !html.contains(*part)).collect();
+        let present: Vec<_> = unexpected.iter().filter(|part| html.contains(*part)).collect();
+        let ok = missing.is_empty() && present.is_empty() && !html.contains("{{");
+        let status = if ok { "✅" } else { "❌" };
+        println!("   {} {:?} → missing {:?}, unexpected {:?}", status, config.input_method, missing, present);
+    }
+
+    // --dump-keys: one line per key event, with what the key gives the
+    // engine; mouse movement is left out
+    println!("\n🔎 Key Dump Tests:");
+    {
+        use rdev::{Event, EventType, Key};
+        use std::time::{Duration, SystemTime};
+        let event = |event_type, name: Option<&str>| Event { time: SystemTime::now(), name: name.map(String::from), event_type };
+        for (event, layout, expected) in [
+            (event(EventType::KeyPress(Key::KeyS), Some("s")), config::KeyboardLayout::Qwerty, Some(vec!["press", "KeyS", "typed 's'", "key_to_char 's'"])),
+            (event(EventType::KeyPress(Key::KeyS), None), config::KeyboardLayout::Dvorak, Some(vec!["typed none", "key_to_char 'o'"])),
+            (event(EventType::KeyPress(Key::F1), None), config::KeyboardLayout::Qwerty, Some(vec!["F1", "key_to_char None"])),
+            (event(EventType::KeyRelease(Key::ShiftLeft), None), config::KeyboardLayout::Qwerty, Some(vec!["release", "ShiftLeft (modifier)"])),
+            (event(EventType::MouseMove { x: 1.0, y: 2.0 }, None), config::KeyboardLayout::Qwerty, None),
+        ] {
+            let line = keyboard::describe_event(&event, layout, Duration::from_millis(1500));
+            let ok = match (&line, &expected) {
+                (Some(line), Some(parts)) => line.trim_start().starts_with("1.500s") && parts.iter().all(|part| line.contains(part)),
+                (None, None) => true,
+                _ => false,
+            };
+            let status = if ok { "✅" } else { "❌" };
+            println!("   {} {:?} ({:?}) → {:?}", status, event.event_type, layout, line);
+        }
+    }
+
+    // Config files written before versioning must load and upgrade cleanly
+    println!("\n⚙️  Config Migration Tests:");
+    let v0_config = r#"
+input_method = "Vni"
+auto_start = true
+show_status_bar = false
+
+[hotkeys]
+toggle_vietnamese = "Ctrl+Space"
+switch_input_method = "Ctrl+Alt+V"
+"#;
+    match Config::from_toml(v0_config) {
+        Ok((config, migrated)) => {
+            let ok = migrated
+                && config.version == config::CONFIG_VERSION
+                && matches!(config.input_method, config::InputMethodType::Vni)
+                && config.auto_start
+                && !config.show_status_bar
+                && config.hotkeys.toggle_vietnamese == "Ctrl+Space"
+                && config.show_notifications
+                && !config.engine.tone_at_end;
+            let status = if ok { "✅" } else { "❌" };
+            println!("   {} v0 file (no version) → version {}, migrated: {}, settings kept", status, config.version, migrated);
+        }
+        Err(e) => println!("   ❌ v0 file failed to load: {}", e),
+    }
+
+    let current = toml::to_string_pretty(&Config::default())?;
+    match Config::from_toml(&current) {
+        Ok((config, migrated)) => {
+            let status = if !migrated && config.version == config::CONFIG_VERSION { "✅" } else { "❌" };
+            println!("   {} current file loads without migration (migrated: {})", status, migrated);
+        }
+        Err(e) => println!("   ❌ current file failed to load: {}", e),
+    }
+
+    let future = current.replacen(
+        &format!("version = {}", config::CONFIG_VERSION),
+        &format!("version = {}", config::CONFIG_VERSION + 1),
+        1,
+    );
+    match Config::from_toml(&future) {
+        Ok(_) => println!("   ❌ newer config version was accepted"),
+        Err(e @ VaixError::UnsupportedVersion { .. }) => println!("   ✅ newer config version rejected: {}", e),
+        Err(e) => println!("   ❌ newer config version rejected with the wrong error: {:?}", e),
+    }
+
+    match Config::from_toml("input_method = ") {
+        Ok(_) => println!("   ❌ malformed file was accepted"),
+        Err(e @ VaixError::ConfigParse(_)) => println!("   ✅ malformed file rejected as a parse error: {}", e.to_string().lines().next().unwrap_or("")),
+        Err(e) => println!("   ❌ malformed file rejected with the wrong error: {:?}", e),
+    }
+
+    // Hotkeys must parse and must not collide with each other
+    println!("\n⌨️  Hotkey Validation Tests:");
+    let hotkey_tests = vec![
+        ("Ctrl+Shift", "Ctrl+Alt+V", Ok(0), "defaults"),
+        ("ctrl+shift", "Shift+Control", Err("both"), "same combo, different spelling"),
+        ("Ctrl+Alt+V", "Ctrl+Alt+V", Err("both"), "identical combos"),
+        ("Ctrl+Shift", "Ctrl+Alt+Foo", Err("unknown key"), "unknown key"),
+        ("Shift", "Ctrl+Alt+V", Err("second modifier"), "lone modifier"),
+        ("Ctrl+Shift", "V", Err("at least one"), "no modifier"),
+        ("Ctrl+Ctrl+V", "Ctrl+Alt+V", Err("repeats"), "repeated modifier"),
+        ("Ctrl+A+B", "Ctrl+Alt+V", Err("more than one key"), "two keys"),
+        ("Cmd+Space", "Ctrl+Alt+V", Ok(1), "Spotlight conflict warns"),
+    ];
+
+    for (toggle, switch, expected, description) in &hotkey_tests {
+        let mut config = Config::default();
+        config.hotkeys.toggle_vietnamese = toggle.to_string();
+        config.hotkeys.switch_input_method = switch.to_string();
+        let result = config.validate_hotkeys();
+        let ok = match (&result, expected) {
+            (Ok(warnings), Ok(count)) => warnings.len() == *count,
+            (Err(e), Err(fragment)) => e.contains(fragment),
+            _ => false,
+        };
+        let status = if ok { "✅" } else { "❌" };
+        let outcome = match &result {
+            Ok(warnings) if warnings.is_empty() => "ok".to_string(),
+            Ok(warnings) => format!("warning: {}", warnings.join("; ")),
+            Err(e) => format!("error: {}", e),
+        };
+        println!("   {} '{}' / '{}' → {} ({})", status, toggle, switch, outcome, description);
+    }
+
+    let mut config = Config::default();
+    config.hotkeys.dump_buffer = Some("Alt+Ctrl+V".to_string());
+    let result = config.validate_hotkeys();
+    let ok = result.as_ref().is_err_and(|e| e.contains("switch_input_method and dump_buffer"));
+    let status = if ok { "✅" } else { "❌" };
+    println!("   {} dump_buffer 'Alt+Ctrl+V' → {:?} (collides with switch_input_method)", status, result);
+
+    // VAIXKEY_CONFIG points load/save at another file
+    println!("\n⌨️  Input Method Name Tests:");
+    let method_name_tests = vec![
+        ("telex", Ok(config::InputMethodType::Telex)),
+        ("VNI", Ok(config::InputMethodType::Vni)),
+        ("SimpleTelex", Ok(config::InputMethodType::SimpleTelex)),
+        ("simple-telex", Ok(config::InputMethodType::SimpleTelex)),
+        ("viqr", Err("valid methods: Telex, Vni, SimpleTelex")),
+        ("", Err("unknown input method ''")),
+    ];
+    for (name, expected) in &method_name_tests {
+        let result = name.parse::<config::InputMethodType>();
+        let ok = match (&result, expected) {
+            (Ok(method), Ok(expected)) => method == expected,
+            (Err(e), Err(fragment)) => e.contains(fragment),
+            _ => false,
+        };
+        let status = if ok { "✅" } else { "❌" };
+        println!("   {} '{}' → {:?}", status, name, result);
+    }
+
+    println!("\n📁 Config Path Override Test:");
+    let override_path = std::env::temp_dir()
+        .join(format!("vaixkey-test-{}", std::process::id()))
+        .join("config.toml");
+    let previous = std::env::var_os(config::CONFIG_ENV_VAR);
+    std::env::set_var(config::CONFIG_ENV_VAR, &override_path);
+    let resolved = Config::config_path()?;
+    let loaded = Config::load();
+    let created = override_path.exists();
+    match previous {
+        Some(value) => std::env::set_var(config::CONFIG_ENV_VAR, value),
+        None => std::env::remove_var(config::CONFIG_ENV_VAR),
+    }
+    if let Some(dir) = override_path.parent() {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+    let status = if resolved == override_path && loaded.is_ok() && created { "✅" } else { "❌" };
+    println!("   {} {}={} → loaded: {}, file created: {}",
+        status, config::CONFIG_ENV_VAR, resolved.display(), loaded.is_ok(), created);
+
+    // --print-default-config must print a file that loads back unchanged,
+    // and --reset-config must repair one that doesn't load
+    println!("\n📄 Default Config Tests:");
+    let printed = Config::default_toml()?;
+    match toml::from_str::<Config>(&printed) {
+        Ok(parsed) => {
+            let reprinted = toml::to_string_pretty(&parsed)?;
+            let status = if reprinted == printed { "✅" } else { "❌" };
+            println!("   {} printed default round-trips through toml::from_str ({} lines)", status, printed.lines().count());
+        }
+        Err(e) => println!("   ❌ printed default doesn't parse: {}", e),
+    }
+    let reset_path = std::env::temp_dir()
+        .join(format!("vaixkey-reset-{}", std::process::id()))
+        .join("config.toml");
+    let previous = std::env::var_os(config::CONFIG_ENV_VAR);
+    std::env::set_var(config::CONFIG_ENV_VAR, &reset_path);
+    let result = (|| -> Result<(bool, bool), Box<dyn std::error::Error>> {
+        std::fs::create_dir_all(reset_path.parent().ok_or("no parent")?)?;
+        std::fs::write(&reset_path, "input_method = ")?;
+        let broken = Config::load().is_err();
+        Config::reset()?;
+        Ok((broken, Config::load().is_ok()))
+    })();
+    match previous {
+        Some(value) => std::env::set_var(config::CONFIG_ENV_VAR, value),
+        None => std::env::remove_var(config::CONFIG_ENV_VAR),
+    }
+    if let Some(dir) = reset_path.parent() {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+    match result {
+        Ok((broken, loads)) => {
+            let status = if broken && loads { "✅" } else { "❌" };
+            println!("   {} corrupted config failed to load: {}, loads after reset: {}", status, broken, loads);
+        }
+        Err(e) => println!("   ❌ reset failed: {}", e),
+    }
+
+    // cache.bin serves the config only while the file it came from is
+    // unchanged
+    println!("\n🗃️  Config Cache Tests:");
+    let cache_dir = std::env::temp_dir().join(format!("vaixkey-cache-{}", std::process::id()));
+    let result = (|| -> Result<Vec<(&str, bool)>, Box<dyn std::error::Error>> {
+        std::fs::create_dir_all(&cache_dir)?;
+        let config_path = cache_dir.join("config.toml");
+        let cache_path = cache_dir.join(cache::CACHE_FILE);
+        let mut cached = Config { input_method: config::InputMethodType::Vni, ..Config::default() };
+        cached.hotkeys.dump_buffer = Some("Cmd+Shift+D".to_string());
+        std::fs::write(&config_path, toml::to_string_pretty(&cached)?)?;
+        let (modified, len) = cache::stamp(&config_path).ok_or("no stamp")?;
+        cache::write(&cache_path, &config_path, modified, len, &cached)?;
+
+        let hit = cache::read(&cache_path, &config_path);
+        let round_trip = hit.as_ref().map(toml::to_string_pretty).transpose()? == Some(toml::to_string_pretty(&cached)?);
+        let other_path = cache::read(&cache_path, &cache_dir.join("other.toml")).is_none();
+
+        // Same length, a later modification time
+        std::fs::write(&config_path, toml::to_string_pretty(&cached)?)?;
+        std::fs::File::options().write(true).open(&config_path)?
+            .set_modified(modified + std::time::Duration::from_secs(2))?;
+        let touched = cache::read(&cache_path, &config_path).is_none();
+
+        cache::write(&cache_path, &config_path, modified + std::time::Duration::from_secs(2), len, &cached)?;
+        let rewritten = cache::read(&cache_path, &config_path).is_some();
+        std::fs::write(&cache_path, b"not a cache")?;
+        let corrupt = cache::read(&cache_path, &config_path).is_none();
+        Ok(vec![
+            ("unchanged file is served from the cache", round_trip),
+            ("another config path misses", other_path),
+            ("newer modification time misses", touched),
+            ("rewritten cache hits again", rewritten),
+            ("corrupt cache misses", corrupt),
+        ])
+    })();
+    let _ = std::fs::remove_dir_all(&cache_dir);
+    match result {
+        Ok(checks) => {
+            for (name, ok) in checks {
+                let status = if ok { "✅" } else { "❌" };
+                println!("   {} {}", status, name);
+            }
+        }
+        Err(e) => println!("   ❌ cache test failed: {}", e),
+    }
+
+    // --uninstall against a throwaway HOME: removes the config directory and
+    // is a no-op the second time
+    println!("\n🧹 Uninstall Test:");
+    let temp_home = std::env::temp_dir().join(format!("vaixkey-home-{}", std::process::id()));
+    let previous_home = std::env::var_os("HOME");
+    std::env::set_var("HOME", &temp_home);
+    let result = (|| -> Result<(bool, usize, usize), Box<dyn std::error::Error>> {
+        let config_dir = Config::config_dir()?;
+        std::fs::create_dir_all(config_dir.join("profiles"))?;
+        std::fs::write(config_dir.join("config.toml"), "version = 1\n")?;
+        // Only what lives under the temp HOME; the /tmp paths are real
+        let first = uninstall::remove(&uninstall::home_targets()?)?;
+        let second = uninstall::remove(&uninstall::home_targets()?)?;
+        Ok((config_dir.exists(), first.len(), second.len()))
+    })();
+    match previous_home {
+        Some(home) => std::env::set_var("HOME", home),
+        None => std::env::remove_var("HOME"),
+    }
+    let _ = std::fs::remove_dir_all(&temp_home);
+    match result {
+        Ok((still_there, first, second)) => {
+            let status = if !still_there && first >= 1 && second == 0 { "✅" } else { "❌" };
+            println!("   {} config dir removed: {}, removed {} then {} items",
+                status, !still_there, first, second);
+        }
+        Err(e) => println!("   ❌ uninstall failed: {}", e),
+    }
+
+    // --repl's key mapping and session, without a terminal
+    println!("\n🖥️  REPL Tests:");
+    for (key, expected) in [
+        ('\x03', Some(repl::Action::Quit)),
+        ('\x14', Some(repl::Action::SwitchMethod)),
+        ('\x05', Some(repl::Action::ToggleVietnamese)),
+        ('\x7f', Some(repl::Action::Key('\x08'))),
+        ('\r', Some(repl::Action::Key('\n'))),
+        ('\x01', None),
+        ('a', Some(repl::Action::Key('a'))),
+    ] {
+        let result = repl::action(key);
+        let status = if result == expected { "✅" } else { "❌" };
+        println!("   {} {:?} → {:?}", status, key, result);
+    }
+    let mut repl_engine = InputMethodEngine::new(Arc::new(Mutex::new(Config::default())));
+    let mut session = repl::Session::new(&mut repl_engine);
+    for c in "vieetj".chars() {
+        session.apply(repl::Action::Key(c));
+    }
+    let status = if session.line() == "việt" { "✅" } else { "❌" };
+    println!("   {} typed 'vieetj' → '{}' | {}", status, session.line(), session.status_line());
+    session.apply(repl::Action::Key('\n'));
+    session.apply(repl::Action::SwitchMethod);
+    for c in "ba2".chars() {
+        session.apply(repl::Action::Key(c));
+    }
+    let status = if session.line() == "bà" { "✅" } else { "❌" };
+    println!("   {} Ctrl+T, typed 'ba2' → '{}' | {}", status, session.line(), session.status_line());
+    let status = if !session.apply(repl::Action::Quit) { "✅" } else { "❌" };
+    println!("   {} Ctrl+C ends the session", status);
+
+    // --doctor's file checks and exit code, without touching the real setup
+    println!("\n🩺 Doctor Tests:");
+    let doctor_dir = std::env::temp_dir().join(format!("vaixkey-doctor-{}", std::process::id()));
+    let doctor_config = doctor_dir.join("config.toml");
+    let missing = doctor::check_config(&doctor_config);
+    let writable = doctor::check_writable(&doctor_dir);
+    let _ = std::fs::write(&doctor_config, "version = 1\n");
+    let valid = doctor::check_config(&doctor_config);
+    let _ = std::fs::write(&doctor_config, "engine = [\n");
+    let broken = doctor::check_config(&doctor_config);
+    let _ = std::fs::remove_dir_all(&doctor_dir);
+    for (description, check, expected) in [
+        ("missing config", &missing, true),
+        ("writable dir", &writable, true),
+        ("valid config", &valid, true),
+        ("broken config", &broken, false),
+    ] {
+        let status = if check.passed == expected { "✅" } else { "❌" };
+        println!("   {} {} → passed: {} ({})", status, description, check.passed, check.detail);
+    }
+    let warning_only = vec![doctor::Check { critical: false, ..broken.clone() }];
+    for (description, checks, expected) in [
+        ("all passed", vec![valid.clone()], 0),
+        ("critical failure", vec![valid, broken], 1),
+        ("non-critical failure", warning_only, 0),
+    ] {
+        let code = doctor::exit_code(&checks);
+        let status = if code == expected { "✅" } else { "❌" };
+        println!("   {} {} → exit {}", status, description, code);
+    }
+
+    // The permission check falls back to the grab probe when the trust API
+    // says no or is missing, and skips it when trusted
+    println!("\n🔐 Permission Fallback Tests:");
+    for (description, trusted, probe, expected, probed) in [
+        ("trusted", Some(true), false, true, false),
+        ("not trusted, grab works", Some(false), true, true, true),
+        ("not trusted, grab fails", Some(false), false, false, true),
+        ("no API, grab works", None, true, true, true),
+        ("no API, grab fails", None, false, false, true),
+    ] {
+        let called = std::cell::Cell::new(false);
+        let granted = accessibility_from_signals(trusted, || {
+            called.set(true);
+            probe
+        });
+        let status = if granted == expected && called.get() == probed { "✅" } else { "❌" };
+        println!("   {} {} → granted: {}, probed: {}", status, description, granted, called.get());
+    }
+
+    // Messages resolve in both languages; every English key has a
+    // Vietnamese message, and --lang takes en or vi
+    println!("\n🌐 Localization Tests:");
+    for (language, expected) in [
+        (Language::En, "🇻🇳 VaixKey Status Report"),
+        (Language::Vi, "🇻🇳 Báo cáo trạng thái VaixKey"),
+    ] {
+        let result = i18n::lookup(language, "status.title");
+        let status = if result == expected { "✅" } else { "❌" };
+        println!("   {} status.title ({:?}) → '{}' (expected: '{}')", status, language, result, expected);
+    }
+    let untranslated: Vec<&str> = i18n::messages(Language::En)
+        .iter()
+        .map(|(key, _)| *key)
+        .filter(|key| !i18n::messages(Language::Vi).iter().any(|(k, _)| k == key))
+        .collect();
+    let status = if untranslated.is_empty() { "✅" } else { "❌" };
+    println!("   {} every message has a Vietnamese translation (missing: {:?})", status, untranslated);
+    let unknown = i18n::lookup(Language::Vi, "no.such.key");
+    let status = if unknown == "no.such.key" { "✅" } else { "❌" };
+    println!("   {} an unknown key shows as itself → '{}'", status, unknown);
+    let parsed = ["vi", "EN", "fr"].map(|lang| lang.parse::<Language>().ok());
+    let status = if parsed == [Some(Language::Vi), Some(Language::En), None] { "✅" } else { "❌" };
+    println!("   {} --lang vi, EN, fr → {:?}", status, parsed);
+
+    println!("\n�🔄 Mode Toggle Test:");
+    println!("   Current mode: {}", if engine.is_vietnamese_mode() { "Vietnamese" } else { "English" });
+    engine.toggle_vietnamese_mode();
+    println!("   After toggle: {}", if engine.is_vietnamese_mode() { "Vietnamese" } else { "English" });
+
+    println!("\n✅ Test complete! VaixKey engine is working properly.");
+    println!("   Note: This tests the processing engine only.");
+    println!("   Keyboard capture is not yet implemented.");
+
+    Ok(())
+}
+
+fn run_fuzz_mode(iterations: usize) -> Result<(), Box<dyn std::error::Error>> {
+    println!("🎲 VaixKey Fuzz Mode");
+    println!("===================");
+
+    // Known tricky sequences first, so regressions are reported by name
+    println!("🌱 Seed corpus ({} inputs):", harness::SEED_CORPUS.len());
+    let mut failures = 0;
+    for input in harness::SEED_CORPUS {
+        if let Err(e) = harness::check_one(input.as_bytes()) {
+            println!("   ❌ {:?}: {}", input, e);
+            failures += 1;
+        }
+    }
+
+    // Tone keys against empty, tiny and overflowing buffers: every pair of
+    // tone keys after each prefix, with panics counted as failures
+    let long_word = "a".repeat(45);
+    let prefixes = ["", "a", "b", "q", "gi", "ngh", " ", "\x08", "a\x08", "ab\x08\x08", "\x01", "a\x01", long_word.as_str()];
+    let tone_keys = ['s', 'f', 'r', 'x', 'j', 'S', 'J'];
+    let mut boundary_inputs = Vec::new();
+    for prefix in prefixes {
+        for first in tone_keys {
+            boundary_inputs.push(format!("{}{}", prefix, first));
+            for second in tone_keys {
+                boundary_inputs.push(format!("{}{}a{}", prefix, first, second));
+                boundary_inputs.push(format!("{}{}{}", prefix, first, second));
+            }
+        }
+    }
+    println!("🧱 Boundary inputs ({} inputs):", boundary_inputs.len());
+    for input in &boundary_inputs {
+        let outcome = std::panic::catch_unwind(|| harness::check_one(input.as_bytes()))
+            .unwrap_or_else(|_| Err("panicked".to_string()));
+        if let Err(e) = outcome {
+            println!("   ❌ {:?}: {}", input, e);
+            save_to_corpus(input.as_bytes());
+            failures += 1;
+        }
+    }
+
+    // Random keystrokes biased towards Telex-relevant keys, plus Left (\x01)
+    // and Right (\x02)
+    const ALPHABET: &[u8] = b"aeiouydwsfrjxqgnhtcmAEIOUYDWSFRJX .,9[]\x08\x01\x02";
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0x9E37_79B9_7F4A_7C15)
+        | 1;
+    let mut state = seed;
+    let mut next = move || {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    println!("🔀 Random inputs ({} iterations, seed {:#x}):", iterations, seed);
+    for _ in 0..iterations {
+        let len = (next() % 32) as usize + 1;
+        let input: Vec<u8> = (0..len)
+            .map(|_| ALPHABET[(next() % ALPHABET.len() as u64) as usize])
+            .collect();
+        if let Err(e) = harness::check_one(&input) {
+            println!("   ❌ {:?}: {}", String::from_utf8_lossy(&input), e);
+            save_to_corpus(&input);
+            failures += 1;
+        }
+    }
+
+    if failures > 0 {
+        // Non-zero exit so CI notices
+        return Err(format!("{} failing inputs", failures).into());
+    }
+    println!("\n✅ No desyncs found.");
+
+    Ok(())
+}
+
+/// Keep a failing fuzz input in tests/corpus, which the regression_corpus
+/// test replays, when run from the repository
+fn save_to_corpus(input: &[u8]) {
+    use std::hash::{Hash, Hasher};
+
+    let corpus = std::path::Path::new("tests/corpus");
+    if !corpus.is_dir() {
+        return;
+    }
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    input.hash(&mut hasher);
+    let path = corpus.join(format!("fuzz-{:016x}", hasher.finish()));
+    match std::fs::write(&path, input) {
+        Ok(()) => println!("      saved as {}", path.display()),
+        Err(e) => println!("      could not save {}: {}", path.display(), e),
+    }
+}
+
+fn list_profiles() -> Result<(), Box<dyn std::error::Error>> {
+    let current = Config::current_profile()?;
+    let profiles = Config::list_profiles()?;
+
+    println!("📂 Config Profiles:");
+    let marker = |active: bool| if active { "*" } else { " " };
+    println!("   {} default", marker(current.is_none()));
+    for name in &profiles {
+        println!("   {} {}", marker(current.as_deref() == Some(name.as_str())), name);
+    }
+    if let Some(name) = current.filter(|name| !profiles.contains(name)) {
+        println!("   * {} (not created yet)", name);
+    }
+    Ok(())
+}
+
+/// `--reset-config`: overwrite the config file with the defaults, after
+/// asking unless `assume_yes`
+fn reset_config(assume_yes: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let path = Config::config_path()?;
+    if path.exists() {
+        println!("This will overwrite {} with the default config", path.display());
+        if !assume_yes && !uninstall::confirm("Continue? [y/N] ")? {
+            println!("❌ Cancelled, the config was not changed");
+            return Ok(());
+        }
+    }
+    let path = Config::reset()?;
+    println!("✅ Wrote the default config to {}", path.display());
+    Ok(())
+}
+
+fn list_methods(config: &Config) {
+    println!("⌨️  Input Methods:");
+    for method in config::InputMethodType::ALL {
+        let marker = if method == config.input_method { "*" } else { " " };
+        println!("   {} {:<12} {}", marker, format!("{:?}", method), method.description());
+    }
+}
+
+/// `--set-method <name>`: validate the name and save it to the config
+/// Save the method to the config and, with `--rpc-port`, switch the
+/// instance serving on that port too
+fn set_method(config: &Arc<Mutex<Config>>, name: &str, rpc_port: Option<u16>) -> Result<(), Box<dyn std::error::Error>> {
+    let method: config::InputMethodType = name.parse()?;
+    let mut config = lock(config);
+    config.input_method = method;
+    config.save()?;
+    println!("✅ Input method set to {:?} in {}", method, Config::config_path()?.display());
+    match rpc_port {
+        Some(port) => match rpc::request(port, "set_method", serde_json::json!({ "method": name })) {
+            Ok(_) => println!("   The running VaixKey on port {} switched too", port),
+            Err(e) => println!("⚠️  Could not switch the running VaixKey: {}", e),
+        },
+        None => println!("   Restart VaixKey if it is running, or pass the --rpc-port it serves on, to pick up the change"),
+    }
+    Ok(())
+}
+
+/// Type `input` into a standalone engine and return the text that would be on
+/// screen after applying every result, the way the keyboard layer does.
+/// `←` and `→` stand for the arrow keys.
+fn type_on_screen(engine: &mut UnikeyEngine, input: &str) -> String {
+    let mut screen = harness::ScreenModel::new();
+    for ch in input.chars() {
+        match ch {
+            '←' => {
+                screen.move_caret_left();
+                if !engine.cursor_left() {
+                    engine.clear_buf();
+                }
+            }
+            '→' => {
+                screen.move_caret_right();
+                if !engine.cursor_right() {
+                    engine.clear_buf();
+                }
+            }
+            _ => {
+                let _ = screen.apply(&engine.process(ch));
+            }
+        }
+    }
+    screen.text()
+}
+
+/// Type `input` into an InputMethodEngine built from `config` and return
+/// the text on screen
+fn type_with_config(config: Config, input: &str) -> String {
+    let mut engine = InputMethodEngine::new(Arc::new(Mutex::new(config)));
+    let mut screen = harness::ScreenModel::new();
+    for ch in input.chars() {
+        let _ = screen.apply(&engine.process_keypress(ch));
+    }
+    screen.text()
+}
+
+/// What `calls` do to the screen. A paste types the text the clipboard was
+/// last set to among them.
+fn apply_backend_calls(screen: &mut harness::ScreenModel, calls: &[BackendCall]) {
+    let mut clipboard = String::new();
+    for call in calls {
+        let _ = match call {
+            BackendCall::Backspaces(n) => screen.apply(&ProcessResult::Replace { backspaces: *n, text: String::new() }),
+            BackendCall::Text(text) => screen.apply(&ProcessResult::Output(text.clone())),
+            BackendCall::Clipboard(text) => {
+                clipboard = text.clone();
+                Ok(())
+            }
+            BackendCall::Paste => screen.apply(&ProcessResult::Output(clipboard.clone())),
+            // What the app shows once its dead keys have composed the text
+            BackendCall::Keystrokes(text) => screen.apply(&ProcessResult::Output(text.clone())),
+        };
+    }
+}
+
+/// Type `input` through a KeyboardMonitor wired to a RecordingBackend and
+/// return the resulting screen text together with every backend call
+fn type_through_monitor(input: &str, tone_at_end: bool) -> (String, Vec<BackendCall>) {
+    let mut config = Config::default();
+    config.engine.tone_at_end = tone_at_end;
+    type_through_monitor_with_config(config, input)
+}
+
+/// `type_through_monitor` with any config
+fn type_through_monitor_with_config(config: Config, input: &str) -> (String, Vec<BackendCall>) {
+    type_through_monitor_with_backend(config, Arc::new(RecordingBackend::new()), input)
+}
+
+/// `type_through_monitor_with_config` on a backend set up by the caller,
+/// e.g. with text on its clipboard
+fn type_through_monitor_with_backend(
+    config: Config,
+    backend: Arc<RecordingBackend>,
+    input: &str,
+) -> (String, Vec<BackendCall>) {
+    let engine = Arc::new(Mutex::new(InputMethodEngine::new(Arc::new(Mutex::new(config)))));
+    let monitor = KeyboardMonitor::with_backend(engine, backend.clone());
+
+    let mut screen = harness::ScreenModel::new();
+    for ch in input.chars() {
+        // Form feed stands in for the focus observer firing
+        if ch == '\x0c' {
+            monitor.notify_focus_changed();
+            continue;
+        }
+
+        // The arrows move the caret; the app does that, not the backend
+        if ch == '←' || ch == '→' {
+            let left = ch == '←';
+            monitor.dispatch_key(if left { rdev::Key::LeftArrow } else { rdev::Key::RightArrow }, None);
+            if left { screen.move_caret_left() } else { screen.move_caret_right() }
+            continue;
+        }
+
+        // Characters without a key of their own (â, ư...) arrive the way a
+        // Vietnamese layout or dead key delivers them: only the character
+        let key = if ch == '\x08' {
+            rdev::Key::Backspace
+        } else {
+            keyboard::char_to_key(ch).unwrap_or(rdev::Key::Unknown(0))
+        };
+        let typed = (!ch.is_control()).then_some(ch);
+
+        let before = backend.calls().len();
+        match monitor.dispatch_key(key, typed) {
+            // The original key reaches the application unchanged
+            GrabAction::PassThrough => {
+                let _ = screen.apply(&ProcessResult::PassThrough(ch));
+            }
+            GrabAction::Block => {}
+            GrabAction::BlockAndInject { .. } => apply_backend_calls(&mut screen, &backend.calls()[before..]),
+        }
+    }
+
+    // Ends the session, so a fallback to key presses doesn't carry over
+    monitor.stop();
+    (screen.text(), backend.calls())
+}
+
+fn run_debug_mode(
+    engine: Arc<Mutex<InputMethodEngine>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("🔍 VaixKey Debug Mode");
+    println!("====================");
+    println!("📝 Comprehensive keystroke capture and processing logging");
+    println!("");
+    println!("🎯 What you'll see:");
+    println!("   🔴 KEY PRESS events with timestamps");
+    println!("   🔵 KEY RELEASE events");
+    println!("   📊 Engine state (Vietnamese mode, buffer contents)");
+    println!("   🔤 Character processing details");
+    println!("   ✨ Vietnamese transformation results");
+    println!("   📋 Complete keystroke information");
+    println!("");
+    println!("⌨️  Start typing to see real-time keystroke capture...");
+    println!("   (Press Ctrl+C to exit)");
+    println!("");
+
+    // Create keyboard monitor in debug mode
+    let mut keyboard_monitor = KeyboardMonitor::new_with_debug(engine.clone());
+    let log_path = std::path::Path::new(replay::SESSION_LOG_PATH);
+    match replay::Recorder::create(log_path) {
+        Ok(recorder) => {
+            keyboard_monitor.set_recorder(recorder);
+            println!("📼 Recording keystrokes to {} (replay with --replay)", log_path.display());
+            println!();
+        }
+        Err(e) => println!("⚠️  Cannot record keystrokes to {}: {}", log_path.display(), e),
+    }
+
+    // Start monitoring
+    keyboard_monitor.start()?;
+
+    Ok(())
+}
+
+fn test_vietnamese_processing(engine: &InputMethodEngine) {
+    // This is a simple test of the Vietnamese engine
+    // We just show that the engine exists and is configured
+    println!("   {}: ✅", t!("status.engine_initialized"));
+    println!("   {}: {}", t!("status.vietnamese_mode"), if engine.is_vietnamese_mode() { "✅" } else { "❌" });
+}
+
+async fn check_permissions() -> Result<(), Box<dyn std::error::Error>> {
+    println!("{}", t!("check.title"));
+    println!("============================");
+
+    // For now, show what permissions we would check
+    // In a real implementation, this would use macOS APIs to check actual permissions
+    println!("{}", t!("check.checking"));
+    println!("");
+
+    // Simulate permission checking (in real implementation, use CGPreflightScreenCaptureAccess, etc.)
+    let input_monitoring = check_input_monitoring_permission().await;
+    let accessibility = check_accessibility_permission().await;
+
+    println!("🔍 {}: {}", t!("perm.input_monitoring"),
+        if input_monitoring { t!("perm.granted") } else { t!("check.denied_capture") });
+    println!("🔧 {}: {}", t!("perm.accessibility"),
+        if accessibility { t!("perm.granted") } else { t!("check.denied_injection") });
+
+    println!("");
+
+    if input_monitoring && accessibility {
+        println!("{}", t!("check.ready"));
+        println!("   {}", t!("check.hint_debug"));
+    } else {
+        println!("{}", t!("check.cannot_function"));
+        println!("   {}", t!("check.hint_setup"));
+    }
+
+    Ok(())
+}
+
+async fn show_security_status() -> Result<(), Box<dyn std::error::Error>> {
+    println!("{}", t!("security.title"));
+    println!("=================================");
+
+    // System information
+    println!("{}", t!("security.system"));
+    let output = std::process::Command::new("sw_vers").output()?;
+    if output.status.success() {
+        let version_info = String::from_utf8_lossy(&output.stdout);
+        for line in version_info.lines() {
+            println!("   {}", line);
+        }
+    }
+
+    println!("");
+
+    // Permission status
+    println!("{}", t!("security.permissions"));
+    let input_monitoring = check_input_monitoring_permission().await;
     let accessibility = check_accessibility_permission().await;
 
-    println!("   Input Monitoring: {}",
-        if input_monitoring { "✅ GRANTED" } else { "❌ DENIED" });
-    println!("   Accessibility: {}",
-        if accessibility { "✅ GRANTED" } else { "❌ DENIED" });
+    println!("   {}: {}", t!("perm.input_monitoring"),
+        if input_monitoring { t!("security.granted") } else { t!("security.denied") });
+    println!("   {}: {}", t!("perm.accessibility"),
+        if accessibility { t!("security.granted") } else { t!("security.denied") });
 
     println!("");
 
     // Current application info
-    println!("📱 Application Status:");
-    println!("   Running as: {}",
+    println!("{}", t!("security.application"));
+    println!("   {}: {}", t!("security.running_as"),
         std::env::current_exe()
-            .unwrap_or_else(|_| "Unknown".into())
+            .unwrap_or_else(|_| t!("security.unknown").into())
             .display()
     );
-    println!("   Process ID: {}", std::process::id());
+    println!("   {}: {}", t!("security.process_id"), std::process::id());
 
     println!("");
 
     // Next steps
     if !input_monitoring || !accessibility {
-        println!("📋 Required Actions:");
+        println!("{}", t!("security.required_actions"));
         if !input_monitoring {
-            println!("   • Grant Input Monitoring permission in System Preferences");
+            println!("   {}", t!("security.grant_input_monitoring"));
         }
         if !accessibility {
-            println!("   • Grant Accessibility permission in System Preferences");
+            println!("   {}", t!("security.grant_accessibility"));
         }
-        println!("   • Run `cargo run -- --setup-permissions` for detailed instructions");
+        println!("   {}", t!("security.hint_setup"));
     } else {
-        println!("🎉 All permissions granted - VaixKey is ready!");
+        println!("{}", t!("security.all_granted"));
     }
 
     Ok(())
 }
 
 async fn setup_permissions_guide() -> Result<(), Box<dyn std::error::Error>> {
-    println!("🔧 VaixKey Permission Setup Guide");
+    println!("{}", t!("setup.title"));
     println!("==================================");
     println!("");
 
@@ -432,32 +3258,29 @@ async fn setup_permissions_guide() -> Result<(), Box<dyn std::error::Error>> {
     let accessibility = check_accessibility_permission().await;
     let input_monitoring = check_input_monitoring_permission().await;
 
-    println!("📊 Current Permission Status:");
-    println!("   🔍 Input Monitoring: {}", if input_monitoring { "✅ Granted" } else { "❌ Not granted" });
-    println!("   🔧 Accessibility: {}", if accessibility { "✅ Granted" } else { "❌ Not granted" });
+    let granted = |yes: bool| if yes { t!("perm.granted") } else { t!("perm.not_granted") };
+    println!("{}", t!("setup.current"));
+    println!("   🔍 {}: {}", t!("perm.input_monitoring"), granted(input_monitoring));
+    println!("   🔧 {}: {}", t!("perm.accessibility"), granted(accessibility));
     println!("");
 
     if accessibility && input_monitoring {
-        println!("🎉 All permissions are already granted!");
-        println!("   VaixKey is ready to use.");
-        println!("   Run `cargo run` to start VaixKey.");
+        println!("{}", t!("setup.all_granted"));
+        println!("   {}", t!("setup.ready"));
+        println!("   {}", t!("setup.hint_run"));
         return Ok(());
     }
 
-    println!("VaixKey needs macOS Accessibility permission to function.");
-    println!("(Both Input Monitoring and Accessibility use the same permission)");
+    println!("{}", t!("setup.needs"));
+    println!("{}", t!("setup.same_permission"));
     println!("");
 
-    println!("🚀 Quick Setup Options:");
+    println!("{}", t!("setup.options"));
     println!("");
-    println!("  1. [Recommended] Let VaixKey request permission automatically");
-    println!("  2. Open System Settings → Privacy & Security → Accessibility");
-    println!("  3. Open System Settings → Privacy & Security → Input Monitoring");
-    println!("  4. Show manual setup instructions");
-    println!("  5. Exit");
+    println!("{}", t!("setup.option_list"));
     println!("");
 
-    print!("Choose an option (1-5): ");
+    print!("{}", t!("setup.choose"));
     use std::io::{self, Write};
     io::stdout().flush()?;
 
@@ -467,118 +3290,142 @@ async fn setup_permissions_guide() -> Result<(), Box<dyn std::error::Error>> {
     match input.trim() {
         "1" => {
             println!("");
-            println!("🔐 Requesting Accessibility permission...");
-            println!("   A system dialog should appear asking for permission.");
+            println!("{}", t!("perm.requesting"));
+            println!("{}", t!("setup.dialog"));
             println!("");
             
             let granted = request_accessibility_permission();
             
             if granted {
-                println!("✅ Permission granted! VaixKey is ready to use.");
+                println!("{}", t!("setup.granted"));
             } else {
-                println!("⏳ Permission request sent.");
+                println!("{}", t!("setup.request_sent"));
                 println!("");
-                println!("📋 If no dialog appeared, please:");
-                println!("   1. Open System Settings → Privacy & Security → Accessibility");
-                println!("   2. Find and enable 'Terminal' (or 'vaixkey' if running the built app)");
-                println!("   3. You may need to click the lock 🔒 icon first");
+                println!("{}", t!("setup.no_dialog"));
                 println!("");
-                println!("Opening System Settings...");
+                println!("{}", t!("setup.opening_settings"));
                 open_system_settings("accessibility");
             }
         }
         "2" => {
             println!("");
-            println!("📱 Opening System Settings → Accessibility...");
+            println!("{}", t!("perm.opening_accessibility"));
             open_system_settings("accessibility");
             println!("");
-            println!("📋 In System Settings:");
-            println!("   1. Click the lock icon 🔒 if needed");
-            println!("   2. Find 'Terminal' (or 'vaixkey') in the list");
-            println!("   3. Toggle the switch to enable it");
-            println!("   4. Restart VaixKey after granting permission");
+            println!("{}", t!("setup.in_settings"));
         }
         "3" => {
             println!("");
-            println!("📱 Opening System Settings → Input Monitoring...");
+            println!("{}", t!("perm.opening_input_monitoring"));
             open_system_settings("input_monitoring");
             println!("");
-            println!("📋 In System Settings:");
-            println!("   1. Click the lock icon 🔒 if needed");
-            println!("   2. Find 'Terminal' (or 'vaixkey') in the list");
-            println!("   3. Toggle the switch to enable it");
-            println!("   4. Restart VaixKey after granting permission");
+            println!("{}", t!("setup.in_settings"));
         }
         "4" => {
             println!("");
             print_manual_setup_instructions();
         }
         _ => {
-            println!("Exiting setup guide.");
+            println!("{}", t!("setup.exiting"));
         }
     }
 
     println!("");
-    println!("🧪 After granting permissions, verify with:");
+    println!("{}", t!("setup.verify"));
     println!("   cargo run -- --permissions");
 
     Ok(())
 }
 
 fn print_manual_setup_instructions() {
-    println!("📋 Manual Setup Instructions");
+    println!("{}", t!("manual.title"));
     println!("=============================");
     println!("");
-    println!("For macOS Ventura (13.0) and later:");
+    println!("{}", t!("manual.ventura"));
     println!("─────────────────────────────────────");
-    println!("1. Click the Apple menu (🍎) → System Settings");
-    println!("2. Click 'Privacy & Security' in the sidebar");
-    println!("3. Scroll down and click 'Accessibility'");
-    println!("4. Click the toggle next to 'Terminal' to enable it");
-    println!("   (You may need to click the lock and enter your password)");
-    println!("5. Also check 'Input Monitoring' and enable 'Terminal'");
+    println!("{}", t!("manual.ventura_steps"));
     println!("");
-    println!("For macOS Monterey (12.0) and earlier:");
+    println!("{}", t!("manual.monterey"));
     println!("────────────────────────────────────────");
-    println!("1. Click the Apple menu (🍎) → System Preferences");
-    println!("2. Click 'Security & Privacy'");
-    println!("3. Click the 'Privacy' tab");
-    println!("4. Select 'Accessibility' from the left sidebar");
-    println!("5. Click the lock icon 🔒 and enter your password");
-    println!("6. Check the box next to 'Terminal'");
-    println!("7. Select 'Input Monitoring' and check 'Terminal' there too");
+    println!("{}", t!("manual.monterey_steps"));
     println!("");
-    println!("💡 Tips:");
-    println!("   • If 'Terminal' is not listed, run VaixKey once first");
-    println!("   • If permission doesn't work, try toggling it off and on");
-    println!("   • You may need to restart Terminal after granting permission");
-    println!("   • For the built app, look for 'vaixkey' instead of 'Terminal'");
+    println!("{}", t!("manual.tips"));
 }
 
 // Helper functions for permission checking using macOS APIs
 
-/// Check if Accessibility permission is granted using AXIsProcessTrustedWithOptions
-fn check_accessibility_trusted(prompt: bool) -> bool {
-    use core_foundation::base::{CFRelease, TCFType};
+/// Log target of the permission checks, so they can be enabled on their own
+/// (`RUST_LOG=vaixkey::permissions=debug`)
+const PERMISSIONS_LOG: &str = "vaixkey::permissions";
+
+/// How long the permission check's grab probe must keep running to count
+const PERMISSION_PROBE: std::time::Duration = std::time::Duration::from_millis(300);
+
+/// Call `AXIsProcessTrustedWithOptions`, looked up at run time. `None` when
+/// ApplicationServices or the symbol can't be loaded (a future macOS, a
+/// sandbox), instead of failing at launch.
+fn ax_is_process_trusted(prompt: bool) -> Option<bool> {
+    use core_foundation::base::TCFType;
     use core_foundation::boolean::CFBoolean;
     use core_foundation::dictionary::CFDictionary;
     use core_foundation::string::CFString;
-    
-    #[link(name = "ApplicationServices", kind = "framework")]
+    use std::ffi::{c_char, c_int, c_void};
+
     extern "C" {
-        fn AXIsProcessTrustedWithOptions(options: *const std::ffi::c_void) -> bool;
+        fn dlopen(path: *const c_char, mode: c_int) -> *mut c_void;
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
     }
-    
-    unsafe {
-        if prompt {
-            // Create options dictionary with kAXTrustedCheckOptionPrompt = true
-            // This will prompt the user to grant permission
-            let key = CFString::new("AXTrustedCheckOptionPrompt");
-            let value = CFBoolean::true_value();
-            let options = CFDictionary::from_CFType_pairs(&[(key.as_CFType(), value.as_CFType())]);
-            AXIsProcessTrustedWithOptions(options.as_concrete_TypeRef() as *const _)
-        } else {
-            AXIsProcessTrustedWithOptions(std::ptr::null())
+    const RTLD_LAZY: c_int = 1;
+
+    let handle = unsafe {
+        dlopen(c"/System/Library/Frameworks/ApplicationServices.framework/ApplicationServices".as_ptr(), RTLD_LAZY)
+    };
+    if handle.is_null() {
+        log::warn!(target: PERMISSIONS_LOG, "ApplicationServices not available, cannot ask for Accessibility trust");
+        return None;
+    }
+    let symbol = unsafe { dlsym(handle, c"AXIsProcessTrustedWithOptions".as_ptr()) };
+    if symbol.is_null() {
+        log::warn!(target: PERMISSIONS_LOG, "AXIsProcessTrustedWithOptions not available, cannot ask for Accessibility trust");
+        return None;
+    }
+    let is_trusted: extern "C" fn(*const c_void) -> bool = unsafe { std::mem::transmute(symbol) };
+
+    if prompt {
+        // Create options dictionary with kAXTrustedCheckOptionPrompt = true
+        // This will prompt the user to grant permission
+        let key = CFString::new("AXTrustedCheckOptionPrompt");
+        let value = CFBoolean::true_value();
+        let options = CFDictionary::from_CFType_pairs(&[(key.as_CFType(), value.as_CFType())]);
+        Some(is_trusted(options.as_concrete_TypeRef() as *const _))
+    } else {
+        Some(is_trusted(std::ptr::null()))
+    }
+}
+
+/// Check if Accessibility permission is granted using AXIsProcessTrustedWithOptions.
+/// Reports not granted when the API is unavailable.
+fn check_accessibility_trusted(prompt: bool) -> bool {
+    ax_is_process_trusted(prompt).unwrap_or(false)
+}
+
+/// Combine the trust API's answer with a grab probe. Trust is taken at its
+/// word; otherwise (not trusted, or no API) a grab that keeps running shows
+/// the permission is there, e.g. granted since the trust cache was filled.
+fn accessibility_from_signals(trusted: Option<bool>, probe: impl FnOnce() -> bool) -> bool {
+    match trusted {
+        Some(true) => true,
+        Some(false) | None => probe(),
+    }
+}
+
+/// Start a pass-through grab and report whether it kept running
+fn grab_probe_succeeds() -> bool {
+    match keyboard::probe_grab(PERMISSION_PROBE) {
+        Ok(()) => true,
+        Err(e) => {
+            log::debug!(target: PERMISSIONS_LOG, "Grab probe failed: {}", e);
+            false
         }
     }
 }
@@ -587,11 +3434,14 @@ async fn check_input_monitoring_permission() -> bool {
     // Input Monitoring permission is tied to Accessibility on macOS
     // The rdev library uses Quartz Event Taps which require Accessibility permission
     // We test this by checking if we can create an event tap
-    check_accessibility_trusted(false)
+    check_accessibility_permission().await
 }
 
 async fn check_accessibility_permission() -> bool {
-    check_accessibility_trusted(false)
+    let trusted = ax_is_process_trusted(false);
+    let granted = accessibility_from_signals(trusted, grab_probe_succeeds);
+    log::debug!(target: PERMISSIONS_LOG, "Accessibility trusted: {:?}, granted: {}", trusted, granted);
+    granted
 }
 
 /// Open macOS System Settings to the appropriate privacy section
@@ -611,7 +3461,7 @@ fn open_system_settings(section: &str) {
         .spawn();
     
     if let Err(e) = result {
-        eprintln!("Failed to open System Settings: {}", e);
+        log::warn!(target: PERMISSIONS_LOG, "Failed to open System Settings, trying System Preferences: {}", e);
         // Fallback: try opening System Preferences app directly
         let _ = Command::new("open")
             .arg("-a")
@@ -622,7 +3472,9 @@ fn open_system_settings(section: &str) {
 
 /// Request accessibility permission with a system prompt
 fn request_accessibility_permission() -> bool {
-    check_accessibility_trusted(true)
+    let granted = check_accessibility_trusted(true);
+    log::debug!(target: PERMISSIONS_LOG, "Accessibility requested with prompt, granted: {}", granted);
+    granted
 }
 
 async fn test_real_keyboard_capture(
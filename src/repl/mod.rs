@@ -0,0 +1,186 @@
+// Interactive engine tester (`vaixkey --repl`). The terminal is switched to
+// raw mode with crossterm, so every key reaches the engine as it is typed, and
+// two lines are redrawn after each key: a status line with the method, the
+// composing buffer and the last ProcessResult, and the text typed so far.
+// Nothing is grabbed or injected, so no permissions are needed.
+//
+// Keys: Ctrl+C or Ctrl+D quits, Ctrl+T switches to the next input method,
+// Ctrl+E toggles Vietnamese and English, Enter starts a new line. Arrow keys
+// and other escape sequences are ignored.
+
+use std::io::{self, Read, Write};
+
+use crossterm::terminal;
+use vaixkey::config::InputMethodType;
+use vaixkey::input_method::{harness::ScreenModel, InputMethodEngine, ProcessResult};
+
+/// What one key read from the terminal does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// Hand the character to the engine
+    Key(char),
+    SwitchMethod,
+    ToggleVietnamese,
+    Quit,
+}
+
+/// Map a raw-mode character: control keys are commands, DEL is Backspace
+/// and CR is Enter
+pub fn action(c: char) -> Option<Action> {
+    match c {
+        '\x03' | '\x04' => Some(Action::Quit),
+        '\x14' => Some(Action::SwitchMethod),
+        '\x05' => Some(Action::ToggleVietnamese),
+        '\x7f' | '\x08' => Some(Action::Key('\x08')),
+        '\r' | '\n' => Some(Action::Key('\n')),
+        '\t' => Some(Action::Key('\t')),
+        c if c.is_control() => None,
+        c => Some(Action::Key(c)),
+    }
+}
+
+/// The input method after `method`, in `--list-methods` order
+pub fn next_method(method: InputMethodType) -> InputMethodType {
+    let all = InputMethodType::ALL;
+    let pos = all.iter().position(|&m| m == method).unwrap_or(0);
+    all[(pos + 1) % all.len()]
+}
+
+/// The engine, the line being typed and what the last key did
+pub struct Session<'a> {
+    engine: &'a mut InputMethodEngine,
+    screen: ScreenModel,
+    last: Option<ProcessResult>,
+}
+
+impl<'a> Session<'a> {
+    pub fn new(engine: &'a mut InputMethodEngine) -> Self {
+        engine.reset_buffer();
+        Self { engine, screen: ScreenModel::new(), last: None }
+    }
+
+    /// Apply one action. Returns false on Quit.
+    pub fn apply(&mut self, action: Action) -> bool {
+        match action {
+            Action::Quit => return false,
+            Action::SwitchMethod => {
+                let method = next_method(self.engine.method());
+                self.engine.set_method(method);
+            }
+            Action::ToggleVietnamese => self.engine.toggle_vietnamese_mode(),
+            Action::Key(c) => {
+                let result = self.engine.process_keypress(c);
+                let _ = self.screen.apply(&result);
+                self.last = Some(result);
+            }
+        }
+        true
+    }
+
+    /// The text typed since the last Enter
+    pub fn line(&self) -> String {
+        let text = self.screen.text();
+        match text.rfind('\n') {
+            Some(pos) => text[pos + 1..].to_string(),
+            None => text,
+        }
+    }
+
+    /// `[Telex · VI] buffer "việ" · last Replace { backspaces: 1, text: "ệ" }`
+    pub fn status_line(&self) -> String {
+        let status = self.engine.status();
+        let mode = match (status.vietnamese, status.temporarily_off) {
+            (false, _) => "EN",
+            (true, true) => "VI, off for this word",
+            (true, false) => "VI",
+        };
+        let last = match &self.last {
+            Some(result) => format!("{:?}", result),
+            None => "-".to_string(),
+        };
+        format!("[{:?} · {}] buffer {:?} · last {}", status.method, mode, status.buffer, last)
+    }
+}
+
+/// Raw mode for as long as this lives; it is turned off on drop, so an
+/// error doesn't leave the shell without echo
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        terminal::enable_raw_mode()
+            .map_err(|e| io::Error::new(e.kind(), format!("--repl needs a terminal: {}", e)))?;
+        Ok(Self)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Read one UTF-8 character, or None at end of input. Escape sequences
+/// (arrow keys) come back as ESC followed by their bytes.
+fn read_char(input: &mut impl Read) -> io::Result<Option<char>> {
+    let mut bytes = [0u8; 4];
+    if input.read(&mut bytes[..1])? == 0 {
+        return Ok(None);
+    }
+    let len = match bytes[0] {
+        0xF0.. => 4,
+        0xE0.. => 3,
+        0xC0.. => 2,
+        _ => 1,
+    };
+    input.read_exact(&mut bytes[1..len])?;
+    Ok(std::str::from_utf8(&bytes[..len]).ok().and_then(|s| s.chars().next()))
+}
+
+/// `vaixkey --repl`: type into the engine until Ctrl+C
+pub fn run(engine: &mut InputMethodEngine) -> Result<(), Box<dyn std::error::Error>> {
+    println!("⌨️  VaixKey REPL: Ctrl+T next method, Ctrl+E Vietnamese/English, Ctrl+C quit");
+    let _raw = RawMode::enable()?;
+    let mut session = Session::new(engine);
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+
+    // The status line, then the line being typed; the caret stays at the
+    // end of the second
+    write!(stdout, "\r\x1b[K{}\r\n", session.status_line())?;
+    stdout.flush()?;
+    while let Some(c) = read_char(&mut stdin)? {
+        let c = if c == '\x1b' {
+            match read_char(&mut stdin)? {
+                Some('[' | 'O') => {
+                    // Skip an escape sequence up to its final letter
+                    while let Some(next) = read_char(&mut stdin)? {
+                        if next.is_ascii_alphabetic() || next == '~' {
+                            break;
+                        }
+                    }
+                    continue;
+                }
+                // A lone Escape: the key after it still counts
+                Some(next) => next,
+                None => break,
+            }
+        } else {
+            c
+        };
+        let Some(action) = action(c) else { continue };
+        if !session.apply(action) {
+            break;
+        }
+        if action == Action::Key('\n') {
+            // Keep the finished line and start a fresh pair below it
+            write!(stdout, "\r\n\r\x1b[K{}\r\n", session.status_line())?;
+        } else {
+            write!(stdout, "\x1b[1A\r\x1b[K{}\r\n\x1b[K{}", session.status_line(), session.line())?;
+        }
+        stdout.flush()?;
+    }
+    write!(stdout, "\r\n")?;
+    stdout.flush()?;
+    Ok(())
+}
@@ -0,0 +1,157 @@
+// Keystroke logs: `--debug` records every key the engine sees and
+// `vaixkey --replay <file>` feeds them back through the engine, so a mangled
+// word can be reproduced without the GUI or any permissions.
+//
+// Log format, one entry per line:
+//   # comment          hash and a space; the first line names the version
+//   a                  a line holding one character is that character
+//   <space> <tab> <enter>
+//                      separators, named so editors can't strip them
+//   <reset>            the buffer was dropped (Backspace, Escape, a focus
+//                      change, secure input)
+//   <left> <right>     arrow keys moving the cursor within the word
+// Replay uses the loaded config, so pass the reporter's file with --config.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+use vaixkey::input_method::{harness::ScreenModel, InputMethodEngine, ProcessResult};
+
+/// Where `--debug` records the session
+pub const SESSION_LOG_PATH: &str = "/tmp/vaixkey_session.log";
+
+const HEADER: &str = "# vaixkey keystroke log v1";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Entry {
+    /// A character handed to the engine
+    Key(char),
+    /// The composing buffer was reset without a key reaching the engine
+    Reset,
+    /// Left or Right arrow
+    CursorLeft,
+    CursorRight,
+}
+
+impl Entry {
+    fn to_line(self) -> String {
+        match self {
+            Entry::Key(' ') => "<space>".to_string(),
+            Entry::Key('\t') => "<tab>".to_string(),
+            Entry::Key('\n') => "<enter>".to_string(),
+            Entry::Key(c) => c.to_string(),
+            Entry::Reset => "<reset>".to_string(),
+            Entry::CursorLeft => "<left>".to_string(),
+            Entry::CursorRight => "<right>".to_string(),
+        }
+    }
+
+    fn from_line(line: &str) -> Option<Entry> {
+        match line {
+            "<space>" => Some(Entry::Key(' ')),
+            "<tab>" => Some(Entry::Key('\t')),
+            "<enter>" => Some(Entry::Key('\n')),
+            "<reset>" => Some(Entry::Reset),
+            "<left>" => Some(Entry::CursorLeft),
+            "<right>" => Some(Entry::CursorRight),
+            _ => {
+                let mut chars = line.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some(Entry::Key(c)),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+/// Appends entries to a log file as they happen, so a crash loses nothing
+pub struct Recorder {
+    file: Mutex<File>,
+}
+
+impl Recorder {
+    /// Start a new log at `path`, replacing any previous one
+    pub fn create(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut file = File::create(path)?;
+        writeln!(file, "{}", HEADER)?;
+        Ok(Self { file: Mutex::new(file) })
+    }
+
+    pub fn record(&self, entry: Entry) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(file, "{}", entry.to_line());
+        }
+    }
+}
+
+/// Parse a log; errors name the offending line
+pub fn parse(log: &str) -> Result<Vec<Entry>, String> {
+    log.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with("# "))
+        .map(|(i, line)| {
+            Entry::from_line(line).ok_or_else(|| format!("line {}: unknown entry '{}'", i + 1, line))
+        })
+        .collect()
+}
+
+/// Feed `entries` through `engine`. Each key yields its result; resets and
+/// cursor moves yield `None`.
+pub fn replay(engine: &mut InputMethodEngine, entries: &[Entry]) -> Vec<(Entry, Option<ProcessResult>)> {
+    let mut results = Vec::with_capacity(entries.len());
+    for &entry in entries {
+        let result = match entry {
+            Entry::Key(c) => Some(engine.process_keypress(c)),
+            Entry::Reset => {
+                engine.reset_buffer();
+                None
+            }
+            Entry::CursorLeft => {
+                engine.cursor_left();
+                None
+            }
+            Entry::CursorRight => {
+                engine.cursor_right();
+                None
+            }
+        };
+        results.push((entry, result));
+    }
+    results
+}
+
+/// `vaixkey --replay <file>`: print every result and the text they produce
+pub fn run(engine: &mut InputMethodEngine, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let log = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let entries = parse(&log)?;
+
+    println!("📼 Replaying {} ({} entries)", path.display(), entries.len());
+    println!("==========================================");
+
+    let mut screen = ScreenModel::new();
+    for (entry, result) in replay(engine, &entries) {
+        match (entry, result) {
+            (_, Some(result)) => {
+                println!("   {:<8} → {:?}", entry.to_line(), result);
+                if let Err(e) = screen.apply(&result) {
+                    println!("   ⚠️  {}", e);
+                }
+            }
+            (Entry::CursorLeft, None) => {
+                screen.move_caret_left();
+                println!("   {:<8} → cursor left", entry.to_line());
+            }
+            (Entry::CursorRight, None) => {
+                screen.move_caret_right();
+                println!("   {:<8} → cursor right", entry.to_line());
+            }
+            (_, None) => println!("   {:<8} → buffer reset", entry.to_line()),
+        }
+    }
+
+    println!("\n📝 Resulting text: {:?}", screen.text());
+    Ok(())
+}
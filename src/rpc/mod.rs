@@ -0,0 +1,171 @@
+// JSON-RPC over localhost TCP (`vaixkey --rpc-port <port>`), so editor
+// plugins and test scripts can drive the running engine.
+//
+// Framing: one JSON-RPC 2.0 request per line, one response line per request.
+//   → {"jsonrpc":"2.0","id":1,"method":"process","params":{"char":"a"}}
+//   ← {"jsonrpc":"2.0","id":1,"result":{"action":"pass_through","char":"a","buffer":"a"}}
+// Methods:
+//   process {char}       type one key; returns what to do on screen
+//   convert {text}       convert a whole text, leaving the buffer alone
+//   reset                drop the word being composed
+//   set_method {method}  telex, vni or simple-telex
+//   switch_profile {name} load a config profile, as --profile does
+//   toggle               switch between Vietnamese and English
+//   status               mode, method and buffer
+// Only 127.0.0.1 is bound; there is no authentication. `request` is the
+// client side, for commands that tell a running instance about a change.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use log::{info, warn};
+use serde_json::{json, Value};
+use vaixkey::config::InputMethodType;
+use vaixkey::input_method::{InputMethodEngine, ProcessResult};
+use vaixkey::{lock, VaixError};
+
+/// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// Listen on 127.0.0.1:`port` (0 picks a free port) and answer requests on
+/// a thread per connection. Returns the bound address.
+pub fn serve(port: u16, engine: Arc<Mutex<InputMethodEngine>>) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let addr = listener.local_addr()?;
+    info!("JSON-RPC server listening on {}", addr);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let engine = engine.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = serve_connection(stream, &engine) {
+                            warn!("JSON-RPC connection closed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("JSON-RPC accept failed: {}", e),
+            }
+        }
+    });
+    Ok(addr)
+}
+
+/// Send one request to the instance serving on 127.0.0.1:`port` and return
+/// its result, or its error message
+pub fn request(port: u16, method: &str, params: Value) -> Result<Value, String> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let exchange = || -> io::Result<String> {
+        let stream = TcpStream::connect_timeout(&addr, Duration::from_secs(1))?;
+        stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+        let mut writer = stream.try_clone()?;
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        writeln!(writer, "{}", request)?;
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        Ok(line)
+    };
+    let line = exchange().map_err(|e| format!("no VaixKey answering on {}: {}", addr, e))?;
+    let mut response: Value = serde_json::from_str(&line).map_err(|e| format!("bad response from {}: {}", addr, e))?;
+    match response.get("error") {
+        Some(error) => Err(error["message"].as_str().unwrap_or("unknown error").to_string()),
+        None => Ok(response["result"].take()),
+    }
+}
+
+fn serve_connection(stream: TcpStream, engine: &Mutex<InputMethodEngine>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(writer, "{}", handle_line(engine, &line))?;
+    }
+    Ok(())
+}
+
+/// Answer one request line with one response line
+pub fn handle_line(engine: &Mutex<InputMethodEngine>, line: &str) -> String {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return error(Value::Null, PARSE_ERROR, &format!("parse error: {}", e)).to_string(),
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return error(id, INVALID_REQUEST, "request needs a \"method\" string").to_string();
+    };
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    match call(&mut lock(engine), method, &params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string(),
+        Err((code, message)) => error(id, code, &message).to_string(),
+    }
+}
+
+fn call(engine: &mut InputMethodEngine, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+    match method {
+        "process" => {
+            let key = string_param(params, "char")?;
+            let mut chars = key.chars();
+            let (Some(c), None) = (chars.next(), chars.next()) else {
+                return Err((INVALID_PARAMS, "\"char\" must be exactly one character".to_string()));
+            };
+            let mut result = process_result(&engine.process_keypress(c));
+            result["buffer"] = json!(engine.get_current_buffer());
+            Ok(result)
+        }
+        "convert" => Ok(json!({ "text": engine.convert(string_param(params, "text")?) })),
+        "reset" => {
+            engine.reset_buffer();
+            Ok(json!(engine.status()))
+        }
+        "set_method" => {
+            let method: InputMethodType = string_param(params, "method")?.parse().map_err(|e| (INVALID_PARAMS, e))?;
+            engine.set_method(method);
+            Ok(json!(engine.status()))
+        }
+        "switch_profile" => {
+            engine.switch_profile(string_param(params, "name")?).map_err(|e| match e {
+                VaixError::InvalidConfig(message) => (INVALID_PARAMS, message),
+                e => (INTERNAL_ERROR, e.to_string()),
+            })?;
+            Ok(json!(engine.status()))
+        }
+        "toggle" => {
+            engine.toggle_vietnamese_mode();
+            Ok(json!(engine.status()))
+        }
+        "status" => Ok(json!(engine.status())),
+        _ => Err((METHOD_NOT_FOUND, format!("unknown method '{}'", method))),
+    }
+}
+
+fn string_param<'a>(params: &'a Value, name: &str) -> Result<&'a str, (i64, String)> {
+    params
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| (INVALID_PARAMS, format!("missing string param \"{}\"", name)))
+}
+
+fn process_result(result: &ProcessResult) -> Value {
+    match result {
+        ProcessResult::PassThrough(c) => json!({ "action": "pass_through", "char": c.to_string() }),
+        ProcessResult::Output(text) => json!({ "action": "output", "text": text }),
+        ProcessResult::Replace { backspaces, text } => {
+            json!({ "action": "replace", "backspaces": backspaces, "text": text })
+        }
+    }
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
@@ -0,0 +1,125 @@
+// Clean removal (`vaixkey --uninstall [--yes]`)
+// Deletes the config directory, the LaunchAgent plist, the temp settings
+// page, the `--debug` keystroke log and the `--learn` log. Anything already gone is skipped, so
+// running it twice is harmless.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::gui::SETTINGS_HTML_PATH;
+use crate::keyboard::LEARN_LOG_PATH;
+use crate::replay::SESSION_LOG_PATH;
+use vaixkey::config::Config;
+
+/// launchd label of the login agent that starts VaixKey
+pub const LAUNCH_AGENT_LABEL: &str = "com.vaixkey.agent";
+
+/// `~/Library/LaunchAgents/<label>.plist`
+pub fn launch_agent_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let home = std::env::var("HOME").map_err(|_| "HOME is not set")?;
+    Ok(PathBuf::from(home)
+        .join("Library")
+        .join("LaunchAgents")
+        .join(format!("{}.plist", LAUNCH_AGENT_LABEL)))
+}
+
+/// What VaixKey may have written under `$HOME`: the config directory and
+/// the LaunchAgent plist
+pub fn home_targets() -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    Ok(vec![Config::config_dir()?, launch_agent_path()?])
+}
+
+/// Everything VaixKey may have written, whether or not it exists: the home
+/// targets plus the fixed paths in /tmp
+pub fn targets() -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut targets = home_targets()?;
+    targets.extend([
+        PathBuf::from(SETTINGS_HTML_PATH),
+        PathBuf::from(SESSION_LOG_PATH),
+        PathBuf::from(LEARN_LOG_PATH),
+    ]);
+    Ok(targets)
+}
+
+/// Delete the targets that exist and return the ones removed. A loaded
+/// LaunchAgent is unloaded before its plist goes.
+pub fn remove(targets: &[PathBuf]) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut removed = Vec::new();
+    for path in targets {
+        if !path.exists() {
+            continue;
+        }
+
+        if path.extension().is_some_and(|ext| ext == "plist") {
+            unload_launch_agent(path);
+        }
+
+        let result = if path.is_dir() {
+            std::fs::remove_dir_all(path)
+        } else {
+            std::fs::remove_file(path)
+        };
+        match result {
+            Ok(()) => removed.push(path.clone()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("cannot remove {}: {}", path.display(), e).into()),
+        }
+    }
+    Ok(removed)
+}
+
+/// Interactive entry point: list what will go, confirm unless `assume_yes`,
+/// then remove it
+pub fn run(assume_yes: bool) -> Result<(), Box<dyn std::error::Error>> {
+    println!("🧹 Uninstall VaixKey");
+    println!("===================");
+
+    let existing: Vec<PathBuf> = targets()?.into_iter().filter(|p| p.exists()).collect();
+
+    // A config chosen with --config or VAIXKEY_CONFIG lives outside the
+    // config directory and belongs to the user
+    let config_dir = Config::config_dir()?;
+    if let Ok(path) = Config::config_path() {
+        if !path.starts_with(&config_dir) && path.exists() {
+            println!("ℹ️  Leaving custom config file in place: {}", path.display());
+        }
+    }
+
+    if existing.is_empty() {
+        println!("✅ Nothing to remove, VaixKey is already uninstalled");
+        return Ok(());
+    }
+
+    println!("This will remove:");
+    for path in &existing {
+        println!("   • {}", path.display());
+    }
+
+    if !assume_yes && !confirm("Continue? [y/N] ")? {
+        println!("❌ Cancelled, nothing was removed");
+        return Ok(());
+    }
+
+    for path in remove(&existing)? {
+        println!("🗑️  Removed {}", path.display());
+    }
+    println!("✅ VaixKey has been uninstalled. Delete the binary to finish.");
+    Ok(())
+}
+
+/// Ask a yes/no question on stdin; anything but y or yes is a no
+pub fn confirm(prompt: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// `launchctl unload` fails when the agent isn't loaded, which is fine here
+fn unload_launch_agent(plist: &Path) {
+    if cfg!(target_os = "macos") {
+        let _ = Command::new("launchctl").arg("unload").arg(plist).output();
+    }
+}
@@ -0,0 +1,213 @@
+// Backspace invariant: a `Replace` never erases more than the word being
+// typed has put on screen, so it can't eat text typed before the word. The
+// engine checks this itself in debug builds (`debug_assert!` in `process`);
+// this test feeds it adversarial keys under every option that changes how
+// words are erased and retyped, and checks the same bound against its own
+// model of the screen so it holds in release builds too.
+//
+//   cargo test --test backspace_invariant
+
+use std::panic::{self, AssertUnwindSafe};
+
+use vaixkey::{InputMethod, ProcessResult, UnikeyEngine, ZKey};
+
+/// Inputs per configuration
+const CASES: usize = 3000;
+
+/// Text typed before every input, which no result may erase
+const PREFIX: &str = "xin ";
+
+/// Stand-ins for the Left and Right arrows, as in the harness
+const LEFT: char = '\x01';
+const RIGHT: char = '\x02';
+
+/// Keys weighted towards the ones that rewrite the word: mark and tone keys
+/// in Telex and VNI, their doubles, brackets, backspace and the arrows
+const KEYS: &[char] = &[
+    'a', 'a', 'e', 'o', 'o', 'u', 'u', 'i', 'y', 'd', 'd', 'w', 'w', 'w', 's', 's', 'f', 'r', 'x',
+    'j', 'j', 'z', 'n', 'g', 'h', 'c', 't', 'q', 'A', 'W', 'S', '1', '2', '5', '6', '7', '8', '9',
+    '0', '[', ']', ' ', '.', '\x08', '\x08', '\x08', LEFT, RIGHT,
+];
+
+/// Words that once tripped the erase count, typed whole inside inputs
+const WORDS: &[&str] = &[
+    "nguwowif", "ddaays", "uwowwf", "ass", "vieetjj", "tieengss", "www", "aaaa", "a8a8", "u7o7",
+    "dd9", "quas", "gias", "thuys", "class", "\x08\x08s",
+];
+
+/// xorshift64, as in `--fuzz`
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Random keys with a known hard word spliced in now and then
+fn input(rng: &mut Rng) -> Vec<char> {
+    let len = 1 + rng.below(40);
+    let mut keys = Vec::with_capacity(len + 8);
+    while keys.len() < len {
+        if rng.below(8) == 0 {
+            keys.extend(WORDS[rng.below(WORDS.len())].chars());
+        } else {
+            keys.push(KEYS[rng.below(KEYS.len())]);
+        }
+    }
+    keys
+}
+
+/// The screen as the results left it, with the caret
+#[derive(Default)]
+struct Screen {
+    text: Vec<char>,
+    caret: usize,
+}
+
+impl Screen {
+    fn insert(&mut self, text: &str) {
+        for c in text.chars() {
+            self.text.insert(self.caret, c);
+            self.caret += 1;
+        }
+    }
+
+    /// Characters before the caret back to the last space, or to the prefix:
+    /// the most the word being typed can have put there
+    fn word_before_caret(&self) -> usize {
+        let start = self.text[PREFIX.len()..self.caret]
+            .iter()
+            .rposition(|&c| c == ' ')
+            .map_or(PREFIX.len(), |i| PREFIX.len() + i + 1);
+        self.caret - start
+    }
+}
+
+/// Type `keys` after the prefix, failing on the first result that erases
+/// past the start of the word
+fn check(engine: &mut UnikeyEngine, keys: &[char]) -> Result<(), String> {
+    let mut screen = Screen::default();
+    screen.insert(PREFIX);
+    for (step, &c) in keys.iter().enumerate() {
+        match c {
+            // The keyboard layer drops the word once the caret leaves it
+            LEFT => {
+                if screen.caret > PREFIX.len() {
+                    screen.caret -= 1;
+                }
+                if !engine.cursor_left() {
+                    engine.clear_buf();
+                }
+            }
+            RIGHT => {
+                screen.caret = (screen.caret + 1).min(screen.text.len());
+                if !engine.cursor_right() {
+                    engine.clear_buf();
+                }
+            }
+            _ => match engine.process(c) {
+                ProcessResult::PassThrough('\x08') => {
+                    // The prefix stands for text in another field
+                    if screen.caret > PREFIX.len() {
+                        screen.caret -= 1;
+                        screen.text.remove(screen.caret);
+                    } else {
+                        engine.clear_buf();
+                    }
+                }
+                ProcessResult::PassThrough(c) => screen.insert(&c.to_string()),
+                ProcessResult::Output(text) => screen.insert(&text),
+                ProcessResult::Replace { backspaces, text } => {
+                    let word = screen.word_before_caret();
+                    if backspaces > word {
+                        return Err(format!("step {} ({:?}): {} backspaces, the word has {}", step, c, backspaces, word));
+                    }
+                    screen.caret -= backspaces;
+                    screen.text.drain(screen.caret..screen.caret + backspaces);
+                    screen.insert(&text);
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+fn check_inputs(setup: impl Fn(&mut UnikeyEngine), seed: u64) {
+    let mut rng = Rng(seed);
+    let mut failures = Vec::new();
+    for _ in 0..CASES {
+        let keys = input(&mut rng);
+        let mut engine = UnikeyEngine::new();
+        setup(&mut engine);
+        // The engine's own debug_assert panics; report it with the input
+        let problem = panic::catch_unwind(AssertUnwindSafe(|| check(&mut engine, &keys)))
+            .unwrap_or_else(|e| Err(e.downcast_ref::<String>().cloned().unwrap_or_else(|| "panicked".to_string())));
+        if let Err(problem) = problem {
+            failures.push(format!("{:?}: {}", keys.iter().collect::<String>(), problem));
+        }
+    }
+    assert!(failures.is_empty(), "{} of {} inputs erase too much:\n{}", failures.len(), CASES, failures.join("\n"));
+}
+
+#[test]
+fn telex_never_erases_past_the_word() {
+    check_inputs(|_| {}, 0xBAC5_0001);
+}
+
+#[test]
+fn vni_never_erases_past_the_word() {
+    check_inputs(|engine| {
+        engine.set_input_method(InputMethod::Vni);
+    }, 0xBAC5_0002);
+}
+
+#[test]
+fn telex_with_vni_keys_never_erases_past_the_word() {
+    check_inputs(|engine| engine.set_allow_vni_in_telex(true), 0xBAC5_0003);
+}
+
+#[test]
+fn restoring_the_previous_syllable_never_erases_past_it() {
+    check_inputs(|engine| engine.set_restore_previous_syllable(true), 0xBAC5_0004);
+}
+
+#[test]
+fn tones_at_the_end_never_erase_past_the_word() {
+    check_inputs(|engine| engine.set_tone_at_end(true), 0xBAC5_0005);
+}
+
+#[test]
+fn dictionary_revert_never_erases_past_the_word() {
+    check_inputs(|engine| engine.set_dictionary_revert(true), 0xBAC5_0006);
+}
+
+#[test]
+fn free_marking_and_double_letters_never_erase_past_the_word() {
+    check_inputs(|engine| {
+        engine.set_free_marking(true);
+        engine.set_english_double_letters(true);
+        engine.set_syllable_cache(64);
+    }, 0xBAC5_0007);
+}
+
+#[test]
+fn z_removing_marks_never_erases_past_the_word() {
+    check_inputs(|engine| engine.set_z_key(ZKey::RemoveMarks), 0xBAC5_0009);
+}
+
+#[test]
+fn custom_rules_never_erase_past_the_word() {
+    check_inputs(|engine| {
+        engine.add_rule("oe", "œ").expect("rule");
+        engine.add_rule("œw", "ɶ").expect("rule");
+        engine.add_rule("ww", "w").expect("rule");
+    }, 0xBAC5_0008);
+}
@@ -0,0 +1,67 @@
+/*
+ * Composes Telex input through the C ABI and checks the text a host would end
+ * up with after applying every result.
+ *
+ *   cargo build --release --lib --no-default-features --features dictionary
+ *   cc tests/c/compose_test.c -Iinclude -Ltarget/release -lvaixkey -o compose_test
+ *   ./compose_test
+ */
+#include <stdio.h>
+#include <string.h>
+
+#include "vaixkey.h"
+
+/* Remove the last `count` UTF-8 characters from `text` */
+static void delete_chars(char *text, int count) {
+    size_t len = strlen(text);
+    while (count-- > 0 && len > 0) {
+        do {
+            len--;
+        } while (len > 0 && (text[len] & 0xC0) == 0x80);
+    }
+    text[len] = '\0';
+}
+
+static int compose(VaixkeyEngine *engine, const char *keys, const char *expected) {
+    char screen[256] = "";
+    char out[VAIXKEY_OUTPUT_CAPACITY];
+
+    vaixkey_reset(engine);
+    for (const char *key = keys; *key; key++) {
+        int backspaces = vaixkey_process(engine, (uint32_t)*key, out, sizeof(out));
+        if (backspaces < 0) {
+            printf("FAIL %s: error %d at '%c'\n", keys, backspaces, *key);
+            return 1;
+        }
+        delete_chars(screen, backspaces);
+        strncat(screen, out, sizeof(screen) - strlen(screen) - 1);
+    }
+
+    if (strcmp(screen, expected) != 0) {
+        printf("FAIL %s: got '%s', expected '%s'\n", keys, screen, expected);
+        return 1;
+    }
+    printf("ok   %s -> %s\n", keys, screen);
+    return 0;
+}
+
+int main(void) {
+    VaixkeyEngine *engine = vaixkey_new();
+    int failures = 0;
+
+    failures += compose(engine, "vieetj", "việt");
+    failures += compose(engine, "nguwowif", "người");
+    failures += compose(engine, "ddaays", "đấy");
+
+    if (vaixkey_set_method(engine, 42) != VAIXKEY_ERR_INVALID) {
+        printf("FAIL unknown method accepted\n");
+        failures++;
+    }
+    if (vaixkey_process(NULL, 'a', NULL, 0) != VAIXKEY_ERR_NULL) {
+        printf("FAIL null engine accepted\n");
+        failures++;
+    }
+
+    vaixkey_free(engine);
+    return failures == 0 ? 0 : 1;
+}
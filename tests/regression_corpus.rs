@@ -0,0 +1,100 @@
+// Regression corpus: every file in tests/corpus is one raw input that once
+// broke the engine or is known to be hard (nghiêng, được, chuyện, quầy,
+// ngoằn). Each is replayed against the screen model the way `--fuzz` does,
+// and, unless it moves the caret, typed key by key must agree with
+// `process_sequence`/`apply_results` and with `convert`. `--fuzz` saves its
+// failing inputs here when run from the repository.
+//
+// Files hold the keys exactly, with Backspace as 0x08 and the Left and Right
+// arrows as 0x01 and 0x02. A trailing newline is ignored, so files can be
+// edited by hand.
+//
+//   cargo test --test regression_corpus
+
+use std::fs;
+use std::panic;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use vaixkey::input_method::harness::{self, ScreenModel};
+use vaixkey::{apply_results, Config, InputMethodEngine, UnikeyEngine};
+
+/// Fuzzer bytes for the Left and Right arrows, as in the harness
+const ARROWS: [u8; 2] = [0x01, 0x02];
+
+/// Every corpus input by file name, in name order
+fn corpus() -> Vec<(String, Vec<u8>)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut inputs: Vec<(String, Vec<u8>)> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("reading {}: {}", dir.display(), e))
+        .map(|entry| {
+            let path = entry.expect("corpus entry").path();
+            let mut bytes = fs::read(&path).unwrap_or_else(|e| panic!("reading {}: {}", path.display(), e));
+            if bytes.last() == Some(&b'\n') {
+                bytes.pop();
+            }
+            (path.file_name().unwrap_or_default().to_string_lossy().into_owned(), bytes)
+        })
+        .collect();
+    inputs.sort();
+    inputs
+}
+
+/// What typing `keys` one at a time leaves on screen
+fn type_keys(keys: &[char]) -> Result<String, String> {
+    let mut engine = UnikeyEngine::new();
+    let mut screen = ScreenModel::new();
+    for &c in keys {
+        screen.apply(&engine.process(c))?;
+    }
+    Ok(screen.text())
+}
+
+/// Everything wrong with one input, or None
+fn check(input: &[u8]) -> Option<String> {
+    if let Err(e) = harness::check_one(input) {
+        return Some(e);
+    }
+    if input.iter().any(|b| ARROWS.contains(b)) {
+        return None;
+    }
+
+    let Ok(text) = std::str::from_utf8(input) else {
+        return Some("not UTF-8".to_string());
+    };
+    let keys: Vec<char> = text.chars().collect();
+    let incremental = match type_keys(&keys) {
+        Ok(screen) => screen,
+        Err(e) => return Some(e),
+    };
+    let batch = apply_results("", &UnikeyEngine::new().process_sequence(&keys));
+    if incremental != batch {
+        return Some(format!("typed {:?}, process_sequence gave {:?}", incremental, batch));
+    }
+
+    // convert() commits the last word as a space would
+    let config = Arc::new(Mutex::new(Config::default()));
+    let converted = InputMethodEngine::new(config.clone()).convert(text);
+    let mut engine = InputMethodEngine::new(config);
+    let results: Vec<_> = text.chars().chain([' ']).map(|c| engine.process_keypress(c)).collect();
+    let typed = apply_results("", &results);
+    if typed != format!("{} ", converted) {
+        return Some(format!("typed {:?}, convert gave {:?}", typed, converted));
+    }
+    None
+}
+
+#[test]
+fn corpus_inputs_stay_in_sync() {
+    let inputs = corpus();
+    assert!(!inputs.is_empty(), "tests/corpus is empty");
+
+    let failures: Vec<String> = inputs
+        .iter()
+        .filter_map(|(name, input)| {
+            let problem = panic::catch_unwind(|| check(input)).unwrap_or_else(|_| Some("panicked".to_string()));
+            problem.map(|problem| format!("{} ({:?}): {}", name, String::from_utf8_lossy(input), problem))
+        })
+        .collect();
+    assert!(failures.is_empty(), "{} of {} corpus inputs fail:\n{}", failures.len(), inputs.len(), failures.join("\n"));
+}
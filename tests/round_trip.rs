@@ -0,0 +1,151 @@
+// Round trip: typing a text key by key and applying every result to a model
+// of the screen must leave exactly what `InputMethodEngine::convert` returns
+// for the whole text. Inputs are Telex sentences generated by proptest from a
+// syllable grammar, so a failure shrinks to the shortest text that still
+// fails, and proptest saves it to replay on the next run.
+//
+//   cargo test --test round_trip
+
+use std::sync::{Arc, Mutex};
+
+use proptest::prelude::*;
+use proptest::sample::select;
+use vaixkey::{Config, InputMethodEngine, ProcessResult};
+
+/// Texts per configuration
+const CASES: u32 = 2000;
+
+const ONSETS: &[&str] = &[
+    "", "b", "c", "ch", "d", "dd", "g", "gh", "gi", "h", "k", "kh", "l", "m", "n", "ng", "ngh",
+    "nh", "p", "ph", "qu", "r", "s", "t", "th", "tr", "v", "x",
+];
+/// Vowel clusters as typed in Telex (aa → â, ow → ơ, uow → ươ...)
+const NUCLEI: &[&str] = &[
+    "a", "aa", "aw", "e", "ee", "i", "o", "oo", "ow", "u", "uw", "y", "ia", "iee", "oa", "oaw",
+    "oe", "ua", "uaa", "uoo", "uow", "uwow", "uy", "uyee", "ai", "ao", "au", "aau", "ay", "aay",
+    "eo", "eeu", "iu", "oi", "ooi", "owi", "ui", "uwi", "uwu", "ieeu", "uoi", "uwowi", "uwowu",
+    "oai", "oay", "uya", "uyu",
+];
+const CODAS: &[&str] = &["", "c", "ch", "m", "n", "ng", "nh", "p", "t"];
+const TONES: &[&str] = &["", "s", "f", "r", "x", "j"];
+const SEPARATORS: &[&str] = &[" ", ", ", ". ", "\n"];
+
+#[derive(Clone, Copy, Debug)]
+enum Case {
+    Lower,
+    Capitalised,
+    Upper,
+}
+
+/// One syllable's keys: the tone key goes after the vowels or at the end,
+/// and the word is sometimes capitalised or typed in all caps
+fn syllable() -> impl Strategy<Value = String> {
+    let case = prop_oneof![7 => Just(Case::Lower), 2 => Just(Case::Capitalised), 1 => Just(Case::Upper)];
+    (select(ONSETS), select(NUCLEI), select(CODAS), select(TONES), any::<bool>(), case).prop_map(
+        |(onset, nucleus, coda, tone, tone_last, case)| {
+            let keys = if tone_last {
+                format!("{}{}{}{}", onset, nucleus, coda, tone)
+            } else {
+                format!("{}{}{}{}", onset, nucleus, tone, coda)
+            };
+            match case {
+                Case::Lower => keys,
+                Case::Capitalised => {
+                    let mut chars = keys.chars();
+                    chars.next().map(|c| c.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+                }
+                Case::Upper => keys.to_uppercase(),
+            }
+        },
+    )
+}
+
+/// One to six syllables joined by separators, without a trailing one
+fn sentence() -> impl Strategy<Value = String> {
+    let separator = prop_oneof![3 => Just(" "), 1 => select(SEPARATORS)];
+    (syllable(), prop::collection::vec((separator, syllable()), 0..6)).prop_map(|(first, rest)| {
+        rest.into_iter().fold(first, |text, (separator, word)| text + separator + &word)
+    })
+}
+
+/// Type `text` into `engine` and rebuild the screen from the results alone:
+/// a backspace deletes, output is inserted, a replacement deletes then
+/// inserts. Fails if a result deletes more than is on screen or, with
+/// `check_buffer`, if the word being composed isn't at the end of it.
+fn type_text(engine: &mut InputMethodEngine, text: &str, check_buffer: bool) -> Result<String, String> {
+    let mut screen: Vec<char> = Vec::new();
+    for (step, c) in text.chars().enumerate() {
+        match engine.process_keypress(c) {
+            ProcessResult::PassThrough('\x08') => {
+                screen.pop();
+            }
+            ProcessResult::PassThrough(c) => screen.push(c),
+            ProcessResult::Output(output) => screen.extend(output.chars()),
+            ProcessResult::Replace { backspaces, text } => {
+                let Some(keep) = screen.len().checked_sub(backspaces) else {
+                    return Err(format!("step {} ({:?}): {} backspaces, {} on screen", step, c, backspaces, screen.len()));
+                };
+                screen.truncate(keep);
+                screen.extend(text.chars());
+            }
+        }
+
+        let buffer = engine.get_current_buffer();
+        if check_buffer && !screen.iter().collect::<String>().ends_with(buffer) {
+            return Err(format!("step {} ({:?}): buffer {:?} is not at the end of the screen", step, c, buffer));
+        }
+    }
+    Ok(screen.into_iter().collect())
+}
+
+fn engine(config: &Config) -> InputMethodEngine {
+    InputMethodEngine::new(Arc::new(Mutex::new(config.clone())))
+}
+
+/// Typing `text` with a trailing space leaves what `convert` gives
+fn check_round_trip(config: &Config, text: &str) -> Result<(), TestCaseError> {
+    let converted = engine(config).convert(text);
+
+    // With compose_on_commit the screen holds raw keys, not the buffer
+    let check_buffer = !config.engine.compose_on_commit;
+    // A trailing space commits the last word, as convert() does
+    let typed = type_text(&mut engine(config), &format!("{} ", text), check_buffer).map_err(TestCaseError::fail)?;
+    prop_assert_eq!(typed, format!("{} ", converted), "typing {:?}", text);
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+    #[test]
+    fn typed_text_matches_convert(text in sentence()) {
+        check_round_trip(&Config::default(), &text)?;
+    }
+
+    #[test]
+    fn typed_text_matches_convert_with_tone_at_end(text in sentence()) {
+        let mut config = Config::default();
+        config.engine.tone_at_end = true;
+        check_round_trip(&config, &text)?;
+    }
+
+    #[test]
+    fn typed_text_matches_convert_with_compose_on_commit(text in sentence()) {
+        let mut config = Config::default();
+        config.engine.compose_on_commit = true;
+        check_round_trip(&config, &text)?;
+    }
+
+    /// The words of a text convert the same on their own: nothing carries
+    /// over from one word to the next
+    #[test]
+    fn words_convert_independently(text in sentence()) {
+        let config = Config::default();
+        let whole = engine(&config).convert(&text);
+        let by_word: String = text
+            .split_inclusive([' ', ',', '.', '\n'])
+            .map(|word| engine(&config).convert(word))
+            .collect();
+        prop_assert_eq!(whole, by_word, "converting {:?} word by word", text);
+    }
+}